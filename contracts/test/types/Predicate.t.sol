@@ -53,4 +53,56 @@ contract PredicateTest is Test {
         bool result = predicate.eval(journal, keccak256(journal));
         assertFalse(result, "Predicate evaluation should be false for non-matching prefix");
     }
+
+    function testEvalAbiFieldMatch() public pure {
+        Predicate memory predicate = PredicateLibrary.createAbiFieldMatchPredicate(1, bytes32(uint256(42)));
+        bytes memory journal = abi.encode(uint256(7), uint256(42));
+
+        bool result = predicate.eval(journal, sha256(journal));
+        assertTrue(result, "Predicate evaluation should be true for matching field");
+    }
+
+    function testEvalAbiFieldMatchFail() public pure {
+        Predicate memory predicate = PredicateLibrary.createAbiFieldMatchPredicate(1, bytes32(uint256(42)));
+        bytes memory journal = abi.encode(uint256(42), uint256(7));
+        assertFalse(predicate.eval(journal, sha256(journal)), "Predicate evaluation should be false for other field");
+
+        journal = abi.encode(uint256(42));
+        assertFalse(predicate.eval(journal, sha256(journal)), "Predicate evaluation should be false for missing field");
+    }
+
+    function testEvalMaskMatch() public pure {
+        Predicate memory predicate = PredicateLibrary.createMaskMatchPredicate(1, hex"f0ff", hex"a0bb");
+        bytes memory journal = hex"00abbbcc";
+
+        bool result = predicate.eval(journal, sha256(journal));
+        assertTrue(result, "Predicate evaluation should be true for matching masked range");
+    }
+
+    function testEvalMaskMatchFail() public pure {
+        Predicate memory predicate = PredicateLibrary.createMaskMatchPredicate(1, hex"f0ff", hex"a0bb");
+        bytes memory journal = hex"00bbbbcc";
+        assertFalse(predicate.eval(journal, sha256(journal)), "Predicate evaluation should be false for other range");
+
+        journal = hex"00ab";
+        assertFalse(predicate.eval(journal, sha256(journal)), "Predicate evaluation should be false for short journal");
+    }
+
+    function testEvalAnyDigestMatch() public pure {
+        bytes32[] memory hashes = new bytes32[](2);
+        hashes[0] = sha256("other");
+        hashes[1] = sha256("test");
+        Predicate memory predicate = PredicateLibrary.createAnyDigestMatchPredicate(hashes);
+        assertEq(
+            uint8(predicate.predicateType),
+            uint8(PredicateType.AnyDigestMatch),
+            "Predicate type should be AnyDigestMatch"
+        );
+
+        bytes memory journal = "test";
+        assertTrue(predicate.eval(journal, sha256(journal)), "Predicate evaluation should be true for any match");
+
+        journal = "different test";
+        assertFalse(predicate.eval(journal, sha256(journal)), "Predicate evaluation should be false for no match");
+    }
 }
@@ -112,7 +112,7 @@ mod tests {
     use guest_util::{ECHO_ELF, ECHO_ID};
     use risc0_zkvm::{
         default_executor,
-        sha::{Digest, Digestible},
+        sha::{Digest, Digestible, Impl, Sha256},
         ExecutorEnv, ExitCode, FakeReceipt, InnerReceipt, MaybePruned, Receipt,
     };
 
@@ -160,6 +160,29 @@ mod tests {
         claim.evaluate_requirements().unwrap();
     }
 
+    #[test]
+    #[test_log::test]
+    fn test_claim_predicates() {
+        let signer = PrivateKeySigner::random();
+        let mut claim = Fulfillment {
+            request: proving_request(1, signer.address(), B256::ZERO, vec![]),
+            signature: vec![],
+            journal: vec![1, 2, 3],
+            require_payment: true,
+        };
+
+        let predicates = [
+            (Predicate::mask_match(1, vec![0xff, 0x0f], vec![2, 0xf3]), true),
+            (Predicate::mask_match(1, vec![0xff, 0xff], vec![2, 0xf3]), false),
+            (Predicate::any_digest_match([Digest::ZERO, *Impl::hash_bytes(&[1, 2, 3])]), true),
+            (Predicate::any_digest_match([Digest::ZERO]), false),
+        ];
+        for (predicate, satisfied) in predicates {
+            claim.request.requirements.predicate = predicate;
+            assert_eq!(claim.evaluate_requirements().is_ok(), satisfied);
+        }
+    }
+
     #[test]
     #[test_log::test]
     fn test_domain_serde() {
@@ -29,10 +29,10 @@ use alloy::{
         Address, Bytes, FixedBytes, B256, U256,
     },
     providers::{network::EthereumWallet, Provider, ProviderBuilder},
-    signers::{local::PrivateKeySigner, Signer},
+    signers::Signer,
 };
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use boundless_cli::{DefaultProver, OrderFulfilled};
+use boundless_cli::{fetch_input, DefaultProver, OrderFulfilled};
 use clap::{Args, Parser, Subcommand};
 use hex::FromHex;
 use risc0_ethereum_contracts::{set_verifier::SetVerifierService, IRiscZeroVerifier};
@@ -48,10 +48,12 @@ use url::Url;
 use boundless_market::{
     client::{Client, ClientBuilder},
     contracts::{
-        boundless_market::BoundlessMarketService, Callback, Input, InputType, Offer, Predicate,
-        PredicateType, ProofRequest, Requirements,
+        boundless_market::BoundlessMarketService, Callback, Input, Offer, Predicate, PredicateType,
+        ProofRequest, Requirements,
     },
     input::{GuestEnv, InputBuilder},
+    pricing::{session_cycles, OfferBuilder, Urgency},
+    signer::SignerConfig,
     storage::{StorageProvider, StorageProviderConfig},
 };
 
@@ -216,7 +218,11 @@ struct SubmitOfferArgs {
     #[clap(flatten)]
     storage_config: Option<StorageProviderConfig>,
     /// Path to a YAML file containing the offer
-    yaml_offer: PathBuf,
+    ///
+    /// If not provided, the offer is priced from the cycle count of a local execution of the
+    /// request and the recent history of the market. This execution is also the preflight of the
+    /// request, and can not be skipped.
+    yaml_offer: Option<PathBuf>,
     /// Optional identifier for the request
     id: Option<u32>,
     /// Wait until the request is fulfilled
@@ -241,6 +247,12 @@ struct SubmitOfferArgs {
     /// Elf file to use as the guest image, given as a path.
     #[clap(long)]
     elf: PathBuf,
+    /// How urgently the request should be fulfilled, when pricing the offer automatically.
+    #[clap(long, value_enum, default_value = "normal", conflicts_with = "yaml_offer")]
+    urgency: Urgency,
+    /// Number of recent blocks of market history used when pricing the offer automatically.
+    #[clap(long, default_value = "10000", conflicts_with = "yaml_offer")]
+    market_history_blocks: u64,
 
     #[command(flatten)]
     input: SubmitOfferInput,
@@ -283,9 +295,9 @@ struct MainArgs {
     /// URL of the Ethereum RPC endpoint
     #[clap(short, long, env, default_value = "http://localhost:8545")]
     rpc_url: Url,
-    /// Private key of the wallet
-    #[clap(long, env)]
-    private_key: PrivateKeySigner,
+    /// Wallet signer, given as a private key, a keystore, or a remote signer
+    #[clap(flatten)]
+    signer: SignerConfig,
     /// Address of the market contract
     #[clap(short, long, env)]
     boundless_market_address: Address,
@@ -322,8 +334,9 @@ async fn main() -> Result<()> {
 }
 
 pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
-    let caller = args.private_key.address();
-    let wallet = EthereumWallet::from(args.private_key.clone());
+    let signer = args.signer.signer().context("failed to construct wallet signer")?;
+    let caller = signer.address();
+    let wallet = EthereumWallet::from(signer.clone());
     let provider = ProviderBuilder::new().wallet(wallet).on_http(args.rpc_url.clone());
     let mut boundless_market =
         BoundlessMarketService::new(args.boundless_market_address, provider.clone(), caller);
@@ -349,7 +362,7 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
             tracing::info!("Balance of {addr}: {}", format_ether(balance));
         }
         Command::DepositStake { amount } => {
-            boundless_market.deposit_stake_with_permit(amount, &args.private_key).await?;
+            boundless_market.deposit_stake_with_permit(amount, &signer).await?;
             tracing::info!("Deposited stake: {}", amount);
         }
         Command::WithdrawStake { amount } => {
@@ -372,7 +385,7 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
                 )
                 .transpose()?;
             let client = ClientBuilder::default()
-                .with_private_key(args.private_key.clone())
+                .with_signer(signer.clone())
                 .with_rpc_url(args.rpc_url.clone())
                 .with_boundless_market_address(args.boundless_market_address)
                 .with_set_verifier_address(args.set_verifier_address)
@@ -382,7 +395,7 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
                 .build()
                 .await?;
 
-            request_id = submit_offer(client, &args.private_key, &offer_args).await?;
+            request_id = submit_offer(client, &signer, &offer_args).await?;
         }
         Command::SubmitRequest {
             storage_config,
@@ -404,7 +417,7 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
                 )
                 .transpose()?;
             let client = ClientBuilder::default()
-                .with_private_key(args.private_key.clone())
+                .with_signer(signer.clone())
                 .with_rpc_url(args.rpc_url.clone())
                 .with_boundless_market_address(args.boundless_market_address)
                 .with_set_verifier_address(args.set_verifier_address)
@@ -414,16 +427,9 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
                 .build()
                 .await?;
 
-            request_id = submit_request(
-                id,
-                yaml_request,
-                client,
-                &args.private_key,
-                wait,
-                offchain,
-                !no_preflight,
-            )
-            .await?;
+            request_id =
+                submit_request(id, yaml_request, client, &signer, wait, offchain, !no_preflight)
+                    .await?;
         }
         Command::Slash { request_id } => {
             boundless_market.slash(request_id).await?;
@@ -450,7 +456,7 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
         }
         Command::GetSetInclusionReceipt { request_id, image_id } => {
             let client = ClientBuilder::default()
-                .with_private_key(args.private_key.clone())
+                .with_signer(signer.clone())
                 .with_rpc_url(args.rpc_url.clone())
                 .with_boundless_market_address(args.boundless_market_address)
                 .with_set_verifier_address(args.set_verifier_address)
@@ -482,7 +488,7 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
                 serde_yaml::from_reader(reader).context("failed to parse request from YAML")?
             } else if let Some(request_id) = request_id {
                 let client = ClientBuilder::default()
-                    .with_private_key(args.private_key.clone())
+                    .with_signer(signer.clone())
                     .with_rpc_url(args.rpc_url.clone())
                     .with_boundless_market_address(args.boundless_market_address)
                     .with_set_verifier_address(args.set_verifier_address)
@@ -527,7 +533,7 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
             let prover = DefaultProver::new(set_builder_elf, assessor_elf, caller, domain)?;
 
             let client = ClientBuilder::default()
-                .with_private_key(args.private_key.clone())
+                .with_signer(signer.clone())
                 .with_rpc_url(args.rpc_url.clone())
                 .with_boundless_market_address(args.boundless_market_address)
                 .with_set_verifier_address(args.set_verifier_address)
@@ -539,11 +545,15 @@ pub(crate) async fn run(args: &MainArgs) -> Result<Option<U256>> {
             let order = client.fetch_order(request_id, tx_hash, request_digest).await?;
             tracing::debug!("Fulfilling request {:?}", order.request);
             let sig: Bytes = order.signature.as_bytes().into();
-            order.request.verify_signature(
-                &sig,
-                args.boundless_market_address,
-                boundless_market.get_chain_id().await?,
-            )?;
+            order
+                .request
+                .verify_signature_with_provider(
+                    &sig,
+                    args.boundless_market_address,
+                    boundless_market.get_chain_id().await?,
+                    client.provider(),
+                )
+                .await?;
 
             let (fill, root_receipt, _, assessor_receipt) =
                 prover.fulfill(order.clone(), require_payment).await?;
@@ -591,20 +601,6 @@ where
     P: Provider<Ethereum> + 'static + Clone,
     S: StorageProvider + Clone,
 {
-    // TODO(victor): Execute the request before sending it.
-    // Read the YAML offer file
-    let file = File::open(&args.yaml_offer)?;
-    let reader = BufReader::new(file);
-    let mut offer: Offer =
-        serde_yaml::from_reader(reader).context("failed to parse offer from YAML")?;
-
-    // If set to 0, override the offer bidding_start field with the current timestamp + 30 seconds.
-    if offer.biddingStart == 0 {
-        // NOTE: Adding a bit of a delay to bidding start lets provers see and evaluate the request
-        // before the price starts to ramp up. 30s is an arbitrary value.
-        offer = Offer { biddingStart: now_timestamp() + 30, ..offer };
-    }
-
     // Resolve the ELF and input from command line arguments.
     let elf: Cow<'static, [u8]> = std::fs::read(&args.elf).map(Into::into)?;
     let input: Vec<u8> = match (&args.input.input, &args.input.input_file) {
@@ -637,6 +633,44 @@ where
         _ => Callback::default(),
     };
 
+    let offer = match &args.yaml_offer {
+        Some(yaml_offer) => {
+            // Read the YAML offer file
+            let file = File::open(yaml_offer)?;
+            let reader = BufReader::new(file);
+            let offer: Offer =
+                serde_yaml::from_reader(reader).context("failed to parse offer from YAML")?;
+
+            // If set to 0, override the offer bidding_start field with the current timestamp + 30
+            // seconds.
+            if offer.biddingStart == 0 {
+                // NOTE: Adding a bit of a delay to bidding start lets provers see and evaluate the
+                // request before the price starts to ramp up. 30s is an arbitrary value.
+                Offer { biddingStart: now_timestamp() + 30, ..offer }
+            } else {
+                offer
+            }
+        }
+        None => {
+            // Price the offer from a local execution, which doubles as the request preflight.
+            tracing::info!("Running request preflight to price the offer");
+            let env = GuestEnv::decode(&encoded_input)?;
+            let session_info = default_executor().execute(env.try_into()?, &elf)?;
+            ensure!(
+                predicate.eval(&session_info.journal.bytes),
+                "Predicate evaluation failed; journal does not match requirements"
+            );
+            let cycles = session_cycles(&session_info);
+            let stats =
+                client.boundless_market.query_market_stats(args.market_history_blocks).await?;
+            tracing::debug!(
+                "Pricing the offer for {cycles} cycles from {} recent locks",
+                stats.samples().len()
+            );
+            OfferBuilder::from_preflight(cycles, args.urgency).with_market_stats(stats).build()
+        }
+    };
+
     // Compute the image_id, then upload the ELF.
     let elf_url = client.upload_image(&elf).await?;
     let image_id = B256::from(<[u8; 32]>::from(risc0_zkvm::compute_image_id(&elf)?));
@@ -665,7 +699,7 @@ where
 
     tracing::debug!("Request: {}", serde_json::to_string_pretty(&request)?);
 
-    if !args.no_preflight {
+    if !args.no_preflight && args.yaml_offer.is_some() {
         tracing::info!("Running request preflight");
         let session_info = execute(&request).await?;
         let journal = session_info.journal.bytes;
@@ -787,19 +821,7 @@ where
 
 async fn execute(request: &ProofRequest) -> Result<SessionInfo> {
     let elf = fetch_url(&request.imageUrl).await?;
-    let input = match request.input.inputType {
-        InputType::Inline => GuestEnv::decode(&request.input.data)?.stdin,
-        InputType::Url => {
-            GuestEnv::decode(
-                &fetch_url(
-                    std::str::from_utf8(&request.input.data).context("input url is not utf8")?,
-                )
-                .await?,
-            )?
-            .stdin
-        }
-        _ => bail!("Unsupported input type"),
-    };
+    let input = fetch_input(&request.input).await?.stdin;
     let env = ExecutorEnv::builder().write_slice(&input).build()?;
     default_executor().execute(env, &elf)
 }
@@ -856,7 +878,7 @@ mod tests {
 
         let mut args = MainArgs {
             rpc_url: anvil.endpoint_url(),
-            private_key: ctx.prover_signer.clone(),
+            signer: SignerConfig::private_key(ctx.prover_signer.clone()),
             boundless_market_address: ctx.boundless_market_address,
             set_verifier_address: ctx.set_verifier_address,
             tx_timeout: None,
@@ -889,7 +911,7 @@ mod tests {
 
         let mut args = MainArgs {
             rpc_url: anvil.endpoint_url(),
-            private_key: ctx.customer_signer.clone(),
+            signer: SignerConfig::private_key(ctx.customer_signer.clone()),
             boundless_market_address: ctx.boundless_market_address,
             set_verifier_address: ctx.set_verifier_address,
             tx_timeout: None,
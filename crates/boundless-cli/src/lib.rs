@@ -32,9 +32,10 @@ use url::Url;
 
 use boundless_market::{
     contracts::{
-        AssessorReceipt, EIP721DomainSaltless, Fulfillment as BoundlessFulfillment, InputType,
+        AssessorReceipt, EIP721DomainSaltless, Fulfillment as BoundlessFulfillment, Input,
+        InputType,
     },
-    input::GuestEnv,
+    input::{GuestEnv, InputManifest},
     order_stream_client::Order,
 };
 
@@ -95,6 +96,30 @@ pub async fn fetch_url(url_str: &str) -> Result<Vec<u8>> {
     }
 }
 
+/// Fetches and decodes the input of a request.
+///
+/// Inputs given by URL are fetched first, and inputs encoded as an [InputManifest] are assembled
+/// from their fetched chunks.
+pub async fn fetch_input(input: &Input) -> Result<GuestEnv> {
+    let encoded = match input.inputType {
+        InputType::Inline => input.data.to_vec(),
+        InputType::Url => {
+            fetch_url(std::str::from_utf8(&input.data).context("input url is not utf8")?).await?
+        }
+        _ => bail!("Unsupported input type"),
+    };
+    Ok(match InputManifest::decode(&encoded)? {
+        Some(manifest) => {
+            let mut chunks = Vec::with_capacity(manifest.chunks.len());
+            for chunk in manifest.chunks.iter() {
+                chunks.push(fetch_url(&chunk.url).await?);
+            }
+            manifest.assemble(&chunks, usize::MAX)?
+        }
+        None => GuestEnv::decode(&encoded)?,
+    })
+}
+
 async fn fetch_http(url: &Url) -> Result<Vec<u8>> {
     let response = reqwest::get(url.as_str()).await?;
     let status = response.status();
@@ -221,20 +246,7 @@ impl DefaultProver {
     )> {
         let request = order.request.clone();
         let order_elf = fetch_url(&request.imageUrl).await?;
-        let order_input: Vec<u8> = match request.input.inputType {
-            InputType::Inline => GuestEnv::decode(&request.input.data)?.stdin,
-            InputType::Url => {
-                GuestEnv::decode(
-                    &fetch_url(
-                        std::str::from_utf8(&request.input.data)
-                            .context("input url is not utf8")?,
-                    )
-                    .await?,
-                )?
-                .stdin
-            }
-            _ => bail!("Unsupported input type"),
-        };
+        let order_input = fetch_input(&request.input).await?.stdin;
         let order_receipt =
             self.prove(order_elf.clone(), order_input, vec![], ProverOpts::succinct()).await?;
         let order_journal = order_receipt.journal.bytes.clone();
@@ -25,8 +25,14 @@ const EXCLUDE_CONTRACTS: [&str; 2] = [
 ];
 
 // Contracts to copy bytecode for. Used for deploying contracts in tests.
-const ARTIFACT_TARGET_CONTRACTS: [&str; 5] =
-    ["BoundlessMarket", "HitPoints", "RiscZeroMockVerifier", "RiscZeroSetVerifier", "ERC1967Proxy"];
+const ARTIFACT_TARGET_CONTRACTS: [&str; 6] = [
+    "BoundlessMarket",
+    "HitPoints",
+    "RiscZeroMockVerifier",
+    "RiscZeroSetVerifier",
+    "ERC1967Proxy",
+    "MockSmartContractWallet",
+];
 
 // Output filename for the generated types. The file is placed in the build directory.
 const BOUNDLESS_MARKET_RS: &str = "boundless_market_generated.rs";
@@ -266,6 +272,11 @@ fn get_interfaces(contract: &str) -> &str {
         }
         "ERC1967Proxy" => "constructor(address implementation, bytes memory data) payable {}",
         "HitPoints" => "constructor(address initialOwner) payable {}",
+        "MockSmartContractWallet" => {
+            r#"constructor(bytes memory expectedSignature, address market, address owner) {}
+            function setExpectedSignature(bytes memory expectedSignature) external {}
+            function execute(address target, bytes memory data, uint256 value) external {}"#
+        }
         _ => "",
     }
 }
@@ -0,0 +1,45 @@
+-- Blocks the indexer has seen, used to detect reorgs. Includes every block with indexed events
+-- and the last block of each sync.
+CREATE TABLE IF NOT EXISTS blocks (
+    block_number BIGINT PRIMARY KEY,
+    block_hash TEXT NOT NULL,
+    block_timestamp BIGINT NOT NULL
+);
+
+-- Requests submitted onchain, or locked after being submitted offchain
+CREATE TABLE IF NOT EXISTS requests (
+    id TEXT PRIMARY KEY,
+    client TEXT NOT NULL,
+    request JSONB NOT NULL,
+    client_signature TEXT NOT NULL,
+    submitted_onchain BOOLEAN NOT NULL,
+    block_number BIGINT NOT NULL,
+    tx_hash TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS requests_client_idx ON requests(client);
+
+CREATE TABLE IF NOT EXISTS locks (
+    id TEXT PRIMARY KEY,
+    prover TEXT NOT NULL,
+    price TEXT,
+    block_number BIGINT NOT NULL,
+    tx_hash TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS locks_prover_idx ON locks(prover);
+
+CREATE TABLE IF NOT EXISTS fulfillments (
+    id TEXT PRIMARY KEY,
+    block_number BIGINT NOT NULL,
+    tx_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS slashes (
+    id TEXT PRIMARY KEY,
+    stake_burned TEXT NOT NULL,
+    stake_transferred TEXT NOT NULL,
+    stake_recipient TEXT NOT NULL,
+    block_number BIGINT NOT NULL,
+    tx_hash TEXT NOT NULL
+);
@@ -12,7 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{env, str::FromStr, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
 use alloy::{
     network::{Ethereum, EthereumWallet},
@@ -24,27 +30,30 @@ use alloy::{
         },
         Identity, Provider, ProviderBuilder, RootProvider,
     },
-    signers::{
-        k256::ecdsa::SigningKey,
-        local::{LocalSigner, PrivateKeySigner},
-        Signer,
-    },
+    signers::{local::PrivateKeySigner, Signer},
 };
 use alloy_primitives::{PrimitiveSignature, B256};
 use alloy_sol_types::SolStruct;
 use anyhow::{anyhow, Context, Result};
-use risc0_aggregation::SetInclusionReceipt;
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use risc0_aggregation::{SetInclusionReceipt, SetInclusionReceiptVerifierParameters};
 use risc0_ethereum_contracts::set_verifier::SetVerifierService;
-use risc0_zkvm::{sha::Digest, ReceiptClaim};
+use risc0_zkvm::{
+    sha::{Digest, Impl, Sha256},
+    Journal, ReceiptClaim, VerifierContext,
+};
+use serde::de::DeserializeOwned;
 use url::Url;
 
 use crate::{
     contracts::{
-        boundless_market::{BoundlessMarketService, MarketError},
-        ProofRequest, RequestError,
+        boundless_market::{BoundlessMarketService, MarketError, RequestEvent},
+        ProofRequest, RequestError, TxnErr,
     },
+    input::{InputChunk, InputManifest},
     now_timestamp,
-    order_stream_client::{Client as OrderStreamClient, Order},
+    order_stream_client::{Client as OrderStreamClient, Order, MAX_BATCH_ORDERS},
+    signer::BoundlessSigner,
     storage::{
         storage_provider_from_config, storage_provider_from_env, BuiltinStorageProvider,
         BuiltinStorageProviderError, StorageProvider, StorageProviderConfig,
@@ -53,6 +62,8 @@ use crate::{
 
 // Default bidding start delay (from the current time) in seconds
 const BIDDING_START_DELAY: u64 = 30;
+// Maximum number of concurrent uploads when submitting a batch of requests
+const MAX_CONCURRENT_UPLOADS: usize = 16;
 
 type ProviderWallet = FillProvider<
     JoinFill<
@@ -78,18 +89,95 @@ pub enum ClientError {
     /// Request error
     #[error("RequestError {0}")]
     RequestError(#[from] RequestError),
+    /// Fulfillment verification error
+    #[error("Verification error {0}")]
+    VerificationError(#[from] VerificationError),
     /// General error
     #[error("Error {0}")]
     Error(#[from] anyhow::Error),
 }
 
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+/// Fulfillment verification error
+pub enum VerificationError {
+    /// The journal does not satisfy the predicate of the request.
+    #[error("journal does not satisfy the request predicate")]
+    PredicateNotSatisfied,
+    /// The seal is not a valid proof of the journal for the request image ID.
+    #[error("invalid seal: {0}")]
+    InvalidSeal(String),
+}
+
+/// The fulfillment of a request, verified against the request requirements.
+///
+/// Returned by [Client::verify_fulfillment] and [Client::verify_fulfillment_offchain].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedFulfillment {
+    /// ID of the fulfilled request.
+    pub request_id: U256,
+    /// Image ID of the guest that produced the journal.
+    pub image_id: Digest,
+    /// Journal of the proof, satisfying the request predicate.
+    pub journal: Bytes,
+    /// Set-inclusion seal of the proof.
+    pub seal: Bytes,
+}
+
+impl VerifiedFulfillment {
+    /// Decodes the journal, as committed by the guest with `env::commit`.
+    pub fn decode_journal<T: DeserializeOwned>(&self) -> Result<T, ClientError> {
+        Journal::new(self.journal.to_vec())
+            .decode()
+            .map_err(|err| anyhow!("failed to decode journal: {err}").into())
+    }
+}
+
+/// A proof request to submit in a batch with [Client::submit_requests], along with the image and
+/// input to upload for it.
+#[derive(Clone, Debug)]
+pub struct BatchRequest {
+    /// The proof request.
+    pub request: ProofRequest,
+    /// ELF of the guest to upload and set as the request image.
+    pub elf: Option<Arc<[u8]>>,
+    /// Encoded input to upload and set as the request input.
+    pub input: Option<Vec<u8>>,
+}
+
+impl BatchRequest {
+    /// Creates a batch request for the given proof request, with nothing to upload.
+    pub fn new(request: ProofRequest) -> Self {
+        Self { request, elf: None, input: None }
+    }
+
+    /// Sets the ELF to upload as the request image.
+    ///
+    /// Identical ELFs in a batch are uploaded once, so the same ELF can be shared by all of the
+    /// requests of a batch cheaply.
+    pub fn with_elf(self, elf: impl Into<Arc<[u8]>>) -> Self {
+        Self { elf: Some(elf.into()), ..self }
+    }
+
+    /// Sets the encoded input to upload as the request input.
+    pub fn with_input(self, input: impl Into<Vec<u8>>) -> Self {
+        Self { input: Some(input.into()), ..self }
+    }
+}
+
+impl From<ProofRequest> for BatchRequest {
+    fn from(request: ProofRequest) -> Self {
+        Self::new(request)
+    }
+}
+
 /// Builder for the client
 pub struct ClientBuilder {
     boundless_market_addr: Option<Address>,
     set_verifier_addr: Option<Address>,
     rpc_url: Option<Url>,
     wallet: Option<EthereumWallet>,
-    local_signer: Option<PrivateKeySigner>,
+    local_signer: Option<BoundlessSigner>,
     order_stream_url: Option<Url>,
     storage_config: Option<StorageProviderConfig>,
     tx_timeout: Option<std::time::Duration>,
@@ -162,7 +250,20 @@ impl ClientBuilder {
     pub fn with_private_key(self, private_key: PrivateKeySigner) -> Self {
         Self {
             wallet: Some(EthereumWallet::from(private_key.clone())),
-            local_signer: Some(private_key),
+            local_signer: Some(private_key.into()),
+            ..self
+        }
+    }
+
+    /// Set the signer, used both for the wallet and for signing requests
+    ///
+    /// Accepts a local key, or a signer from a keystore or remote signing service. See
+    /// [crate::signer::SignerConfig].
+    pub fn with_signer(self, signer: impl Into<BoundlessSigner>) -> Self {
+        let signer = signer.into();
+        Self {
+            wallet: Some(EthereumWallet::from(signer.clone())),
+            local_signer: Some(signer),
             ..self
         }
     }
@@ -210,7 +311,7 @@ pub struct Client<P, S> {
     /// Order stream client to submit requests off-chain.
     pub offchain_client: Option<OrderStreamClient>,
     /// Local signer for signing requests.
-    pub local_signer: Option<LocalSigner<SigningKey>>,
+    pub local_signer: Option<BoundlessSigner>,
     /// Bidding start delay with regard to the current time, in seconds.
     pub bidding_start_delay: u64,
 }
@@ -277,8 +378,8 @@ where
     }
 
     /// Set the local signer
-    pub fn with_local_signer(self, local_signer: LocalSigner<SigningKey>) -> Self {
-        Self { local_signer: Some(local_signer), ..self }
+    pub fn with_local_signer(self, local_signer: impl Into<BoundlessSigner>) -> Self {
+        Self { local_signer: Some(local_signer.into()), ..self }
     }
 
     /// Set the bidding start delay, in seconds.
@@ -308,6 +409,26 @@ where
             .map_err(|_| anyhow!("Failed to upload input"))?)
     }
 
+    /// Upload an encoded input to the storage provider in chunks of at most `chunk_size` bytes.
+    ///
+    /// Returns the [InputManifest] referencing the uploaded chunks. Its encoding can be used as
+    /// the request input, either inline or uploaded with [Client::upload_input].
+    pub async fn upload_chunked_input(
+        &self,
+        input: &[u8],
+        chunk_size: usize,
+    ) -> Result<InputManifest, ClientError> {
+        if chunk_size == 0 {
+            return Err(anyhow!("Chunk size must be greater than 0").into());
+        }
+        let mut chunks = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            let url = self.upload_input(chunk).await?;
+            chunks.push(InputChunk::new(url, chunk));
+        }
+        Ok(InputManifest::new(chunks))
+    }
+
     /// Submit a proof request.
     ///
     /// Requires a local signer to be set to sign the request.
@@ -323,7 +444,9 @@ where
 
     /// Submit a proof request.
     ///
-    /// Accepts a signer to sign the request.
+    /// Accepts a signer to sign the request. For a request signed by a smart contract wallet, the
+    /// signer is an owner of the wallet, and the request ID must be set, e.g. with
+    /// [BoundlessMarketService::smart_contract_request_id_from_rand].
     /// If the request ID is not set, a random ID will be generated.
     /// If the bidding start is not set, the current time will be used, plus a delay.
     pub async fn submit_request_with_signer(
@@ -340,7 +463,7 @@ where
             request.id = self.boundless_market.request_id_from_rand().await?;
         };
         let client_address = request.client_address()?;
-        if client_address != signer.address() {
+        if !request.is_smart_contract_signed() && client_address != signer.address() {
             return Err(MarketError::AddressMismatch(client_address, signer.address()))?;
         };
         if request.offer.biddingStart == 0 {
@@ -355,7 +478,9 @@ where
 
     /// Submit a proof request offchain via the order stream service.
     ///
-    /// Accepts a signer to sign the request.
+    /// Accepts a signer to sign the request. For a request signed by a smart contract wallet, the
+    /// signer is an owner of the wallet, and the request ID must be set, e.g. with
+    /// [BoundlessMarketService::smart_contract_request_id_from_rand].
     /// If the request ID is not set, a random ID will be generated.
     /// If the bidding start is not set, the current time plus a delay will be used.
     pub async fn submit_request_offchain_with_signer(
@@ -376,7 +501,7 @@ where
             request.id = self.boundless_market.request_id_from_rand().await?;
         };
         let client_address = request.client_address()?;
-        if client_address != signer.address() {
+        if !request.is_smart_contract_signed() && client_address != signer.address() {
             return Err(MarketError::AddressMismatch(client_address, signer.address()))?;
         };
         if request.offer.biddingStart == 0 {
@@ -413,6 +538,176 @@ where
         self.submit_request_offchain_with_signer(request, signer).await
     }
 
+    /// Submit a batch of proof requests.
+    ///
+    /// Requires a local signer to be set to sign the requests.
+    /// See [Client::submit_requests_with_signer].
+    pub async fn submit_requests<R: Into<BatchRequest>>(
+        &self,
+        requests: impl IntoIterator<Item = R>,
+    ) -> Result<Vec<(U256, u64)>, ClientError>
+    where
+        <S as StorageProvider>::Error: std::fmt::Debug,
+    {
+        let signer = self.local_signer.as_ref().context("Local signer not set")?;
+        self.submit_requests_with_signer(requests, signer).await
+    }
+
+    /// Submit a batch of proof requests.
+    ///
+    /// Accepts a signer to sign the requests.
+    /// Uploads the images and inputs of the requests concurrently, uploading identical images and
+    /// inputs only once.
+    /// Requests without an ID are given random IDs, distinct from each other and from the other
+    /// requests of the batch.
+    /// If the bidding start is not set, the current time will be used, plus a delay.
+    /// The requests are sent in a pipelined sequence of transactions, see
+    /// [BoundlessMarketService::submit_requests].
+    ///
+    /// Returns the request ID and expiration of each request, in the order of the batch.
+    pub async fn submit_requests_with_signer<R: Into<BatchRequest>>(
+        &self,
+        requests: impl IntoIterator<Item = R>,
+        signer: &impl Signer,
+    ) -> Result<Vec<(U256, u64)>, ClientError>
+    where
+        <S as StorageProvider>::Error: std::fmt::Debug,
+    {
+        let requests = self.prepare_requests(requests).await?;
+        let request_ids = self.boundless_market.submit_requests(&requests, signer).await?;
+        Ok(request_ids.into_iter().zip(requests.iter().map(ProofRequest::expires_at)).collect())
+    }
+
+    /// Submit a batch of proof requests offchain via the order stream service.
+    ///
+    /// Requires a local signer to be set to sign the requests.
+    /// See [Client::submit_requests_offchain_with_signer].
+    pub async fn submit_requests_offchain<R: Into<BatchRequest>>(
+        &self,
+        requests: impl IntoIterator<Item = R>,
+    ) -> Result<Vec<(U256, u64)>, ClientError>
+    where
+        <S as StorageProvider>::Error: std::fmt::Debug,
+    {
+        let signer = self.local_signer.as_ref().context("Local signer not set")?;
+        self.submit_requests_offchain_with_signer(requests, signer).await
+    }
+
+    /// Submit a batch of proof requests offchain via the order stream service.
+    ///
+    /// Accepts a signer to sign the requests.
+    /// The images, inputs, IDs and bidding starts of the requests are set as in
+    /// [Client::submit_requests_with_signer].
+    /// The requests are sent in bulk, in calls of at most [MAX_BATCH_ORDERS] requests.
+    ///
+    /// Returns the request ID and expiration of each request, in the order of the batch.
+    pub async fn submit_requests_offchain_with_signer<R: Into<BatchRequest>>(
+        &self,
+        requests: impl IntoIterator<Item = R>,
+        signer: &impl Signer,
+    ) -> Result<Vec<(U256, u64)>, ClientError>
+    where
+        <S as StorageProvider>::Error: std::fmt::Debug,
+    {
+        let offchain_client = self
+            .offchain_client
+            .as_ref()
+            .context("Order stream client not available. Please provide an order stream URL")?;
+        let requests = self.prepare_requests(requests).await?;
+        for request in &requests {
+            let client_address = request.client_address()?;
+            if !request.is_smart_contract_signed() && client_address != signer.address() {
+                return Err(MarketError::AddressMismatch(client_address, signer.address()))?;
+            };
+        }
+        // Ensure each client balance is sufficient to cover all its requests
+        let mut total_max_prices: HashMap<Address, U256> = HashMap::new();
+        for request in &requests {
+            let total = total_max_prices.entry(request.client_address()?).or_default();
+            *total = total.saturating_add(request.offer.maxPrice);
+        }
+        for (client_address, total_max_price) in total_max_prices {
+            let balance = self.boundless_market.balance_of(client_address).await?;
+            if balance < total_max_price {
+                return Err(ClientError::Error(anyhow!(
+                    "Insufficient balance of {client_address} to cover requests: {balance} < {total_max_price}.\nMake sure to top up your balance by depositing on the Boundless Market."
+                )));
+            }
+        }
+
+        let mut submitted = Vec::with_capacity(requests.len());
+        for batch in requests.chunks(MAX_BATCH_ORDERS) {
+            let orders = offchain_client.submit_requests(batch, signer).await?;
+            submitted.extend(
+                orders.into_iter().map(|order| (order.request.id, order.request.expires_at())),
+            );
+        }
+        Ok(submitted)
+    }
+
+    /// Uploads the images and inputs of the requests, and sets their IDs and bidding starts.
+    async fn prepare_requests<R: Into<BatchRequest>>(
+        &self,
+        requests: impl IntoIterator<Item = R>,
+    ) -> Result<Vec<ProofRequest>, ClientError>
+    where
+        <S as StorageProvider>::Error: std::fmt::Debug,
+    {
+        let batch: Vec<BatchRequest> = requests.into_iter().map(Into::into).collect();
+
+        // Upload each distinct image and input once
+        let mut images = HashMap::new();
+        let mut inputs = HashMap::new();
+        for request in &batch {
+            if let Some(elf) = &request.elf {
+                images.entry(*Impl::hash_bytes(elf)).or_insert(elf);
+            }
+            if let Some(input) = &request.input {
+                inputs.entry(*Impl::hash_bytes(input)).or_insert(input);
+            }
+        }
+        let image_urls: HashMap<Digest, Url> = stream::iter(images)
+            .map(|(digest, elf)| async move {
+                Ok::<_, ClientError>((digest, self.upload_image(elf).await?))
+            })
+            .buffer_unordered(MAX_CONCURRENT_UPLOADS)
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+        let input_urls: HashMap<Digest, Url> = stream::iter(inputs)
+            .map(|(digest, input)| async move {
+                Ok::<_, ClientError>((digest, self.upload_input(input).await?))
+            })
+            .buffer_unordered(MAX_CONCURRENT_UPLOADS)
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+
+        let mut ids: HashSet<U256> =
+            batch.iter().map(|request| request.request.id).filter(|id| *id != U256::ZERO).collect();
+        let mut requests = Vec::with_capacity(batch.len());
+        for BatchRequest { mut request, elf, input } in batch {
+            if let Some(elf) = elf {
+                request.imageUrl = image_urls[&*Impl::hash_bytes(&elf)].to_string();
+            }
+            if let Some(input) = input {
+                request.input = input_urls[&*Impl::hash_bytes(&input)].clone().into();
+            }
+            if request.id == U256::ZERO {
+                request.id = loop {
+                    let id = self.boundless_market.request_id_from_rand().await?;
+                    if ids.insert(id) {
+                        break id;
+                    }
+                };
+            }
+            if request.offer.biddingStart == 0 {
+                request.offer.biddingStart = now_timestamp() + self.bidding_start_delay
+            };
+            request.validate()?;
+            requests.push(request);
+        }
+        Ok(requests)
+    }
+
     /// Wait for a request to be fulfilled.
     ///
     /// The check interval is the time between each check for fulfillment.
@@ -429,6 +724,29 @@ where
             .await?)
     }
 
+    /// Watch the status transitions of a request, driven by market events.
+    ///
+    /// See [BoundlessMarketService::watch_request].
+    pub fn watch_request(
+        &self,
+        request_id: U256,
+    ) -> impl Stream<Item = Result<RequestEvent, MarketError>> + '_ {
+        self.boundless_market.watch_request(request_id)
+    }
+
+    /// Watch the status transitions of a request, reporting its expiry at `expires_at`.
+    ///
+    /// The poll interval is only used when the RPC provider does not support subscriptions.
+    /// See [BoundlessMarketService::watch_request_with].
+    pub fn watch_request_with(
+        &self,
+        request_id: U256,
+        expires_at: Option<u64>,
+        poll_interval: std::time::Duration,
+    ) -> impl Stream<Item = Result<RequestEvent, MarketError>> + '_ {
+        self.boundless_market.watch_request_with(request_id, expires_at, poll_interval)
+    }
+
     /// Get the [SetInclusionReceipt] for a request.
     ///
     /// Example:
@@ -458,6 +776,88 @@ where
         Ok((journal, receipt))
     }
 
+    /// Verify the fulfillment of a request with the set verifier contract.
+    ///
+    /// Checks that the journal satisfies the request predicate, and that the seal is a valid
+    /// proof of the journal for the request image ID, by calling the `verify` function of the
+    /// set verifier contract. The Merkle root of the seal must have been submitted to the
+    /// contract, which is the case for any request fulfilled on the market.
+    ///
+    /// Example:
+    /// ```
+    /// use anyhow::Result;
+    /// use alloy::primitives::U256;
+    /// use boundless_market::client::ClientBuilder;
+    ///
+    /// async fn fetch_verified_journal(request_id: U256) -> Result<Vec<u8>> {
+    ///     let client = ClientBuilder::default().build().await?;
+    ///     let (request, _) =
+    ///         client.boundless_market.get_submitted_request(request_id, None).await?;
+    ///     let (journal, seal) =
+    ///         client.boundless_market.get_request_fulfillment(request_id).await?;
+    ///     let fulfillment = client.verify_fulfillment(&request, journal, seal).await?;
+    ///     Ok(fulfillment.journal.to_vec())
+    /// }
+    /// ```
+    pub async fn verify_fulfillment(
+        &self,
+        request: &ProofRequest,
+        journal: Bytes,
+        seal: Bytes,
+    ) -> Result<VerifiedFulfillment, ClientError> {
+        check_predicate(request, &journal)?;
+        let journal_digest = <[u8; 32]>::from(*Impl::hash_bytes(&journal));
+        let verification = self
+            .set_verifier
+            .instance()
+            .verify(seal.clone(), request.requirements.imageId, journal_digest.into())
+            .call()
+            .await;
+        if let Err(err) = verification {
+            return match TxnErr::from(err) {
+                TxnErr::SetVerifierErr(err) => {
+                    Err(VerificationError::InvalidSeal(format!("{err:?}")).into())
+                }
+                err => Err(anyhow!("failed to call the set verifier: {err}").into()),
+            };
+        }
+        Ok(VerifiedFulfillment {
+            request_id: request.id,
+            image_id: Digest::from(request.requirements.imageId.0),
+            journal,
+            seal,
+        })
+    }
+
+    /// Verify the fulfillment of a request locally.
+    ///
+    /// Checks that the journal satisfies the request predicate, and that the seal is a valid
+    /// proof of the journal for the request image ID. The proof of the Merkle root of the seal is
+    /// fetched from the events of the set verifier contract, and verified locally against the
+    /// given set builder image ID, such that only the contract events are trusted, not its
+    /// execution.
+    pub async fn verify_fulfillment_offchain(
+        &self,
+        request: &ProofRequest,
+        journal: Bytes,
+        seal: Bytes,
+        set_builder_image_id: impl Into<Digest>,
+    ) -> Result<VerifiedFulfillment, ClientError> {
+        check_predicate(request, &journal)?;
+        let image_id = Digest::from(request.requirements.imageId.0);
+        let claim = ReceiptClaim::ok(image_id, journal.to_vec());
+        let receipt = self
+            .set_verifier
+            .fetch_receipt_with_claim(seal.clone(), claim, journal.to_vec())
+            .await?;
+        let verifier_parameters =
+            SetInclusionReceiptVerifierParameters { image_id: set_builder_image_id.into() };
+        receipt
+            .verify_integrity_with_context(&VerifierContext::default(), verifier_parameters, None)
+            .map_err(|err| VerificationError::InvalidSeal(format!("{err:?}")))?;
+        Ok(VerifiedFulfillment { request_id: request.id, image_id, journal, seal })
+    }
+
     /// Fetch an order as a proof request and signature pair.
     ///
     /// If the request is not found in the boundless market, it will be fetched from the order stream service.
@@ -493,6 +893,14 @@ where
     }
 }
 
+/// Checks that the journal satisfies the predicate of the request.
+fn check_predicate(request: &ProofRequest, journal: &[u8]) -> Result<(), VerificationError> {
+    if !request.requirements.predicate.eval(journal) {
+        return Err(VerificationError::PredicateNotSatisfied);
+    }
+    Ok(())
+}
+
 impl Client<ProviderWallet, BuiltinStorageProvider> {
     /// Create a new client from environment variables
     ///
@@ -547,7 +955,7 @@ impl Client<ProviderWallet, BuiltinStorageProvider> {
             set_verifier,
             storage_provider,
             offchain_client,
-            local_signer: Some(private_key),
+            local_signer: Some(private_key.into()),
             bidding_start_delay: BIDDING_START_DELAY,
         })
     }
@@ -583,3 +991,72 @@ impl Client<ProviderWallet, BuiltinStorageProvider> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        contracts::{
+            boundless_market::tests::{mock_singleton, new_request},
+            test_utils::create_test_ctx,
+            Predicate,
+        },
+        storage::TempFileStorageProvider,
+    };
+    use alloy::{node_bindings::Anvil, sol_types::eip712_domain};
+    use guest_assessor::ASSESSOR_GUEST_ID;
+    use guest_set_builder::SET_BUILDER_ID;
+    use guest_util::ECHO_ID;
+    use tracing_test::traced_test;
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_verify_fulfillment() {
+        // Setup anvil
+        let anvil = Anvil::new().spawn();
+
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+
+        let eip712_domain = eip712_domain! {
+            name: "IBoundlessMarket",
+            version: "1",
+            chain_id: anvil.chain_id(),
+            verifying_contract: *ctx.customer_market.instance().address(),
+        };
+
+        // publish the committed root of a mock fulfillment
+        let request = new_request(1, &ctx).await;
+        let (root, set_verifier_seal, fulfillment, _) =
+            mock_singleton(&request, eip712_domain, ctx.prover_signer.address());
+        ctx.set_verifier.submit_merkle_root(root, set_verifier_seal).await.unwrap();
+        let (journal, seal) = (fulfillment.journal, fulfillment.seal);
+
+        let client = Client::<_, TempFileStorageProvider>::new(
+            ctx.customer_market.clone(),
+            ctx.set_verifier.clone(),
+        );
+
+        // the fulfillment is verified against the request and the set verifier
+        let verified =
+            client.verify_fulfillment(&request, journal.clone(), seal.clone()).await.unwrap();
+        assert_eq!(verified.request_id, request.id);
+        assert_eq!(verified.image_id, Digest::from(ECHO_ID));
+        assert_eq!(verified.journal, journal);
+
+        // the journal must satisfy the request predicate
+        let mut mismatched_request = request.clone();
+        mismatched_request.requirements.predicate = Predicate::prefix_match(vec![0x42]);
+        let err = client
+            .verify_fulfillment(&mismatched_request, journal.clone(), seal.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ClientError::VerificationError(VerificationError::PredicateNotSatisfied)
+        ));
+
+        // the seal must prove the journal
+        let tampered_journal = Bytes::from(vec![0x41, 0x41, 0x41, 0x42]);
+        assert!(client.verify_fulfillment(&request, tampered_journal, seal).await.is_err());
+    }
+}
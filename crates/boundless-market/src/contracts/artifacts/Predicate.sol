@@ -13,9 +13,16 @@ struct Predicate {
     bytes data;
 }
 
+/// @notice Type of a predicate, encoded as a uint8.
+/// @dev New predicate types must only be appended to the end of this enum, such that the encoding,
+/// and so the EIP-712 digest, of requests using the existing predicate types never changes.
 enum PredicateType {
     DigestMatch,
-    PrefixMatch
+    PrefixMatch,
+    // Since v2
+    AbiFieldMatch,
+    MaskMatch,
+    AnyDigestMatch
 }
 
 library PredicateLibrary {
@@ -36,6 +43,34 @@ library PredicateLibrary {
         return Predicate({predicateType: PredicateType.PrefixMatch, data: prefix});
     }
 
+    /// @notice Creates an ABI field match predicate.
+    /// @param index The index of the static field of the ABI-encoded journal to match.
+    /// @param value The value the field must be equal to, as an ABI-encoded 32-byte word.
+    /// @return A Predicate struct with type AbiFieldMatch and the encoded index and value.
+    function createAbiFieldMatchPredicate(uint256 index, bytes32 value) internal pure returns (Predicate memory) {
+        return Predicate({predicateType: PredicateType.AbiFieldMatch, data: abi.encode(index, value)});
+    }
+
+    /// @notice Creates a mask match predicate.
+    /// @param offset The offset of the range of the journal to match.
+    /// @param mask The mask of the bits of the range to compare.
+    /// @param value The value the masked range must be equal to. Must have the same length as the mask.
+    /// @return A Predicate struct with type MaskMatch and the encoded offset, mask and value.
+    function createMaskMatchPredicate(uint256 offset, bytes memory mask, bytes memory value)
+        internal
+        pure
+        returns (Predicate memory)
+    {
+        return Predicate({predicateType: PredicateType.MaskMatch, data: abi.encode(offset, mask, value)});
+    }
+
+    /// @notice Creates an any digest match predicate.
+    /// @param hashes The hashes, any of which the journal digest must match.
+    /// @return A Predicate struct with type AnyDigestMatch and the packed hashes.
+    function createAnyDigestMatchPredicate(bytes32[] memory hashes) internal pure returns (Predicate memory) {
+        return Predicate({predicateType: PredicateType.AnyDigestMatch, data: abi.encodePacked(hashes)});
+    }
+
     /// @notice Evaluates the predicate against the given journal and journal digest.
     /// @param predicate The predicate to evaluate.
     /// @param journal The journal to evaluate against.
@@ -50,6 +85,15 @@ library PredicateLibrary {
             return bytes32(predicate.data) == journalDigest;
         } else if (predicate.predicateType == PredicateType.PrefixMatch) {
             return startsWith(journal, predicate.data);
+        } else if (predicate.predicateType == PredicateType.AbiFieldMatch) {
+            (uint256 index, bytes32 value) = abi.decode(predicate.data, (uint256, bytes32));
+            return fieldEquals(journal, index, value);
+        } else if (predicate.predicateType == PredicateType.MaskMatch) {
+            (uint256 offset, bytes memory mask, bytes memory value) =
+                abi.decode(predicate.data, (uint256, bytes, bytes));
+            return maskedEquals(journal, offset, mask, value);
+        } else if (predicate.predicateType == PredicateType.AnyDigestMatch) {
+            return containsDigest(predicate.data, journalDigest);
         } else {
             revert("Unreachable code");
         }
@@ -75,6 +119,64 @@ library PredicateLibrary {
         return keccak256(slice) == keccak256(prefix);
     }
 
+    /// @notice Checks if the static field at the given index of the ABI-encoded journal equals the value.
+    /// @param journal The journal to check.
+    /// @param index The index of the field.
+    /// @param value The value to check for.
+    /// @return True if the journal has a field at the index equal to the value, false otherwise.
+    function fieldEquals(bytes memory journal, uint256 index, bytes32 value) internal pure returns (bool) {
+        if (index >= journal.length / 32) {
+            return false;
+        }
+        bytes32 field;
+        assembly {
+            field := mload(add(add(journal, 0x20), mul(index, 0x20)))
+        }
+        return field == value;
+    }
+
+    /// @notice Checks if the range of the journal at the given offset equals the value, on the bits of the mask.
+    /// @param journal The journal to check.
+    /// @param offset The offset of the range.
+    /// @param mask The mask of the bits to compare.
+    /// @param value The value to check for.
+    /// @return True if the masked range of the journal equals the masked value, false otherwise.
+    function maskedEquals(bytes memory journal, uint256 offset, bytes memory mask, bytes memory value)
+        internal
+        pure
+        returns (bool)
+    {
+        if (mask.length != value.length || offset > journal.length || journal.length - offset < mask.length) {
+            return false;
+        }
+        for (uint256 i = 0; i < mask.length; i++) {
+            if (journal[offset + i] & mask[i] != value[i] & mask[i]) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// @notice Checks if the packed hashes contain the given digest.
+    /// @param hashes The packed 32-byte hashes.
+    /// @param journalDigest The digest to check for.
+    /// @return True if any of the hashes equals the digest, false otherwise.
+    function containsDigest(bytes memory hashes, bytes32 journalDigest) internal pure returns (bool) {
+        if (hashes.length % 32 != 0) {
+            return false;
+        }
+        for (uint256 i = 0; i < hashes.length; i += 32) {
+            bytes32 hash;
+            assembly {
+                hash := mload(add(add(hashes, 0x20), i))
+            }
+            if (hash == journalDigest) {
+                return true;
+            }
+        }
+        return false;
+    }
+
     /// @notice Computes the EIP-712 digest for the given predicate.
     /// @param predicate The predicate to compute the digest for.
     /// @return The EIP-712 digest of the predicate.
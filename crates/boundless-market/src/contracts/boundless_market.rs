@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::{
+    collections::{hash_map::Entry, HashMap},
     fmt::Debug,
     sync::atomic::{AtomicU64, Ordering},
     time::Duration,
@@ -20,19 +21,24 @@ use std::{
 
 use alloy::{
     consensus::{BlockHeader, Transaction},
-    eips::BlockNumberOrTag,
+    eips::{BlockId, BlockNumberOrTag},
     network::Ethereum,
     primitives::{Address, Bytes, B256, U256},
-    providers::Provider,
-    rpc::types::{BlockTransactionsKind, Log, TransactionReceipt},
+    providers::{PendingTransactionBuilder, Provider},
+    rpc::types::{BlockTransactionsKind, Filter, Log, TransactionReceipt},
     signers::Signer,
 };
 use alloy_sol_types::{SolCall, SolEvent};
 use anyhow::{anyhow, Context, Result};
+use async_stream::try_stream;
+use futures_util::{future::try_join_all, Stream, StreamExt};
 use risc0_ethereum_contracts::event_query::EventQueryConfig;
 use thiserror::Error;
 
-use crate::contracts::token::{IERC20Permit, IHitPoints::IHitPointsErrors, Permit, IERC20};
+use crate::{
+    contracts::token::{IERC20Permit, IHitPoints::IHitPointsErrors, Permit, IERC20},
+    pricing::{LockSample, MarketStats},
+};
 
 use super::{
     eip712_domain, AssessorReceipt, EIP721DomainSaltless, Fulfillment,
@@ -40,6 +46,10 @@ use super::{
     Offer, ProofRequest, ProofStatus, RequestError, RequestId, TxnErr, TXN_CONFIRM_TIMEOUT,
 };
 
+/// Interval at which [BoundlessMarketService::watch_request] polls for new logs, when the
+/// provider does not support subscriptions.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Boundless market errors.
 #[derive(Error, Debug)]
 pub enum MarketError {
@@ -71,10 +81,18 @@ pub enum MarketError {
     #[error("Request not found in event logs 0x{0:x}")]
     RequestNotFound(U256),
 
+    /// Lock not found.
+    #[error("Lock not found for request in event logs 0x{0:x}")]
+    LockNotFound(U256),
+
     /// Lock request reverted, possibly outbid.
     #[error("Lock request reverted, possibly outbid: txn_hash: {0}")]
     LockRevert(B256),
 
+    /// Lock request simulation reverted against the pending block.
+    #[error("Lock request simulation reverted: {0}")]
+    LockSimulationRevert(TxnErr),
+
     /// General market error.
     #[error("Market error: {0}")]
     Error(#[from] anyhow::Error),
@@ -91,6 +109,68 @@ impl From<alloy::contract::Error> for MarketError {
     }
 }
 
+/// Status transition of a request, reported by [BoundlessMarketService::watch_request].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RequestEvent {
+    /// The request was submitted onchain.
+    ///
+    /// Also reported as the current status of a request that is not locked, fulfilled or slashed
+    /// yet, which includes requests submitted offchain.
+    Submitted,
+    /// The request was locked by a prover.
+    Locked {
+        /// Address of the prover that locked the request.
+        prover: Address,
+        /// Price of the request at the lock, if the lock transaction could be decoded.
+        price: Option<U256>,
+    },
+    /// The request was fulfilled.
+    Fulfilled,
+    /// The prover that locked the request was slashed.
+    Slashed {
+        /// Amount of stake burned.
+        stake_burned: U256,
+        /// Amount of stake transferred to the recipient.
+        stake_transferred: U256,
+        /// Recipient of the transferred stake, the fulfilling prover or the market.
+        stake_recipient: Address,
+    },
+    /// The request expired without being fulfilled.
+    Expired,
+}
+
+/// Status of a watched request, used to report each transition once.
+#[derive(Default)]
+struct WatchState {
+    submitted: bool,
+    locked: bool,
+    fulfilled: bool,
+    slashed: bool,
+    expired: bool,
+}
+
+impl WatchState {
+    /// Applies the event, returning false if it was already reported.
+    fn apply(&mut self, event: &RequestEvent) -> bool {
+        let flag = match event {
+            RequestEvent::Submitted => &mut self.submitted,
+            RequestEvent::Locked { .. } => &mut self.locked,
+            RequestEvent::Fulfilled => &mut self.fulfilled,
+            RequestEvent::Slashed { .. } => &mut self.slashed,
+            RequestEvent::Expired => &mut self.expired,
+        };
+        !std::mem::replace(flag, true)
+    }
+
+    /// Whether no further transition can happen.
+    ///
+    /// An expired request can still be slashed if it was locked.
+    fn done(&self) -> bool {
+        self.fulfilled || self.slashed || (self.expired && !self.locked)
+    }
+}
+
 /// Proof market service.
 pub struct BoundlessMarketService<P> {
     instance: IBoundlessMarketInstance<(), P, Ethereum>,
@@ -267,7 +347,7 @@ impl<P: Provider> BoundlessMarketService<P> {
     ) -> Result<U256, MarketError> {
         tracing::debug!("calling submitRequest({:x?})", request);
         let client_address = request.client_address()?;
-        if client_address != signer.address() {
+        if !request.is_smart_contract_signed() && client_address != signer.address() {
             return Err(MarketError::AddressMismatch(client_address, signer.address()));
         };
         let chain_id = self.get_chain_id().await.context("failed to get chain ID")?;
@@ -297,11 +377,18 @@ impl<P: Provider> BoundlessMarketService<P> {
     /// Submit a request such that it is publicly available for provers to evaluate and bid
     /// on. Deposits funds to the client account if there are not enough to cover the max price on
     /// the offer.
+    ///
+    /// Requests signed by a smart contract wallet are signed by an owner of the wallet, and paid
+    /// from the balance of the wallet, which must be funded beforehand.
     pub async fn submit_request(
         &self,
         request: &ProofRequest,
         signer: &impl Signer,
     ) -> Result<U256, MarketError> {
+        if request.is_smart_contract_signed() {
+            // Funds sent with the transaction are deposited to the sender, not to the wallet.
+            return self.submit_request_with_value(request, signer, U256::ZERO).await;
+        }
         let balance = self
             .balance_of(signer.address())
             .await
@@ -311,6 +398,76 @@ impl<P: Provider> BoundlessMarketService<P> {
         self.submit_request_with_value(request, signer, value).await
     }
 
+    /// Submit a batch of requests such that they are publicly available for provers to evaluate
+    /// and bid on.
+    ///
+    /// The market has no batch submission function, so the requests are submitted as a pipelined
+    /// sequence of transactions with consecutive nonces, without waiting for each transaction to
+    /// be confirmed before sending the next one. Deposits funds to the client account with the
+    /// first transaction if there are not enough to cover the sum of the max prices of the offers.
+    ///
+    /// Returns the IDs of the submitted requests, in the order of the batch.
+    pub async fn submit_requests(
+        &self,
+        requests: &[ProofRequest],
+        signer: &impl Signer,
+    ) -> Result<Vec<U256>, MarketError> {
+        if requests.is_empty() {
+            return Ok(vec![]);
+        }
+        for request in requests {
+            let client_address = request.client_address()?;
+            if !request.is_smart_contract_signed() && client_address != signer.address() {
+                return Err(MarketError::AddressMismatch(client_address, signer.address()));
+            };
+        }
+        let chain_id = self.get_chain_id().await.context("failed to get chain ID")?;
+        let balance = self
+            .balance_of(signer.address())
+            .await
+            .context("failed to get whether the client balance can cover the offers max price")?;
+        // Requests signed by a smart contract are paid from the balance of the wallet
+        let total_max_price = requests
+            .iter()
+            .filter(|request| !request.is_smart_contract_signed())
+            .fold(U256::ZERO, |total, request| total.saturating_add(request.offer.maxPrice));
+        let mut value = total_max_price.saturating_sub(balance);
+        let nonce = self
+            .instance
+            .provider()
+            .get_transaction_count(self.caller)
+            .pending()
+            .await
+            .context(format!("Failed to get EOA nonce for {:?}", self.caller))?;
+
+        let mut pending_txs = Vec::with_capacity(requests.len());
+        for (i, request) in requests.iter().enumerate() {
+            tracing::debug!("calling submitRequest({:x?})", request);
+            let client_sig = request
+                .sign_request(signer, *self.instance.address(), chain_id)
+                .await
+                .context("failed to sign request")?;
+            let call = self
+                .instance
+                .submitRequest(request.clone(), client_sig.as_bytes().into())
+                .from(self.caller)
+                .value(std::mem::take(&mut value))
+                .nonce(nonce + i as u64);
+            let pending_tx = call.send().await?;
+            tracing::debug!("broadcasting tx {}", pending_tx.tx_hash());
+            pending_txs.push(pending_tx.with_timeout(Some(self.timeout)).get_receipt());
+        }
+
+        let receipts = try_join_all(pending_txs).await.context("failed to confirm tx")?;
+        receipts
+            .iter()
+            .map(|receipt| -> Result<U256, MarketError> {
+                let log = extract_tx_log::<IBoundlessMarket::RequestSubmitted>(receipt)?;
+                Ok(U256::from(log.inner.data.requestId))
+            })
+            .collect()
+    }
+
     /// Lock the request to the prover, giving them exclusive rights to be paid to
     /// fulfill this request, and also making them subject to slashing penalties if they fail to
     /// deliver. At this point, the price for fulfillment is also set, based on the reverse Dutch
@@ -330,6 +487,95 @@ impl<P: Provider> BoundlessMarketService<P> {
             return Err(MarketError::Error(anyhow!("request is already locked")));
         }
 
+        self.send_lock_request(request, client_sig, priority_gas).await
+    }
+
+    /// Broadcast a lock transaction for the request with the given nonce and gas limit, without
+    /// waiting for it to be confirmed.
+    ///
+    /// Allows several lock transactions from the same prover to be in flight at once, with the
+    /// caller responsible for assigning consecutive nonces. There is no `requestIsLocked`
+    /// pre-check, so a lost race reverts on-chain with [MarketError::LockRevert]. Callers should
+    /// run [Self::simulate_lock_request] first, and confirm the returned transaction with
+    /// [Self::confirm_lock_request]. See [Self::lock_request].
+    ///
+    /// An error means the transaction was not broadcast, so its nonce is still unused.
+    pub async fn broadcast_lock_request(
+        &self,
+        request: &ProofRequest,
+        client_sig: &Bytes,
+        priority_gas: Option<u64>,
+        nonce: u64,
+        gas_limit: u64,
+    ) -> Result<PendingTransactionBuilder<Ethereum>, MarketError> {
+        self.broadcast_lock(request, client_sig, priority_gas, Some((nonce, gas_limit))).await
+    }
+
+    /// Wait for a lock transaction sent with [Self::broadcast_lock_request] to be confirmed,
+    /// returning the block number it was included in.
+    pub async fn confirm_lock_request(
+        &self,
+        request_id: U256,
+        pending_tx: PendingTransactionBuilder<Ethereum>,
+    ) -> Result<u64, MarketError> {
+        let receipt = pending_tx
+            .with_timeout(Some(self.timeout))
+            .get_receipt()
+            .await
+            .context("failed to confirm tx")?;
+
+        if !receipt.status() {
+            // TODO: Get + print revertReason
+            return Err(MarketError::LockRevert(receipt.transaction_hash));
+        }
+
+        tracing::info!("Registered request {:x}: {}", request_id, receipt.transaction_hash);
+
+        self.check_stake_balance().await?;
+
+        Ok(receipt.block_number.context("TXN Receipt missing block number")?)
+    }
+
+    /// Simulate locking the request against the pending block, returning the estimated gas of
+    /// the lock transaction.
+    ///
+    /// Returns [MarketError::LockSimulationRevert] if the lock transaction would revert, e.g.
+    /// because the request was already locked by another prover.
+    pub async fn simulate_lock_request(
+        &self,
+        request: &ProofRequest,
+        client_sig: &Bytes,
+    ) -> Result<u64, MarketError> {
+        tracing::debug!("Simulating lockRequest({:x})", request.id);
+        let gas = self
+            .instance
+            .lockRequest(request.clone(), client_sig.clone())
+            .from(self.caller)
+            .block(BlockId::pending())
+            .estimate_gas()
+            .await
+            .map_err(|err| MarketError::LockSimulationRevert(err.into()))?;
+
+        Ok(gas)
+    }
+
+    async fn send_lock_request(
+        &self,
+        request: &ProofRequest,
+        client_sig: &Bytes,
+        priority_gas: Option<u64>,
+    ) -> Result<u64, MarketError> {
+        let pending_tx = self.broadcast_lock(request, client_sig, priority_gas, None).await?;
+        self.confirm_lock_request(U256::from(request.id), pending_tx).await
+    }
+
+    async fn broadcast_lock(
+        &self,
+        request: &ProofRequest,
+        client_sig: &Bytes,
+        priority_gas: Option<u64>,
+        nonce_and_gas: Option<(u64, u64)>,
+    ) -> Result<PendingTransactionBuilder<Ethereum>, MarketError> {
         tracing::debug!("Calling lockRequest({:x?}, {:x?})", request, client_sig);
 
         let mut call =
@@ -348,28 +594,17 @@ impl<P: Provider> BoundlessMarketService<P> {
                 .max_priority_fee_per_gas(priority_fee.max_priority_fee_per_gas + gas as u128);
         }
 
+        if let Some((nonce, gas_limit)) = nonce_and_gas {
+            call = call.nonce(nonce).gas(gas_limit);
+        }
+
         tracing::debug!("Sending tx {}", format!("{:?}", call));
 
         let pending_tx = call.send().await?;
 
         tracing::debug!("Broadcasting tx {}", pending_tx.tx_hash());
 
-        let receipt = pending_tx
-            .with_timeout(Some(self.timeout))
-            .get_receipt()
-            .await
-            .context("failed to confirm tx")?;
-
-        if !receipt.status() {
-            // TODO: Get + print revertReason
-            return Err(MarketError::LockRevert(receipt.transaction_hash));
-        }
-
-        tracing::info!("Registered request {:x}: {}", request.id, receipt.transaction_hash);
-
-        self.check_stake_balance().await?;
-
-        Ok(receipt.block_number.context("TXN Receipt missing block number")?)
+        Ok(pending_tx)
     }
 
     /// Lock the request to the prover, giving them exclusive rights to be paid to
@@ -956,6 +1191,99 @@ impl<P: Provider> BoundlessMarketService<P> {
         Err(MarketError::RequestNotFound(request_id))
     }
 
+    /// Query the RequestLocked event based on request ID and block options.
+    ///
+    /// Returns the address of the prover that locked the request and the block number of the
+    /// lock. The search follows the same block range iteration as the other event queries.
+    pub async fn query_request_locked_event(
+        &self,
+        request_id: U256,
+        lower_bound: Option<u64>,
+        upper_bound: Option<u64>,
+    ) -> Result<(Address, u64), MarketError> {
+        let (event, log) =
+            self.query_request_locked_log(request_id, lower_bound, upper_bound).await?;
+        let block_number = log.block_number.context("block number is none")?;
+        Ok((event.prover, block_number))
+    }
+
+    async fn query_request_locked_log(
+        &self,
+        request_id: U256,
+        lower_bound: Option<u64>,
+        upper_bound: Option<u64>,
+    ) -> Result<(IBoundlessMarket::RequestLocked, Log), MarketError> {
+        let mut upper_block = upper_bound.unwrap_or(self.get_latest_block_number().await?);
+        let start_block = lower_bound.unwrap_or(upper_block.saturating_sub(
+            self.event_query_config.block_range * self.event_query_config.max_iterations,
+        ));
+
+        // Loop to progressively search through blocks
+        for _ in 0..self.event_query_config.max_iterations {
+            // If the current end block is less than or equal to the starting block, stop searching
+            if upper_block <= start_block {
+                break;
+            }
+
+            // Calculate the block range to query: from [lower_block] to [upper_block]
+            let lower_block = upper_block.saturating_sub(self.event_query_config.block_range);
+
+            // Set up the event filter for the specified block range
+            let mut event_filter = self.instance.RequestLocked_filter();
+            event_filter.filter = event_filter
+                .filter
+                .topic1(request_id)
+                .from_block(lower_block)
+                .to_block(upper_block);
+
+            // Query the logs for the event
+            let logs = event_filter.query().await?;
+
+            if let Some(log) = logs.into_iter().next() {
+                return Ok(log);
+            }
+
+            // Move the upper_block down for the next iteration
+            upper_block = lower_block.saturating_sub(1);
+        }
+
+        // Return error if no logs are found after all iterations
+        Err(MarketError::LockNotFound(request_id))
+    }
+
+    /// Query the ProverSlashed event based on request ID, following the same block range
+    /// iteration as the other event queries.
+    pub async fn query_prover_slashed_event(
+        &self,
+        request_id: U256,
+    ) -> Result<IBoundlessMarket::ProverSlashed, MarketError> {
+        let mut upper_block = self.get_latest_block_number().await?;
+        let start_block = upper_block.saturating_sub(
+            self.event_query_config.block_range * self.event_query_config.max_iterations,
+        );
+
+        for _ in 0..self.event_query_config.max_iterations {
+            if upper_block <= start_block {
+                break;
+            }
+            let lower_block = upper_block.saturating_sub(self.event_query_config.block_range);
+
+            let mut event_filter = self.instance.ProverSlashed_filter();
+            event_filter.filter = event_filter
+                .filter
+                .topic1(request_id)
+                .from_block(lower_block)
+                .to_block(upper_block);
+
+            if let Some((event, _)) = event_filter.query().await?.into_iter().next() {
+                return Ok(event);
+            }
+            upper_block = lower_block.saturating_sub(1);
+        }
+
+        Err(anyhow!("Slash not found for request in event logs 0x{request_id:x}").into())
+    }
+
     /// Returns journal and seal if the request is fulfilled.
     pub async fn get_request_fulfillment(
         &self,
@@ -1022,6 +1350,296 @@ impl<P: Provider> BoundlessMarketService<P> {
         }
     }
 
+    /// Returns a stream of the status transitions of a request, driven by market events.
+    ///
+    /// The current status of the request is reported first: submitted, locked, fulfilled or
+    /// slashed. Events are then received over a log subscription when the provider supports it,
+    /// and otherwise by polling for new logs. The stream ends once the request is fulfilled or
+    /// slashed, or when a locked request expires.
+    ///
+    /// See [Self::watch_request_with] to also report the expiry of requests that are not locked.
+    pub fn watch_request(
+        &self,
+        request_id: U256,
+    ) -> impl Stream<Item = Result<RequestEvent, MarketError>> + '_ {
+        self.watch_request_with(request_id, None, WATCH_POLL_INTERVAL)
+    }
+
+    /// Returns a stream of the status transitions of a request, see [Self::watch_request].
+    ///
+    /// The `expires_at` parameter is the time at which the request expires, used to report
+    /// [RequestEvent::Expired] for requests that are not locked. New logs are polled for every
+    /// `poll_interval` when the provider does not support subscriptions.
+    pub fn watch_request_with(
+        &self,
+        request_id: U256,
+        expires_at: Option<u64>,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<RequestEvent, MarketError>> + '_ {
+        try_stream! {
+            let mut state = WatchState::default();
+            let mut deadline = expires_at;
+            // Logs after this block are not reflected in the current status
+            let mut next_block = self.get_latest_block_number().await? + 1;
+
+            if self.is_fulfilled(request_id).await? {
+                state.apply(&RequestEvent::Fulfilled);
+                yield RequestEvent::Fulfilled;
+            } else if self.is_slashed(request_id).await? {
+                let slash = self.query_prover_slashed_event(request_id).await?;
+                let event = RequestEvent::Slashed {
+                    stake_burned: slash.stakeBurned,
+                    stake_transferred: slash.stakeTransferred,
+                    stake_recipient: slash.stakeRecipient,
+                };
+                state.apply(&event);
+                yield event;
+            } else if self.is_locked(request_id).await? {
+                let (locked, log) = self.query_request_locked_log(request_id, None, None).await?;
+                let price = self.lock_price(&log).await?;
+                let event = RequestEvent::Locked { prover: locked.prover, price };
+                state.apply(&event);
+                deadline = Some(self.instance.requestDeadline(request_id).call().await?._0);
+                yield event;
+            } else {
+                state.apply(&RequestEvent::Submitted);
+                yield RequestEvent::Submitted;
+            }
+
+            let filter = Filter::new()
+                .address(*self.instance.address())
+                .event_signature(vec![
+                    IBoundlessMarket::RequestSubmitted::SIGNATURE_HASH,
+                    IBoundlessMarket::RequestLocked::SIGNATURE_HASH,
+                    IBoundlessMarket::RequestFulfilled::SIGNATURE_HASH,
+                    IBoundlessMarket::ProverSlashed::SIGNATURE_HASH,
+                ])
+                .topic1(request_id);
+            let provider = self.instance.provider();
+            let mut subscription = match provider.subscribe_logs(&filter).await {
+                Ok(subscription) => Some(subscription.into_stream()),
+                Err(err) => {
+                    tracing::debug!("Log subscription unavailable, polling for logs: {err}");
+                    None
+                }
+            };
+            let mut interval = tokio::time::interval(poll_interval);
+            // Logs emitted before the subscription started are fetched once
+            let mut catch_up = true;
+
+            while !state.done() {
+                let logs = match subscription.as_mut() {
+                    Some(logs) if !catch_up => tokio::select! {
+                        log = logs.next() => log.map(|log| vec![log]),
+                        // Wake up to check for expiry
+                        _ = interval.tick() => Some(vec![]),
+                    },
+                    _ => {
+                        if !std::mem::take(&mut catch_up) {
+                            interval.tick().await;
+                        }
+                        let latest = self.get_latest_block_number().await?;
+                        let mut logs = vec![];
+                        if latest >= next_block {
+                            let range = filter.clone().from_block(next_block).to_block(latest);
+                            logs = provider.get_logs(&range).await.context("Failed to get logs")?;
+                            next_block = latest + 1;
+                        }
+                        Some(logs)
+                    }
+                };
+                let Some(logs) = logs else {
+                    tracing::warn!("Log subscription closed, polling for logs");
+                    subscription = None;
+                    continue;
+                };
+
+                for log in logs {
+                    if let Some(block_number) = log.block_number {
+                        next_block = next_block.max(block_number + 1);
+                    }
+                    let Some(event) = self.request_event_from_log(&log).await? else {
+                        continue;
+                    };
+                    if !state.apply(&event) {
+                        continue;
+                    }
+                    if matches!(event, RequestEvent::Locked { .. }) {
+                        deadline = Some(self.instance.requestDeadline(request_id).call().await?._0);
+                    }
+                    yield event;
+                }
+
+                // Only query the chain once the deadline has passed locally
+                if let Some(deadline) = deadline {
+                    if !state.done()
+                        && !state.expired
+                        && crate::now_timestamp() > deadline
+                        && self.get_latest_block_timestamp().await? > deadline
+                    {
+                        state.apply(&RequestEvent::Expired);
+                        yield RequestEvent::Expired;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn request_event_from_log(&self, log: &Log) -> Result<Option<RequestEvent>, MarketError> {
+        let Some(topic) = log.topic0() else {
+            return Ok(None);
+        };
+        let event = if *topic == IBoundlessMarket::RequestSubmitted::SIGNATURE_HASH {
+            RequestEvent::Submitted
+        } else if *topic == IBoundlessMarket::RequestLocked::SIGNATURE_HASH {
+            let locked = log
+                .log_decode::<IBoundlessMarket::RequestLocked>()
+                .context("Failed to decode RequestLocked event")?;
+            RequestEvent::Locked { prover: locked.inner.prover, price: self.lock_price(log).await? }
+        } else if *topic == IBoundlessMarket::RequestFulfilled::SIGNATURE_HASH {
+            RequestEvent::Fulfilled
+        } else if *topic == IBoundlessMarket::ProverSlashed::SIGNATURE_HASH {
+            let slash = log
+                .log_decode::<IBoundlessMarket::ProverSlashed>()
+                .context("Failed to decode ProverSlashed event")?;
+            RequestEvent::Slashed {
+                stake_burned: slash.inner.stakeBurned,
+                stake_transferred: slash.inner.stakeTransferred,
+                stake_recipient: slash.inner.stakeRecipient,
+            }
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(event))
+    }
+
+    /// Returns the price a request was locked at, from the lock transaction of the given log.
+    ///
+    /// Returns `None` if the lock was not a direct call to the market, e.g. through a multicall.
+    async fn lock_price(&self, log: &Log) -> Result<Option<U256>, MarketError> {
+        let Some(tx_hash) = log.transaction_hash else {
+            return Ok(None);
+        };
+        let tx = self
+            .instance
+            .provider()
+            .get_transaction_by_hash(tx_hash)
+            .await
+            .context("Failed to get transaction")?
+            .context("Transaction not found")?;
+        let Some((request, _)) = decode_lock_calldata(tx.input()) else {
+            return Ok(None);
+        };
+
+        let timestamp = match log.block_timestamp {
+            Some(timestamp) => timestamp,
+            None => {
+                self.get_block_timestamp(log.block_number.context("block number is none")?).await?
+            }
+        };
+        Ok(request.offer.price_at(timestamp).ok())
+    }
+
+    async fn get_block_timestamp(&self, block_number: u64) -> Result<u64, MarketError> {
+        let block = self
+            .instance
+            .provider()
+            .get_block_by_number(block_number.into(), BlockTransactionsKind::Hashes)
+            .await
+            .context("failed to get block")?
+            .context("failed to get block")?;
+        Ok(block.header.timestamp())
+    }
+
+    /// Queries the requests locked in the last `lookback_blocks` blocks, with their price range,
+    /// the price they were locked at and how long it took to lock and fulfill them.
+    ///
+    /// The returned statistics can be used to price new offers with
+    /// [OfferBuilder](crate::pricing::OfferBuilder). Locks that were not a direct call to the
+    /// market, e.g. through a multicall, are skipped.
+    pub async fn query_market_stats(
+        &self,
+        lookback_blocks: u64,
+    ) -> Result<MarketStats, MarketError> {
+        let latest_block = self.get_latest_block_number().await?;
+        let mut from_block = latest_block.saturating_sub(lookback_blocks);
+
+        // Lock price, price range, lock delay and lock timestamp of each request
+        let mut locks = HashMap::new();
+        // Fulfillment timestamp of each request
+        let mut fulfillments = HashMap::new();
+        let mut timestamps = HashMap::new();
+        while from_block <= latest_block {
+            let to_block = (from_block + self.event_query_config.block_range).min(latest_block);
+            let filter = Filter::new()
+                .address(*self.instance.address())
+                .event_signature(vec![
+                    IBoundlessMarket::RequestLocked::SIGNATURE_HASH,
+                    IBoundlessMarket::RequestFulfilled::SIGNATURE_HASH,
+                ])
+                .from_block(from_block)
+                .to_block(to_block);
+            let logs =
+                self.instance.provider().get_logs(&filter).await.context("failed to get logs")?;
+
+            for log in logs {
+                let block_number = log.block_number.context("block number is none")?;
+                let timestamp = match timestamps.entry(block_number) {
+                    Entry::Occupied(entry) => *entry.get(),
+                    Entry::Vacant(entry) => {
+                        *entry.insert(self.get_block_timestamp(block_number).await?)
+                    }
+                };
+
+                if let Ok(fulfilled) = log.log_decode::<IBoundlessMarket::RequestFulfilled>() {
+                    fulfillments.insert(fulfilled.inner.data.requestId, timestamp);
+                    continue;
+                }
+                let locked = log
+                    .log_decode::<IBoundlessMarket::RequestLocked>()
+                    .context("failed to decode event RequestLocked")?;
+                let tx_hash = log.transaction_hash.context("tx hash is none")?;
+                let tx = self
+                    .instance
+                    .provider()
+                    .get_transaction_by_hash(tx_hash)
+                    .await
+                    .context("Failed to get transaction")?
+                    .context("Transaction not found")?;
+                let Some((request, _)) = decode_lock_calldata(tx.input()) else {
+                    tracing::debug!("Skipping lock from an unsupported call: {tx_hash}");
+                    continue;
+                };
+                let Ok(price) = request.offer.price_at(timestamp) else {
+                    continue;
+                };
+                let lock_delay = timestamp.saturating_sub(request.offer.biddingStart);
+                let price_range = (request.offer.minPrice, request.offer.maxPrice);
+                locks.insert(
+                    locked.inner.data.requestId,
+                    (price, price_range, lock_delay, timestamp),
+                );
+            }
+            from_block = to_block + 1;
+        }
+
+        let samples = locks
+            .into_iter()
+            .map(|(request_id, (price, (min_price, max_price), lock_delay, locked_at))| {
+                LockSample {
+                    price,
+                    min_price,
+                    max_price,
+                    lock_delay,
+                    fulfillment_delay: fulfillments
+                        .get(&request_id)
+                        .map(|fulfilled_at: &u64| fulfilled_at.saturating_sub(locked_at)),
+                }
+            })
+            .collect();
+        Ok(MarketStats::new(samples))
+    }
+
     /// Generates a request index based on the EOA nonce.
     ///
     /// It does not guarantee that the index is not in use by the time the caller uses it.
@@ -1053,10 +1671,15 @@ impl<P: Provider> BoundlessMarketService<P> {
     /// It retries up to 10 times to generate a unique index, after which it returns an error.
     /// It does not guarantee that the index is not in use by the time the caller uses it.
     pub async fn index_from_rand(&self) -> Result<u32, MarketError> {
+        self.index_from_rand_for(self.caller).await
+    }
+
+    /// Randomly generates a request index for the given client address.
+    async fn index_from_rand_for(&self, client: Address) -> Result<u32, MarketError> {
         let attempts = 10usize;
         for _ in 0..attempts {
             let id: u32 = rand::random();
-            let request_id = RequestId::u256(self.caller, id);
+            let request_id = RequestId::u256(client, id);
             match self.get_status(request_id, None).await? {
                 ProofStatus::Unknown => return Ok(id),
                 _ => continue,
@@ -1075,6 +1698,39 @@ impl<P: Provider> BoundlessMarketService<P> {
         Ok(RequestId::u256(self.caller, index))
     }
 
+    /// Randomly generates a new request ID for a request signed by the given smart contract
+    /// wallet, using ERC-1271.
+    ///
+    /// It does not guarantee that the ID is not in use by the time the caller uses it.
+    pub async fn smart_contract_request_id_from_rand(
+        &self,
+        wallet: Address,
+    ) -> Result<U256, MarketError> {
+        let index = self.index_from_rand_for(wallet).await?;
+        Ok(RequestId::new(wallet, index).with_smart_contract_signed(true).into())
+    }
+
+    /// Verifies the signature of a request against the EIP-712 domain of the market.
+    ///
+    /// For requests signed by a smart contract, calls `isValidSignature` on the client contract,
+    /// as specified by ERC-1271.
+    pub async fn verify_request_signature(
+        &self,
+        request: &ProofRequest,
+        signature: &Bytes,
+    ) -> Result<(), MarketError> {
+        let chain_id = self.get_chain_id().await.context("failed to get chain ID")?;
+        request
+            .verify_signature_with_provider(
+                signature,
+                *self.instance.address(),
+                chain_id,
+                self.instance.provider(),
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Returns the image ID and URL of the assessor guest.
     pub async fn image_info(&self) -> Result<(B256, String)> {
         tracing::debug!("Calling imageInfo()");
@@ -1216,6 +1872,21 @@ impl<P: Provider> BoundlessMarketService<P> {
         Ok(balance)
     }
 
+    /// Returns the stake token (HP) balance held by the given account outside of the market.
+    pub async fn stake_token_balance_of(&self, account: Address) -> Result<U256, MarketError> {
+        let token_address = self
+            .instance
+            .STAKE_TOKEN_CONTRACT()
+            .call()
+            .await
+            .context("STAKE_TOKEN_CONTRACT call failed")?
+            ._0;
+        let contract = IERC20::new(token_address, self.instance.provider());
+        tracing::debug!("Calling balanceOf({}) on stake token {}", account, token_address);
+        let balance = contract.balanceOf(account).call().await.context("call failed")?._0;
+        Ok(balance)
+    }
+
     /// Check the current stake balance against the alert config
     /// and log a warning or error or below the thresholds.
     async fn check_stake_balance(&self) -> Result<(), MarketError> {
@@ -1302,6 +1973,19 @@ impl Offer {
     }
 }
 
+/// Decodes the request and client signature from the calldata of a lock transaction.
+///
+/// Returns `None` if the calldata is not a direct call to one of the market lock functions.
+pub(crate) fn decode_lock_calldata(data: &Bytes) -> Option<(ProofRequest, Bytes)> {
+    if let Ok(call) = IBoundlessMarket::lockRequestCall::abi_decode(data, true) {
+        return Some((call.request, call.clientSignature));
+    }
+    if let Ok(call) = IBoundlessMarket::lockRequestWithSignatureCall::abi_decode(data, true) {
+        return Some((call.request, call.clientSignature));
+    }
+    None
+}
+
 fn decode_calldata(data: &Bytes) -> Result<Vec<Fulfillment>> {
     if let Ok(call) = IBoundlessMarket::submitRootAndFulfillBatchCall::abi_decode(data, true) {
         return Ok(call.fills);
@@ -1344,8 +2028,8 @@ fn decode_calldata(data: &Bytes) -> Result<Vec<Fulfillment>> {
 }
 
 #[cfg(test)]
-mod tests {
-    use super::decode_calldata;
+pub(crate) mod tests {
+    use super::{decode_calldata, MarketError, RequestEvent};
     use crate::{
         contracts::{
             hit_points::default_allowance,
@@ -1364,6 +2048,7 @@ mod tests {
         sol_types::{eip712_domain, Eip712Domain, SolStruct, SolValue},
     };
     use alloy_sol_types::SolCall;
+    use futures_util::StreamExt;
     use guest_assessor::ASSESSOR_GUEST_ID;
     use guest_set_builder::SET_BUILDER_ID;
     use guest_util::ECHO_ID;
@@ -1375,6 +2060,7 @@ mod tests {
         sha::{Digest, Digestible},
         FakeReceipt, InnerReceipt, Journal, MaybePruned, Receipt, ReceiptClaim,
     };
+    use std::time::Duration;
     use tracing_test::traced_test;
 
     fn ether(value: &str) -> U256 {
@@ -1393,7 +2079,7 @@ mod tests {
         }
     }
 
-    async fn new_request<P: Provider>(idx: u32, ctx: &TestCtx<P>) -> ProofRequest {
+    pub(crate) async fn new_request<P: Provider>(idx: u32, ctx: &TestCtx<P>) -> ProofRequest {
         ProofRequest::new(
             idx,
             &ctx.customer_signer.address(),
@@ -1419,7 +2105,7 @@ mod tests {
         <[u8; 32]>::from(digest).into()
     }
 
-    fn mock_singleton(
+    pub(crate) fn mock_singleton(
         request: &ProofRequest,
         eip712_domain: Eip712Domain,
         prover: Address,
@@ -1618,6 +2304,32 @@ mod tests {
         assert!(log.requestId == request_id);
     }
 
+    #[tokio::test]
+    async fn test_submit_requests() {
+        // Setup anvil
+        let anvil = Anvil::new().spawn();
+
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+
+        let mut requests = Vec::new();
+        for idx in 1..=3 {
+            requests.push(new_request(idx, &ctx).await);
+        }
+
+        let request_ids =
+            ctx.customer_market.submit_requests(&requests, &ctx.customer_signer).await.unwrap();
+        let expected_ids: Vec<_> = requests.iter().map(|request| request.id).collect();
+        assert_eq!(request_ids, expected_ids);
+
+        // The deposit covers all of the requests
+        let balance = ctx.customer_market.balance_of(ctx.customer_signer.address()).await.unwrap();
+        assert_eq!(balance, requests[0].offer.maxPrice * U256::from(3));
+
+        let logs = ctx.customer_market.instance().RequestSubmitted_filter().query().await.unwrap();
+        let logged_ids: Vec<_> = logs.iter().map(|(log, _)| log.requestId).collect();
+        assert_eq!(logged_ids, expected_ids);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_e2e() {
@@ -1695,6 +2407,194 @@ mod tests {
         assert_eq!(seal, fulfillment.seal);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_query_market_stats() {
+        // Setup anvil
+        let anvil = Anvil::new().spawn();
+
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+
+        let eip712_domain = eip712_domain! {
+            name: "IBoundlessMarket",
+            version: "1",
+            chain_id: anvil.chain_id(),
+            verifying_contract: *ctx.customer_market.instance().address(),
+        };
+
+        // no requests were locked yet
+        let stats = ctx.customer_market.query_market_stats(100).await.unwrap();
+        assert!(stats.samples().is_empty());
+
+        let request = new_request(1, &ctx).await;
+        ctx.customer_market.submit_request(&request, &ctx.customer_signer).await.unwrap();
+        let customer_sig: Bytes = request
+            .sign_request(
+                &ctx.customer_signer,
+                *ctx.customer_market.instance().address(),
+                anvil.chain_id(),
+            )
+            .await
+            .unwrap()
+            .as_bytes()
+            .into();
+
+        let deposit = default_allowance();
+        ctx.prover_market.deposit_stake_with_permit(deposit, &ctx.prover_signer).await.unwrap();
+        ctx.prover_market.lock_request(&request, &customer_sig, None).await.unwrap();
+
+        // the lock is reported, without a fulfillment yet
+        let stats = ctx.customer_market.query_market_stats(100).await.unwrap();
+        assert_eq!(stats.samples().len(), 1);
+        let sample = &stats.samples()[0];
+        assert!(sample.price >= request.offer.minPrice && sample.price <= request.offer.maxPrice);
+        assert_eq!(sample.min_price, request.offer.minPrice);
+        assert_eq!(sample.max_price, request.offer.maxPrice);
+        assert!(sample.fulfillment_delay.is_none());
+
+        let (root, set_verifier_seal, fulfillment, assessor_seal) =
+            mock_singleton(&request, eip712_domain, ctx.prover_signer.address());
+        ctx.set_verifier.submit_merkle_root(root, set_verifier_seal).await.unwrap();
+        let assessor_fill = AssessorReceipt {
+            seal: assessor_seal,
+            selectors: vec![],
+            prover: ctx.prover_signer.address(),
+            callbacks: vec![],
+        };
+        ctx.prover_market.fulfill(&fulfillment, assessor_fill).await.unwrap();
+
+        // the fulfillment is reported with the lock
+        let stats = ctx.customer_market.query_market_stats(100).await.unwrap();
+        assert_eq!(stats.samples().len(), 1);
+        assert!(stats.samples()[0].fulfillment_delay.is_some());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_watch_request() {
+        // Setup anvil, whose HTTP endpoint does not support subscriptions
+        let anvil = Anvil::new().spawn();
+
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+
+        let eip712_domain = eip712_domain! {
+            name: "IBoundlessMarket",
+            version: "1",
+            chain_id: anvil.chain_id(),
+            verifying_contract: *ctx.customer_market.instance().address(),
+        };
+
+        let request = new_request(1, &ctx).await;
+        let request_id = request.id;
+        let watcher = ctx
+            .customer_market
+            .watch_request_with(request_id, Some(request.expires_at()), Duration::from_millis(100))
+            .collect::<Vec<_>>();
+
+        let actions = async {
+            // Let the watcher read the initial status first
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            ctx.customer_market.submit_request(&request, &ctx.customer_signer).await.unwrap();
+            let customer_sig: Bytes = request
+                .sign_request(
+                    &ctx.customer_signer,
+                    *ctx.customer_market.instance().address(),
+                    anvil.chain_id(),
+                )
+                .await
+                .unwrap()
+                .as_bytes()
+                .into();
+
+            let deposit = default_allowance();
+            ctx.prover_market.deposit_stake_with_permit(deposit, &ctx.prover_signer).await.unwrap();
+            ctx.prover_market.lock_request(&request, &customer_sig, None).await.unwrap();
+
+            // A new watcher reports the lock as the current status
+            let mut current = Box::pin(ctx.customer_market.watch_request(request_id));
+            let RequestEvent::Locked { prover, .. } = current.next().await.unwrap().unwrap() else {
+                panic!("locked request not reported as locked");
+            };
+            assert_eq!(prover, ctx.prover_signer.address());
+
+            let (root, set_verifier_seal, fulfillment, assessor_seal) =
+                mock_singleton(&request, eip712_domain, ctx.prover_signer.address());
+            ctx.set_verifier.submit_merkle_root(root, set_verifier_seal).await.unwrap();
+            let assessor_fill = AssessorReceipt {
+                seal: assessor_seal,
+                selectors: vec![],
+                prover: ctx.prover_signer.address(),
+                callbacks: vec![],
+            };
+            ctx.prover_market.fulfill(&fulfillment, assessor_fill).await.unwrap();
+        };
+        let (events, ()) = tokio::join!(watcher, actions);
+        let events = events.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(events.len(), 3, "unexpected events: {events:?}");
+        assert_eq!(events[0], RequestEvent::Submitted);
+        let RequestEvent::Locked { prover, price: Some(price) } = events[1] else {
+            panic!("unexpected lock event: {:?}", events[1]);
+        };
+        assert_eq!(prover, ctx.prover_signer.address());
+        assert!(price >= request.offer.minPrice && price <= request.offer.maxPrice);
+        assert_eq!(events[2], RequestEvent::Fulfilled);
+
+        // The stream of a fulfilled request only reports its current status
+        let events = ctx.customer_market.watch_request(request_id).collect::<Vec<_>>().await;
+        assert!(matches!(&events[..], [Ok(RequestEvent::Fulfilled)]));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_simulate_lock_request() {
+        // Setup anvil
+        let anvil = Anvil::new().spawn();
+
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+
+        let request = new_request(1, &ctx).await;
+        let customer_sig: Bytes = request
+            .sign_request(
+                &ctx.customer_signer,
+                *ctx.customer_market.instance().address(),
+                anvil.chain_id(),
+            )
+            .await
+            .unwrap()
+            .as_bytes()
+            .into();
+        ctx.customer_market.deposit(U256::from(request.offer.maxPrice)).await.unwrap();
+
+        let deposit = default_allowance();
+        ctx.prover_market.deposit_stake_with_permit(deposit, &ctx.prover_signer).await.unwrap();
+
+        // Simulation passes while the request is open
+        let gas = ctx.prover_market.simulate_lock_request(&request, &customer_sig).await.unwrap();
+        assert!(gas > 0);
+
+        let nonce = ctx
+            .prover_market
+            .instance()
+            .provider()
+            .get_transaction_count(ctx.prover_signer.address())
+            .pending()
+            .await
+            .unwrap();
+        let pending_tx = ctx
+            .prover_market
+            .broadcast_lock_request(&request, &customer_sig, None, nonce, 1_000_000)
+            .await
+            .unwrap();
+        ctx.prover_market.confirm_lock_request(U256::from(request.id), pending_tx).await.unwrap();
+        assert!(ctx.customer_market.is_locked(request.id).await.unwrap());
+
+        // Once locked, a second lock would revert
+        let err =
+            ctx.prover_market.simulate_lock_request(&request, &customer_sig).await.unwrap_err();
+        assert!(matches!(err, MarketError::LockSimulationRevert(_)));
+    }
+
     #[tokio::test]
     async fn test_e2e_merged_submit_fulfill() {
         // Setup anvil
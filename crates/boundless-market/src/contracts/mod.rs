@@ -20,15 +20,16 @@ use std::str::FromStr;
 use alloy::{
     contract::Error as ContractErr,
     primitives::{PrimitiveSignature, SignatureError},
+    providers::Provider,
     signers::Signer,
-    sol_types::{Error as DecoderErr, SolInterface, SolStruct},
+    sol_types::{Error as DecoderErr, SolCall, SolInterface, SolStruct},
     transports::TransportError,
 };
 use alloy_primitives::{
     aliases::{U160, U32, U96},
     Address, Bytes, FixedBytes, B256, U256,
 };
-use alloy_sol_types::{eip712_domain, Eip712Domain};
+use alloy_sol_types::{eip712_domain, Eip712Domain, SolValue};
 use serde::{Deserialize, Serialize};
 #[cfg(not(target_os = "zkvm"))]
 use std::time::Duration;
@@ -55,6 +56,20 @@ const TXN_CONFIRM_TIMEOUT: Duration = Duration::from_secs(45);
 include!(concat!(env!("OUT_DIR"), "/boundless_market_generated.rs"));
 pub use boundless_market_contract::*;
 
+#[allow(missing_docs)]
+#[cfg(not(target_os = "zkvm"))]
+pub mod erc1271 {
+    alloy::sol! {
+        #[sol(rpc)]
+        interface IERC1271 {
+            function isValidSignature(bytes32 hash, bytes memory signature)
+                external
+                view
+                returns (bytes4 magicValue);
+        }
+    }
+}
+
 #[allow(missing_docs)]
 #[cfg(not(target_os = "zkvm"))]
 pub mod token {
@@ -192,6 +207,13 @@ impl RequestId {
         Self::new(addr, index).into()
     }
 
+    /// Sets the flag indicating that the request is signed by a smart contract using ERC-1271.
+    ///
+    /// The address of the request ID must then be the address of the smart contract.
+    pub fn with_smart_contract_signed(self, smart_contract_signed: bool) -> Self {
+        Self { smart_contract_signed, ..self }
+    }
+
     /// Unpack a [RequestId] from a [U256] ignoring bits that do not correspond to known fields.
     ///
     /// Note that this is a lossy conversion in that converting the resulting [RequestId] back into
@@ -310,6 +332,19 @@ pub enum RequestError {
     /// Request digest mismatch.
     #[error("request digest mismatch")]
     DigestMismatch,
+
+    /// The request is signed by a smart contract, whose signature can only be verified onchain.
+    #[error("smart contract signature must be verified by calling the client contract")]
+    SmartContractSigned,
+
+    /// The client smart contract rejected the signature.
+    #[error("signature rejected by the client smart contract")]
+    SmartContractSignatureRejected,
+
+    /// Error calling the client smart contract to verify the signature.
+    #[cfg(not(target_os = "zkvm"))]
+    #[error("failed to call the client smart contract: {0}")]
+    SmartContractCallError(#[from] ContractErr),
 }
 
 #[cfg(not(target_os = "zkvm"))]
@@ -397,18 +432,15 @@ impl ProofRequest {
     }
 
     /// Returns the client address from the request ID.
+    ///
+    /// For smart contract signed requests, this is the address of the smart contract.
     pub fn client_address(&self) -> Result<Address, RequestError> {
-        let shifted_id: U256 = self.id >> 32;
-        if self.id >> 192 != U256::ZERO {
-            return Err(RequestError::MalformedRequestId);
-        }
-        let shifted_bytes: [u8; 32] = shifted_id.to_be_bytes();
-        let addr_bytes: [u8; 20] = shifted_bytes[12..32]
-            .try_into()
-            .expect("error in converting slice of 20 bytes into array of 20 bytes");
-        let lower_160_bits = U160::from_be_bytes(addr_bytes);
+        Ok(RequestId::try_from(self.id)?.addr)
+    }
 
-        Ok(Address::from(lower_160_bits))
+    /// Returns true if the request is signed by a smart contract using ERC-1271.
+    pub fn is_smart_contract_signed(&self) -> bool {
+        RequestId::from_lossy(self.id).smart_contract_signed
     }
 
     /// Returns the time, in seconds since the UNIX epoch, at which the request expires.
@@ -475,12 +507,18 @@ impl ProofRequest {
 
     /// Verifies the request signature with the given signer and EIP-712 domain derived from
     /// the given contract address and chain ID.
+    ///
+    /// Returns [RequestError::SmartContractSigned] for requests signed by a smart contract, which
+    /// are verified with [ProofRequest::verify_signature_with_provider].
     pub fn verify_signature(
         &self,
         signature: &Bytes,
         contract_addr: Address,
         chain_id: u64,
     ) -> Result<(), RequestError> {
+        if self.is_smart_contract_signed() {
+            return Err(RequestError::SmartContractSigned);
+        }
         let sig = PrimitiveSignature::try_from(signature.as_ref())?;
         let domain = eip712_domain(contract_addr, chain_id);
         let hash = self.eip712_signing_hash(&domain.alloy_struct());
@@ -491,6 +529,32 @@ impl ProofRequest {
             Err(SignatureError::FromBytes("Address mismatch").into())
         }
     }
+
+    /// Verifies the request signature with the EIP-712 domain derived from the given contract
+    /// address and chain ID.
+    ///
+    /// For requests signed by a smart contract, calls `isValidSignature` on the client contract
+    /// as specified by ERC-1271, as the market contract does when locking the request. Otherwise,
+    /// checks the ECDSA signature as [ProofRequest::verify_signature].
+    pub async fn verify_signature_with_provider(
+        &self,
+        signature: &Bytes,
+        contract_addr: Address,
+        chain_id: u64,
+        provider: impl Provider,
+    ) -> Result<(), RequestError> {
+        if !self.is_smart_contract_signed() {
+            return self.verify_signature(signature, contract_addr, chain_id);
+        }
+        let domain = eip712_domain(contract_addr, chain_id);
+        let hash = self.eip712_signing_hash(&domain.alloy_struct());
+        let client = erc1271::IERC1271::new(self.client_address()?, provider);
+        let magic_value = client.isValidSignature(hash, signature.clone()).call().await?.magicValue;
+        if magic_value != erc1271::IERC1271::isValidSignatureCall::SELECTOR {
+            return Err(RequestError::SmartContractSignatureRejected);
+        }
+        Ok(())
+    }
 }
 
 impl Requirements {
@@ -514,6 +578,41 @@ impl Requirements {
         Self { predicate, ..self }
     }
 
+    /// Sets the predicate to match the journal digest. See [Predicate::digest_match].
+    pub fn with_digest_match(self, digest: impl Into<Digest>) -> Self {
+        self.with_predicate(Predicate::digest_match(digest))
+    }
+
+    /// Sets the predicate to match the journal prefix. See [Predicate::prefix_match].
+    pub fn with_prefix_match(self, prefix: impl Into<Bytes>) -> Self {
+        self.with_predicate(Predicate::prefix_match(prefix))
+    }
+
+    /// Sets the predicate to match a static field of the ABI-encoded journal.
+    /// See [Predicate::abi_field_match].
+    pub fn with_abi_field_match(self, index: usize, value: impl Into<B256>) -> Self {
+        self.with_predicate(Predicate::abi_field_match(index, value))
+    }
+
+    /// Sets the predicate to match a masked range of the journal. See [Predicate::mask_match].
+    pub fn with_mask_match(
+        self,
+        offset: usize,
+        mask: impl Into<Bytes>,
+        value: impl Into<Bytes>,
+    ) -> Self {
+        self.with_predicate(Predicate::mask_match(offset, mask, value))
+    }
+
+    /// Sets the predicate to match any of the given journal digests.
+    /// See [Predicate::any_digest_match].
+    pub fn with_any_digest_match<D: Into<Digest>>(
+        self,
+        digests: impl IntoIterator<Item = D>,
+    ) -> Self {
+        self.with_predicate(Predicate::any_digest_match(digests))
+    }
+
     /// Sets the callback.
     pub fn with_callback(self, callback: Callback) -> Self {
         Self { callback, ..self }
@@ -540,6 +639,36 @@ impl Predicate {
     pub fn prefix_match(prefix: impl Into<Bytes>) -> Self {
         Self { predicateType: PredicateType::PrefixMatch, data: prefix.into() }
     }
+
+    /// Returns a predicate to match a static field of the ABI-encoded journal. This ensures that
+    /// the request's fulfillment will contain a journal whose 32-byte word at the given index is
+    /// equal to the given value, e.g. a `uint256` or `address` returned by `abi.encode`.
+    pub fn abi_field_match(index: usize, value: impl Into<B256>) -> Self {
+        Self {
+            predicateType: PredicateType::AbiFieldMatch,
+            data: (U256::from(index), value.into()).abi_encode_params().into(),
+        }
+    }
+
+    /// Returns a predicate to match a masked range of the journal. This ensures that the
+    /// request's fulfillment will contain a journal whose bytes starting at the given offset are
+    /// equal to the given value, on the bits set in the mask.
+    ///
+    /// The mask and the value must have the same length, otherwise the predicate never matches.
+    pub fn mask_match(offset: usize, mask: impl Into<Bytes>, value: impl Into<Bytes>) -> Self {
+        Self {
+            predicateType: PredicateType::MaskMatch,
+            data: (U256::from(offset), mask.into(), value.into()).abi_encode_params().into(),
+        }
+    }
+
+    /// Returns a predicate to match any of the given journal digests. This ensures that the
+    /// request's fulfillment will contain a journal with one of the given digests.
+    pub fn any_digest_match<D: Into<Digest>>(digests: impl IntoIterator<Item = D>) -> Self {
+        let data: Vec<u8> =
+            digests.into_iter().flat_map(|digest| digest.into().as_bytes().to_vec()).collect();
+        Self { predicateType: PredicateType::AnyDigestMatch, data: data.into() }
+    }
 }
 
 impl Callback {
@@ -657,9 +786,47 @@ impl Predicate {
     /// Evaluates the predicate against the given journal.
     #[inline]
     pub fn eval(&self, journal: impl AsRef<[u8]>) -> bool {
+        let journal = journal.as_ref();
         match self.predicateType {
             PredicateType::DigestMatch => self.data.as_ref() == Sha256::digest(journal).as_slice(),
-            PredicateType::PrefixMatch => journal.as_ref().starts_with(&self.data),
+            PredicateType::PrefixMatch => journal.starts_with(&self.data),
+            PredicateType::AbiFieldMatch => {
+                let Ok((index, value)) = <(U256, B256)>::abi_decode_params(&self.data, true) else {
+                    return false;
+                };
+                let Some(start) = usize::try_from(index).ok().and_then(|i| i.checked_mul(32))
+                else {
+                    return false;
+                };
+                start
+                    .checked_add(32)
+                    .and_then(|end| journal.get(start..end))
+                    .is_some_and(|field| field == value.as_slice())
+            }
+            PredicateType::MaskMatch => {
+                let Ok((offset, mask, value)) =
+                    <(U256, Bytes, Bytes)>::abi_decode_params(&self.data, true)
+                else {
+                    return false;
+                };
+                if mask.len() != value.len() {
+                    return false;
+                }
+                let Some(range) = usize::try_from(offset)
+                    .ok()
+                    .and_then(|start| journal.get(start..start.checked_add(mask.len())?))
+                else {
+                    return false;
+                };
+                range.iter().zip(mask.iter()).zip(value.iter()).all(|((j, m), v)| j & m == v & m)
+            }
+            PredicateType::AnyDigestMatch => {
+                if self.data.len() % 32 != 0 {
+                    return false;
+                }
+                let digest = Sha256::digest(journal);
+                self.data.chunks_exact(32).any(|hash| hash == digest.as_slice())
+            }
             PredicateType::__Invalid => panic!("invalid PredicateType"),
         }
     }
@@ -798,12 +965,14 @@ pub mod test_utils {
         boundless_market::BoundlessMarketService,
         bytecode::*,
         hit_points::{default_allowance, HitPointsService},
+        IBoundlessMarket,
     };
     use alloy::{
-        network::EthereumWallet,
+        network::{EthereumWallet, TransactionBuilder},
         node_bindings::AnvilInstance,
-        primitives::{Address, FixedBytes},
+        primitives::{Address, Bytes, FixedBytes, U256},
         providers::{ext::AnvilApi, Provider, ProviderBuilder, WalletProvider},
+        rpc::types::TransactionRequest,
         signers::local::PrivateKeySigner,
         sol_types::SolCall,
     };
@@ -905,6 +1074,51 @@ pub mod test_utils {
         Ok(proxy)
     }
 
+    /// Deploys a mock ERC-1271 wallet owned by the default signer of the provider, and deposits
+    /// `value` to the market balance of the wallet.
+    ///
+    /// The wallet accepts only the signature set with [set_mock_wallet_signature].
+    pub async fn deploy_mock_wallet<P: Provider + WalletProvider>(
+        owner_provider: P,
+        market: Address,
+        value: U256,
+    ) -> Result<Address> {
+        let owner = owner_provider.default_signer_address();
+        let instance =
+            MockSmartContractWallet::deploy(&owner_provider, Bytes::new(), market, owner)
+                .await
+                .context("failed to deploy MockSmartContractWallet")?;
+        let wallet = *instance.address();
+        if value > U256::ZERO {
+            let tx = TransactionRequest::default().with_to(wallet).with_value(value);
+            owner_provider.send_transaction(tx).await?.watch().await?;
+            instance
+                .execute(market, IBoundlessMarket::depositCall {}.abi_encode().into(), value)
+                .send()
+                .await?
+                .watch()
+                .await
+                .context("failed to deposit to the market from the mock wallet")?;
+        }
+        Ok(wallet)
+    }
+
+    /// Sets the signature accepted by a wallet deployed with [deploy_mock_wallet].
+    pub async fn set_mock_wallet_signature<P: Provider>(
+        provider: P,
+        wallet: Address,
+        signature: Bytes,
+    ) -> Result<()> {
+        MockSmartContractWallet::new(wallet, provider)
+            .setExpectedSignature(signature)
+            .send()
+            .await?
+            .watch()
+            .await
+            .context("failed to set the mock wallet signature")?;
+        Ok(())
+    }
+
     async fn deploy_contracts(
         anvil: &AnvilInstance,
         set_builder_id: Digest,
@@ -1019,7 +1233,11 @@ pub mod test_utils {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy::signers::local::PrivateKeySigner;
+    use crate::contracts::{
+        boundless_market::MarketError,
+        test_utils::{create_test_ctx, deploy_mock_wallet, set_mock_wallet_signature},
+    };
+    use alloy::{node_bindings::Anvil, signers::local::PrivateKeySigner};
 
     async fn create_order(
         signer: &impl Signer,
@@ -1086,6 +1304,113 @@ mod tests {
         req.verify_signature(&Bytes::from(client_sig), contract_addr, chain_id).unwrap();
     }
 
+    #[tokio::test]
+    async fn smart_contract_signed_request() {
+        let signer: PrivateKeySigner =
+            "6f142508b4eea641e33cb2a0161221105086a84584c74245ca463a49effea30b".parse().unwrap();
+        let wallet_addr = Address::from_str("0x7FA9385bE102ac3EAc297483Dd6233D62b3e1496").unwrap();
+        let contract_addr = Address::ZERO;
+        let chain_id = 1;
+
+        let (mut req, client_sig) =
+            create_order(&signer, signer.address(), 1, contract_addr, chain_id).await;
+        req.id = RequestId::new(wallet_addr, 1).with_smart_contract_signed(true).into();
+
+        // The client address is the smart contract wallet, not the owner signing the request
+        assert!(req.is_smart_contract_signed());
+        assert_eq!(req.client_address().unwrap(), wallet_addr);
+        // The signature can only be verified by calling the wallet contract
+        let err =
+            req.verify_signature(&Bytes::from(client_sig), contract_addr, chain_id).unwrap_err();
+        assert!(matches!(err, RequestError::SmartContractSigned));
+    }
+
+    #[tokio::test]
+    async fn smart_contract_signed_request_with_provider() {
+        let anvil = Anvil::new().spawn();
+        let ctx = create_test_ctx(&anvil, Digest::ZERO, Digest::ZERO).await.unwrap();
+        let market = ctx.boundless_market_address;
+        let chain_id = anvil.chain_id();
+        let owner = &ctx.customer_signer;
+
+        let wallet = deploy_mock_wallet(&ctx.customer_provider, market, U256::ZERO).await.unwrap();
+        let (mut req, _) = create_order(owner, owner.address(), 1, market, chain_id).await;
+        req.id = RequestId::new(wallet, 1).with_smart_contract_signed(true).into();
+        let sig: Bytes = req.sign_request(owner, market, chain_id).await.unwrap().as_bytes().into();
+
+        // The wallet rejects the signature until it is set as the expected one
+        let err = req
+            .verify_signature_with_provider(&sig, market, chain_id, &ctx.customer_provider)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RequestError::SmartContractSignatureRejected));
+
+        set_mock_wallet_signature(&ctx.customer_provider, wallet, sig.clone()).await.unwrap();
+        req.verify_signature_with_provider(&sig, market, chain_id, &ctx.customer_provider)
+            .await
+            .unwrap();
+        ctx.customer_market.verify_request_signature(&req, &sig).await.unwrap();
+
+        // Another signature is rejected
+        let mut bad_sig = sig.to_vec();
+        bad_sig[0] ^= 1;
+        let err = ctx.customer_market.verify_request_signature(&req, &bad_sig.into()).await;
+        assert!(matches!(
+            err,
+            Err(MarketError::RequestError(RequestError::SmartContractSignatureRejected))
+        ));
+    }
+
+    #[test]
+    fn test_predicate_eval() {
+        use sha2::Digest as _;
+
+        let journal = b"test";
+        let digest = Digest::try_from(Sha256::digest(journal).as_slice()).unwrap();
+        assert!(Predicate::digest_match(digest).eval(journal));
+        assert!(Predicate::prefix_match(b"te".to_vec()).eval(journal));
+        assert!(!Predicate::prefix_match(b"es".to_vec()).eval(journal));
+
+        let journal = (U256::from(7), Address::repeat_byte(0x11)).abi_encode();
+        let owner = B256::left_padding_from(Address::repeat_byte(0x11).as_slice());
+        assert!(Predicate::abi_field_match(0, U256::from(7)).eval(&journal));
+        assert!(Predicate::abi_field_match(1, owner).eval(&journal));
+        assert!(!Predicate::abi_field_match(0, owner).eval(&journal));
+        assert!(!Predicate::abi_field_match(2, owner).eval(&journal));
+        assert!(!Predicate::abi_field_match(usize::MAX, owner).eval(&journal));
+
+        let journal: [u8; 4] = [0x00, 0xab, 0xbb, 0xcc];
+        assert!(Predicate::mask_match(1, vec![0xf0, 0xff], vec![0xa0, 0xbb]).eval(journal));
+        assert!(Predicate::mask_match(3, vec![0xff], vec![0xcc]).eval(journal));
+        assert!(!Predicate::mask_match(1, vec![0xff, 0xff], vec![0xa0, 0xbb]).eval(journal));
+        assert!(!Predicate::mask_match(3, vec![0xff, 0xff], vec![0xcc, 0x00]).eval(journal));
+        assert!(!Predicate::mask_match(1, vec![0xf0, 0xff], vec![0xa0]).eval(journal));
+        assert!(!Predicate::mask_match(usize::MAX, vec![0xff], vec![0xcc]).eval(journal));
+
+        let journal = b"test";
+        let other = Digest::try_from(Sha256::digest(b"other").as_slice()).unwrap();
+        assert!(Predicate::any_digest_match([other, digest]).eval(journal));
+        assert!(!Predicate::any_digest_match([other]).eval(journal));
+        assert!(!Predicate::any_digest_match(Vec::<Digest>::new()).eval(journal));
+
+        // Malformed predicate data never matches
+        let malformed =
+            |predicate_type| Predicate { predicateType: predicate_type, data: vec![0x01].into() };
+        assert!(!malformed(PredicateType::AbiFieldMatch).eval(journal));
+        assert!(!malformed(PredicateType::MaskMatch).eval(journal));
+        assert!(!malformed(PredicateType::AnyDigestMatch).eval(journal));
+    }
+
+    #[test]
+    fn test_predicate_type_encoding() {
+        // New predicate types are appended, such that existing requests keep the same encoding
+        assert_eq!(PredicateType::DigestMatch as u8, 0);
+        assert_eq!(PredicateType::PrefixMatch as u8, 1);
+        assert_eq!(PredicateType::AbiFieldMatch as u8, 2);
+        assert_eq!(PredicateType::MaskMatch as u8, 3);
+        assert_eq!(PredicateType::AnyDigestMatch as u8, 4);
+    }
+
     #[tokio::test]
     async fn test_request_id() {
         // Test case 1: Regular signature
@@ -0,0 +1,427 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow},
+    types::Json,
+    Row,
+};
+use thiserror::Error;
+
+use crate::contracts::ProofRequest;
+
+/// Indexer database errors.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DbError {
+    /// SQL error.
+    #[error("SQL error: {0}")]
+    SqlErr(#[from] sqlx::Error),
+
+    /// SQL migration error.
+    #[error("SQL migration error: {0}")]
+    MigrateErr(#[from] sqlx::migrate::MigrateError),
+
+    /// A stored value could not be parsed.
+    #[error("Invalid value in database: {0}")]
+    BadValue(String),
+}
+
+/// Request indexed from its onchain submission, or from its lock if it was submitted offchain.
+#[derive(Clone, Debug)]
+pub struct IndexedRequest {
+    /// The proof request.
+    pub request: ProofRequest,
+    /// Signature of the client over the request.
+    pub client_signature: Bytes,
+    /// Whether the request was submitted onchain, otherwise it was indexed from its lock.
+    pub submitted_onchain: bool,
+    /// Block number of the submission, or of the lock.
+    pub block_number: u64,
+    /// Timestamp of the block.
+    pub block_timestamp: u64,
+    /// Hash of the transaction.
+    pub tx_hash: B256,
+}
+
+/// Lock of a request by a prover.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedLock {
+    /// ID of the locked request.
+    pub request_id: U256,
+    /// Address of the prover that locked the request.
+    pub prover: Address,
+    /// Price of the request at the lock, if the lock transaction could be decoded.
+    pub price: Option<U256>,
+    /// Block number of the lock.
+    pub block_number: u64,
+    /// Timestamp of the block.
+    pub block_timestamp: u64,
+    /// Hash of the transaction.
+    pub tx_hash: B256,
+}
+
+/// Fulfillment of a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedFulfillment {
+    /// ID of the fulfilled request.
+    pub request_id: U256,
+    /// Block number of the fulfillment.
+    pub block_number: u64,
+    /// Timestamp of the block.
+    pub block_timestamp: u64,
+    /// Hash of the transaction.
+    pub tx_hash: B256,
+}
+
+/// Slash of the prover that locked a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedSlash {
+    /// ID of the request.
+    pub request_id: U256,
+    /// Amount of stake burned.
+    pub stake_burned: U256,
+    /// Amount of stake transferred to the recipient.
+    pub stake_transferred: U256,
+    /// Recipient of the transferred stake.
+    pub stake_recipient: Address,
+    /// Block number of the slash.
+    pub block_number: u64,
+    /// Timestamp of the block.
+    pub block_timestamp: u64,
+    /// Hash of the transaction.
+    pub tx_hash: B256,
+}
+
+/// Market event ready to be stored.
+#[derive(Clone, Debug)]
+pub(super) enum IndexedEvent {
+    Request(IndexedRequest),
+    Lock(IndexedLock),
+    Fulfillment(IndexedFulfillment),
+    Slash(IndexedSlash),
+}
+
+/// Block seen by the indexer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct IndexedBlock {
+    pub(super) number: u64,
+    pub(super) hash: B256,
+    pub(super) timestamp: u64,
+}
+
+/// SQLite store of the indexed market events.
+#[derive(Clone, Debug)]
+pub struct IndexerDb {
+    pool: SqlitePool,
+}
+
+impl IndexerDb {
+    /// Opens the database at the given connection string, creating it if missing.
+    ///
+    /// For example `sqlite:indexer.db`, or `sqlite::memory:` for an in-memory database.
+    pub async fn new(conn_str: &str) -> Result<Self, DbError> {
+        let opts = SqliteConnectOptions::from_str(conn_str)?
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
+            .create_if_missing(true)
+            .busy_timeout(std::time::Duration::from_secs(5));
+
+        let pool = SqlitePoolOptions::new()
+            // set timeouts to None for sqlite in-memory:
+            // https://github.com/launchbadge/sqlx/issues/1647
+            .max_lifetime(None)
+            .idle_timeout(None)
+            .min_connections(1)
+            // A single connection keeps in-memory databases shared, and avoids database-locked
+            // errors between the indexer and queries
+            .max_connections(1);
+
+        let pool = pool.connect_with(opts).await?;
+
+        sqlx::migrate!("./migrations").run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+
+    /// Returns the last indexed block number.
+    pub async fn last_block_number(&self) -> Result<Option<u64>, DbError> {
+        Ok(self.last_block().await?.map(|block| block.number))
+    }
+
+    /// Returns the request with the given ID.
+    pub async fn get_request(&self, request_id: U256) -> Result<Option<IndexedRequest>, DbError> {
+        let row = sqlx::query(&format!("{REQUEST_SELECT} WHERE r.id = $1"))
+            .bind(format!("{request_id:x}"))
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| request_from_row(&row)).transpose()
+    }
+
+    /// Returns the requests of the given client, in block order.
+    pub async fn requests_by_client(
+        &self,
+        client: Address,
+    ) -> Result<Vec<IndexedRequest>, DbError> {
+        let rows =
+            sqlx::query(&format!("{REQUEST_SELECT} WHERE r.client = $1 ORDER BY r.block_number"))
+                .bind(format!("{client:x}"))
+                .fetch_all(&self.pool)
+                .await?;
+        rows.iter().map(request_from_row).collect()
+    }
+
+    /// Returns the lock of the request with the given ID.
+    pub async fn get_lock(&self, request_id: U256) -> Result<Option<IndexedLock>, DbError> {
+        let row = sqlx::query(&format!("{LOCK_SELECT} WHERE l.id = $1"))
+            .bind(format!("{request_id:x}"))
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| lock_from_row(&row)).transpose()
+    }
+
+    /// Returns the locks of the given prover, in block order.
+    pub async fn locks_by_prover(&self, prover: Address) -> Result<Vec<IndexedLock>, DbError> {
+        let rows =
+            sqlx::query(&format!("{LOCK_SELECT} WHERE l.prover = $1 ORDER BY l.block_number"))
+                .bind(format!("{prover:x}"))
+                .fetch_all(&self.pool)
+                .await?;
+        rows.iter().map(lock_from_row).collect()
+    }
+
+    /// Returns the fulfillment of the request with the given ID.
+    pub async fn get_fulfillment(
+        &self,
+        request_id: U256,
+    ) -> Result<Option<IndexedFulfillment>, DbError> {
+        let row = sqlx::query(
+            "SELECT f.id, f.block_number, f.tx_hash, b.block_timestamp FROM fulfillments f \
+             JOIN blocks b ON b.block_number = f.block_number WHERE f.id = $1",
+        )
+        .bind(format!("{request_id:x}"))
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| {
+            Ok(IndexedFulfillment {
+                request_id: parse_u256(row.try_get("id")?)?,
+                block_number: row.try_get::<i64, _>("block_number")? as u64,
+                block_timestamp: row.try_get::<i64, _>("block_timestamp")? as u64,
+                tx_hash: parse(row.try_get("tx_hash")?)?,
+            })
+        })
+        .transpose()
+    }
+
+    /// Returns the slash of the request with the given ID.
+    pub async fn get_slash(&self, request_id: U256) -> Result<Option<IndexedSlash>, DbError> {
+        let row = sqlx::query(
+            "SELECT s.*, b.block_timestamp FROM slashes s \
+             JOIN blocks b ON b.block_number = s.block_number WHERE s.id = $1",
+        )
+        .bind(format!("{request_id:x}"))
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| {
+            Ok(IndexedSlash {
+                request_id: parse_u256(row.try_get("id")?)?,
+                stake_burned: parse_u256(row.try_get("stake_burned")?)?,
+                stake_transferred: parse_u256(row.try_get("stake_transferred")?)?,
+                stake_recipient: parse(row.try_get("stake_recipient")?)?,
+                block_number: row.try_get::<i64, _>("block_number")? as u64,
+                block_timestamp: row.try_get::<i64, _>("block_timestamp")? as u64,
+                tx_hash: parse(row.try_get("tx_hash")?)?,
+            })
+        })
+        .transpose()
+    }
+
+    /// Returns the median price of the requests locked at or after the given timestamp.
+    ///
+    /// Cycle counts are not published onchain, a price per mcycle requires executing the
+    /// requests, e.g. with the inputs of [IndexedRequest::request].
+    pub async fn median_lock_price(&self, since: u64) -> Result<Option<U256>, DbError> {
+        let prices: Vec<String> = sqlx::query_scalar(
+            "SELECT l.price FROM locks l JOIN blocks b ON b.block_number = l.block_number \
+             WHERE l.price IS NOT NULL AND b.block_timestamp >= $1",
+        )
+        .bind(since as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut prices =
+            prices.iter().map(|price| parse_u256(price)).collect::<Result<Vec<_>, _>>()?;
+        prices.sort();
+
+        let mid = prices.len() / 2;
+        Ok(match prices.len() {
+            0 => None,
+            len if len % 2 == 1 => Some(prices[mid]),
+            _ => Some((prices[mid - 1] + prices[mid]) / U256::from(2)),
+        })
+    }
+
+    pub(super) async fn last_block(&self) -> Result<Option<IndexedBlock>, DbError> {
+        let row = sqlx::query("SELECT * FROM blocks ORDER BY block_number DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| {
+            Ok(IndexedBlock {
+                number: row.try_get::<i64, _>("block_number")? as u64,
+                hash: parse(row.try_get("block_hash")?)?,
+                timestamp: row.try_get::<i64, _>("block_timestamp")? as u64,
+            })
+        })
+        .transpose()
+    }
+
+    /// Removes the given block, all blocks after it, and their events.
+    pub(super) async fn remove_from_block(&self, block_number: u64) -> Result<(), DbError> {
+        let mut txn = self.pool.begin().await?;
+        for table in ["requests", "locks", "fulfillments", "slashes", "blocks"] {
+            sqlx::query(&format!("DELETE FROM {table} WHERE block_number >= $1"))
+                .bind(block_number as i64)
+                .execute(&mut *txn)
+                .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Stores the events of a range of blocks, along with the blocks, atomically.
+    pub(super) async fn insert_events(
+        &self,
+        blocks: impl IntoIterator<Item = &IndexedBlock>,
+        events: &[IndexedEvent],
+    ) -> Result<(), DbError> {
+        let mut txn = self.pool.begin().await?;
+        for block in blocks {
+            sqlx::query(
+                "REPLACE INTO blocks (block_number, block_hash, block_timestamp) \
+                 VALUES ($1, $2, $3)",
+            )
+            .bind(block.number as i64)
+            .bind(format!("{:x}", block.hash))
+            .bind(block.timestamp as i64)
+            .execute(&mut *txn)
+            .await?;
+        }
+        for event in events {
+            match event {
+                IndexedEvent::Request(request) => {
+                    let client = request.request.client_address().map_err(|err| {
+                        DbError::BadValue(format!("request client address: {err}"))
+                    })?;
+                    // A request submitted onchain is not replaced by its lock
+                    sqlx::query(
+                        "INSERT OR IGNORE INTO requests (id, client, request, client_signature, \
+                         submitted_onchain, block_number, tx_hash) \
+                         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                    )
+                    .bind(format!("{:x}", request.request.id))
+                    .bind(format!("{client:x}"))
+                    .bind(Json(&request.request))
+                    .bind(hex::encode(&request.client_signature))
+                    .bind(request.submitted_onchain)
+                    .bind(request.block_number as i64)
+                    .bind(format!("{:x}", request.tx_hash))
+                    .execute(&mut *txn)
+                    .await?;
+                }
+                IndexedEvent::Lock(lock) => {
+                    sqlx::query(
+                        "REPLACE INTO locks (id, prover, price, block_number, tx_hash) \
+                         VALUES ($1, $2, $3, $4, $5)",
+                    )
+                    .bind(format!("{:x}", lock.request_id))
+                    .bind(format!("{:x}", lock.prover))
+                    .bind(lock.price.map(|price| format!("{price:x}")))
+                    .bind(lock.block_number as i64)
+                    .bind(format!("{:x}", lock.tx_hash))
+                    .execute(&mut *txn)
+                    .await?;
+                }
+                IndexedEvent::Fulfillment(fulfillment) => {
+                    sqlx::query(
+                        "REPLACE INTO fulfillments (id, block_number, tx_hash) VALUES ($1, $2, $3)",
+                    )
+                    .bind(format!("{:x}", fulfillment.request_id))
+                    .bind(fulfillment.block_number as i64)
+                    .bind(format!("{:x}", fulfillment.tx_hash))
+                    .execute(&mut *txn)
+                    .await?;
+                }
+                IndexedEvent::Slash(slash) => {
+                    sqlx::query(
+                        "REPLACE INTO slashes (id, stake_burned, stake_transferred, \
+                         stake_recipient, block_number, tx_hash) VALUES ($1, $2, $3, $4, $5, $6)",
+                    )
+                    .bind(format!("{:x}", slash.request_id))
+                    .bind(format!("{:x}", slash.stake_burned))
+                    .bind(format!("{:x}", slash.stake_transferred))
+                    .bind(format!("{:x}", slash.stake_recipient))
+                    .bind(slash.block_number as i64)
+                    .bind(format!("{:x}", slash.tx_hash))
+                    .execute(&mut *txn)
+                    .await?;
+                }
+            }
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+const REQUEST_SELECT: &str = "SELECT r.request, r.client_signature, r.submitted_onchain, \
+    r.block_number, r.tx_hash, b.block_timestamp FROM requests r \
+    JOIN blocks b ON b.block_number = r.block_number";
+
+const LOCK_SELECT: &str = "SELECT l.id, l.prover, l.price, l.block_number, l.tx_hash, \
+    b.block_timestamp FROM locks l JOIN blocks b ON b.block_number = l.block_number";
+
+fn request_from_row(row: &SqliteRow) -> Result<IndexedRequest, DbError> {
+    let Json(request) = row.try_get("request")?;
+    let client_signature: &str = row.try_get("client_signature")?;
+    Ok(IndexedRequest {
+        request,
+        client_signature: hex::decode(client_signature)
+            .map_err(|_| DbError::BadValue(client_signature.to_string()))?
+            .into(),
+        submitted_onchain: row.try_get("submitted_onchain")?,
+        block_number: row.try_get::<i64, _>("block_number")? as u64,
+        block_timestamp: row.try_get::<i64, _>("block_timestamp")? as u64,
+        tx_hash: parse(row.try_get("tx_hash")?)?,
+    })
+}
+
+fn lock_from_row(row: &SqliteRow) -> Result<IndexedLock, DbError> {
+    let price: Option<&str> = row.try_get("price")?;
+    Ok(IndexedLock {
+        request_id: parse_u256(row.try_get("id")?)?,
+        prover: parse(row.try_get("prover")?)?,
+        price: price.map(parse_u256).transpose()?,
+        block_number: row.try_get::<i64, _>("block_number")? as u64,
+        block_timestamp: row.try_get::<i64, _>("block_timestamp")? as u64,
+        tx_hash: parse(row.try_get("tx_hash")?)?,
+    })
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, DbError> {
+    value.parse().map_err(|_| DbError::BadValue(value.to_string()))
+}
+
+fn parse_u256(value: &str) -> Result<U256, DbError> {
+    U256::from_str_radix(value, 16).map_err(|_| DbError::BadValue(value.to_string()))
+}
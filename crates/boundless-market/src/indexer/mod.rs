@@ -0,0 +1,463 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Indexer of the Boundless Market events.
+//!
+//! The [MarketIndexer] ingests the `RequestSubmitted`, `RequestLocked`, `RequestFulfilled` and
+//! `ProverSlashed` events of a market into a local SQLite [IndexerDb], which can then answer
+//! historical queries such as all the requests of a client, or all the locks of a prover.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use alloy::{primitives::Address, providers::ProviderBuilder};
+//! use boundless_market::indexer::{IndexerDb, MarketIndexer};
+//! use std::time::Duration;
+//!
+//! # async fn example(market_address: Address, client: Address) -> anyhow::Result<()> {
+//! let provider = ProviderBuilder::new().on_http("http://localhost:8545".parse()?);
+//! let db = IndexerDb::new("sqlite:indexer.db").await?;
+//! let indexer = MarketIndexer::new(market_address, provider, db.clone());
+//!
+//! // Index up to the latest block, or keep indexing with `indexer.run(interval)`
+//! indexer.sync().await?;
+//! let requests = db.requests_by_client(client).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    time::Duration,
+};
+
+use alloy::{
+    consensus::{BlockHeader, Transaction},
+    network::Ethereum,
+    primitives::{Address, Bytes, Log as LogData, B256},
+    providers::Provider,
+    rpc::types::{BlockTransactionsKind, Filter, Log},
+    transports::TransportError,
+};
+use alloy_sol_types::{SolCall, SolEvent};
+use anyhow::{anyhow, Context};
+use thiserror::Error;
+
+use crate::contracts::{
+    boundless_market::decode_lock_calldata,
+    IBoundlessMarket::{self, IBoundlessMarketInstance},
+};
+
+mod db;
+
+pub use db::{DbError, IndexedFulfillment, IndexedLock, IndexedRequest, IndexedSlash, IndexerDb};
+use db::{IndexedBlock, IndexedEvent};
+
+/// Indexer errors.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum IndexerError {
+    /// Database error.
+    #[error("Database error: {0}")]
+    DbError(#[from] DbError),
+
+    /// RPC error.
+    #[error("RPC error: {0}")]
+    RpcError(#[from] TransportError),
+
+    /// General indexer error.
+    #[error("Indexer error: {0}")]
+    Error(#[from] anyhow::Error),
+}
+
+/// Indexer of the events of a Boundless Market deployment.
+///
+/// Each [MarketIndexer::sync] indexes the blocks since the last indexed one. Blocks that were
+/// reorged out since the last sync are detected from their hashes, and their events removed
+/// before indexing the new chain.
+pub struct MarketIndexer<P> {
+    instance: IBoundlessMarketInstance<(), P, Ethereum>,
+    db: IndexerDb,
+    start_block: u64,
+    block_range: u64,
+    confirmations: u64,
+}
+
+impl<P: Provider> MarketIndexer<P> {
+    /// Creates a new indexer of the market at the given address, storing its events in `db`.
+    pub fn new(market_address: Address, provider: P, db: IndexerDb) -> Self {
+        Self {
+            instance: IBoundlessMarketInstance::new(market_address, provider),
+            db,
+            start_block: 0,
+            block_range: 1000,
+            confirmations: 0,
+        }
+    }
+
+    /// Sets the block to start indexing from, e.g. the market deployment block.
+    ///
+    /// Only used when the database has no indexed blocks yet.
+    pub fn with_start_block(self, start_block: u64) -> Self {
+        Self { start_block, ..self }
+    }
+
+    /// Sets the number of blocks queried for logs at once.
+    pub fn with_block_range(self, block_range: u64) -> Self {
+        Self { block_range: block_range.max(1), ..self }
+    }
+
+    /// Sets the number of confirmations a block needs before it is indexed.
+    ///
+    /// Reorgs are handled regardless, confirmations avoid indexing events that are later removed.
+    pub fn with_confirmations(self, confirmations: u64) -> Self {
+        Self { confirmations, ..self }
+    }
+
+    /// Returns the database of the indexer.
+    pub fn db(&self) -> &IndexerDb {
+        &self.db
+    }
+
+    /// Indexes the blocks since the last indexed one, returning the last indexed block number.
+    pub async fn sync(&self) -> Result<Option<u64>, IndexerError> {
+        let latest = self.instance.provider().get_block_number().await?;
+        let to_block = latest.saturating_sub(self.confirmations);
+
+        let mut from_block = match self.remove_reorged_blocks().await? {
+            Some(last_block) => last_block + 1,
+            None => self.start_block,
+        };
+        while from_block <= to_block {
+            let range_end = to_block.min(from_block + self.block_range - 1);
+            self.index_range(from_block, range_end).await?;
+            from_block = range_end + 1;
+        }
+
+        Ok(self.db.last_block_number().await?)
+    }
+
+    /// Syncs the indexer every `interval`, until an error occurs.
+    pub async fn run(&self, interval: Duration) -> Result<(), IndexerError> {
+        loop {
+            let last_block = self.sync().await?;
+            tracing::debug!("Indexed market events up to block {last_block:?}");
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Removes the indexed blocks that are no longer part of the chain, and their events.
+    ///
+    /// Returns the last indexed block that is still part of the chain.
+    async fn remove_reorged_blocks(&self) -> Result<Option<u64>, IndexerError> {
+        while let Some(block) = self.db.last_block().await? {
+            let chain_block = self
+                .instance
+                .provider()
+                .get_block_by_number(block.number.into(), BlockTransactionsKind::Hashes)
+                .await?;
+            if chain_block.map(|chain_block| chain_block.header.hash) == Some(block.hash) {
+                return Ok(Some(block.number));
+            }
+            tracing::warn!("Block {} was reorged out, removing its indexed events", block.number);
+            self.db.remove_from_block(block.number).await?;
+        }
+        Ok(None)
+    }
+
+    async fn index_range(&self, from_block: u64, to_block: u64) -> Result<(), IndexerError> {
+        let filter = Filter::new()
+            .address(*self.instance.address())
+            .event_signature(vec![
+                IBoundlessMarket::RequestSubmitted::SIGNATURE_HASH,
+                IBoundlessMarket::RequestLocked::SIGNATURE_HASH,
+                IBoundlessMarket::RequestFulfilled::SIGNATURE_HASH,
+                IBoundlessMarket::ProverSlashed::SIGNATURE_HASH,
+            ])
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = self.instance.provider().get_logs(&filter).await?;
+
+        let mut blocks = BTreeMap::new();
+        let mut events = Vec::new();
+        for log in logs {
+            let block_number = log.block_number.context("block number is none")?;
+            let block = match blocks.entry(block_number) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.get_block(block_number).await?),
+            };
+            // The block fetched after the logs must be the one the logs were emitted in
+            if log.block_hash != Some(block.hash) {
+                return Err(anyhow!("Block {block_number} was reorged during indexing").into());
+            }
+            events.extend(self.events_from_log(&log, block).await?);
+        }
+        // Record the end of the range, to detect reorgs of it on the next sync
+        if let Entry::Vacant(entry) = blocks.entry(to_block) {
+            entry.insert(self.get_block(to_block).await?);
+        }
+
+        tracing::debug!(
+            "Indexing {} market events from blocks {from_block} to {to_block}",
+            events.len()
+        );
+        self.db.insert_events(blocks.values(), &events).await?;
+        Ok(())
+    }
+
+    async fn get_block(&self, block_number: u64) -> Result<IndexedBlock, IndexerError> {
+        let block = self
+            .instance
+            .provider()
+            .get_block_by_number(block_number.into(), BlockTransactionsKind::Hashes)
+            .await?
+            .with_context(|| format!("Block {block_number} not found"))?;
+        Ok(IndexedBlock {
+            number: block_number,
+            hash: block.header.hash,
+            timestamp: block.header.timestamp(),
+        })
+    }
+
+    async fn events_from_log(
+        &self,
+        log: &Log,
+        block: &IndexedBlock,
+    ) -> Result<Vec<IndexedEvent>, IndexerError> {
+        let topic = log.topic0().context("log has no topic")?;
+        let tx_hash = log.transaction_hash.context("tx hash is none")?;
+
+        if *topic == IBoundlessMarket::RequestSubmitted::SIGNATURE_HASH {
+            let tx = self.get_transaction_input(tx_hash).await?;
+            let Ok(call) = IBoundlessMarket::submitRequestCall::abi_decode(&tx, true) else {
+                tracing::warn!("Skipping request submitted by an unsupported call: {tx_hash}");
+                return Ok(vec![]);
+            };
+            return Ok(vec![IndexedEvent::Request(IndexedRequest {
+                request: call.request,
+                client_signature: call.clientSignature,
+                submitted_onchain: true,
+                block_number: block.number,
+                block_timestamp: block.timestamp,
+                tx_hash,
+            })]);
+        }
+
+        if *topic == IBoundlessMarket::RequestLocked::SIGNATURE_HASH {
+            let locked = decode_log::<IBoundlessMarket::RequestLocked>(log)?;
+            let mut lock = IndexedLock {
+                request_id: locked.requestId,
+                prover: locked.prover,
+                price: None,
+                block_number: block.number,
+                block_timestamp: block.timestamp,
+                tx_hash,
+            };
+            // Requests submitted offchain are only published onchain in the lock calldata
+            let tx = self.get_transaction_input(tx_hash).await?;
+            let Some((request, client_signature)) = decode_lock_calldata(&tx) else {
+                tracing::warn!("Indexing lock without its request, unsupported call: {tx_hash}");
+                return Ok(vec![IndexedEvent::Lock(lock)]);
+            };
+            lock.price = request.offer.price_at(block.timestamp).ok();
+            return Ok(vec![
+                IndexedEvent::Request(IndexedRequest {
+                    request,
+                    client_signature,
+                    submitted_onchain: false,
+                    block_number: block.number,
+                    block_timestamp: block.timestamp,
+                    tx_hash,
+                }),
+                IndexedEvent::Lock(lock),
+            ]);
+        }
+
+        if *topic == IBoundlessMarket::RequestFulfilled::SIGNATURE_HASH {
+            let fulfilled = decode_log::<IBoundlessMarket::RequestFulfilled>(log)?;
+            return Ok(vec![IndexedEvent::Fulfillment(IndexedFulfillment {
+                request_id: fulfilled.requestId,
+                block_number: block.number,
+                block_timestamp: block.timestamp,
+                tx_hash,
+            })]);
+        }
+
+        if *topic == IBoundlessMarket::ProverSlashed::SIGNATURE_HASH {
+            let slashed = decode_log::<IBoundlessMarket::ProverSlashed>(log)?;
+            return Ok(vec![IndexedEvent::Slash(IndexedSlash {
+                request_id: slashed.requestId,
+                stake_burned: slashed.stakeBurned,
+                stake_transferred: slashed.stakeTransferred,
+                stake_recipient: slashed.stakeRecipient,
+                block_number: block.number,
+                block_timestamp: block.timestamp,
+                tx_hash,
+            })]);
+        }
+
+        Ok(vec![])
+    }
+
+    async fn get_transaction_input(&self, tx_hash: B256) -> Result<Bytes, IndexerError> {
+        let tx = self
+            .instance
+            .provider()
+            .get_transaction_by_hash(tx_hash)
+            .await?
+            .with_context(|| format!("Transaction {tx_hash} not found"))?;
+        Ok(tx.input().clone())
+    }
+}
+
+fn decode_log<E: SolEvent>(log: &Log) -> Result<E, IndexerError> {
+    let decoded: LogData<E> = log
+        .log_decode::<E>()
+        .with_context(|| format!("Failed to decode event {}", E::SIGNATURE))?
+        .inner;
+    Ok(decoded.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        contracts::{
+            hit_points::default_allowance, test_utils::create_test_ctx, Offer, Predicate,
+            PredicateType, ProofRequest, Requirements,
+        },
+        input::InputBuilder,
+        now_timestamp,
+    };
+    use alloy::{node_bindings::Anvil, primitives::U256};
+    use guest_assessor::ASSESSOR_GUEST_ID;
+    use guest_set_builder::SET_BUILDER_ID;
+    use guest_util::ECHO_ID;
+    use risc0_zkvm::sha::Digest;
+    use tracing_test::traced_test;
+
+    fn new_request(idx: u32, client: Address) -> ProofRequest {
+        ProofRequest::new(
+            idx,
+            &client,
+            Requirements::new(
+                Digest::from(ECHO_ID),
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            "http://image_uri.null",
+            InputBuilder::new().build_inline().unwrap(),
+            Offer {
+                minPrice: U256::from(20000000000000u64),
+                maxPrice: U256::from(40000000000000u64),
+                biddingStart: now_timestamp(),
+                timeout: 100,
+                rampUpPeriod: 1,
+                lockStake: U256::from(10),
+                lockTimeout: 100,
+            },
+        )
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_index_requests_and_locks() {
+        let anvil = Anvil::new().spawn();
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+        let db = IndexerDb::new("sqlite::memory:").await.unwrap();
+        let indexer = MarketIndexer::new(
+            ctx.boundless_market_address,
+            ctx.customer_provider.clone(),
+            db.clone(),
+        );
+        let client = ctx.customer_signer.address();
+
+        // Submitted onchain
+        let request = new_request(1, client);
+        ctx.customer_market.submit_request(&request, &ctx.customer_signer).await.unwrap();
+
+        // Submitted offchain, only published onchain by its lock
+        let offchain_request = new_request(2, client);
+        let client_sig: Bytes = offchain_request
+            .sign_request(&ctx.customer_signer, ctx.boundless_market_address, anvil.chain_id())
+            .await
+            .unwrap()
+            .as_bytes()
+            .into();
+        ctx.customer_market.deposit(offchain_request.offer.maxPrice).await.unwrap();
+        ctx.prover_market
+            .deposit_stake_with_permit(default_allowance(), &ctx.prover_signer)
+            .await
+            .unwrap();
+        ctx.prover_market.lock_request(&offchain_request, &client_sig, None).await.unwrap();
+
+        let last_block = indexer.sync().await.unwrap();
+        assert_eq!(last_block, Some(ctx.customer_provider.get_block_number().await.unwrap()));
+
+        let requests = db.requests_by_client(client).await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].request, request);
+        assert!(requests[0].submitted_onchain);
+        assert_eq!(requests[1].request, offchain_request);
+        assert_eq!(requests[1].client_signature, client_sig);
+        assert!(!requests[1].submitted_onchain);
+
+        let locks = db.locks_by_prover(ctx.prover_signer.address()).await.unwrap();
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].request_id, offchain_request.id);
+        let price = locks[0].price.unwrap();
+        assert!(price >= offchain_request.offer.minPrice);
+        assert!(price <= offchain_request.offer.maxPrice);
+        assert_eq!(db.get_lock(offchain_request.id).await.unwrap(), Some(locks[0].clone()));
+        assert_eq!(db.get_lock(request.id).await.unwrap(), None);
+
+        assert_eq!(db.median_lock_price(locks[0].block_timestamp).await.unwrap(), Some(price));
+        assert_eq!(db.median_lock_price(locks[0].block_timestamp + 1).await.unwrap(), None);
+
+        // Syncing again without new blocks indexes nothing
+        assert_eq!(indexer.sync().await.unwrap(), last_block);
+        assert_eq!(db.requests_by_client(client).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_reorg() {
+        let anvil = Anvil::new().spawn();
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+        let db = IndexerDb::new("sqlite::memory:").await.unwrap();
+        let indexer = MarketIndexer::new(
+            ctx.boundless_market_address,
+            ctx.customer_provider.clone(),
+            db.clone(),
+        );
+        let client = ctx.customer_signer.address();
+        let provider = &ctx.customer_provider;
+
+        let snapshot: U256 = provider.raw_request("evm_snapshot".into(), ()).await.unwrap();
+        let request = new_request(1, client);
+        ctx.customer_market.submit_request(&request, &ctx.customer_signer).await.unwrap();
+        indexer.sync().await.unwrap();
+        assert!(db.get_request(request.id).await.unwrap().is_some());
+
+        // Replace the block of the submission with another one
+        let reverted: bool = provider.raw_request("evm_revert".into(), (snapshot,)).await.unwrap();
+        assert!(reverted);
+        let other_request = new_request(2, client);
+        ctx.customer_market.submit_request(&other_request, &ctx.customer_signer).await.unwrap();
+
+        indexer.sync().await.unwrap();
+        assert!(db.get_request(request.id).await.unwrap().is_none());
+        assert!(db.get_request(other_request.id).await.unwrap().is_some());
+        assert_eq!(db.requests_by_client(client).await.unwrap().len(), 1);
+    }
+}
@@ -12,9 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{collections::BTreeMap, io::Read};
+
 use bytemuck::Pod;
 use risc0_zkvm::serde::to_vec;
-use risc0_zkvm::ExecutorEnv;
+use risc0_zkvm::sha::{Digest, Impl, Sha256};
+use risc0_zkvm::{ExecutorEnv, Receipt};
 use rmp_serde;
 use serde::{Deserialize, Serialize};
 
@@ -30,6 +33,12 @@ enum Version {
     // MessagePack encoded version based on [InputV1].
     #[default]
     V1 = 1,
+    // MessagePack encoded version, that may also set env vars, args and assumptions.
+    V2 = 2,
+    // zstd compressed MessagePack encoded version.
+    V3 = 3,
+    // MessagePack encoded [InputManifest], referencing the chunks of an encoded [GuestEnv].
+    Manifest = 4,
 }
 
 impl From<Version> for u8 {
@@ -45,6 +54,9 @@ impl TryFrom<u8> for Version {
         match v {
             v if v == Version::V0 as u8 => Ok(Version::V0),
             v if v == Version::V1 as u8 => Ok(Version::V1),
+            v if v == Version::V2 as u8 => Ok(Version::V2),
+            v if v == Version::V3 as u8 => Ok(Version::V3),
+            v if v == Version::Manifest as u8 => Ok(Version::Manifest),
             _ => Err(Error::UnsupportedVersion(v as u64)),
         }
     }
@@ -69,6 +81,26 @@ pub enum Error {
     /// Encoded input buffer is empty, which is an invalid encoding.
     #[error("Cannot decode empty buffer as input")]
     EmptyEncodedInput,
+    /// zstd compression error
+    #[error("zstd compression error: {0}")]
+    Zstd(#[from] std::io::Error),
+    /// Decoded input is larger than the allowed size
+    #[error("Decoded input exceeds the size limit of {0} bytes")]
+    SizeLimitExceeded(usize),
+    /// Input is an [InputManifest], its chunks must be fetched and assembled before decoding
+    #[error("Input is a manifest, its chunks must be fetched and assembled first")]
+    ManifestInput,
+    /// Number of chunks does not match the [InputManifest]
+    #[error("Expected {expected} input chunks, got {actual}")]
+    ChunkCountMismatch {
+        /// Number of chunks in the manifest
+        expected: usize,
+        /// Number of chunks provided
+        actual: usize,
+    },
+    /// Chunk content does not match the digest in the [InputManifest]
+    #[error("Input chunk {0} does not match its digest")]
+    ChunkDigestMismatch(usize),
 }
 
 /// Structured input used by the Boundless prover to execute the guest for the proof request.
@@ -88,28 +120,105 @@ pub struct GuestEnv {
     /// be read. If the guest uses `env::read`, this should be encoded using the default RISC Zero
     /// codec. [InputBuilder::write] will encode the data given using the default codec.
     pub stdin: Vec<u8>,
+    /// Environment variables to be provided to the guest.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env_vars: BTreeMap<String, String>,
+    /// Program arguments to be provided to the guest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// URLs of the receipts added as assumptions of the execution, for composition.
+    ///
+    /// Each URL must point to a bincode encoded [Receipt], that the prover fetches and provides
+    /// to the executor through [ExecutorEnv::builder]'s `add_assumption`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assumption_urls: Vec<String>,
 }
 
 impl GuestEnv {
     /// Parse an encoded [GuestEnv] with version support.
+    ///
+    /// Compressed inputs are decompressed transparently. Inputs encoded as an [InputManifest]
+    /// return [Error::ManifestInput], use [InputManifest::assemble] with the fetched chunks instead.
     pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::decode_with_limit(bytes, usize::MAX)
+    }
+
+    /// Parse an encoded [GuestEnv] with version support, failing if a compressed input
+    /// decompresses to more than `max_size` bytes.
+    pub fn decode_with_limit(bytes: &[u8], max_size: usize) -> Result<Self, Error> {
         if bytes.is_empty() {
             return Err(Error::EmptyEncodedInput);
         }
         match Version::try_from(bytes[0])? {
-            Version::V0 => Ok(Self { stdin: bytes[1..].to_vec() }),
-            Version::V1 => Ok(rmp_serde::from_read(&bytes[1..])?),
+            Version::V0 => Ok(Self { stdin: bytes[1..].to_vec(), ..Default::default() }),
+            Version::V1 | Version::V2 => Ok(rmp_serde::from_read(&bytes[1..])?),
+            Version::V3 => {
+                let mut decompressed = Vec::new();
+                zstd::stream::Decoder::new(&bytes[1..])?
+                    .take((max_size as u64).saturating_add(1))
+                    .read_to_end(&mut decompressed)?;
+                if decompressed.len() > max_size {
+                    return Err(Error::SizeLimitExceeded(max_size));
+                }
+                Ok(rmp_serde::from_slice(&decompressed)?)
+            }
+            Version::Manifest => Err(Error::ManifestInput),
         }
     }
 
     /// Encode the [GuestEnv] for inclusion in a proof request.
+    ///
+    /// Environments that only set stdin are encoded as V1, so that provers that do not support
+    /// env vars, args and assumptions can still decode them.
     pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let version = if self.env_vars.is_empty()
+            && self.args.is_empty()
+            && self.assumption_urls.is_empty()
+        {
+            Version::V1
+        } else {
+            Version::V2
+        };
         let mut encoded = Vec::<u8>::new();
         // Push the version as the first byte to indicate the message version.
-        encoded.push(Version::V1.into());
+        encoded.push(version.into());
         encoded.extend_from_slice(&rmp_serde::to_vec_named(&self)?);
         Ok(encoded)
     }
+
+    /// Encode the [GuestEnv] with zstd compression, for large inputs such as blocks.
+    ///
+    /// Provers that do not support compressed inputs will fail to decode it.
+    pub fn encode_compressed(&self) -> Result<Vec<u8>, Error> {
+        let serialized = rmp_serde::to_vec_named(&self)?;
+        let mut encoded = vec![Version::V3.into()];
+        encoded.extend(zstd::encode_all(serialized.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)?);
+        Ok(encoded)
+    }
+
+    /// Create an [ExecutorEnv] from the [GuestEnv], with the given assumption receipts.
+    ///
+    /// The receipts must be the ones fetched from [GuestEnv::assumption_urls], in the same order.
+    pub fn into_executor_env<'a>(
+        self,
+        assumptions: Vec<Receipt>,
+    ) -> Result<ExecutorEnv<'a>, anyhow::Error> {
+        anyhow::ensure!(
+            assumptions.len() == self.assumption_urls.len(),
+            "expected {} assumption receipts, got {}",
+            self.assumption_urls.len(),
+            assumptions.len()
+        );
+        let mut builder = ExecutorEnv::builder();
+        builder
+            .write_slice(&self.stdin)
+            .env_vars(self.env_vars.into_iter().collect())
+            .args(&self.args);
+        for receipt in assumptions {
+            builder.add_assumption(receipt);
+        }
+        builder.build()
+    }
 }
 
 impl TryFrom<GuestEnv> for ExecutorEnv<'_> {
@@ -118,8 +227,114 @@ impl TryFrom<GuestEnv> for ExecutorEnv<'_> {
     /// Create an [ExecutorEnv], which can be used for execution and proving through the
     /// [risc0_zkvm] [Prover][risc0_zkvm::Prover] and [Executor][risc0_zkvm::Executor] traits, from
     /// the given [GuestEnv].
+    ///
+    /// Fails if the [GuestEnv] has assumptions, use [GuestEnv::into_executor_env] with the fetched
+    /// receipts instead.
     fn try_from(env: GuestEnv) -> Result<Self, Self::Error> {
-        ExecutorEnv::builder().write_slice(&env.stdin).build()
+        env.into_executor_env(vec![])
+    }
+}
+
+/// Manifest of an encoded [GuestEnv] split into chunks, each hosted at its own URL.
+///
+/// Used for inputs too large to be fetched as a single file. The manifest itself is small enough
+/// to be sent inline in the request, or uploaded like any other input. Provers fetch every chunk,
+/// check it against its digest, and decode the concatenation of the chunks as a [GuestEnv].
+///
+/// # Example
+///
+/// ```
+/// use boundless_market::input::{GuestEnv, InputBuilder, InputChunk, InputManifest};
+///
+/// let encoded = InputBuilder::new().write_slice(&[0u8; 1024]).build_env()?.encode_compressed()?;
+/// // Each chunk is uploaded, e.g. with a StorageProvider, and referenced by its URL.
+/// let chunks: Vec<Vec<u8>> = encoded.chunks(16).map(|c| c.to_vec()).collect();
+/// let manifest = InputManifest::new(
+///     chunks
+///         .iter()
+///         .enumerate()
+///         .map(|(i, chunk)| InputChunk::new(format!("https://example.com/input/{i}"), chunk))
+///         .collect(),
+/// );
+///
+/// let manifest = InputManifest::decode(&manifest.encode()?)?.unwrap();
+/// let env = manifest.assemble(&chunks, usize::MAX)?;
+/// assert_eq!(env.stdin, vec![0u8; 1024]);
+/// # Ok::<(), boundless_market::input::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct InputManifest {
+    /// Chunks of the encoded [GuestEnv], in order.
+    pub chunks: Vec<InputChunk>,
+}
+
+/// Chunk of an encoded [GuestEnv], referenced by an [InputManifest].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct InputChunk {
+    /// URL the chunk is hosted at.
+    pub url: String,
+    /// SHA-256 digest of the chunk content.
+    pub digest: Digest,
+}
+
+impl InputChunk {
+    /// Create a new chunk reference, hosted at the given URL, with the digest of its content.
+    pub fn new(url: impl Into<String>, data: &[u8]) -> Self {
+        Self { url: url.into(), digest: *Impl::hash_bytes(data) }
+    }
+}
+
+impl InputManifest {
+    /// Create a new manifest from the chunks of an encoded [GuestEnv], in order.
+    pub fn new(chunks: Vec<InputChunk>) -> Self {
+        Self { chunks }
+    }
+
+    /// Encode the [InputManifest] for inclusion in a proof request.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut encoded = vec![Version::Manifest.into()];
+        encoded.extend_from_slice(&rmp_serde::to_vec_named(&self)?);
+        Ok(encoded)
+    }
+
+    /// Parse an encoded input as an [InputManifest].
+    ///
+    /// Returns `None` if the input is an encoded [GuestEnv] instead.
+    pub fn decode(bytes: &[u8]) -> Result<Option<Self>, Error> {
+        if bytes.is_empty() {
+            return Err(Error::EmptyEncodedInput);
+        }
+        match Version::try_from(bytes[0])? {
+            Version::Manifest => Ok(Some(rmp_serde::from_read(&bytes[1..])?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Check the fetched chunks against the manifest, and decode their concatenation as a
+    /// [GuestEnv].
+    ///
+    /// Fails if the assembled input, or its decompressed content, is larger than `max_size`.
+    pub fn assemble(&self, chunks: &[Vec<u8>], max_size: usize) -> Result<GuestEnv, Error> {
+        if chunks.len() != self.chunks.len() {
+            return Err(Error::ChunkCountMismatch {
+                expected: self.chunks.len(),
+                actual: chunks.len(),
+            });
+        }
+        let mut encoded = Vec::new();
+        for (i, (chunk, data)) in self.chunks.iter().zip(chunks).enumerate() {
+            if *Impl::hash_bytes(data) != chunk.digest {
+                return Err(Error::ChunkDigestMismatch(i));
+            }
+            if encoded.len() + data.len() > max_size {
+                return Err(Error::SizeLimitExceeded(max_size));
+            }
+            encoded.extend_from_slice(data);
+        }
+        // A manifest cannot reference another manifest, decode reports it as an error
+        GuestEnv::decode_with_limit(&encoded, max_size)
     }
 }
 
@@ -134,17 +349,34 @@ pub struct InputBuilder {
     ///
     /// See [GuestEnv::stdin]
     pub stdin: Vec<u8>,
+    /// Environment variables to be provided to the guest.
+    ///
+    /// See [GuestEnv::env_vars]
+    pub env_vars: BTreeMap<String, String>,
+    /// Program arguments to be provided to the guest.
+    ///
+    /// See [GuestEnv::args]
+    pub args: Vec<String>,
+    /// URLs of the receipts added as assumptions of the execution.
+    ///
+    /// See [GuestEnv::assumption_urls]
+    pub assumption_urls: Vec<String>,
 }
 
 impl InputBuilder {
     /// Create a new input builder.
     pub fn new() -> Self {
-        Self { stdin: Vec::new() }
+        Self::default()
     }
 
     /// Build the [GuestEnv] for inclusion in a proof request.
     pub fn build_env(self) -> Result<GuestEnv, Error> {
-        Ok(GuestEnv { stdin: self.stdin })
+        Ok(GuestEnv {
+            stdin: self.stdin,
+            env_vars: self.env_vars,
+            args: self.args,
+            assumption_urls: self.assumption_urls,
+        })
     }
 
     /// Build the and encode [GuestEnv] for inclusion in a proof request.
@@ -220,6 +452,55 @@ impl InputBuilder {
         input.extend_from_slice(payload);
         Self { stdin: input, ..self }
     }
+
+    /// Set an environment variable, that the guest can read with `risc0_zkvm::guest::env::var`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use boundless_market::input::InputBuilder;
+    ///
+    /// let input = InputBuilder::new().with_env_var("RUST_LOG", "info");
+    /// ```
+    pub fn with_env_var(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let mut env_vars = self.env_vars;
+        env_vars.insert(name.into(), value.into());
+        Self { env_vars, ..self }
+    }
+
+    /// Append program arguments, that the guest can read with `risc0_zkvm::guest::env::args`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use boundless_market::input::InputBuilder;
+    ///
+    /// let input = InputBuilder::new().with_args(["--rounds", "10"]);
+    /// ```
+    pub fn with_args(self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let mut all_args = self.args;
+        all_args.extend(args.into_iter().map(Into::into));
+        Self { args: all_args, ..self }
+    }
+
+    /// Add the receipt at the given URL as an assumption of the execution.
+    ///
+    /// The URL must point to a bincode encoded [Receipt], for example one uploaded with a
+    /// [StorageProvider][crate::storage::StorageProvider]. The guest can then verify the receipt
+    /// with `risc0_zkvm::guest::env::verify`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use boundless_market::input::InputBuilder;
+    ///
+    /// let input = InputBuilder::new().with_assumption_url("https://example.com/receipt.bin");
+    /// ```
+    pub fn with_assumption_url(self, url: impl Into<String>) -> Self {
+        let mut assumption_urls = self.assumption_urls;
+        assumption_urls.push(url.into());
+        Self { assumption_urls, ..self }
+    }
 }
 
 #[cfg(test)]
@@ -239,8 +520,27 @@ mod tests {
         let parsed = GuestEnv::decode(&bytes)?;
         assert_eq!(parsed.stdin, vec![1, 2, 3]);
 
+        // Test V2
+        let v2 = InputBuilder::new().write_slice(&[1u8, 2, 3]).with_env_var("KEY", "value");
+        let bytes = v2.build_vec()?;
+        assert_eq!(bytes[0], 2);
+        let parsed = GuestEnv::decode(&bytes)?;
+        assert_eq!(parsed.stdin, vec![1, 2, 3]);
+        assert_eq!(parsed.env_vars["KEY"], "value");
+
+        // Test V3
+        let v3 = InputBuilder::new().write_slice(&[1u8, 2, 3]).build_env()?;
+        let bytes = v3.encode_compressed()?;
+        assert_eq!(bytes[0], 3);
+        let parsed = GuestEnv::decode(&bytes)?;
+        assert_eq!(parsed.stdin, vec![1, 2, 3]);
+
+        // Test manifest, which needs its chunks to be assembled
+        let bytes = InputManifest::new(vec![]).encode()?;
+        assert!(matches!(GuestEnv::decode(&bytes), Err(Error::ManifestInput)));
+
         // Test unsupported version
-        let bytes = vec![2u8, 1, 2, 3];
+        let bytes = vec![5u8, 1, 2, 3];
         let parsed = GuestEnv::decode(&bytes);
         assert!(parsed.is_err());
 
@@ -256,4 +556,96 @@ mod tests {
         assert_eq!(env, decoded_env);
         Ok(())
     }
+
+    #[test]
+    fn test_encode_decode_env_v2() -> Result<(), Error> {
+        let env = InputBuilder::new()
+            .write_slice(&[1u8, 2, 3])
+            .with_env_var("RUST_LOG", "info")
+            .with_args(["--rounds", "10"])
+            .with_assumption_url("https://example.com/receipt.bin")
+            .build_env()?;
+
+        let decoded_env = GuestEnv::decode(&env.encode()?)?;
+        assert_eq!(env, decoded_env);
+        assert_eq!(decoded_env.args, vec!["--rounds", "10"]);
+        assert_eq!(decoded_env.assumption_urls, vec!["https://example.com/receipt.bin"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1_encoding_unchanged() -> Result<(), Error> {
+        // Environments with only stdin keep the V1 encoding, readable by older provers
+        #[derive(Serialize)]
+        struct GuestEnvV1 {
+            stdin: Vec<u8>,
+        }
+        let stdin = vec![1u8, 2, 3];
+        let mut expected = vec![1u8];
+        expected.extend(rmp_serde::to_vec_named(&GuestEnvV1 { stdin: stdin.clone() })?);
+
+        let bytes = InputBuilder::new().write_slice(&stdin).build_vec()?;
+        assert_eq!(bytes, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_size_limit() -> Result<(), Error> {
+        let env = InputBuilder::new().write_slice(&[0u8; 100_000]).build_env()?;
+        let bytes = env.encode_compressed()?;
+        assert!(bytes.len() < env.encode()?.len());
+
+        assert_eq!(GuestEnv::decode_with_limit(&bytes, 200_000)?, env);
+        assert!(matches!(
+            GuestEnv::decode_with_limit(&bytes, 10_000),
+            Err(Error::SizeLimitExceeded(10_000))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_assemble() -> Result<(), Error> {
+        let env = InputBuilder::new()
+            .write_slice(&[7u8; 1000])
+            .with_env_var("KEY", "value")
+            .build_env()?;
+        let encoded = env.encode()?;
+        let mut chunks: Vec<Vec<u8>> = encoded.chunks(100).map(|c| c.to_vec()).collect();
+        let manifest = InputManifest::new(
+            chunks
+                .iter()
+                .enumerate()
+                .map(|(i, chunk)| InputChunk::new(format!("https://example.com/{i}"), chunk))
+                .collect(),
+        );
+        assert_eq!(InputManifest::decode(&manifest.encode()?)?, Some(manifest.clone()));
+        assert_eq!(InputManifest::decode(&encoded)?, None);
+
+        assert_eq!(manifest.assemble(&chunks, usize::MAX)?, env);
+        assert!(matches!(manifest.assemble(&chunks, 500), Err(Error::SizeLimitExceeded(500))));
+        assert!(matches!(
+            manifest.assemble(&chunks[1..], usize::MAX),
+            Err(Error::ChunkCountMismatch { .. })
+        ));
+
+        chunks[2][0] ^= 1;
+        assert!(matches!(
+            manifest.assemble(&chunks, usize::MAX),
+            Err(Error::ChunkDigestMismatch(2))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_executor_env_assumptions() {
+        let env = InputBuilder::new()
+            .with_assumption_url("https://example.com/receipt.bin")
+            .build_env()
+            .unwrap();
+        // The assumption receipts need to be fetched first
+        assert!(ExecutorEnv::try_from(env).is_err());
+
+        let env = InputBuilder::new().with_env_var("KEY", "value").with_args(["arg"]);
+        assert!(ExecutorEnv::try_from(env.build_env().unwrap()).is_ok());
+    }
 }
@@ -28,6 +28,9 @@ pub use alloy;
 pub mod client;
 /// Contracts module for interacting with the Boundless Market smart contracts.
 pub mod contracts;
+#[cfg(all(not(target_os = "zkvm"), feature = "indexer"))]
+/// Indexer module for querying historical market events from a local database.
+pub mod indexer;
 #[cfg(not(target_os = "zkvm"))]
 /// Input module for serializing input.
 pub mod input;
@@ -35,6 +38,15 @@ pub mod input;
 /// Order stream client module for submitting requests off-chain.
 pub mod order_stream_client;
 #[cfg(not(target_os = "zkvm"))]
+/// Pricing module for building offers from preflight cycle counts and the market history.
+pub mod pricing;
+#[cfg(not(target_os = "zkvm"))]
+/// Request manager module for resubmitting requests with escalated offers until fulfilled.
+pub mod request_manager;
+#[cfg(not(target_os = "zkvm"))]
+/// Signer module for local, keystore and remote wallet signers.
+pub mod signer;
+#[cfg(not(target_os = "zkvm"))]
 /// Storage module for interacting with the storage provider.
 pub mod storage;
 
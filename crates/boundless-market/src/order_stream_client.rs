@@ -14,6 +14,7 @@
 
 use alloy::{
     primitives::{Address, PrimitiveSignature, U256},
+    providers::Provider,
     signers::{Error as SignerErr, Signer},
 };
 use alloy_primitives::B256;
@@ -39,6 +40,10 @@ use crate::contracts::{eip712_domain, ProofRequest, RequestError};
 
 /// Order stream submission API path.
 pub const ORDER_SUBMISSION_PATH: &str = "/api/submit_order";
+/// Order stream batch submission API path.
+pub const ORDER_BATCH_SUBMISSION_PATH: &str = "/api/submit_orders";
+/// Maximum number of orders accepted in a single batch submission.
+pub const MAX_BATCH_ORDERS: usize = 1000;
 /// Order stream order list API path.
 pub const ORDER_LIST_PATH: &str = "/api/orders";
 /// Order stream nonce API path.
@@ -125,6 +130,16 @@ pub struct SubmitOrderRes {
     pub request_id: U256,
 }
 
+/// Response for submitting a batch of new orders
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct SubmitOrdersRes {
+    /// Status of the orders submission
+    pub status: String,
+    /// Request IDs submitted, in the order of the batch
+    #[schema(value_type = Vec<Object>)]
+    pub request_ids: Vec<U256>,
+}
+
 impl Order {
     /// Create a new Order
     pub fn new(request: ProofRequest, request_digest: B256, signature: PrimitiveSignature) -> Self {
@@ -132,18 +147,47 @@ impl Order {
     }
 
     /// Validate the Order
+    ///
+    /// Orders signed by a smart contract wallet are rejected, as their signature can only be
+    /// verified onchain. See [Order::validate_with_provider].
     pub fn validate(&self, market_address: Address, chain_id: u64) -> Result<(), OrderError> {
+        self.validate_request(market_address, chain_id)?;
+        self.request.verify_signature(
+            &self.signature.as_bytes().into(),
+            market_address,
+            chain_id,
+        )?;
+        Ok(())
+    }
+
+    /// Validate the Order, calling the client contract to verify the signature of orders signed
+    /// by a smart contract wallet, as specified by ERC-1271.
+    pub async fn validate_with_provider(
+        &self,
+        market_address: Address,
+        chain_id: u64,
+        provider: impl Provider,
+    ) -> Result<(), OrderError> {
+        self.validate_request(market_address, chain_id)?;
+        self.request
+            .verify_signature_with_provider(
+                &self.signature.as_bytes().into(),
+                market_address,
+                chain_id,
+                provider,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Validate the request and its digest.
+    fn validate_request(&self, market_address: Address, chain_id: u64) -> Result<(), OrderError> {
         self.request.validate()?;
         let domain = eip712_domain(market_address, chain_id);
         let hash = self.request.eip712_signing_hash(&domain.alloy_struct());
         if hash != self.request_digest {
             return Err(OrderError::RequestError(RequestError::DigestMismatch));
         }
-        self.request.verify_signature(
-            &self.signature.as_bytes().into(),
-            market_address,
-            chain_id,
-        )?;
         Ok(())
     }
 }
@@ -221,12 +265,7 @@ impl Client {
         signer: &impl Signer,
     ) -> Result<Order> {
         let url = self.base_url.join(ORDER_SUBMISSION_PATH)?;
-        let signature =
-            request.sign_request(signer, self.boundless_market_address, self.chain_id).await?;
-        let domain = eip712_domain(self.boundless_market_address, self.chain_id);
-        let request_digest = request.eip712_signing_hash(&domain.alloy_struct());
-        let order = Order { request: request.clone(), request_digest, signature };
-        order.validate(self.boundless_market_address, self.chain_id)?;
+        let order = self.sign_order(request, signer).await?;
         let order_json = serde_json::to_value(&order)?;
         let response = self
             .client
@@ -235,19 +274,55 @@ impl Client {
             .json(&order_json)
             .send()
             .await?;
+        check_response(response).await?;
 
-        // Check for any errors in the response
-        if let Err(err) = response.error_for_status_ref() {
-            let error_message = match response.json::<serde_json::Value>().await {
-                Ok(json_body) => {
-                    json_body["msg"].as_str().unwrap_or("Unknown server error").to_string()
-                }
-                Err(_) => "Failed to read server error message".to_string(),
-            };
+        Ok(order)
+    }
 
-            return Err(anyhow::Error::new(err).context(error_message));
+    /// Submit a batch of proof requests to the order stream server in a single call.
+    ///
+    /// The server either accepts all of the requests, or none of them. Batches are limited to
+    /// [MAX_BATCH_ORDERS] requests.
+    pub async fn submit_requests(
+        &self,
+        requests: &[ProofRequest],
+        signer: &impl Signer,
+    ) -> Result<Vec<Order>> {
+        anyhow::ensure!(
+            requests.len() <= MAX_BATCH_ORDERS,
+            "Batch of {} requests exceeds the maximum of {MAX_BATCH_ORDERS}",
+            requests.len()
+        );
+        let url = self.base_url.join(ORDER_BATCH_SUBMISSION_PATH)?;
+        let mut orders = Vec::with_capacity(requests.len());
+        for request in requests {
+            orders.push(self.sign_order(request, signer).await?);
         }
+        let orders_json = serde_json::to_value(&orders)?;
+        let response = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .json(&orders_json)
+            .send()
+            .await?;
+        check_response(response).await?;
+
+        Ok(orders)
+    }
 
+    async fn sign_order(&self, request: &ProofRequest, signer: &impl Signer) -> Result<Order> {
+        let signature =
+            request.sign_request(signer, self.boundless_market_address, self.chain_id).await?;
+        let domain = eip712_domain(self.boundless_market_address, self.chain_id);
+        let request_digest = request.eip712_signing_hash(&domain.alloy_struct());
+        let order = Order { request: request.clone(), request_digest, signature };
+        if request.is_smart_contract_signed() {
+            // The signature of smart contract signed requests is verified onchain by the server
+            order.validate_request(self.boundless_market_address, self.chain_id)?;
+        } else {
+            order.validate(self.boundless_market_address, self.chain_id)?;
+        }
         Ok(order)
     }
 
@@ -359,6 +434,21 @@ impl Client {
     }
 }
 
+/// Returns an error with the message of the server if the response is not successful.
+async fn check_response(response: reqwest::Response) -> Result<()> {
+    if let Err(err) = response.error_for_status_ref() {
+        let error_message = match response.json::<serde_json::Value>().await {
+            Ok(json_body) => {
+                json_body["msg"].as_str().unwrap_or("Unknown server error").to_string()
+            }
+            Err(_) => "Failed to read server error message".to_string(),
+        };
+
+        return Err(anyhow::Error::new(err).context(error_message));
+    }
+    Ok(())
+}
+
 /// Stream of Order messages from a WebSocket
 ///
 /// This function takes a WebSocket stream and returns a stream of `Order` messages.
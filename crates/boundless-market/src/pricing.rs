@@ -0,0 +1,434 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers to price an [Offer] from the cycle count of a preflight execution and the recent
+//! history of the market.
+//!
+//! The market does not record the cycle count of the requests it fulfills, so the history can not
+//! be turned into a price per million cycles. Instead, [MarketStats] records where between their
+//! minimum and maximum price requests were locked, how long after the bidding start they were
+//! locked and how long provers took to fulfill them. The position of the lock in the price range
+//! does not depend on the size of the request. [OfferBuilder] uses the configured prices per
+//! million cycles as a baseline, shifts the price range up by the position below which the
+//! fraction of recent requests given by the [Urgency] were locked, and raises the timeouts to what
+//! the market needed to lock and fulfill them.
+//!
+//! ```no_run
+//! use boundless_market::{
+//!     contracts::boundless_market::BoundlessMarketService,
+//!     input::GuestEnv,
+//!     pricing::{OfferBuilder, Urgency},
+//! };
+//! # async fn example<P: alloy::providers::Provider>(
+//! #     market: BoundlessMarketService<P>,
+//! #     elf: &[u8],
+//! #     env: GuestEnv,
+//! # ) -> anyhow::Result<()> {
+//! let cycles = OfferBuilder::preflight(elf, env)?;
+//! let stats = market.query_market_stats(10_000).await?;
+//! let offer = OfferBuilder::from_preflight(cycles, Urgency::Normal)
+//!     .with_market_stats(stats)
+//!     .build();
+//! # Ok(())
+//! # }
+//! ```
+
+use alloy::primitives::U256;
+use clap::ValueEnum;
+use risc0_zkvm::{default_executor, SessionInfo};
+
+use crate::{contracts::Offer, input::GuestEnv, now_timestamp};
+
+/// Default price per million cycles used as the minimum price, 0.001 ETH.
+const DEFAULT_MIN_PRICE_PER_MCYCLE: u64 = 1_000_000_000_000_000;
+/// Default price per million cycles used as the maximum price, 0.002 ETH.
+const DEFAULT_MAX_PRICE_PER_MCYCLE: u64 = 2_000_000_000_000_000;
+/// Default lock-in stake, 5 HP.
+const DEFAULT_LOCK_STAKE: u128 = 5_000_000_000_000_000_000;
+/// Default delay between the creation of the offer and its bidding start, in seconds.
+///
+/// Lets provers see and evaluate the request before the price starts to ramp up.
+const DEFAULT_BIDDING_DELAY: u64 = 30;
+/// Default estimate of the proving time of a million cycles, in seconds.
+const DEFAULT_SECS_PER_MCYCLE: u64 = 10;
+/// Default minimum ramp-up period, in seconds.
+const DEFAULT_MIN_RAMP_UP_PERIOD: u32 = 300;
+/// Default minimum lock-in timeout, in seconds.
+const DEFAULT_MIN_LOCK_TIMEOUT: u32 = 900;
+/// Default limit of the raise of the prices by the recent locks, in percent of the maximum price.
+const DEFAULT_MAX_PRICE_BUMP_PERCENT: u64 = 50;
+
+/// How urgently a request should be fulfilled.
+///
+/// Each level targets a probability for the request to be locked and fulfilled within its
+/// timeouts, based on the recent history of the market.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Urgency {
+    /// Targets the conditions under which half of the recent requests were locked.
+    Low,
+    /// Targets the conditions under which 90% of the recent requests were locked.
+    #[default]
+    Normal,
+    /// Targets the conditions under which 99% of the recent requests were locked.
+    High,
+}
+
+impl Urgency {
+    /// The target probability for the request to be locked and fulfilled.
+    pub fn target_probability(&self) -> f64 {
+        match self {
+            Urgency::Low => 0.5,
+            Urgency::Normal => 0.9,
+            Urgency::High => 0.99,
+        }
+    }
+}
+
+/// Scale of the lock positions returned by [LockSample::position], one million for a lock at the
+/// maximum price.
+pub const LOCK_POSITION_SCALE: u64 = 1_000_000;
+
+/// A request locked in the market.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockSample {
+    /// Price the request was locked at.
+    pub price: U256,
+    /// Minimum price of the request.
+    pub min_price: U256,
+    /// Maximum price of the request.
+    pub max_price: U256,
+    /// Seconds between the bidding start of the request and its lock.
+    pub lock_delay: u64,
+    /// Seconds between the lock of the request and its fulfillment, if it was fulfilled.
+    pub fulfillment_delay: Option<u64>,
+}
+
+impl LockSample {
+    /// Returns where between its minimum and maximum price the request was locked, from 0 at the
+    /// minimum price to [LOCK_POSITION_SCALE] at the maximum price.
+    ///
+    /// Requests with a fixed price are locked at their maximum price.
+    pub fn position(&self) -> u64 {
+        if self.max_price <= self.min_price {
+            return LOCK_POSITION_SCALE;
+        }
+        let position = self.price.saturating_sub(self.min_price) * U256::from(LOCK_POSITION_SCALE)
+            / (self.max_price - self.min_price);
+        position.saturating_to::<u64>().min(LOCK_POSITION_SCALE)
+    }
+}
+
+/// Recent history of the requests locked in the market.
+#[derive(Clone, Debug, Default)]
+pub struct MarketStats {
+    samples: Vec<LockSample>,
+}
+
+impl MarketStats {
+    /// Creates the statistics from the given locked requests.
+    pub fn new(samples: Vec<LockSample>) -> Self {
+        Self { samples }
+    }
+
+    /// Returns the locked requests the statistics were built from.
+    pub fn samples(&self) -> &[LockSample] {
+        &self.samples
+    }
+
+    /// Returns the position in their price range, as given by [LockSample::position], under
+    /// which the given fraction of the requests were locked.
+    pub fn lock_position(&self, probability: f64) -> Option<u64> {
+        quantile(self.samples.iter().map(LockSample::position).collect(), probability)
+    }
+
+    /// Returns the number of seconds after the bidding start within which the given fraction of
+    /// the requests were locked.
+    pub fn lock_delay(&self, probability: f64) -> Option<u64> {
+        quantile(self.samples.iter().map(|sample| sample.lock_delay).collect(), probability)
+    }
+
+    /// Returns the number of seconds after the lock within which the given fraction of the
+    /// fulfilled requests were fulfilled.
+    pub fn fulfillment_delay(&self, probability: f64) -> Option<u64> {
+        quantile(
+            self.samples.iter().filter_map(|sample| sample.fulfillment_delay).collect(),
+            probability,
+        )
+    }
+}
+
+/// Returns the smallest value such that at least the given fraction of the values are below or
+/// equal to it.
+fn quantile<T: Ord + Copy>(mut values: Vec<T>, probability: f64) -> Option<T> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let rank = (probability.clamp(0.0, 1.0) * values.len() as f64).ceil() as usize;
+    Some(values[rank.clamp(1, values.len()) - 1])
+}
+
+/// Returns the number of cycles a prover is paid for in the given session.
+///
+/// Provers prove each segment padded to a power of two, so this is the sum of the padded segment
+/// sizes rather than the user cycles of the session.
+pub fn session_cycles(session: &SessionInfo) -> u64 {
+    session.segments.iter().map(|segment| 1 << segment.po2).sum()
+}
+
+/// Builder of an [Offer] from the cycle count of a request and the recent history of the market.
+#[derive(Clone, Debug)]
+pub struct OfferBuilder {
+    cycles: u64,
+    urgency: Urgency,
+    min_price_per_mcycle: U256,
+    max_price_per_mcycle: U256,
+    lock_stake: U256,
+    bidding_delay: u64,
+    secs_per_mcycle: u64,
+    min_ramp_up_period: u32,
+    min_lock_timeout: u32,
+    max_price_bump_percent: u64,
+    stats: MarketStats,
+}
+
+impl OfferBuilder {
+    /// Creates a builder of an offer for a request of the given number of cycles, as returned by
+    /// [OfferBuilder::preflight], with the default prices and timeouts and no market history.
+    pub fn from_preflight(cycles: u64, urgency: Urgency) -> Self {
+        Self {
+            cycles,
+            urgency,
+            min_price_per_mcycle: U256::from(DEFAULT_MIN_PRICE_PER_MCYCLE),
+            max_price_per_mcycle: U256::from(DEFAULT_MAX_PRICE_PER_MCYCLE),
+            lock_stake: U256::from(DEFAULT_LOCK_STAKE),
+            bidding_delay: DEFAULT_BIDDING_DELAY,
+            secs_per_mcycle: DEFAULT_SECS_PER_MCYCLE,
+            min_ramp_up_period: DEFAULT_MIN_RAMP_UP_PERIOD,
+            min_lock_timeout: DEFAULT_MIN_LOCK_TIMEOUT,
+            max_price_bump_percent: DEFAULT_MAX_PRICE_BUMP_PERCENT,
+            stats: MarketStats::default(),
+        }
+    }
+
+    /// Sets the price per million cycles used as the minimum price of the offer.
+    pub fn with_min_price_per_mcycle(self, min_price_per_mcycle: U256) -> Self {
+        Self { min_price_per_mcycle, ..self }
+    }
+
+    /// Sets the price per million cycles used as the baseline of the maximum price of the offer.
+    pub fn with_max_price_per_mcycle(self, max_price_per_mcycle: U256) -> Self {
+        Self { max_price_per_mcycle, ..self }
+    }
+
+    /// Sets the lock-in stake of the offer.
+    pub fn with_lock_stake(self, lock_stake: U256) -> Self {
+        Self { lock_stake, ..self }
+    }
+
+    /// Sets the delay, in seconds, between the creation of the offer and its bidding start.
+    pub fn with_bidding_delay(self, bidding_delay: u64) -> Self {
+        Self { bidding_delay, ..self }
+    }
+
+    /// Sets the estimated number of seconds a prover needs to prove a million cycles.
+    pub fn with_secs_per_mcycle(self, secs_per_mcycle: u64) -> Self {
+        Self { secs_per_mcycle, ..self }
+    }
+
+    /// Sets the minimum ramp-up period of the offer, in seconds.
+    pub fn with_min_ramp_up_period(self, min_ramp_up_period: u32) -> Self {
+        Self { min_ramp_up_period, ..self }
+    }
+
+    /// Sets the minimum lock-in timeout of the offer, in seconds.
+    pub fn with_min_lock_timeout(self, min_lock_timeout: u32) -> Self {
+        Self { min_lock_timeout, ..self }
+    }
+
+    /// Sets how much the recent locks can raise the prices of the offer, in percent of the maximum
+    /// price per million cycles times the cycles of the request.
+    pub fn with_max_price_bump_percent(self, max_price_bump_percent: u64) -> Self {
+        Self { max_price_bump_percent, ..self }
+    }
+
+    /// Sets the market history used to adjust the prices and timeouts of the offer.
+    pub fn with_market_stats(self, stats: MarketStats) -> Self {
+        Self { stats, ..self }
+    }
+
+    /// Executes the given guest with the [default_executor] and returns the number of cycles a
+    /// prover is paid for.
+    pub fn preflight(elf: &[u8], env: GuestEnv) -> anyhow::Result<u64> {
+        let session = default_executor().execute(env.try_into()?, elf)?;
+        Ok(session_cycles(&session))
+    }
+
+    /// Builds the offer.
+    ///
+    /// The prices start from the configured prices per million cycles. Both are raised by the
+    /// position in the price range under which the target fraction of the recent requests were
+    /// locked, by at most the configured percentage of the maximum price, and the ramp-up period
+    /// is extended to the delay within which they were locked. The lock-in timeout leaves twice the longest of the
+    /// estimated proving time and the recent fulfillment delay for the prover to fulfill the
+    /// request, and the timeout extends it by a third.
+    pub fn build(self) -> Offer {
+        let probability = self.urgency.target_probability();
+        let mcycles = self.cycles.div_ceil(1_000_000);
+
+        let base_max_price = self.max_price_per_mcycle * U256::from(mcycles);
+        let base_min_price = (self.min_price_per_mcycle * U256::from(mcycles)).min(base_max_price);
+        let position = self.stats.lock_position(probability).unwrap_or_default();
+        let bump = ((base_max_price - base_min_price) * U256::from(position)
+            / U256::from(LOCK_POSITION_SCALE))
+        .min(base_max_price * U256::from(self.max_price_bump_percent) / U256::from(100));
+        let min_price = base_min_price + bump;
+        let max_price = base_max_price + bump;
+
+        let lock_delay = self.stats.lock_delay(probability).unwrap_or_default();
+        let ramp_up_period = saturating_u32(lock_delay).max(self.min_ramp_up_period);
+
+        let proving_time = (mcycles * self.secs_per_mcycle)
+            .max(self.stats.fulfillment_delay(probability).unwrap_or_default());
+        let lock_timeout =
+            saturating_u32(ramp_up_period as u64 + 2 * proving_time).max(self.min_lock_timeout);
+        let timeout = lock_timeout.saturating_add(lock_timeout / 3);
+
+        Offer::default()
+            .with_min_price(min_price)
+            .with_max_price(max_price)
+            .with_lock_stake(self.lock_stake)
+            .with_bidding_start(now_timestamp() + self.bidding_delay)
+            .with_ramp_up_period(ramp_up_period)
+            .with_lock_timeout(lock_timeout)
+            .with_timeout(timeout)
+    }
+}
+
+fn saturating_u32(value: u64) -> u32 {
+    value.try_into().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(
+        min_price: u128,
+        max_price: u128,
+        position_percent: u128,
+        lock_delay: u64,
+        fulfillment_delay: Option<u64>,
+    ) -> LockSample {
+        let price = min_price + (max_price - min_price) * position_percent / 100;
+        LockSample {
+            price: U256::from(price),
+            min_price: U256::from(min_price),
+            max_price: U256::from(max_price),
+            lock_delay,
+            fulfillment_delay,
+        }
+    }
+
+    #[test]
+    fn test_quantile() {
+        assert_eq!(quantile::<u64>(vec![], 0.5), None);
+        assert_eq!(quantile(vec![3u64, 1, 2, 4], 0.5), Some(2));
+        assert_eq!(quantile(vec![3u64, 1, 2, 4], 0.9), Some(4));
+        assert_eq!(quantile(vec![3u64, 1, 2, 4], 0.0), Some(1));
+        assert_eq!(quantile(vec![3u64, 1, 2, 4], 1.0), Some(4));
+    }
+
+    #[test]
+    fn test_from_preflight_without_history() {
+        let offer = OfferBuilder::from_preflight(2_500_000, Urgency::Normal).build();
+        assert_eq!(offer.minPrice, U256::from(3 * DEFAULT_MIN_PRICE_PER_MCYCLE));
+        assert_eq!(offer.maxPrice, U256::from(3 * DEFAULT_MAX_PRICE_PER_MCYCLE));
+        assert_eq!(offer.lockStake, U256::from(DEFAULT_LOCK_STAKE));
+        assert_eq!(offer.rampUpPeriod, DEFAULT_MIN_RAMP_UP_PERIOD);
+        assert_eq!(offer.lockTimeout, DEFAULT_MIN_LOCK_TIMEOUT);
+        assert_eq!(offer.timeout, DEFAULT_MIN_LOCK_TIMEOUT * 4 / 3);
+        assert!(offer.biddingStart >= now_timestamp());
+    }
+
+    #[test]
+    fn test_lock_position() {
+        // The position does not depend on the size of the request
+        assert_eq!(sample(1_000, 2_000, 50, 0, None).position(), LOCK_POSITION_SCALE / 2);
+        assert_eq!(
+            sample(10u128.pow(18), 3 * 10u128.pow(18), 50, 0, None).position(),
+            LOCK_POSITION_SCALE / 2
+        );
+        assert_eq!(sample(1_000, 2_000, 0, 0, None).position(), 0);
+        assert_eq!(sample(1_000, 2_000, 100, 0, None).position(), LOCK_POSITION_SCALE);
+        // Fixed price requests are locked at their maximum price
+        assert_eq!(sample(1_000, 1_000, 0, 0, None).position(), LOCK_POSITION_SCALE);
+    }
+
+    #[test]
+    fn test_from_preflight_with_history() {
+        // Small and large requests, the i-th locked at i tenths of its price range
+        let stats = MarketStats::new(
+            (1..=10u64)
+                .map(|i| {
+                    let (min_price, max_price) = match i % 2 {
+                        0 => (10u128.pow(18), 3 * 10u128.pow(18)),
+                        _ => (1_000_000_000, 2_000_000_000),
+                    };
+                    sample(min_price, max_price, i as u128 * 10, i * 100, Some(i * 200))
+                })
+                .collect(),
+        );
+        let build = |cycles, urgency| {
+            OfferBuilder::from_preflight(cycles, urgency).with_market_stats(stats.clone()).build()
+        };
+        let range = DEFAULT_MAX_PRICE_PER_MCYCLE - DEFAULT_MIN_PRICE_PER_MCYCLE;
+
+        // Both prices are raised by where in their range 90% of the recent requests were locked
+        let offer = build(1_000_000, Urgency::Normal);
+        assert_eq!(offer.minPrice, U256::from(DEFAULT_MIN_PRICE_PER_MCYCLE + range * 9 / 10));
+        assert_eq!(offer.maxPrice, U256::from(DEFAULT_MAX_PRICE_PER_MCYCLE + range * 9 / 10));
+        assert_eq!(offer.rampUpPeriod, 900);
+        assert_eq!(offer.lockTimeout, 900 + 2 * 1800);
+
+        // Lower urgency targets less of the recent requests
+        let offer = build(4_000_000, Urgency::Low);
+        assert_eq!(offer.minPrice, U256::from(4 * DEFAULT_MIN_PRICE_PER_MCYCLE + 4 * range / 2));
+        assert_eq!(offer.maxPrice, U256::from(4 * DEFAULT_MAX_PRICE_PER_MCYCLE + 4 * range / 2));
+        assert_eq!(offer.rampUpPeriod, 500);
+        assert_eq!(offer.lockTimeout, 500 + 2 * 1000);
+
+        // The raise is bounded, and can be disabled
+        let offer = OfferBuilder::from_preflight(1_000_000, Urgency::Normal)
+            .with_max_price_bump_percent(10)
+            .with_market_stats(stats.clone())
+            .build();
+        let bump = DEFAULT_MAX_PRICE_PER_MCYCLE / 10;
+        assert_eq!(offer.minPrice, U256::from(DEFAULT_MIN_PRICE_PER_MCYCLE + bump));
+        assert_eq!(offer.maxPrice, U256::from(DEFAULT_MAX_PRICE_PER_MCYCLE + bump));
+        let offer = OfferBuilder::from_preflight(1_000_000, Urgency::Normal)
+            .with_max_price_bump_percent(0)
+            .with_market_stats(stats.clone())
+            .build();
+        assert_eq!(offer.minPrice, U256::from(DEFAULT_MIN_PRICE_PER_MCYCLE));
+        assert_eq!(offer.maxPrice, U256::from(DEFAULT_MAX_PRICE_PER_MCYCLE));
+
+        // Large requests keep their price per mcycle and estimated proving time
+        let offer = build(300_000_000, Urgency::High);
+        assert_eq!(offer.minPrice, U256::from(300 * DEFAULT_MAX_PRICE_PER_MCYCLE));
+        assert_eq!(offer.maxPrice, U256::from(300 * (DEFAULT_MAX_PRICE_PER_MCYCLE + range)));
+        assert_eq!(offer.lockTimeout, 1000 + 2 * 300 * DEFAULT_SECS_PER_MCYCLE as u32);
+        assert!(offer.timeout > offer.lockTimeout);
+    }
+}
@@ -0,0 +1,415 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Submission of requests that are resubmitted with a higher price until they are fulfilled.
+//!
+//! The market has no way to cancel a request, so a resubmitted request does not replace the
+//! previous ones: they all stay open until they expire, and any of them can still be locked and
+//! fulfilled. The [RequestManager] keeps watching all of them, reports the first fulfillment, and
+//! bounds the sum of the max prices of all the submitted requests, which is the most the client
+//! can be charged.
+//!
+//! ```no_run
+//! use boundless_market::{
+//!     client::ClientBuilder, contracts::ProofRequest, request_manager::RequestManager,
+//! };
+//! use std::time::Duration;
+//! # async fn example(request: ProofRequest) -> anyhow::Result<()> {
+//! let client = ClientBuilder::default().build().await?;
+//! let manager = RequestManager::new(client)
+//!     .with_unlocked_timeout(Duration::from_secs(120))
+//!     .with_price_increase_percent(25);
+//! let fulfillment = manager.submit_and_wait(request).await?;
+//! println!("Fulfilled request 0x{:x}", fulfillment.request.id);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{fmt::Debug, time::Duration};
+
+use alloy::{
+    network::Ethereum,
+    primitives::{Bytes, U256},
+    providers::Provider,
+};
+use futures_util::{stream::SelectAll, StreamExt};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::{
+    client::{Client, ClientError},
+    contracts::{
+        boundless_market::{MarketError, RequestEvent},
+        Offer, ProofRequest,
+    },
+    now_timestamp,
+    storage::StorageProvider,
+};
+
+/// Default time after which a request that was not locked is resubmitted.
+const DEFAULT_UNLOCKED_TIMEOUT: Duration = Duration::from_secs(300);
+/// Default increase of the prices of a resubmitted request, in percent.
+const DEFAULT_PRICE_INCREASE_PERCENT: u64 = 20;
+/// Default maximum number of submissions of a request.
+const DEFAULT_MAX_ATTEMPTS: usize = 5;
+/// Default interval between status checks of the submitted requests.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Request manager errors.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum RequestManagerError {
+    /// Client error.
+    #[error("Client error {0}")]
+    ClientError(#[from] ClientError),
+
+    /// Market error.
+    #[error("Market error {0}")]
+    MarketError(#[from] MarketError),
+
+    /// The request was not fulfilled within the maximum number of submissions.
+    #[error("Request not fulfilled after {0} submissions")]
+    MaxAttemptsReached(usize),
+
+    /// The request could not be resubmitted with a higher price within the maximum total spend.
+    #[error("Request not fulfilled within the maximum total spend of {0}")]
+    MaxSpendReached(U256),
+}
+
+/// The fulfillment of a request submitted by a [RequestManager].
+#[derive(Clone, Debug)]
+pub struct ManagedFulfillment {
+    /// The submitted request that was fulfilled.
+    pub request: ProofRequest,
+    /// Journal of the proof.
+    pub journal: Bytes,
+    /// Seal of the proof.
+    pub seal: Bytes,
+    /// All the submitted requests, including the fulfilled one, in the order of submission.
+    pub attempts: Vec<ProofRequest>,
+}
+
+/// Status of a submitted request, as last reported by the market.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AttemptStatus {
+    Open,
+    Locked,
+    Done,
+}
+
+/// Submits requests, and resubmits them with an escalated offer until they are fulfilled.
+///
+/// A request is resubmitted, with a new ID and the same image and input, when none of its
+/// submissions is locked within the unlocked timeout, or when all of them expired unfulfilled.
+/// Each resubmission increases the min and max prices of the offer by the configured percentage.
+pub struct RequestManager<P, S> {
+    client: Client<P, S>,
+    unlocked_timeout: Duration,
+    price_increase_percent: u64,
+    max_total_spend: Option<U256>,
+    max_attempts: usize,
+    poll_interval: Duration,
+    offchain: bool,
+}
+
+impl<P, S> RequestManager<P, S>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+    S: StorageProvider + Clone,
+    <S as StorageProvider>::Error: Debug,
+{
+    /// Creates a new request manager submitting requests with the given client.
+    ///
+    /// The client must have a local signer to sign the requests.
+    pub fn new(client: Client<P, S>) -> Self {
+        Self {
+            client,
+            unlocked_timeout: DEFAULT_UNLOCKED_TIMEOUT,
+            price_increase_percent: DEFAULT_PRICE_INCREASE_PERCENT,
+            max_total_spend: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            offchain: false,
+        }
+    }
+
+    /// Sets the time after which a request that was not locked is resubmitted.
+    pub fn with_unlocked_timeout(self, unlocked_timeout: Duration) -> Self {
+        Self { unlocked_timeout, ..self }
+    }
+
+    /// Sets the increase of the prices of a resubmitted request, in percent.
+    pub fn with_price_increase_percent(self, price_increase_percent: u64) -> Self {
+        Self { price_increase_percent, ..self }
+    }
+
+    /// Sets the maximum sum of the max prices of all the submissions of a request.
+    pub fn with_max_total_spend(self, max_total_spend: U256) -> Self {
+        Self { max_total_spend: Some(max_total_spend), ..self }
+    }
+
+    /// Sets the maximum number of submissions of a request.
+    pub fn with_max_attempts(self, max_attempts: usize) -> Self {
+        Self { max_attempts, ..self }
+    }
+
+    /// Sets the interval between status checks of the submitted requests.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self { poll_interval, ..self }
+    }
+
+    /// Sets whether to submit the requests offchain via the order stream service.
+    pub fn with_offchain(self, offchain: bool) -> Self {
+        Self { offchain, ..self }
+    }
+
+    /// Returns the client used to submit the requests.
+    pub fn client(&self) -> &Client<P, S> {
+        &self.client
+    }
+
+    /// Submits the request and waits until one of its submissions is fulfilled, resubmitting it
+    /// with an escalated offer when it stays unlocked or expires unfulfilled.
+    ///
+    /// If the request ID is not set, a random ID will be generated.
+    /// If the bidding start is not set, the current time will be used, plus a delay.
+    pub async fn submit_and_wait(
+        &self,
+        request: ProofRequest,
+    ) -> Result<ManagedFulfillment, RequestManagerError> {
+        let mut attempts: Vec<ProofRequest> = Vec::new();
+        let mut statuses = Vec::new();
+        let mut events = SelectAll::new();
+        let mut total_spend = U256::ZERO;
+        let mut request = request;
+
+        loop {
+            if attempts.len() >= self.max_attempts {
+                return Err(RequestManagerError::MaxAttemptsReached(attempts.len()));
+            }
+            let request_id = self.submit(&mut request).await?;
+            tracing::info!(
+                "Submitted request 0x{request_id:x}, attempt {} with max price {}",
+                attempts.len() + 1,
+                request.offer.maxPrice
+            );
+            total_spend += request.offer.maxPrice;
+            let index = attempts.len();
+            events.push(Box::pin(
+                self.client
+                    .watch_request_with(request_id, Some(request.expires_at()), self.poll_interval)
+                    .map(move |event| (index, event)),
+            ));
+            attempts.push(request.clone());
+            statuses.push(AttemptStatus::Open);
+            let resubmit_at = Instant::now() + self.unlocked_timeout;
+
+            // Wait for a fulfillment, or until none of the submissions is making progress
+            loop {
+                let locked = statuses.contains(&AttemptStatus::Locked);
+                let all_done = statuses.iter().all(|status| *status == AttemptStatus::Done);
+                if all_done || (!locked && Instant::now() >= resubmit_at) {
+                    break;
+                }
+                let event = tokio::select! {
+                    event = events.next() => event,
+                    _ = tokio::time::sleep_until(resubmit_at), if !locked => continue,
+                };
+                let Some((index, event)) = event else {
+                    // All the watched submissions reached a final status without a fulfillment
+                    break;
+                };
+                match event? {
+                    RequestEvent::Locked { prover, .. } => {
+                        tracing::info!("Request 0x{:x} locked by {prover}", attempts[index].id);
+                        statuses[index] = AttemptStatus::Locked;
+                    }
+                    RequestEvent::Fulfilled => {
+                        let request = attempts[index].clone();
+                        let (journal, seal) = self
+                            .client
+                            .boundless_market
+                            .get_request_fulfillment(request.id)
+                            .await?;
+                        return Ok(ManagedFulfillment { request, journal, seal, attempts });
+                    }
+                    RequestEvent::Expired => {
+                        tracing::info!("Request 0x{:x} expired unfulfilled", attempts[index].id);
+                        statuses[index] = AttemptStatus::Done;
+                    }
+                    RequestEvent::Slashed { .. } => {
+                        tracing::info!(
+                            "Request 0x{:x} not fulfilled by its prover",
+                            attempts[index].id
+                        );
+                        statuses[index] = AttemptStatus::Done;
+                    }
+                    _ => {}
+                }
+            }
+
+            request = self.escalate(&request, total_spend)?;
+        }
+    }
+
+    /// Submits the request, setting its ID and bidding start if not set, and returns its ID.
+    async fn submit(&self, request: &mut ProofRequest) -> Result<U256, RequestManagerError> {
+        if request.id == U256::ZERO {
+            request.id = self.client.boundless_market.request_id_from_rand().await?;
+        }
+        if request.offer.biddingStart == 0 {
+            request.offer.biddingStart = now_timestamp() + self.client.bidding_start_delay;
+        }
+        let (request_id, _) = if self.offchain {
+            self.client.submit_request_offchain(request).await?
+        } else {
+            self.client.submit_request(request).await?
+        };
+        Ok(request_id)
+    }
+
+    /// Returns the resubmission of the request, with a new ID and bidding start and increased
+    /// prices, capped to the remaining spend.
+    fn escalate(
+        &self,
+        request: &ProofRequest,
+        total_spend: U256,
+    ) -> Result<ProofRequest, RequestManagerError> {
+        let mut offer = escalate_offer(&request.offer, self.price_increase_percent);
+        if let Some(max_total_spend) = self.max_total_spend {
+            let remaining = max_total_spend.saturating_sub(total_spend);
+            if remaining <= request.offer.maxPrice {
+                return Err(RequestManagerError::MaxSpendReached(max_total_spend));
+            }
+            offer.maxPrice = offer.maxPrice.min(remaining);
+            offer.minPrice = offer.minPrice.min(offer.maxPrice);
+        }
+        offer.biddingStart = 0;
+        Ok(ProofRequest { id: U256::ZERO, offer, ..request.clone() })
+    }
+}
+
+/// Increases the min and max prices of the offer by the given percentage.
+fn escalate_offer(offer: &Offer, price_increase_percent: u64) -> Offer {
+    let increase = |price: U256| {
+        price.saturating_mul(U256::from(100 + price_increase_percent)) / U256::from(100)
+    };
+    Offer {
+        minPrice: increase(offer.minPrice),
+        maxPrice: increase(offer.maxPrice),
+        ..offer.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        contracts::{test_utils::create_test_ctx, Predicate, PredicateType, Requirements},
+        input::InputBuilder,
+        storage::TempFileStorageProvider,
+    };
+    use alloy::node_bindings::Anvil;
+    use guest_assessor::ASSESSOR_GUEST_ID;
+    use guest_set_builder::SET_BUILDER_ID;
+    use guest_util::ECHO_ID;
+    use risc0_zkvm::sha::Digest;
+    use tracing_test::traced_test;
+
+    fn new_request(max_price: u64) -> ProofRequest {
+        ProofRequest::builder()
+            .with_image_url("http://image_uri.null")
+            .with_input(InputBuilder::new().build_inline().unwrap())
+            .with_requirements(Requirements::new(
+                Digest::from(ECHO_ID),
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ))
+            .with_offer(
+                Offer::default()
+                    .with_min_price(U256::from(max_price / 2))
+                    .with_max_price(U256::from(max_price))
+                    .with_timeout(100)
+                    .with_lock_timeout(100)
+                    .with_ramp_up_period(1)
+                    .with_lock_stake(U256::from(10)),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_escalate_offer() {
+        let offer =
+            Offer::default().with_min_price(U256::from(100)).with_max_price(U256::from(200));
+        let offer = escalate_offer(&offer, 20);
+        assert_eq!(offer.minPrice, U256::from(120));
+        assert_eq!(offer.maxPrice, U256::from(240));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_resubmit_unlocked_request() {
+        let anvil = Anvil::new().spawn();
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+        let client = Client::<_, TempFileStorageProvider>::new(
+            ctx.customer_market.clone(),
+            ctx.set_verifier.clone(),
+        )
+        .with_local_signer(ctx.customer_signer.clone());
+        let manager = RequestManager::new(client)
+            .with_unlocked_timeout(Duration::from_secs(1))
+            .with_poll_interval(Duration::from_millis(100))
+            .with_max_attempts(2);
+
+        let err = manager.submit_and_wait(new_request(1000)).await.unwrap_err();
+        assert!(
+            matches!(err, RequestManagerError::MaxAttemptsReached(2)),
+            "unexpected error {err}"
+        );
+
+        // The resubmission has a new ID and increased prices
+        let logs = ctx.customer_market.instance().RequestSubmitted_filter().query().await.unwrap();
+        assert_eq!(logs.len(), 2);
+        let (first, _) =
+            ctx.customer_market.get_submitted_request(logs[0].0.requestId, None).await.unwrap();
+        let (second, _) =
+            ctx.customer_market.get_submitted_request(logs[1].0.requestId, None).await.unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(second.imageUrl, first.imageUrl);
+        assert_eq!(second.offer.minPrice, U256::from(600));
+        assert_eq!(second.offer.maxPrice, U256::from(1200));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_max_total_spend() {
+        let anvil = Anvil::new().spawn();
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+        let client = Client::<_, TempFileStorageProvider>::new(
+            ctx.customer_market.clone(),
+            ctx.set_verifier.clone(),
+        )
+        .with_local_signer(ctx.customer_signer.clone());
+        let manager = RequestManager::new(client)
+            .with_unlocked_timeout(Duration::from_secs(1))
+            .with_poll_interval(Duration::from_millis(100))
+            .with_max_total_spend(U256::from(1500));
+
+        // The remaining 500 after the first submission is less than its max price
+        let err = manager.submit_and_wait(new_request(1000)).await.unwrap_err();
+        assert!(matches!(err, RequestManagerError::MaxSpendReached(_)), "unexpected error {err}");
+        let logs = ctx.customer_market.instance().RequestSubmitted_filter().query().await.unwrap();
+        assert_eq!(logs.len(), 1);
+    }
+}
@@ -0,0 +1,533 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signer implementations for wallets whose keys are not passed as raw private keys.
+//!
+//! [BoundlessSigner] can be backed by a local private key, an encrypted JSON keystore, or a
+//! remote signing service that signs raw 32 byte digests over HTTP. It implements both
+//! [Signer] and [TxSigner], so it can be used to sign proof requests and to build an
+//! [EthereumWallet](alloy::network::EthereumWallet).
+
+use std::path::{Path, PathBuf};
+
+use alloy::{
+    consensus::{SignableTransaction, Transaction},
+    network::TxSigner,
+    primitives::{Address, ChainId, PrimitiveSignature, B256},
+    signers::{local::PrivateKeySigner, Error as AlloySignerError, Signer},
+};
+use async_trait::async_trait;
+use clap::Parser;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// API path, relative to the remote signer base URL, for signing a digest with a secp256k1 key.
+pub const REMOTE_SIGN_PATH: &str = "api/v1/digest/sign/";
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+/// Errors from configuring or using a signer.
+pub enum SignerError {
+    /// Failed to decrypt or read the keystore.
+    #[error("keystore error: {0}")]
+    Keystore(String),
+
+    /// HTTP error talking to the remote signer.
+    #[error("remote signer http error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// Remote signer returned a non-success status.
+    #[error("remote signer returned status {0}: {1}")]
+    RemoteStatus(reqwest::StatusCode, String),
+
+    /// Remote signer returned a malformed signature.
+    #[error("remote signer returned an invalid signature: {0}")]
+    InvalidSignature(String),
+
+    /// Remote signer signed with a different key than the configured address.
+    #[error("remote signer address mismatch: expected {expected}, recovered {recovered}")]
+    AddressMismatch {
+        /// Configured signer address.
+        expected: Address,
+        /// Address recovered from the returned signature.
+        recovered: Address,
+    },
+
+    /// The remote signer URL is invalid.
+    #[error("invalid remote signer url: {0}")]
+    InvalidUrl(String),
+
+    /// No signer was configured.
+    #[error("no signer configured; set a private key, a keystore, or a remote signer")]
+    Missing,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RemoteSignRequest {
+    data: String,
+}
+
+/// Signer backed by a remote signing service that signs raw digests.
+///
+/// Signing requests are sent as `POST {url}/api/v1/digest/sign/{address}` with a JSON body of the
+/// form `{"data": "0x..."}` containing the 32 byte digest to sign. The service is expected to
+/// sign the digest as is, without any prefix or further hashing, and to return the 65 byte hex
+/// encoded signature as the response body. Each returned signature is checked to recover to the
+/// configured address.
+///
+/// Note that this is not the web3signer `eth1/sign` API, which hashes the data it receives with
+/// an EIP-191 prefix before signing it.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: Url,
+    address: Address,
+    chain_id: Option<ChainId>,
+}
+
+impl RemoteSigner {
+    /// Create a new remote signer for the given key address, served from the given base URL.
+    ///
+    /// The base URL may include a path, e.g. `https://host/signer`, under which the signing API
+    /// is served.
+    pub fn new(mut url: Url, address: Address) -> Self {
+        // Make sure joining the API path appends to the base path instead of replacing its last
+        // segment
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Self { client: reqwest::Client::new(), url, address, chain_id: None }
+    }
+
+    /// URL of the signing endpoint for the configured key.
+    fn sign_url(&self) -> Result<Url, SignerError> {
+        self.url
+            .join(&format!("{REMOTE_SIGN_PATH}{}", self.address))
+            .map_err(|err| SignerError::InvalidUrl(err.to_string()))
+    }
+
+    /// Request a signature over the given digest from the remote signer.
+    pub async fn sign_digest(&self, hash: &B256) -> Result<PrimitiveSignature, SignerError> {
+        let url = self.sign_url()?;
+        let res = self
+            .client
+            .post(url)
+            .json(&RemoteSignRequest { data: format!("{hash}") })
+            .send()
+            .await?;
+        let status = res.status();
+        let body = res.text().await?;
+        if !status.is_success() {
+            return Err(SignerError::RemoteStatus(status, body));
+        }
+
+        let sig_bytes = hex::decode(body.trim().trim_start_matches("0x"))
+            .map_err(|err| SignerError::InvalidSignature(err.to_string()))?;
+        let sig = PrimitiveSignature::try_from(sig_bytes.as_slice())
+            .map_err(|err| SignerError::InvalidSignature(err.to_string()))?;
+
+        let recovered = sig
+            .recover_address_from_prehash(hash)
+            .map_err(|err| SignerError::InvalidSignature(err.to_string()))?;
+        if recovered != self.address {
+            return Err(SignerError::AddressMismatch { expected: self.address, recovered });
+        }
+        Ok(sig)
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    async fn sign_hash(&self, hash: &B256) -> alloy::signers::Result<PrimitiveSignature> {
+        self.sign_digest(hash).await.map_err(AlloySignerError::other)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
+
+#[async_trait]
+impl TxSigner<PrimitiveSignature> for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<PrimitiveSignature>,
+    ) -> alloy::signers::Result<PrimitiveSignature> {
+        if let Some(chain_id) = self.chain_id {
+            if !tx.set_chain_id_checked(chain_id) {
+                return Err(AlloySignerError::TransactionChainIdMismatch {
+                    signer: chain_id,
+                    // we can only end up here if the tx has a chain id
+                    tx: tx.chain_id().unwrap(),
+                });
+            }
+        }
+        Signer::sign_hash(self, &tx.signature_hash()).await
+    }
+}
+
+/// Decrypt a JSON keystore into a local signer.
+pub fn load_keystore(
+    path: impl AsRef<Path>,
+    password: impl AsRef<[u8]>,
+) -> Result<PrivateKeySigner, SignerError> {
+    PrivateKeySigner::decrypt_keystore(path, password)
+        .map_err(|err| SignerError::Keystore(err.to_string()))
+}
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+/// A signer that can be backed by a local key or a remote signing service.
+pub enum BoundlessSigner {
+    /// Local private key, either given directly or decrypted from a keystore.
+    Local(PrivateKeySigner),
+    /// Remote signing service.
+    Remote(RemoteSigner),
+}
+
+impl From<PrivateKeySigner> for BoundlessSigner {
+    fn from(signer: PrivateKeySigner) -> Self {
+        Self::Local(signer)
+    }
+}
+
+impl From<RemoteSigner> for BoundlessSigner {
+    fn from(signer: RemoteSigner) -> Self {
+        Self::Remote(signer)
+    }
+}
+
+impl BoundlessSigner {
+    /// Returns the underlying private key signer, if the key is held locally.
+    pub fn as_local(&self) -> Option<&PrivateKeySigner> {
+        match self {
+            Self::Local(signer) => Some(signer),
+            Self::Remote(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for BoundlessSigner {
+    async fn sign_hash(&self, hash: &B256) -> alloy::signers::Result<PrimitiveSignature> {
+        match self {
+            Self::Local(signer) => Signer::sign_hash(signer, hash).await,
+            Self::Remote(signer) => Signer::sign_hash(signer, hash).await,
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            Self::Local(signer) => Signer::address(signer),
+            Self::Remote(signer) => Signer::address(signer),
+        }
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        match self {
+            Self::Local(signer) => Signer::chain_id(signer),
+            Self::Remote(signer) => Signer::chain_id(signer),
+        }
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        match self {
+            Self::Local(signer) => Signer::set_chain_id(signer, chain_id),
+            Self::Remote(signer) => Signer::set_chain_id(signer, chain_id),
+        }
+    }
+}
+
+#[async_trait]
+impl TxSigner<PrimitiveSignature> for BoundlessSigner {
+    fn address(&self) -> Address {
+        Signer::address(self)
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<PrimitiveSignature>,
+    ) -> alloy::signers::Result<PrimitiveSignature> {
+        match self {
+            Self::Local(signer) => TxSigner::sign_transaction(signer, tx).await,
+            Self::Remote(signer) => TxSigner::sign_transaction(signer, tx).await,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Parser)]
+/// Configuration for the wallet signer.
+///
+/// Exactly one of a private key, a keystore, or a remote signer should be set.
+pub struct SignerConfig {
+    /// Private key of the wallet
+    #[arg(long, env, conflicts_with_all = ["keystore_path", "remote_signer_url"])]
+    pub private_key: Option<PrivateKeySigner>,
+
+    /// Path to an encrypted JSON keystore holding the wallet key
+    #[arg(long, env, requires = "keystore_password", conflicts_with = "remote_signer_url")]
+    pub keystore_path: Option<PathBuf>,
+    /// Password to decrypt the keystore
+    #[arg(long, env, requires = "keystore_path")]
+    pub keystore_password: Option<String>,
+
+    /// Base URL of a remote signer signing raw digests, see [RemoteSigner]
+    #[arg(long, env, requires = "remote_signer_address")]
+    pub remote_signer_url: Option<Url>,
+    /// Address of the key held by the remote signer
+    #[arg(long, env, requires = "remote_signer_url")]
+    pub remote_signer_address: Option<Address>,
+}
+
+impl SignerConfig {
+    /// Create a configuration for a local private key.
+    pub fn private_key(private_key: PrivateKeySigner) -> Self {
+        Self { private_key: Some(private_key), ..Default::default() }
+    }
+
+    /// Returns true if any signer is configured.
+    pub fn is_set(&self) -> bool {
+        self.private_key.is_some()
+            || self.keystore_path.is_some()
+            || self.remote_signer_url.is_some()
+    }
+
+    /// Construct the configured signer.
+    pub fn signer(&self) -> Result<BoundlessSigner, SignerError> {
+        if let Some(private_key) = &self.private_key {
+            return Ok(private_key.clone().into());
+        }
+        if let Some(path) = &self.keystore_path {
+            let password = self.keystore_password.as_deref().unwrap_or_default();
+            return Ok(load_keystore(path, password)?.into());
+        }
+        if let (Some(url), Some(address)) = (&self.remote_signer_url, self.remote_signer_address) {
+            return Ok(RemoteSigner::new(url.clone(), address).into());
+        }
+        Err(SignerError::Missing)
+    }
+}
+
+/// Stand-in remote signer server, used for testing.
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils {
+    use alloy::{
+        primitives::B256,
+        signers::{local::PrivateKeySigner, SignerSync},
+    };
+    use reqwest::Url;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        task::JoinHandle,
+    };
+
+    use super::{RemoteSignRequest, REMOTE_SIGN_PATH};
+
+    /// Minimal HTTP server implementing the remote signer API for a single local key.
+    pub struct TestSignerServer {
+        /// Base URL of the server.
+        pub url: Url,
+        handle: JoinHandle<()>,
+    }
+
+    impl TestSignerServer {
+        /// Start serving signatures for the given key on a random local port.
+        pub async fn spawn(signer: PrivateKeySigner) -> std::io::Result<Self> {
+            Self::spawn_with_base_path(signer, "/").await
+        }
+
+        /// Start serving signatures for the given key under the given base path, which must
+        /// start and end with a `/`.
+        pub async fn spawn_with_base_path(
+            signer: PrivateKeySigner,
+            base_path: &str,
+        ) -> std::io::Result<Self> {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let url = Url::parse(&format!("http://{}{base_path}", listener.local_addr()?)).unwrap();
+            let sign_path = format!("{base_path}{REMOTE_SIGN_PATH}{}", signer.address());
+            let handle = tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let signer = signer.clone();
+                    let sign_path = sign_path.clone();
+                    tokio::spawn(async move {
+                        let Ok(Some((head, body))) = Self::read_request(&mut stream).await else {
+                            return;
+                        };
+                        let (status, body) = Self::handle(&signer, &sign_path, &head, &body);
+                        let resp = format!(
+                            "HTTP/1.1 {status}\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                            body.len()
+                        );
+                        let _ = stream.write_all(resp.as_bytes()).await;
+                    });
+                }
+            });
+            Ok(Self { url, handle })
+        }
+
+        /// Read a full request, returning its head and body, or None if the connection was
+        /// closed early.
+        async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<(String, String)>> {
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+            loop {
+                let len = stream.read(&mut chunk).await?;
+                if len == 0 {
+                    return Ok(None);
+                }
+                buf.extend_from_slice(&chunk[..len]);
+
+                let Some(head_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                let body_start = head_end + 4;
+                if buf.len() >= body_start + content_length {
+                    let body =
+                        String::from_utf8_lossy(&buf[body_start..body_start + content_length])
+                            .to_string();
+                    return Ok(Some((head, body)));
+                }
+            }
+        }
+
+        fn handle(
+            signer: &PrivateKeySigner,
+            sign_path: &str,
+            head: &str,
+            body: &str,
+        ) -> (&'static str, String) {
+            if !head.starts_with(&format!("POST {sign_path} ")) {
+                return ("404 Not Found", "unknown key".into());
+            }
+            let Ok(sign_req) = serde_json::from_str::<RemoteSignRequest>(body) else {
+                return ("400 Bad Request", "invalid body".into());
+            };
+            let Ok(hash) = sign_req.data.parse::<B256>() else {
+                return ("400 Bad Request", "invalid data".into());
+            };
+            match signer.sign_hash_sync(&hash) {
+                Ok(sig) => ("200 OK", format!("0x{}", hex::encode(sig.as_bytes()))),
+                Err(err) => ("500 Internal Server Error", err.to_string()),
+            }
+        }
+    }
+
+    impl Drop for TestSignerServer {
+        fn drop(&mut self) {
+            self.handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_utils::TestSignerServer, *};
+    use alloy::{
+        network::{EthereumWallet, TransactionBuilder},
+        node_bindings::Anvil,
+        primitives::{Bytes, U256},
+        providers::{Provider, ProviderBuilder},
+        rpc::types::TransactionRequest,
+    };
+
+    use crate::contracts::{
+        test_utils::create_test_ctx, Input, Offer, Predicate, ProofRequest, Requirements,
+    };
+    use guest_assessor::ASSESSOR_GUEST_ID;
+    use guest_set_builder::SET_BUILDER_ID;
+    use risc0_zkvm::sha::Digest;
+
+    #[tokio::test]
+    async fn remote_signer_sign_request() {
+        let local = PrivateKeySigner::random();
+        let server = TestSignerServer::spawn(local.clone()).await.unwrap();
+        let remote = RemoteSigner::new(server.url.clone(), local.address());
+
+        let request = ProofRequest::new(
+            1,
+            &local.address(),
+            Requirements::new(Digest::ZERO, Predicate::prefix_match(Bytes::default())),
+            "http://image_uri.null",
+            Input::inline(Bytes::default()),
+            Offer::default(),
+        );
+        let market = Address::ZERO;
+        let sig = request.sign_request(&remote, market, 1).await.unwrap();
+        request.verify_signature(&sig.as_bytes().into(), market, 1).unwrap();
+        assert_eq!(sig, request.sign_request(&local, market, 1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn remote_signer_base_path() {
+        let local = PrivateKeySigner::random();
+        let server =
+            TestSignerServer::spawn_with_base_path(local.clone(), "/signer/").await.unwrap();
+        // The base path is kept whether or not the configured URL has a trailing slash
+        let url = server.url.as_str().trim_end_matches('/').parse::<Url>().unwrap();
+        for url in [url, server.url.clone()] {
+            let remote = RemoteSigner::new(url, local.address());
+            let hash = B256::repeat_byte(0x11);
+            let sig = remote.sign_digest(&hash).await.unwrap();
+            assert_eq!(sig, local.sign_hash(&hash).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn remote_signer_address_mismatch() {
+        let local = PrivateKeySigner::random();
+        let server = TestSignerServer::spawn(local.clone()).await.unwrap();
+        let other = PrivateKeySigner::random();
+        // The server only holds `local`, so requests for any other key are rejected.
+        let remote = RemoteSigner::new(server.url.clone(), other.address());
+        let err = remote.sign_digest(&B256::ZERO).await.unwrap_err();
+        assert!(matches!(err, SignerError::RemoteStatus(..)));
+    }
+
+    #[tokio::test]
+    async fn remote_signer_wallet() {
+        let anvil = Anvil::new().spawn();
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+        let server = TestSignerServer::spawn(ctx.prover_signer.clone()).await.unwrap();
+        let signer: BoundlessSigner =
+            RemoteSigner::new(server.url.clone(), ctx.prover_signer.address()).into();
+
+        let provider = ProviderBuilder::new()
+            .wallet(EthereumWallet::from(signer))
+            .on_http(anvil.endpoint_url());
+        let to = Address::repeat_byte(0x42);
+        let tx = TransactionRequest::default().with_to(to).with_value(U256::from(1000));
+        provider.send_transaction(tx).await.unwrap().watch().await.unwrap();
+        assert_eq!(provider.get_balance(to).await.unwrap(), U256::from(1000));
+    }
+}
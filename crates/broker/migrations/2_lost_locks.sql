@@ -0,0 +1,4 @@
+CREATE TABLE lost_locks (
+    id TEXT PRIMARY KEY,
+    data JSONB
+);
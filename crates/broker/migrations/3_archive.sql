@@ -0,0 +1,14 @@
+CREATE TABLE archived_orders (
+    id TEXT PRIMARY KEY,
+    data JSONB
+);
+
+CREATE TABLE archived_batches (
+    id INTEGER PRIMARY KEY,
+    data JSONB
+);
+
+CREATE TABLE archive_stats (
+    id INTEGER PRIMARY KEY,
+    data JSONB
+);
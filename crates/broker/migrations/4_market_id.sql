@@ -0,0 +1,47 @@
+-- Namespace orders and batches by market deployment, so one DB can serve several deployments,
+-- including several on the same chain. Each (chain ID, market address) pair gets a market ID.
+-- Existing rows get market ID 0 and are adopted by the primary market on startup.
+-- last_block and archive_stats are keyed by the market ID, their existing rows use key 0.
+-- Orders get a sequence number that is never reused, so new orders can be read incrementally.
+
+CREATE TABLE markets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chain_id INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    UNIQUE (chain_id, address)
+);
+
+CREATE TABLE orders_by_market (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    market_id INTEGER NOT NULL DEFAULT 0,
+    id TEXT NOT NULL,
+    data JSONB,
+    UNIQUE (market_id, id)
+);
+INSERT INTO orders_by_market (id, data) SELECT id, data FROM orders;
+DROP TABLE orders;
+ALTER TABLE orders_by_market RENAME TO orders;
+
+CREATE TABLE lost_locks_by_market (
+    market_id INTEGER NOT NULL DEFAULT 0,
+    id TEXT NOT NULL,
+    data JSONB,
+    PRIMARY KEY (market_id, id)
+);
+INSERT INTO lost_locks_by_market (id, data) SELECT id, data FROM lost_locks;
+DROP TABLE lost_locks;
+ALTER TABLE lost_locks_by_market RENAME TO lost_locks;
+
+CREATE TABLE archived_orders_by_market (
+    market_id INTEGER NOT NULL DEFAULT 0,
+    id TEXT NOT NULL,
+    data JSONB,
+    PRIMARY KEY (market_id, id)
+);
+INSERT INTO archived_orders_by_market (id, data) SELECT id, data FROM archived_orders;
+DROP TABLE archived_orders;
+ALTER TABLE archived_orders_by_market RENAME TO archived_orders;
+
+-- Batch IDs stay unique across markets
+ALTER TABLE batches ADD COLUMN market_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE archived_batches ADD COLUMN market_id INTEGER NOT NULL DEFAULT 0;
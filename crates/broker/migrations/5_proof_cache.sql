@@ -0,0 +1,7 @@
+CREATE TABLE proof_cache (
+    image_id TEXT NOT NULL,
+    input_digest TEXT NOT NULL,
+    proof_id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (image_id, input_digest)
+);
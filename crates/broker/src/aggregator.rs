@@ -2,7 +2,7 @@
 //
 // All rights reserved.
 
-use alloy::primitives::{utils, Address, U256};
+use alloy::primitives::{Address, U256};
 use anyhow::{bail, Context, Result};
 use boundless_assessor::{AssessorInput, Fulfillment};
 use boundless_market::contracts::eip712_domain;
@@ -262,17 +262,10 @@ impl AggregatorService {
         let (conf_batch_size, conf_batch_time, conf_batch_fees, conf_max_journal_bytes) = {
             let config = self.config.lock_all().context("Failed to lock config")?;
 
-            // TODO: Move this parse into config
-            let batch_max_fees = match config.batcher.batch_max_fees.as_ref() {
-                Some(elm) => {
-                    Some(utils::parse_ether(elm).context("Failed to parse batch max fees")?)
-                }
-                None => None,
-            };
             (
                 config.batcher.batch_size,
                 config.batcher.batch_max_time,
-                batch_max_fees,
+                config.batcher.batch_max_fees.map(|fees| fees.wei()),
                 config.batcher.batch_max_journal_bytes,
             )
         };
@@ -651,6 +644,10 @@ mod tests {
             client_sig: client_sig.into(),
             lock_price: Some(U256::from(min_price)),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
         let order_id = U256::from(order.request.id);
         db.add_order(order_id, order.clone()).await.unwrap();
@@ -694,6 +691,10 @@ mod tests {
             client_sig,
             lock_price: Some(U256::from(min_price)),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
         let order_id = U256::from(order.request.id);
         db.add_order(order_id, order.clone()).await.unwrap();
@@ -802,6 +803,10 @@ mod tests {
             client_sig: client_sig.into(),
             lock_price: Some(U256::from(min_price)),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
             request: order_request,
         };
         let order_id = U256::from(order.request.id);
@@ -860,6 +865,10 @@ mod tests {
             client_sig,
             lock_price: Some(U256::from(min_price)),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
             request: order_request,
         };
         let order_id = U256::from(order.request.id);
@@ -893,7 +902,7 @@ mod tests {
         {
             let mut config = config.load_write().unwrap();
             config.batcher.batch_size = Some(2);
-            config.batcher.batch_max_fees = Some("0.1".into());
+            config.batcher.batch_max_fees = Some("0.1".parse().unwrap());
         }
 
         let prover: ProverObj = Arc::new(MockProver::default());
@@ -966,6 +975,10 @@ mod tests {
             client_sig: client_sig.into(),
             lock_price: Some(U256::from(min_price)),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
         let order_id = U256::from(order.request.id);
         db.add_order(order_id, order.clone()).await.unwrap();
@@ -1074,6 +1087,10 @@ mod tests {
             client_sig: client_sig.into(),
             lock_price: Some(U256::from(min_price)),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
         let order_id = U256::from(order.request.id);
         db.add_order(order_id, order.clone()).await.unwrap();
@@ -1190,6 +1207,10 @@ mod tests {
             client_sig: client_sig.into(),
             lock_price: Some(U256::from(min_price)),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
 
         // add first order and aggregate
@@ -0,0 +1,754 @@
+// Copyright (c) 2025 RISC Zero, Inc.
+//
+// All rights reserved.
+
+//! Replays historical market orders through the [OrderPicker] to estimate which orders a broker
+//! config would have locked and what it would have earned, without sending any transactions.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use alloy::{
+    network::{Ethereum, EthereumWallet},
+    primitives::{keccak256, utils::format_ether, Address, Bytes, I256, U256},
+    providers::{Provider, ProviderBuilder, WalletProvider},
+    rpc::types::BlockTransactionsKind,
+    signers::local::PrivateKeySigner,
+};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use boundless_market::{contracts::ProofRequest, input::GuestEnv, order_stream_client::OrderData};
+use clap::Parser;
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    config::{Config, ConfigLock, EtherAmount},
+    db::{DbObj, SqliteDb},
+    market_monitor::MarketMonitor,
+    order_picker::{callback_gas_limit, OrderPicker, PricingAt},
+    provers::{ExecutorResp, MockProver, ProofResult, Prover, ProverError, ProverObj},
+    Order, OrderStatus,
+};
+
+/// Max number of blocks to query for `RequestSubmitted` events at once
+const LOG_QUERY_BLOCK_RANGE: u64 = 1000;
+
+/// Arguments of the broker backtest mode
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct BacktestArgs {
+    /// RPC URL
+    #[clap(long, env, default_value = "http://localhost:8545")]
+    pub rpc_url: Url,
+
+    /// Boundless Market address
+    #[clap(long, env)]
+    pub boundless_market_address: Address,
+
+    /// Config file path
+    #[clap(short, long, default_value = "broker.toml")]
+    pub config_file: PathBuf,
+
+    /// Override the `mcycle_price` of the config file
+    #[clap(long)]
+    pub mcycle_price: Option<EtherAmount>,
+
+    /// First block to replay `RequestSubmitted` events from
+    ///
+    /// On-chain orders are only replayed if this is set
+    #[clap(long)]
+    pub from_block: Option<u64>,
+
+    /// Last block to replay `RequestSubmitted` events from, defaults to the latest block
+    #[clap(long, requires = "from_block")]
+    pub to_block: Option<u64>,
+
+    /// Recorded off-chain orders
+    ///
+    /// File of orders as served by the order-stream, one JSON object per line
+    #[clap(long)]
+    pub offchain_orders: Option<PathBuf>,
+
+    /// Preflight cache file
+    ///
+    /// Cached preflight results are used instead of executing the guest, and new results are
+    /// written back to the file once the backtest completes
+    #[clap(long)]
+    pub preflight_cache: Option<PathBuf>,
+
+    /// Gas price (in wei) for lock and fulfill cost estimates, defaults to the current gas price
+    #[clap(long)]
+    pub gas_price: Option<u128>,
+
+    /// Report output path, defaults to stdout
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Where a replayed order was published
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSource {
+    Onchain,
+    Offchain,
+}
+
+/// What the broker would have done with a replayed order
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BacktestOutcome {
+    /// Order would have been locked
+    Locked,
+    /// Order was rejected by the pricing checks
+    Skipped,
+    /// Order was accepted, but its target lock time is past the lock deadline
+    Expired,
+    /// Pricing the order failed
+    Failed,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BacktestOrder {
+    pub order_id: U256,
+    pub source: OrderSource,
+    /// UNIX timestamp the order was first seen
+    pub seen_at: u64,
+    pub outcome: BacktestOutcome,
+    /// Preflight cycle count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_cycles: Option<u64>,
+    /// UNIX timestamp the order would have been locked at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_timestamp: Option<u64>,
+    /// Price the order would have been locked at (in wei)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_price: Option<U256>,
+    /// Estimated gas cost to lock and fulfill the order (in wei)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_cost: Option<U256>,
+    /// Lock price minus the gas cost (in wei)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<I256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BacktestReport {
+    pub mcycle_price: String,
+    pub gas_price: u128,
+    pub orders_replayed: usize,
+    pub orders_locked: usize,
+    /// Sum of the lock prices of all locked orders (in wei)
+    pub total_revenue: U256,
+    /// Sum of the gas costs of all locked orders (in wei)
+    pub total_gas_cost: U256,
+    /// Sum of the margins of all locked orders (in wei)
+    pub total_margin: I256,
+    pub orders: Vec<BacktestOrder>,
+}
+
+/// Order to replay through the order picker
+struct ReplayOrder {
+    request: ProofRequest,
+    client_sig: Bytes,
+    source: OrderSource,
+    /// UNIX timestamp the broker would have first seen the order
+    seen_at: u64,
+    /// Block the order was submitted in, unknown for off-chain orders
+    block: Option<u64>,
+}
+
+/// Preflight result stored in the cache file
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedPreflight {
+    total_cycles: u64,
+    journal: Bytes,
+}
+
+/// Prover serving preflights from a cache, executing with the [MockProver] on a miss
+///
+/// Entries are keyed by image ID and input hash so they can be reused across backtest runs.
+#[derive(Default)]
+struct PreflightCacheProver {
+    inner: MockProver,
+    /// Input ID to input hash
+    input_hashes: Mutex<HashMap<String, String>>,
+    cache: Mutex<HashMap<String, CachedPreflight>>,
+    /// Journals of the preflights served from the cache, by proof ID
+    journals: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl PreflightCacheProver {
+    async fn load(path: Option<&Path>) -> Result<Self> {
+        let cache = match path {
+            Some(path) if path.exists() => {
+                let data = tokio::fs::read_to_string(path)
+                    .await
+                    .context("Failed to read preflight cache")?;
+                serde_json::from_str(&data).context("Failed to parse preflight cache")?
+            }
+            _ => HashMap::new(),
+        };
+        Ok(Self { cache: Mutex::new(cache), ..Default::default() })
+    }
+
+    async fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string_pretty(&*self.cache.lock().unwrap())
+            .context("Failed to serialize preflight cache")?;
+        tokio::fs::write(path, data).await.context("Failed to write preflight cache")
+    }
+
+    fn cache_key(&self, image_id: &str, input_id: &str) -> Option<String> {
+        let input_hashes = self.input_hashes.lock().unwrap();
+        input_hashes.get(input_id).map(|input_hash| format!("{image_id}:{input_hash}"))
+    }
+
+    fn cycles(&self, image_id: &str, input_id: &str) -> Option<u64> {
+        let key = self.cache_key(image_id, input_id)?;
+        self.cache.lock().unwrap().get(&key).map(|cached| cached.total_cycles)
+    }
+}
+
+#[async_trait]
+impl Prover for PreflightCacheProver {
+    async fn upload_input(&self, input: Vec<u8>) -> Result<String, ProverError> {
+        let input_hash = keccak256(&input).to_string();
+        let input_id = self.inner.upload_input(input).await?;
+        self.input_hashes.lock().unwrap().insert(input_id.clone(), input_hash);
+        Ok(input_id)
+    }
+
+    async fn upload_env(&self, env: GuestEnv) -> Result<String, ProverError> {
+        let mut input_hash = keccak256(&env.stdin);
+        // Env vars and args change the execution, so they are part of the cache key
+        if !env.env_vars.is_empty() || !env.args.is_empty() {
+            let extra = serde_json::to_vec(&(&env.env_vars, &env.args)).map_err(|err| {
+                ProverError::ProvingFailed(format!("Failed to encode guest env: {err}"))
+            })?;
+            input_hash = keccak256([input_hash.as_slice(), &extra].concat());
+        }
+        let input_id = self.inner.upload_env(env).await?;
+        self.input_hashes.lock().unwrap().insert(input_id.clone(), input_hash.to_string());
+        Ok(input_id)
+    }
+
+    async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError> {
+        self.inner.upload_image(image_id, image).await
+    }
+
+    async fn preflight(
+        &self,
+        image_id: &str,
+        input_id: &str,
+        assumptions: Vec<String>,
+        executor_limit: Option<u64>,
+    ) -> Result<ProofResult, ProverError> {
+        let key = self.cache_key(image_id, input_id);
+        let cached = key.as_ref().and_then(|key| self.cache.lock().unwrap().get(key).cloned());
+        if let Some(cached) = cached {
+            if let Some(limit) = executor_limit {
+                if cached.total_cycles > limit {
+                    return Err(ProverError::ProvingFailed(format!(
+                        "Session limit exceeded: {} > {limit}",
+                        cached.total_cycles
+                    )));
+                }
+            }
+            let id = Uuid::new_v4().to_string();
+            self.journals.lock().unwrap().insert(id.clone(), cached.journal.to_vec());
+            return Ok(ProofResult {
+                id,
+                stats: ExecutorResp {
+                    assumption_count: assumptions.len() as u64,
+                    segments: 0,
+                    user_cycles: cached.total_cycles,
+                    total_cycles: cached.total_cycles,
+                },
+                elapsed_time: 0.0,
+            });
+        }
+
+        let proof_res =
+            self.inner.preflight(image_id, input_id, assumptions, executor_limit).await?;
+        if let Some(key) = key {
+            let journal =
+                self.inner.get_preflight_journal(&proof_res.id).await?.unwrap_or_default();
+            self.cache.lock().unwrap().insert(
+                key,
+                CachedPreflight {
+                    total_cycles: proof_res.stats.total_cycles,
+                    journal: journal.into(),
+                },
+            );
+        }
+        Ok(proof_res)
+    }
+
+    async fn prove_stark(
+        &self,
+        image_id: &str,
+        input_id: &str,
+        assumptions: Vec<String>,
+    ) -> Result<String, ProverError> {
+        self.inner.prove_stark(image_id, input_id, assumptions).await
+    }
+
+    async fn prove_and_monitor_stark(
+        &self,
+        image_id: &str,
+        input_id: &str,
+        assumptions: Vec<String>,
+    ) -> Result<ProofResult, ProverError> {
+        self.inner.prove_and_monitor_stark(image_id, input_id, assumptions).await
+    }
+
+    async fn wait_for_stark(&self, proof_id: &str) -> Result<ProofResult, ProverError> {
+        self.inner.wait_for_stark(proof_id).await
+    }
+
+    async fn get_receipt(&self, proof_id: &str) -> Result<Option<Receipt>, ProverError> {
+        self.inner.get_receipt(proof_id).await
+    }
+
+    async fn get_preflight_journal(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+        if let Some(journal) = self.journals.lock().unwrap().get(proof_id) {
+            return Ok(Some(journal.clone()));
+        }
+        self.inner.get_preflight_journal(proof_id).await
+    }
+
+    async fn get_journal(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+        self.inner.get_journal(proof_id).await
+    }
+
+    async fn compress(&self, proof_id: &str) -> Result<String, ProverError> {
+        self.inner.compress(proof_id).await
+    }
+
+    async fn get_compressed_receipt(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+        self.inner.get_compressed_receipt(proof_id).await
+    }
+
+    async fn upload_receipt(&self, receipt: Receipt) -> Result<String, ProverError> {
+        self.inner.upload_receipt(receipt).await
+    }
+}
+
+/// Fetches the orders submitted on-chain in the block range, seen at their block timestamp
+async fn fetch_onchain_orders<P>(
+    provider: &Arc<P>,
+    market_addr: Address,
+    from_block: u64,
+    to_block: Option<u64>,
+) -> Result<Vec<ReplayOrder>>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+{
+    let to_block = match to_block {
+        Some(to_block) => to_block,
+        None => provider.get_block_number().await.context("Failed to get latest block")?,
+    };
+
+    let mut orders = vec![];
+    let mut start_block = from_block;
+    while start_block <= to_block {
+        let end_block = (start_block + LOG_QUERY_BLOCK_RANGE - 1).min(to_block);
+        tracing::info!("Fetching submitted requests: {start_block} - {end_block}");
+        let submitted = MarketMonitor::<P>::query_submitted_requests(
+            provider.as_ref(),
+            market_addr,
+            start_block,
+            Some(end_block),
+        )
+        .await
+        .context("Failed to query submitted requests")?;
+
+        for (calldata, block_number) in submitted {
+            let seen_at = provider
+                .get_block_by_number(block_number.into(), BlockTransactionsKind::Hashes)
+                .await
+                .with_context(|| format!("Failed to get block {block_number}"))?
+                .with_context(|| format!("Missing block {block_number}"))?
+                .header
+                .timestamp;
+            orders.push(ReplayOrder {
+                request: calldata.request,
+                client_sig: calldata.clientSignature,
+                source: OrderSource::Onchain,
+                seen_at,
+                block: Some(block_number),
+            });
+        }
+        start_block = end_block + 1;
+    }
+
+    Ok(orders)
+}
+
+/// Loads recorded off-chain orders, seen at the time they were submitted to the order-stream
+async fn load_offchain_orders(path: &Path) -> Result<Vec<ReplayOrder>> {
+    let data =
+        tokio::fs::read_to_string(path).await.context("Failed to read off-chain orders file")?;
+
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let order_data: OrderData =
+                serde_json::from_str(line).context("Failed to parse off-chain order")?;
+            Ok(ReplayOrder {
+                request: order_data.order.request,
+                client_sig: order_data.order.signature.as_bytes().into(),
+                source: OrderSource::Offchain,
+                seen_at: order_data.created_at.timestamp().try_into().unwrap_or_default(),
+                block: None,
+            })
+        })
+        .collect()
+}
+
+/// Prices replayed orders in a scratch DB with the [OrderPicker]
+struct Backtester<P> {
+    db: DbObj,
+    picker: OrderPicker<P>,
+    prover: Arc<PreflightCacheProver>,
+    config: ConfigLock,
+    gas_price: u128,
+}
+
+impl<P> Backtester<P>
+where
+    P: Provider<Ethereum> + 'static + Clone + WalletProvider,
+{
+    async fn new(
+        config: ConfigLock,
+        prover: Arc<PreflightCacheProver>,
+        market_addr: Address,
+        provider: Arc<P>,
+        gas_price: u128,
+    ) -> Result<Self> {
+        let db: DbObj = Arc::new(
+            SqliteDb::new("sqlite::memory:").await.context("Failed to create backtest DB")?,
+        );
+        let prover_obj: ProverObj = prover.clone();
+        // No transactions are sent, so the signer balances do not limit what gets locked
+        let picker =
+            OrderPicker::new(db.clone(), config.clone(), prover_obj, market_addr, provider)
+                .with_balance_checks(false);
+
+        Ok(Self { db, picker, prover, config, gas_price })
+    }
+
+    async fn replay_order(&self, replay: ReplayOrder) -> Result<Option<BacktestOrder>> {
+        let order_id = U256::from(replay.request.id);
+        // TODO(#162) Handle the case where multiple requests share an ID.
+        if self.db.order_exists(order_id).await? {
+            tracing::warn!("Skipping duplicate order {order_id:x}");
+            return Ok(None);
+        }
+
+        let order = Order::new(replay.request, replay.client_sig);
+        self.db.add_order(order_id, order.clone()).await?;
+        // Callbacks are costed at the backtest gas price and simulated at the submission block
+        let at =
+            PricingAt { now: replay.seen_at, gas_price: Some(self.gas_price), block: replay.block };
+        let res = self.picker.price_order_at(order_id, &order, at).await;
+
+        let db_order =
+            self.db.get_order(order_id).await?.context("Order missing from backtest DB")?;
+        let total_cycles = match (db_order.image_id.as_ref(), db_order.input_id.as_ref()) {
+            (Some(image_id), Some(input_id)) => self.prover.cycles(image_id, input_id),
+            _ => None,
+        };
+
+        let mut result = BacktestOrder {
+            order_id,
+            source: replay.source,
+            seen_at: replay.seen_at,
+            outcome: BacktestOutcome::Skipped,
+            total_cycles,
+            lock_timestamp: None,
+            lock_price: None,
+            gas_cost: None,
+            margin: None,
+            error: None,
+        };
+
+        match res {
+            Err(err) => {
+                result.outcome = BacktestOutcome::Failed;
+                result.error = Some(err.to_string());
+            }
+            Ok(()) if db_order.status == OrderStatus::Locking => {
+                let offer = &db_order.request.offer;
+                let expiration = offer.biddingStart + offer.lockTimeout as u64;
+                let lock_timestamp = db_order
+                    .target_timestamp
+                    .unwrap_or_default()
+                    .max(replay.seen_at)
+                    .max(offer.biddingStart);
+                result.lock_timestamp = Some(lock_timestamp);
+
+                if lock_timestamp >= expiration {
+                    result.outcome = BacktestOutcome::Expired;
+                } else {
+                    let gas_estimate = {
+                        let config = self.config.lock_all().context("Failed to read config")?;
+                        config.market.lockin_gas_estimate
+                            + config.market.fulfill_gas_estimate
+                            + callback_gas_limit(&order.request).unwrap_or(0)
+                    };
+                    let lock_price =
+                        offer.price_at(lock_timestamp).context("Failed to calculate lock price")?;
+                    let gas_cost = U256::from(self.gas_price) * U256::from(gas_estimate);
+
+                    result.outcome = BacktestOutcome::Locked;
+                    result.lock_price = Some(lock_price);
+                    result.gas_cost = Some(gas_cost);
+                    result.margin = Some(I256::from_raw(lock_price) - I256::from_raw(gas_cost));
+                }
+            }
+            Ok(()) => {}
+        }
+
+        Ok(Some(result))
+    }
+
+    async fn run(&self, mut orders: Vec<ReplayOrder>) -> Result<BacktestReport> {
+        orders.sort_by_key(|order| order.seen_at);
+
+        let mcycle_price = self
+            .config
+            .lock_all()
+            .context("Failed to read config")?
+            .market
+            .mcycle_price
+            .to_string();
+        let mut report = BacktestReport {
+            mcycle_price,
+            gas_price: self.gas_price,
+            orders_replayed: 0,
+            orders_locked: 0,
+            total_revenue: U256::ZERO,
+            total_gas_cost: U256::ZERO,
+            total_margin: I256::ZERO,
+            orders: vec![],
+        };
+
+        for replay in orders {
+            let Some(result) = self.replay_order(replay).await? else {
+                continue;
+            };
+            report.orders_replayed += 1;
+            if result.outcome == BacktestOutcome::Locked {
+                report.orders_locked += 1;
+                report.total_revenue += result.lock_price.unwrap_or_default();
+                report.total_gas_cost += result.gas_cost.unwrap_or_default();
+                report.total_margin += result.margin.unwrap_or_default();
+            }
+            report.orders.push(result);
+        }
+
+        Ok(report)
+    }
+}
+
+/// Runs the backtest described by the args and writes out the report
+pub async fn run_backtest(args: BacktestArgs) -> Result<()> {
+    ensure!(
+        args.from_block.is_some() || args.offchain_orders.is_some(),
+        "No orders to replay, set --from-block and/or --offchain-orders"
+    );
+
+    let mut config = Config::load(&args.config_file).await?;
+    if let Some(mcycle_price) = args.mcycle_price {
+        config.market.mcycle_price = mcycle_price;
+    }
+    let config = ConfigLock::from(config);
+
+    // Orders are only priced, never locked, so a throwaway wallet is enough
+    let provider = Arc::new(
+        ProviderBuilder::new()
+            .wallet(EthereumWallet::from(PrivateKeySigner::random()))
+            .on_http(args.rpc_url.clone()),
+    );
+
+    let mut orders = vec![];
+    if let Some(from_block) = args.from_block {
+        orders.extend(
+            fetch_onchain_orders(
+                &provider,
+                args.boundless_market_address,
+                from_block,
+                args.to_block,
+            )
+            .await?,
+        );
+    }
+    if let Some(path) = args.offchain_orders.as_ref() {
+        orders.extend(load_offchain_orders(path).await?);
+    }
+
+    let gas_price = match args.gas_price {
+        Some(gas_price) => gas_price,
+        None => provider.get_gas_price().await.context("Failed to get gas price")?,
+    };
+
+    let prover = Arc::new(PreflightCacheProver::load(args.preflight_cache.as_deref()).await?);
+    let backtester =
+        Backtester::new(config, prover.clone(), args.boundless_market_address, provider, gas_price)
+            .await?;
+    let report = backtester.run(orders).await?;
+
+    if let Some(path) = args.preflight_cache.as_ref() {
+        prover.save(path).await?;
+    }
+
+    tracing::info!(
+        "Replayed {} orders, {} would have been locked for {} ETH with an estimated margin of {} wei",
+        report.orders_replayed,
+        report.orders_locked,
+        format_ether(report.total_revenue),
+        report.total_margin,
+    );
+
+    let report_json =
+        serde_json::to_string_pretty(&report).context("Failed to serialize backtest report")?;
+    match args.output {
+        Some(path) => {
+            tokio::fs::write(path, report_json).await.context("Failed to write backtest report")?
+        }
+        None => println!("{report_json}"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::now_timestamp;
+    use alloy::{node_bindings::Anvil, primitives::B256};
+    use boundless_market::{
+        contracts::{Input, Offer, Predicate, PredicateType, Requirements},
+        order_stream_client::Order as StreamOrder,
+    };
+    use chrono::Utc;
+    use guest_util::{ECHO_ELF, ECHO_ID};
+    use httpmock::prelude::*;
+    use risc0_zkvm::sha::Digest;
+    use tempfile::NamedTempFile;
+    use tracing_test::traced_test;
+
+    async fn stream_order(
+        signer: &PrivateKeySigner,
+        idx: u32,
+        image_url: String,
+        min_price: u64,
+        max_price: u64,
+    ) -> OrderData {
+        let request = ProofRequest::new(
+            idx,
+            &signer.address(),
+            Requirements::new(
+                Digest::from(ECHO_ID),
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            image_url,
+            Input::builder().write_slice(&[0x41, 0x41, 0x41, 0x41]).build_inline().unwrap(),
+            Offer {
+                minPrice: U256::from(min_price),
+                maxPrice: U256::from(max_price),
+                biddingStart: now_timestamp(),
+                timeout: 1200,
+                lockTimeout: 900,
+                rampUpPeriod: 1,
+                lockStake: U256::ZERO,
+            },
+        );
+        let signature = request.sign_request(signer, Address::ZERO, 1).await.unwrap();
+        OrderData {
+            id: idx.into(),
+            order: StreamOrder { request, request_digest: B256::ZERO, signature },
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn backtest_offchain_orders() {
+        let anvil = Anvil::new().spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer.clone()))
+                .on_builtin(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+
+        let image_server = MockServer::start();
+        let _get_mock = image_server.mock(|when, then| {
+            when.method(GET).path("/image");
+            then.status(200).body(ECHO_ELF);
+        });
+        let image_url = image_server.url("/image");
+
+        // One order priced well above the configured mcycle price, one that can never pay for it
+        let orders_file = NamedTempFile::new().unwrap();
+        let lines: Vec<String> = vec![
+            stream_order(&signer, 1, image_url.clone(), 200000000000, 400000000000).await,
+            stream_order(&signer, 2, image_url.clone(), 1, 1000).await,
+        ]
+        .iter()
+        .map(|order| serde_json::to_string(order).unwrap())
+        .collect();
+        std::fs::write(orders_file.path(), lines.join("\n")).unwrap();
+
+        let mut config = Config::default();
+        config.market.mcycle_price = "0.0000001".parse().unwrap();
+        let config = ConfigLock::from(config);
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let prover = Arc::new(PreflightCacheProver::load(None).await.unwrap());
+        let backtester =
+            Backtester::new(config.clone(), prover.clone(), Address::ZERO, provider.clone(), 0)
+                .await
+                .unwrap();
+        let orders = load_offchain_orders(orders_file.path()).await.unwrap();
+        let report = backtester.run(orders).await.unwrap();
+
+        assert_eq!(report.orders_replayed, 2);
+        assert_eq!(report.orders_locked, 1);
+
+        let locked = &report.orders[0];
+        assert_eq!(locked.outcome, BacktestOutcome::Locked);
+        assert_eq!(locked.source, OrderSource::Offchain);
+        assert!(locked.total_cycles.unwrap() > 0);
+        let lock_price = locked.lock_price.unwrap();
+        assert!(lock_price >= U256::from(200000000000u64));
+        // Free gas, so the whole lock price is margin
+        assert_eq!(locked.margin, Some(I256::from_raw(lock_price)));
+        assert_eq!(report.total_revenue, lock_price);
+
+        assert_eq!(report.orders[1].outcome, BacktestOutcome::Skipped);
+
+        // A second run is served from the saved preflight cache
+        prover.save(cache_file.path()).await.unwrap();
+        let cached_prover =
+            Arc::new(PreflightCacheProver::load(Some(cache_file.path())).await.unwrap());
+        assert_eq!(cached_prover.cache.lock().unwrap().len(), 1);
+        let backtester =
+            Backtester::new(config, cached_prover, Address::ZERO, provider, 0).await.unwrap();
+        let orders = load_offchain_orders(orders_file.path()).await.unwrap();
+        let cached_report = backtester.run(orders).await.unwrap();
+        assert_eq!(cached_report.orders_locked, 1);
+        assert_eq!(cached_report.orders[0].total_cycles, locked.total_cycles);
+    }
+}
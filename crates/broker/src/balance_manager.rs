@@ -0,0 +1,419 @@
+// Copyright (c) 2025 RISC Zero, Inc.
+//
+// All rights reserved.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    network::{Ethereum, TransactionBuilder},
+    primitives::{utils::format_ether, Address, U256},
+    providers::{Provider, WalletProvider},
+    rpc::types::TransactionRequest,
+};
+use anyhow::{Context, Result};
+use boundless_market::contracts::boundless_market::BoundlessMarketService;
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::{
+    config::ConfigLock,
+    task::{RetryRes, RetryTask, SupervisorErr},
+};
+
+/// Shared flag used to pause order locking while balances are below their floors
+#[derive(Clone, Default)]
+pub struct LockPause(Arc<AtomicBool>);
+
+impl LockPause {
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, paused: bool) {
+        let prev = self.0.swap(paused, Ordering::Relaxed);
+        if prev != paused {
+            if paused {
+                tracing::warn!("Balances below configured floor, pausing order locking");
+            } else {
+                tracing::info!("Balances restored above configured floor, resuming order locking");
+            }
+        }
+    }
+}
+
+/// Lock held while sending transactions from the lock wallet
+///
+/// The order monitor assigns the nonces of its lock transactions itself, so other transactions
+/// from the same wallet must not be sent while it does.
+#[derive(Clone, Default)]
+pub struct WalletTxLock(Arc<Mutex<()>>);
+
+impl WalletTxLock {
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.0.lock().await
+    }
+}
+
+/// Action required to move a balance back inside its configured band
+#[derive(Debug, PartialEq)]
+enum Rebalance {
+    None,
+    Deposit(U256),
+    Withdraw(U256),
+}
+
+/// Compute the action required to bring `balance` back into the [min, max] band
+///
+/// Balances outside the band are moved to the midpoint of the band, or to the single
+/// configured bound if only one side is set.
+fn rebalance_amount(balance: U256, min: Option<U256>, max: Option<U256>) -> Rebalance {
+    let target = match (min, max) {
+        (Some(min), Some(max)) => min + (max.saturating_sub(min)) / U256::from(2),
+        (Some(min), None) => min,
+        (None, Some(max)) => max,
+        (None, None) => return Rebalance::None,
+    };
+
+    if min.is_some_and(|min| balance < min) {
+        return Rebalance::Deposit(target - balance);
+    }
+    if max.is_some_and(|max| balance > max) {
+        return Rebalance::Withdraw(balance - target);
+    }
+    Rebalance::None
+}
+
+/// Parsed snapshot of the [crate::config::BalanceConf]
+struct BalanceBands {
+    check_interval: Duration,
+    min_market: Option<U256>,
+    max_market: Option<U256>,
+    min_stake: Option<U256>,
+    max_stake: Option<U256>,
+    pause_market: Option<U256>,
+    pause_stake: Option<U256>,
+    wallet_reserve: U256,
+    sweep_address: Option<Address>,
+    sweep_interval: Option<Duration>,
+}
+
+#[derive(Clone)]
+pub struct BalanceManager<P> {
+    provider: Arc<P>,
+    market: BoundlessMarketService<Arc<P>>,
+    config: ConfigLock,
+    lock_pause: LockPause,
+    tx_lock: WalletTxLock,
+    last_sweep: Arc<Mutex<Option<Instant>>>,
+}
+
+impl<P> BalanceManager<P>
+where
+    P: Provider<Ethereum> + WalletProvider + 'static + Clone,
+{
+    pub fn new(
+        provider: Arc<P>,
+        config: ConfigLock,
+        market_addr: Address,
+        lock_pause: LockPause,
+    ) -> Result<Self> {
+        let txn_timeout_opt = {
+            let config = config.lock_all().context("Failed to read config")?;
+            config.batcher.txn_timeout
+        };
+
+        let mut market = BoundlessMarketService::new(
+            market_addr,
+            provider.clone(),
+            provider.default_signer_address(),
+        );
+        if let Some(txn_timeout) = txn_timeout_opt {
+            market = market.with_timeout(Duration::from_secs(txn_timeout));
+        }
+
+        Ok(Self {
+            provider,
+            market,
+            config,
+            lock_pause,
+            tx_lock: WalletTxLock::default(),
+            last_sweep: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Hold the given lock while sending transactions, see [WalletTxLock]
+    pub fn with_tx_lock(self, tx_lock: WalletTxLock) -> Self {
+        Self { tx_lock, ..self }
+    }
+
+    fn read_bands(&self) -> Result<BalanceBands> {
+        let config = self.config.lock_all().context("Failed to read config")?;
+        let conf = &config.balance;
+        Ok(BalanceBands {
+            check_interval: Duration::from_secs(conf.check_interval_secs),
+            min_market: conf.min_market_balance.map(|amount| amount.wei()),
+            max_market: conf.max_market_balance.map(|amount| amount.wei()),
+            min_stake: conf.min_stake_balance.map(|amount| amount.wei()),
+            max_stake: conf.max_stake_balance.map(|amount| amount.wei()),
+            pause_market: conf.lock_pause_market_balance.map(|amount| amount.wei()),
+            pause_stake: conf.lock_pause_stake_balance.map(|amount| amount.wei()),
+            wallet_reserve: conf.wallet_reserve.wei(),
+            sweep_address: conf.sweep_address,
+            sweep_interval: conf.sweep_interval_secs.map(Duration::from_secs),
+        })
+    }
+
+    /// Keep the market ETH balance inside the configured band
+    async fn rebalance_market(&self, bands: &BalanceBands) -> Result<()> {
+        let addr = self.provider.default_signer_address();
+        let balance = self.market.balance_of(addr).await.context("Failed to get market balance")?;
+
+        match rebalance_amount(balance, bands.min_market, bands.max_market) {
+            Rebalance::None => {}
+            Rebalance::Deposit(amount) => {
+                let wallet = self
+                    .provider
+                    .get_balance(addr)
+                    .await
+                    .context("Failed to get wallet balance")?;
+                let amount = amount.min(wallet.saturating_sub(bands.wallet_reserve));
+                if amount.is_zero() {
+                    tracing::warn!(
+                        "Market balance {} below min but wallet balance {} is at reserve, unable to top up",
+                        format_ether(balance),
+                        format_ether(wallet)
+                    );
+                    return Ok(());
+                }
+                tracing::info!(
+                    "Market balance {} below min, depositing {}",
+                    format_ether(balance),
+                    format_ether(amount)
+                );
+                let _tx_guard = self.tx_lock.lock().await;
+                self.market.deposit(amount).await.context("Failed to deposit to market")?;
+            }
+            Rebalance::Withdraw(amount) => {
+                tracing::info!(
+                    "Market balance {} above max, withdrawing {}",
+                    format_ether(balance),
+                    format_ether(amount)
+                );
+                let _tx_guard = self.tx_lock.lock().await;
+                self.market.withdraw(amount).await.context("Failed to withdraw from market")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keep the market stake balance inside the configured band
+    async fn rebalance_stake(&self, bands: &BalanceBands) -> Result<()> {
+        let addr = self.provider.default_signer_address();
+        let balance =
+            self.market.balance_of_stake(addr).await.context("Failed to get stake balance")?;
+
+        match rebalance_amount(balance, bands.min_stake, bands.max_stake) {
+            Rebalance::None => {}
+            Rebalance::Deposit(amount) => {
+                let wallet = self
+                    .market
+                    .stake_token_balance_of(addr)
+                    .await
+                    .context("Failed to get wallet stake token balance")?;
+                let amount = amount.min(wallet);
+                if amount.is_zero() {
+                    tracing::warn!(
+                        "Stake balance {} below min but wallet holds no stake tokens, unable to top up",
+                        format_ether(balance)
+                    );
+                    return Ok(());
+                }
+                tracing::info!(
+                    "Stake balance {} below min, depositing {}",
+                    format_ether(balance),
+                    format_ether(amount)
+                );
+                let _tx_guard = self.tx_lock.lock().await;
+                self.market
+                    .approve_deposit_stake(amount)
+                    .await
+                    .context("Failed to approve stake deposit")?;
+                self.market.deposit_stake(amount).await.context("Failed to deposit stake")?;
+            }
+            Rebalance::Withdraw(amount) => {
+                tracing::info!(
+                    "Stake balance {} above max, withdrawing {}",
+                    format_ether(balance),
+                    format_ether(amount)
+                );
+                let _tx_guard = self.tx_lock.lock().await;
+                self.market.withdraw_stake(amount).await.context("Failed to withdraw stake")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pause or resume locking depending on the configured floors
+    async fn update_lock_pause(&self, bands: &BalanceBands) -> Result<()> {
+        let addr = self.provider.default_signer_address();
+        let mut paused = false;
+
+        if let Some(floor) = bands.pause_market {
+            let balance =
+                self.market.balance_of(addr).await.context("Failed to get market balance")?;
+            paused |= balance < floor;
+        }
+        if let Some(floor) = bands.pause_stake {
+            let balance =
+                self.market.balance_of_stake(addr).await.context("Failed to get stake balance")?;
+            paused |= balance < floor;
+        }
+
+        self.lock_pause.set(paused);
+        Ok(())
+    }
+
+    /// Transfer the wallet balance above the reserve to the cold address, if a sweep is due
+    async fn sweep(&self, bands: &BalanceBands) -> Result<()> {
+        let (Some(sweep_address), Some(sweep_interval)) =
+            (bands.sweep_address, bands.sweep_interval)
+        else {
+            return Ok(());
+        };
+
+        let mut last_sweep = self.last_sweep.lock().await;
+        if last_sweep.is_some_and(|last| last.elapsed() < sweep_interval) {
+            return Ok(());
+        }
+
+        let addr = self.provider.default_signer_address();
+        let wallet =
+            self.provider.get_balance(addr).await.context("Failed to get wallet balance")?;
+        let amount = wallet.saturating_sub(bands.wallet_reserve);
+        *last_sweep = Some(Instant::now());
+        if amount.is_zero() {
+            tracing::debug!("Wallet balance {} at reserve, nothing to sweep", format_ether(wallet));
+            return Ok(());
+        }
+
+        tracing::info!("Sweeping {} to cold wallet {sweep_address}", format_ether(amount));
+        let tx = TransactionRequest::default().with_to(sweep_address).with_value(amount);
+        let _tx_guard = self.tx_lock.lock().await;
+        let tx_hash = self
+            .provider
+            .send_transaction(tx)
+            .await
+            .context("Failed to send sweep transaction")?
+            .watch()
+            .await
+            .context("Failed to confirm sweep transaction")?;
+        tracing::info!("Swept {} to {sweep_address}: {tx_hash}", format_ether(amount));
+
+        Ok(())
+    }
+
+    /// Run a single balance management pass
+    async fn check_balances(&self) -> Result<BalanceBands> {
+        let bands = self.read_bands()?;
+
+        self.rebalance_market(&bands).await?;
+        self.rebalance_stake(&bands).await?;
+        self.update_lock_pause(&bands).await?;
+        self.sweep(&bands).await?;
+
+        Ok(bands)
+    }
+
+    async fn start_manager(&self) -> Result<()> {
+        loop {
+            let bands = self.check_balances().await?;
+            tokio::time::sleep(bands.check_interval).await;
+        }
+    }
+}
+
+impl<P> RetryTask for BalanceManager<P>
+where
+    P: Provider<Ethereum> + WalletProvider + 'static + Clone,
+{
+    fn spawn(&self) -> RetryRes {
+        let manager = self.clone();
+        Box::pin(async move {
+            tracing::info!("Starting balance manager");
+            manager.start_manager().await.map_err(SupervisorErr::Recover)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{node_bindings::Anvil, primitives::utils::parse_ether};
+    use boundless_market::contracts::test_utils::create_test_ctx;
+    use guest_assessor::ASSESSOR_GUEST_ID;
+    use guest_set_builder::SET_BUILDER_ID;
+    use tracing_test::traced_test;
+
+    #[test]
+    fn rebalance_band() {
+        let one = parse_ether("1").unwrap();
+        let two = parse_ether("2").unwrap();
+        let three = parse_ether("3").unwrap();
+
+        assert_eq!(rebalance_amount(two, None, None), Rebalance::None);
+        assert_eq!(rebalance_amount(two, Some(one), Some(three)), Rebalance::None);
+        assert_eq!(rebalance_amount(U256::ZERO, Some(one), Some(three)), Rebalance::Deposit(two));
+        assert_eq!(
+            rebalance_amount(parse_ether("4").unwrap(), Some(one), Some(three)),
+            Rebalance::Withdraw(two)
+        );
+        assert_eq!(rebalance_amount(U256::ZERO, Some(one), None), Rebalance::Deposit(one));
+        assert_eq!(rebalance_amount(three, None, Some(one)), Rebalance::Withdraw(two));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn top_up_and_pause() {
+        let anvil = Anvil::new().spawn();
+        let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+        let provider = Arc::new(ctx.prover_provider.clone());
+
+        let config = ConfigLock::default();
+        {
+            let mut config = config.load_write().unwrap();
+            config.balance.min_market_balance = Some("1".parse().unwrap());
+            config.balance.max_market_balance = Some("3".parse().unwrap());
+            config.balance.lock_pause_stake_balance = Some("1".parse().unwrap());
+        }
+
+        let lock_pause = LockPause::default();
+        let manager = BalanceManager::new(
+            provider.clone(),
+            config.clone(),
+            ctx.boundless_market_address,
+            lock_pause.clone(),
+        )
+        .unwrap();
+
+        manager.check_balances().await.unwrap();
+        let balance = ctx.prover_market.balance_of(ctx.prover_signer.address()).await.unwrap();
+        assert_eq!(balance, parse_ether("2").unwrap());
+        // No stake deposited, locking should be paused
+        assert!(lock_pause.is_paused());
+
+        {
+            let mut config = config.load_write().unwrap();
+            config.balance.lock_pause_stake_balance = None;
+        }
+        manager.check_balances().await.unwrap();
+        assert!(!lock_pause.is_paused());
+    }
+}
@@ -0,0 +1,16 @@
+// Copyright (c) 2025 RISC Zero, Inc.
+//
+// All rights reserved.
+
+use anyhow::{Context, Result};
+use broker::{run_backtest, BacktestArgs};
+use clap::Parser;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    run_backtest(BacktestArgs::parse()).await.context("Backtest failed")
+}
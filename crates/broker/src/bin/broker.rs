@@ -2,16 +2,56 @@
 //
 // All rights reserved.
 
+use std::path::PathBuf;
+
 use alloy::{
     providers::{network::EthereumWallet, ProviderBuilder, WalletProvider},
     rpc::client::RpcClient,
+    signers::Signer,
     transports::layers::RetryBackoffLayer,
 };
 use alloy_chains::NamedChain;
 use anyhow::{Context, Result};
 use boundless_market::contracts::boundless_market::BoundlessMarketService;
-use broker::{Args, Broker, CustomRetryPolicy};
-use clap::Parser;
+use broker::{check_config, Args, Broker, CustomRetryPolicy};
+use clap::{Parser, Subcommand};
+use url::Url;
+
+/// Runs the broker, unless a subcommand is given
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Broker options, only required when running the broker
+    #[command(flatten)]
+    args: Option<Args>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Broker config file commands
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Validate a config file and print the effective config, with all defaults filled in
+    Check {
+        /// Config file path
+        #[clap(short, long, default_value = "broker.toml")]
+        config_file: PathBuf,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -19,37 +59,80 @@ async fn main() -> Result<()> {
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
-    let args = Args::parse();
+    let cli = Cli::parse();
+    // Config commands only need the config file, not the wallet and market args of the broker
+    if let Some(Command::Config(command)) = cli.command {
+        match command {
+            ConfigCommand::Check { config_file } => {
+                let config = check_config(&config_file).await.context("Invalid broker config")?;
+                println!("{config}");
+            }
+        }
+        return Ok(());
+    }
+    let args = cli.args.context("Missing broker arguments")?;
 
-    let wallet = EthereumWallet::from(args.private_key.clone());
+    let signer = args.signer.signer().context("Failed to construct wallet signer")?;
+    let lock_signer = args.lock_signer().context("Failed to construct lock wallet signer")?;
 
-    let retry_layer = RetryBackoffLayer::new_with_policy(
-        args.rpc_retry_max,
-        args.rpc_retry_backoff,
-        args.rpc_retry_cu,
-        CustomRetryPolicy,
-    );
-    let client = RpcClient::builder().layer(retry_layer).http(args.rpc_url.clone());
+    let (rpc_retry_max, rpc_retry_backoff, rpc_retry_cu) =
+        (args.rpc_retry_max, args.rpc_retry_backoff, args.rpc_retry_cu);
+    let build_provider = |wallet: EthereumWallet, rpc_url: Url| {
+        let retry_layer = RetryBackoffLayer::new_with_policy(
+            rpc_retry_max,
+            rpc_retry_backoff,
+            rpc_retry_cu,
+            CustomRetryPolicy,
+        );
+        let client = RpcClient::builder().layer(retry_layer).http(rpc_url);
+        ProviderBuilder::new().wallet(wallet).with_chain(NamedChain::Sepolia).on_client(client)
+    };
 
-    let provider =
-        ProviderBuilder::new().wallet(wallet).with_chain(NamedChain::Sepolia).on_client(client);
+    let provider = build_provider(EthereumWallet::from(signer.clone()), args.rpc_url.clone());
+    let lock_provider = lock_signer.as_ref().map(|lock_signer| {
+        build_provider(EthereumWallet::from(lock_signer.clone()), args.rpc_url.clone())
+    });
 
-    // TODO: Move this code somewhere else / monitor our balanceOf and top it up as needed
+    // NOTE: ongoing balance / stake top ups are handled by the balance manager, see the
+    // [balance] section of the broker config
     if let Some(deposit_amount) = args.deposit_amount.as_ref() {
+        // Stake is held by the address that locks orders
+        let (stake_provider, stake_signer) = match (&lock_provider, &lock_signer) {
+            (Some(lock_provider), Some(lock_signer)) => (lock_provider.clone(), lock_signer),
+            _ => (provider.clone(), &signer),
+        };
         let boundless_market = BoundlessMarketService::new(
             args.boundless_market_address,
-            provider.clone(),
-            provider.default_signer_address(),
+            stake_provider.clone(),
+            stake_provider.default_signer_address(),
         );
 
-        tracing::info!("pre-depositing {deposit_amount} HP into the market contract");
+        tracing::info!(
+            "pre-depositing {deposit_amount} HP into the market contract for {}",
+            stake_signer.address()
+        );
         boundless_market
-            .deposit_stake_with_permit(*deposit_amount, &args.private_key)
+            .deposit_stake_with_permit(*deposit_amount, stake_signer)
             .await
             .context("Failed to deposit to market")?;
     }
 
-    let broker = Broker::new(args, provider).await?;
+    let mut broker = Broker::new(args, provider).await?.with_signer(signer.clone());
+    if let Some(lock_provider) = lock_provider {
+        broker = broker.with_lock_provider(lock_provider);
+    }
+    // Additional deployments use the same wallets, on their own chains
+    for deployment in broker.deployments()? {
+        let provider =
+            build_provider(EthereumWallet::from(signer.clone()), deployment.rpc_url.clone());
+        let lock_provider = lock_signer.as_ref().map(|lock_signer| {
+            build_provider(EthereumWallet::from(lock_signer.clone()), deployment.rpc_url.clone())
+        });
+        broker = broker
+            .with_market(deployment, provider, lock_provider)
+            .await
+            .context("Failed to add market deployment")?;
+    }
 
     broker.start_service().await.context("Broker service failed")?;
 
@@ -3,14 +3,19 @@
 // All rights reserved.
 
 use std::{
+    fmt,
     path::{Path, PathBuf},
+    str::FromStr,
     sync::{Arc, RwLock},
 };
 
-use alloy::primitives::{Address, B256};
+use alloy::primitives::{
+    utils::{format_ether, parse_ether},
+    Address, B256, U256,
+};
 use anyhow::{Context, Result};
 use notify::{EventKind, Watcher};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 use tokio::{
     fs,
@@ -19,10 +24,16 @@ use tokio::{
 };
 
 mod defaults {
+    use super::EtherAmount;
+
     pub const fn max_journal_bytes() -> usize {
         10_000
     }
 
+    pub const fn max_input_size() -> usize {
+        500_000_000
+    }
+
     pub const fn batch_max_journal_bytes() -> usize {
         10_000
     }
@@ -38,16 +49,111 @@ mod defaults {
     pub const fn max_submission_attempts() -> u32 {
         3
     }
+
+    pub const fn balance_check_interval_secs() -> u64 {
+        60
+    }
+
+    pub const fn max_concurrent_locks() -> usize {
+        4
+    }
+
+    pub const fn max_concurrent_preflights() -> usize {
+        4
+    }
+
+    pub const fn max_concurrent_downloads() -> usize {
+        4
+    }
+
+    pub fn wallet_reserve() -> EtherAmount {
+        "0.05".parse().unwrap()
+    }
+
+    pub const fn proof_retry_count() -> u32 {
+        3
+    }
+
+    pub const fn proof_retry_sleep_ms() -> u64 {
+        5000
+    }
+
+    pub const fn proof_retry_deadline_buffer_secs() -> u64 {
+        300
+    }
+
+    pub const fn proof_escalate_after() -> u32 {
+        1
+    }
+
+    pub const fn webhook_max_retries() -> u32 {
+        3
+    }
+
+    pub const fn webhook_retry_delay_ms() -> u64 {
+        1000
+    }
+
+    pub const fn webhook_timeout_secs() -> u64 {
+        10
+    }
+
+    pub const fn retention_interval_secs() -> u64 {
+        24 * 60 * 60
+    }
+}
+/// Amount of native or stake tokens, written in the config file in ether units, eg "0.1"
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EtherAmount(U256);
+
+impl EtherAmount {
+    /// The amount in wei
+    pub const fn wei(&self) -> U256 {
+        self.0
+    }
+}
+
+impl FromStr for EtherAmount {
+    type Err = ConfigErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // parse_ether wraps negative amounts around
+        if s.trim_start().starts_with('-') {
+            return Err(ConfigErr::InvalidAmount(s.into(), "amount is negative".into()));
+        }
+        parse_ether(s).map(Self).map_err(|err| ConfigErr::InvalidAmount(s.into(), err.to_string()))
+    }
+}
+
+impl fmt::Display for EtherAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let amount = format_ether(self.0);
+        f.write_str(amount.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
+impl Serialize for EtherAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
+
+impl<'de> Deserialize<'de> for EtherAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// All configuration related to markets mechanics
 #[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct MarketConf {
     /// Mega Cycle price (in native token)
-    pub mcycle_price: String,
+    pub mcycle_price: EtherAmount,
     /// Assumption price (in native token)
     ///
     /// UNUSED CURRENTLY
-    pub assumption_price: Option<String>,
+    pub assumption_price: Option<EtherAmount>,
     /// Optional max cycles (in mcycles)
     ///
     /// Orders over this max_cycles will be skipped after preflight
@@ -68,7 +174,7 @@ pub struct MarketConf {
     /// On startup the number of blocks to look back for possible open orders
     pub lookback_blocks: u64,
     /// Max stake amount, in (native token)
-    pub max_stake: String,
+    pub max_stake: EtherAmount,
     /// ImageID's that skip preflight
     pub skip_preflight_ids: Option<Vec<B256>>,
     /// Optional allow list for customer address
@@ -82,51 +188,87 @@ pub struct MarketConf {
     /// same block
     pub lockin_priority_gas: Option<u64>,
     /// Max input / image file size allowed for downloading from request URLs
+    ///
+    /// Applies to each chunk of a chunked input
     pub max_file_size: usize,
+    /// Max size of an input after decompressing it and assembling its chunks
+    #[serde(default = "defaults::max_input_size")]
+    pub max_input_size: usize,
     /// Max retries for fetching input / image contents from URLs
     pub max_fetch_retries: Option<u8>,
     /// Gas Estimation
     ///
     /// Gas estimate for lockin call to use if it cannot be estimated using the node RPC
+    ///
+    /// Used for pricing, lock transactions are sent with the gas estimated by the node
     #[serde(default = "defaults::lockin_gas_estimate")]
     pub lockin_gas_estimate: u64,
     /// Gas estimate for fulfill call to use if it cannot be estimated using the node RPC
     #[serde(default = "defaults::fulfill_gas_estimate")]
     pub fulfill_gas_estimate: u64,
+    /// Max callback gas limit of an order
+    ///
+    /// The prover pays for the callback gas at fulfillment, orders with a larger callback
+    /// gas limit are skipped. Leaving this unset accepts any callback gas limit.
+    pub max_callback_gas: Option<u64>,
     /// Stake balance warning threshold (in stake tokens)
     /// if the stake balance drops below this the broker will issue warning logs
-    pub stake_balance_warn_threshold: Option<String>,
+    pub stake_balance_warn_threshold: Option<EtherAmount>,
     /// Stake balance error threshold (in stake tokens)
     /// if the stake balance drops below this the broker will issue error logs
-    pub stake_balance_error_threshold: Option<String>,
+    pub stake_balance_error_threshold: Option<EtherAmount>,
+    /// Max number of lock transactions in flight at once
+    ///
+    /// Each lock is simulated against the pending block before it is sent
+    #[serde(default = "defaults::max_concurrent_locks")]
+    pub max_concurrent_locks: usize,
+    /// Optional list of client addresses whose orders are priced before all others
+    pub priority_client_addresses: Option<Vec<Address>>,
+    /// Max number of orders priced at once
+    ///
+    /// Each order being priced runs at most one preflight. Only read at startup.
+    #[serde(default = "defaults::max_concurrent_preflights")]
+    pub max_concurrent_preflights: usize,
+    /// Max number of image / input downloads at once, across all orders being priced
+    ///
+    /// Only read at startup.
+    #[serde(default = "defaults::max_concurrent_downloads")]
+    pub max_concurrent_downloads: usize,
 }
 
 impl Default for MarketConf {
     fn default() -> Self {
         Self {
-            mcycle_price: "0.1".to_string(),
+            mcycle_price: "0.1".parse().unwrap(),
             assumption_price: None,
             max_mcycle_limit: None,
             max_journal_bytes: defaults::max_journal_bytes(), // 10 KB
             peak_prove_khz: None,
             min_deadline: 300, // 5 mins
             lookback_blocks: 100,
-            max_stake: "0.1".to_string(),
+            max_stake: "0.1".parse().unwrap(),
             skip_preflight_ids: None,
             allow_client_addresses: None,
             lockin_priority_gas: None,
             max_file_size: 50_000_000,
+            max_input_size: defaults::max_input_size(),
             max_fetch_retries: Some(2),
             lockin_gas_estimate: defaults::lockin_gas_estimate(),
             fulfill_gas_estimate: defaults::fulfill_gas_estimate(),
+            max_callback_gas: None,
             stake_balance_warn_threshold: None,
             stake_balance_error_threshold: None,
+            max_concurrent_locks: defaults::max_concurrent_locks(),
+            priority_client_addresses: None,
+            max_concurrent_preflights: defaults::max_concurrent_preflights(),
+            max_concurrent_downloads: defaults::max_concurrent_downloads(),
         }
     }
 }
 
 /// All configuration related to prover (bonsai / Bento) mechanics
 #[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct ProverConf {
     /// Polling interval to monitor proving status (in millisecs)
     pub status_poll_ms: u64,
@@ -143,6 +285,18 @@ pub struct ProverConf {
     pub set_builder_guest_path: Option<PathBuf>,
     /// Assessor ELF path
     pub assessor_set_guest_path: Option<PathBuf>,
+    /// Max number of times a failed proof is retried before the order is failed
+    #[serde(default = "defaults::proof_retry_count")]
+    pub proof_retry_count: u32,
+    /// Delay between proof retries (in millisecs)
+    #[serde(default = "defaults::proof_retry_sleep_ms")]
+    pub proof_retry_sleep_ms: u64,
+    /// Min number of seconds left before the lock deadline to start another proof attempt
+    #[serde(default = "defaults::proof_retry_deadline_buffer_secs")]
+    pub proof_retry_deadline_buffer_secs: u64,
+    /// Number of failed attempts on the primary backend before re-routing to the fallback
+    #[serde(default = "defaults::proof_escalate_after")]
+    pub proof_escalate_after: u32,
 }
 
 impl Default for ProverConf {
@@ -153,12 +307,17 @@ impl Default for ProverConf {
             req_retry_count: 0,
             set_builder_guest_path: None,
             assessor_set_guest_path: None,
+            proof_retry_count: defaults::proof_retry_count(),
+            proof_retry_sleep_ms: defaults::proof_retry_sleep_ms(),
+            proof_retry_deadline_buffer_secs: defaults::proof_retry_deadline_buffer_secs(),
+            proof_escalate_after: defaults::proof_escalate_after(),
         }
     }
 }
 
 /// All configuration related to batching / aggregation
 #[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct BatcherConfig {
     /// Max batch duration before publishing (in seconds)
     pub batch_max_time: Option<u64>,
@@ -168,7 +327,7 @@ pub struct BatcherConfig {
     #[serde(default = "defaults::batch_max_journal_bytes")]
     pub batch_max_journal_bytes: usize,
     /// max batch fees (in ETH) before publishing
-    pub batch_max_fees: Option<String>,
+    pub batch_max_fees: Option<EtherAmount>,
     /// Batch blocktime buffer
     ///
     /// Number of seconds before the lowest block deadline in the order batch
@@ -207,8 +366,146 @@ impl Default for BatcherConfig {
     }
 }
 
+/// All configuration related to automatic market balance / stake management
+///
+/// All amounts are optional, leaving a band unset disables management of that balance
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BalanceConf {
+    /// Interval between balance checks (in seconds)
+    #[serde(default = "defaults::balance_check_interval_secs")]
+    pub check_interval_secs: u64,
+    /// Min market balance (in native token)
+    ///
+    /// If the market balance drops below this, it is topped up from the wallet
+    pub min_market_balance: Option<EtherAmount>,
+    /// Max market balance (in native token)
+    ///
+    /// If the market balance exceeds this, the excess is withdrawn to the wallet
+    pub max_market_balance: Option<EtherAmount>,
+    /// Min stake balance (in stake tokens)
+    ///
+    /// If the market stake balance drops below this, it is topped up from the wallet
+    pub min_stake_balance: Option<EtherAmount>,
+    /// Max stake balance (in stake tokens)
+    ///
+    /// If the market stake balance exceeds this, the excess is withdrawn to the wallet
+    pub max_stake_balance: Option<EtherAmount>,
+    /// Market balance floor (in native token) below which order locking is paused
+    pub lock_pause_market_balance: Option<EtherAmount>,
+    /// Stake balance floor (in stake tokens) below which order locking is paused
+    pub lock_pause_stake_balance: Option<EtherAmount>,
+    /// Amount of native token to always keep in the wallet for gas (in native token)
+    #[serde(default = "defaults::wallet_reserve")]
+    pub wallet_reserve: EtherAmount,
+    /// Cold wallet address to sweep earnings to
+    ///
+    /// Any wallet balance above `wallet_reserve` is transferred to this address
+    pub sweep_address: Option<Address>,
+    /// Interval between sweeps to the cold wallet (in seconds)
+    pub sweep_interval_secs: Option<u64>,
+}
+
+impl Default for BalanceConf {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: defaults::balance_check_interval_secs(),
+            min_market_balance: None,
+            max_market_balance: None,
+            min_stake_balance: None,
+            max_stake_balance: None,
+            lock_pause_market_balance: None,
+            lock_pause_stake_balance: None,
+            wallet_reserve: defaults::wallet_reserve(),
+            sweep_address: None,
+            sweep_interval_secs: None,
+        }
+    }
+}
+
+/// All configuration related to order / batch lifecycle webhooks
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConf {
+    /// URL to POST lifecycle events to
+    ///
+    /// Leaving this unset disables webhooks
+    pub url: Option<String>,
+    /// Shared secret used to sign each payload
+    ///
+    /// When set, the hex HMAC-SHA256 of the body is sent in the `X-Boundless-Signature` header
+    pub secret: Option<String>,
+    /// Max number of retries for a failed delivery
+    #[serde(default = "defaults::webhook_max_retries")]
+    pub max_retries: u32,
+    /// Initial delay between retries, doubled on each attempt up to 60 seconds (in milliseconds)
+    #[serde(default = "defaults::webhook_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// Request timeout (in seconds)
+    #[serde(default = "defaults::webhook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConf {
+    fn default() -> Self {
+        Self {
+            url: None,
+            secret: None,
+            max_retries: defaults::webhook_max_retries(),
+            retry_delay_ms: defaults::webhook_retry_delay_ms(),
+            timeout_secs: defaults::webhook_timeout_secs(),
+        }
+    }
+}
+
+/// All configuration related to retention of old orders and batches
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConf {
+    /// Archive terminal orders and submitted / failed batches older than this (in days)
+    ///
+    /// Leaving this unset keeps everything in the DB forever
+    pub retention_days: Option<u64>,
+    /// Directory to export archived orders and batches to, as JSON lines
+    ///
+    /// Exported entries are removed from the DB. When unset, they are moved to the archive
+    /// tables of the DB instead.
+    pub archive_dir: Option<PathBuf>,
+    /// Interval between retention runs (in seconds)
+    #[serde(default = "defaults::retention_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl Default for RetentionConf {
+    fn default() -> Self {
+        Self {
+            retention_days: None,
+            archive_dir: None,
+            interval_secs: defaults::retention_interval_secs(),
+        }
+    }
+}
+
+/// Additional market deployment served by the broker, on the same or another chain
+///
+/// Each deployment gets its own monitors, order picker, order monitor and submitter, and shares
+/// the prover backend and pricing limits with the primary market set on the command line.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DeploymentConf {
+    /// RPC URL of the deployment's chain
+    pub rpc_url: String,
+    /// Boundless market address
+    pub boundless_market_address: Address,
+    /// Risc zero Set verifier address
+    pub set_verifier_address: Address,
+    /// Order stream server URL
+    pub order_stream_url: Option<String>,
+}
+
 /// Top level config for the broker service
 #[derive(Deserialize, Serialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// Market / bidding configurations
     pub market: MarketConf,
@@ -216,13 +513,135 @@ pub struct Config {
     pub prover: ProverConf,
     /// Aggregation batch configs
     pub batcher: BatcherConfig,
+    /// Balance / stake management configs
+    #[serde(default)]
+    pub balance: BalanceConf,
+    /// Lifecycle webhook configs
+    #[serde(default)]
+    pub webhook: WebhookConf,
+    /// Order / batch retention configs
+    #[serde(default)]
+    pub retention: RetentionConf,
+    /// Additional market deployments, only read at startup
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deployments: Vec<DeploymentConf>,
 }
 
 impl Config {
-    /// Load the config from disk
+    /// Load and validate the config from disk
     pub async fn load(path: &Path) -> Result<Self> {
         let data = fs::read_to_string(path).await.context("Failed to read config file")?;
-        toml::from_str(&data).context("Failed to parse toml file")
+        let config: Self = toml::from_str(&data).context("Failed to parse toml file")?;
+        config.validate().context("Failed to validate config")?;
+        Ok(config)
+    }
+
+    /// Checks the bounds of values that are not fully constrained by their types
+    ///
+    /// Returns every invalid value at once, so a config file can be fixed in a single pass.
+    pub fn validate(&self) -> Result<(), ConfigErr> {
+        let mut errors = vec![];
+        let mut check = |valid: bool, msg: &str| {
+            if !valid {
+                errors.push(msg.to_string());
+            }
+        };
+
+        let market = &self.market;
+        check(market.mcycle_price.wei() > U256::ZERO, "market.mcycle_price must be greater than 0");
+        check(market.max_mcycle_limit != Some(0), "market.max_mcycle_limit must be greater than 0");
+        check(market.max_journal_bytes > 0, "market.max_journal_bytes must be greater than 0");
+        check(market.peak_prove_khz != Some(0), "market.peak_prove_khz must be greater than 0");
+        check(market.max_file_size > 0, "market.max_file_size must be greater than 0");
+        check(market.max_input_size > 0, "market.max_input_size must be greater than 0");
+        check(market.lockin_gas_estimate > 0, "market.lockin_gas_estimate must be greater than 0");
+        check(
+            market.fulfill_gas_estimate > 0,
+            "market.fulfill_gas_estimate must be greater than 0",
+        );
+        check(
+            market.max_concurrent_locks > 0,
+            "market.max_concurrent_locks must be greater than 0",
+        );
+        check(
+            market.max_concurrent_preflights > 0,
+            "market.max_concurrent_preflights must be greater than 0",
+        );
+        check(
+            market.max_concurrent_downloads > 0,
+            "market.max_concurrent_downloads must be greater than 0",
+        );
+        if let (Some(warn), Some(error)) =
+            (market.stake_balance_warn_threshold, market.stake_balance_error_threshold)
+        {
+            check(
+                error <= warn,
+                "market.stake_balance_error_threshold must not exceed stake_balance_warn_threshold",
+            );
+        }
+
+        check(self.prover.status_poll_ms > 0, "prover.status_poll_ms must be greater than 0");
+
+        let batcher = &self.batcher;
+        check(batcher.batch_size != Some(0), "batcher.batch_size must be greater than 0");
+        check(
+            batcher.batch_max_journal_bytes > 0,
+            "batcher.batch_max_journal_bytes must be greater than 0",
+        );
+        check(
+            batcher.batch_poll_time_ms != Some(0),
+            "batcher.batch_poll_time_ms must be greater than 0",
+        );
+        check(
+            batcher.max_submission_attempts > 0,
+            "batcher.max_submission_attempts must be greater than 0",
+        );
+
+        let balance = &self.balance;
+        check(
+            balance.check_interval_secs > 0,
+            "balance.check_interval_secs must be greater than 0",
+        );
+        if let (Some(min), Some(max)) = (balance.min_market_balance, balance.max_market_balance) {
+            check(min <= max, "balance.min_market_balance must not exceed max_market_balance");
+        }
+        if let (Some(min), Some(max)) = (balance.min_stake_balance, balance.max_stake_balance) {
+            check(min <= max, "balance.min_stake_balance must not exceed max_stake_balance");
+        }
+        check(
+            balance.sweep_interval_secs != Some(0),
+            "balance.sweep_interval_secs must be greater than 0",
+        );
+
+        if let Some(url) = self.webhook.url.as_ref() {
+            check(url::Url::parse(url).is_ok(), "webhook.url is not a valid URL");
+        }
+        check(self.webhook.timeout_secs > 0, "webhook.timeout_secs must be greater than 0");
+
+        check(
+            self.retention.retention_days != Some(0),
+            "retention.retention_days must be greater than 0",
+        );
+        check(self.retention.interval_secs > 0, "retention.interval_secs must be greater than 0");
+
+        for deployment in &self.deployments {
+            check(
+                url::Url::parse(&deployment.rpc_url).is_ok(),
+                "deployments.rpc_url is not a valid URL",
+            );
+            if let Some(url) = deployment.order_stream_url.as_ref() {
+                check(
+                    url::Url::parse(url).is_ok(),
+                    "deployments.order_stream_url is not a valid URL",
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErr::InvalidValues(errors))
+        }
     }
 
     /// Write the config to disk
@@ -240,6 +659,26 @@ pub enum ConfigErr {
 
     #[error("Invalid configuration")]
     InvalidConfig,
+
+    #[error("Invalid ether amount {0:?}: {1}")]
+    InvalidAmount(String, String),
+
+    #[error("Invalid config values: {}", .0.join(", "))]
+    InvalidValues(Vec<String>),
+}
+
+/// Placeholder printed instead of secret config values
+const REDACTED: &str = "<redacted>";
+
+/// Validates a config file, returning the effective config with all defaults filled in
+///
+/// Secrets are redacted, so the output is safe to print.
+pub async fn check_config(path: &Path) -> Result<String> {
+    let mut config = Config::load(path).await?;
+    if config.webhook.secret.is_some() {
+        config.webhook.secret = Some(REDACTED.into());
+    }
+    toml::to_string_pretty(&config).context("Failed to serialize config")
 }
 
 #[derive(Clone, Default)]
@@ -262,9 +701,27 @@ impl ConfigLock {
     }
 }
 
+impl From<Config> for ConfigLock {
+    fn from(config: Config) -> Self {
+        Self::new(Arc::new(RwLock::new(config)))
+    }
+}
+
 /// Max number of pending filesystem events from the config file
 const FILE_MONITOR_EVENT_BUFFER: usize = 32;
 
+/// Log the changes of a reloaded config that only take effect after a restart
+fn warn_startup_only_changes(old: &Config, new: &Config) {
+    if old.market.max_concurrent_preflights != new.market.max_concurrent_preflights
+        || old.market.max_concurrent_downloads != new.market.max_concurrent_downloads
+    {
+        tracing::warn!(
+            "market.max_concurrent_preflights and market.max_concurrent_downloads are only read \
+             at startup, restart the broker to apply the new limits"
+        );
+    }
+}
+
 /// Monitor service for watching config files for changes
 pub struct ConfigWatcher {
     /// Current config data
@@ -314,7 +771,9 @@ impl ConfigWatcher {
                         let new_config = match Config::load(&config_path_copy).await {
                             Ok(val) => val,
                             Err(err) => {
-                                tracing::error!("Failed to load modified config: {err:?}");
+                                tracing::error!(
+                                    "Failed to load modified config, keeping the current config: {err:?}"
+                                );
                                 continue;
                             }
                         };
@@ -327,6 +786,7 @@ impl ConfigWatcher {
                                 continue;
                             }
                         };
+                        warn_startup_only_changes(&config, &new_config);
                         *config = new_config;
                     }
                     _ => {
@@ -397,14 +857,24 @@ lookback_blocks = 100
 max_stake = "0.1"
 skip_preflight_ids = ["0x0000000000000000000000000000000000000000000000000000000000000001"]
 max_file_size = 50_000_000
+max_input_size = 200_000_000
 max_fetch_retries = 10
 allow_client_addresses = ["0x0000000000000000000000000000000000000000"]
 lockin_priority_gas = 100
 max_mcycle_limit = 10
+max_concurrent_locks = 2
+priority_client_addresses = ["0x0000000000000000000000000000000000000001"]
+max_concurrent_preflights = 8
+max_concurrent_downloads = 3
+max_callback_gas = 500000
 
 [prover]
 status_poll_ms = 1000
 req_retry_count = 0
+proof_retry_count = 5
+proof_retry_sleep_ms = 100
+proof_retry_deadline_buffer_secs = 60
+proof_escalate_after = 2
 
 [batcher]
 batch_max_time = 300
@@ -412,12 +882,38 @@ batch_size = 2
 block_deadline_buffer_secs = 120
 txn_timeout = 45
 batch_poll_time_ms = 1200
-single_txn_fulfill = true"#;
+single_txn_fulfill = true
+
+[balance]
+min_market_balance = "0.5"
+max_market_balance = "2"
+lock_pause_stake_balance = "10"
+sweep_address = "0x0000000000000000000000000000000000000001"
+sweep_interval_secs = 3600
+
+[webhook]
+url = "http://localhost:9000/hook"
+secret = "hunter2"
+max_retries = 5
+
+[retention]
+retention_days = 30
+archive_dir = "/var/lib/broker/archive"
+
+[[deployments]]
+rpc_url = "http://localhost:8546"
+boundless_market_address = "0x0000000000000000000000000000000000000001"
+set_verifier_address = "0x0000000000000000000000000000000000000002"
+"#;
 
     const BAD_CONFIG: &str = r#"
 [market]
 error = ?"#;
 
+    fn ether(amount: &str) -> EtherAmount {
+        amount.parse().unwrap()
+    }
+
     fn write_config(data: &str, file: &mut File) {
         file.seek(std::io::SeekFrom::Start(0)).unwrap();
         file.write_all(data.as_bytes()).unwrap();
@@ -430,19 +926,21 @@ error = ?"#;
         write_config(CONFIG_TEMPL, config_temp.as_file_mut());
         let config = Config::load(config_temp.path()).await.unwrap();
 
-        assert_eq!(config.market.mcycle_price, "0.1");
+        assert_eq!(config.market.mcycle_price, ether("0.1"));
         assert_eq!(config.market.assumption_price, None);
         assert_eq!(config.market.peak_prove_khz, Some(500));
         assert_eq!(config.market.min_deadline, 300);
         assert_eq!(config.market.lookback_blocks, 100);
-        assert_eq!(config.market.max_stake, "0.1");
+        assert_eq!(config.market.max_stake, ether("0.1"));
         assert_eq!(config.market.max_file_size, 50_000_000);
+        assert_eq!(config.market.max_input_size, 500_000_000);
         assert_eq!(
             config.market.skip_preflight_ids.unwrap()[0],
             B256::from_hex("0x0000000000000000000000000000000000000000000000000000000000000001")
                 .unwrap()
         );
         assert_eq!(config.market.lockin_priority_gas, None);
+        assert_eq!(config.market.max_callback_gas, None);
 
         assert_eq!(config.prover.status_poll_ms, 1000);
         assert_eq!(config.prover.bonsai_r0_zkvm_ver.unwrap(), "1.0.1");
@@ -452,10 +950,20 @@ error = ?"#;
 
         assert_eq!(config.batcher.batch_max_time, Some(300));
         assert_eq!(config.batcher.batch_size, Some(2));
-        assert_eq!(config.batcher.batch_max_fees, Some("0.1".into()));
+        assert_eq!(config.batcher.batch_max_fees, Some(ether("0.1")));
         assert_eq!(config.batcher.block_deadline_buffer_secs, 120);
         assert_eq!(config.batcher.txn_timeout, None);
         assert_eq!(config.batcher.batch_poll_time_ms, None);
+
+        assert_eq!(config.balance.check_interval_secs, 60);
+        assert_eq!(config.balance.min_market_balance, None);
+        assert_eq!(config.balance.wallet_reserve, ether("0.05"));
+        assert_eq!(config.balance.sweep_address, None);
+        assert_eq!(config.webhook.url, None);
+        assert_eq!(config.webhook.max_retries, 3);
+        assert_eq!(config.retention.retention_days, None);
+        assert_eq!(config.retention.interval_secs, 86400);
+        assert!(config.deployments.is_empty());
     }
 
     #[tokio::test]
@@ -475,7 +983,7 @@ error = ?"#;
 
         {
             let config = config_mgnr.config.lock_all().unwrap();
-            assert_eq!(config.market.mcycle_price, "0.1");
+            assert_eq!(config.market.mcycle_price, ether("0.1"));
             assert_eq!(config.market.assumption_price, None);
             assert_eq!(config.market.peak_prove_khz, Some(500));
             assert_eq!(config.market.min_deadline, 300);
@@ -490,24 +998,137 @@ error = ?"#;
         {
             tracing::debug!("Locking config for reading...");
             let config = config_mgnr.config.lock_all().unwrap();
-            assert_eq!(config.market.mcycle_price, "0.1");
-            assert_eq!(config.market.assumption_price, Some("0.1".into()));
+            assert_eq!(config.market.mcycle_price, ether("0.1"));
+            assert_eq!(config.market.assumption_price, Some(ether("0.1")));
             assert_eq!(config.market.peak_prove_khz, Some(10000));
             assert_eq!(config.market.min_deadline, 300);
             assert_eq!(config.market.lookback_blocks, 100);
             assert_eq!(config.market.allow_client_addresses, Some(vec![Address::ZERO]));
             assert_eq!(config.market.lockin_priority_gas, Some(100));
             assert_eq!(config.market.max_fetch_retries, Some(10));
+            assert_eq!(config.market.max_input_size, 200_000_000);
             assert_eq!(config.market.max_mcycle_limit, Some(10));
+            assert_eq!(config.market.max_concurrent_locks, 2);
+            assert_eq!(
+                config.market.priority_client_addresses,
+                Some(vec![Address::with_last_byte(1)])
+            );
+            assert_eq!(config.market.max_concurrent_preflights, 8);
+            assert_eq!(config.market.max_concurrent_downloads, 3);
+            assert_eq!(config.market.max_callback_gas, Some(500000));
             assert_eq!(config.prover.status_poll_ms, 1000);
             assert!(config.prover.bonsai_r0_zkvm_ver.is_none());
+            assert_eq!(config.prover.proof_retry_count, 5);
+            assert_eq!(config.prover.proof_retry_sleep_ms, 100);
+            assert_eq!(config.prover.proof_retry_deadline_buffer_secs, 60);
+            assert_eq!(config.prover.proof_escalate_after, 2);
             assert_eq!(config.batcher.txn_timeout, Some(45));
             assert_eq!(config.batcher.batch_poll_time_ms, Some(1200));
             assert!(config.batcher.single_txn_fulfill);
+            assert_eq!(config.balance.min_market_balance, Some(ether("0.5")));
+            assert_eq!(config.balance.max_market_balance, Some(ether("2")));
+            assert_eq!(config.balance.lock_pause_stake_balance, Some(ether("10")));
+            assert_eq!(
+                config.balance.sweep_address,
+                Some(Address::from_hex("0x0000000000000000000000000000000000000001").unwrap())
+            );
+            assert_eq!(config.balance.sweep_interval_secs, Some(3600));
+            assert_eq!(config.webhook.url, Some("http://localhost:9000/hook".into()));
+            assert_eq!(config.webhook.secret, Some("hunter2".into()));
+            assert_eq!(config.webhook.max_retries, 5);
+            assert_eq!(config.webhook.retry_delay_ms, 1000);
+            assert_eq!(config.retention.retention_days, Some(30));
+            assert_eq!(config.retention.archive_dir, Some("/var/lib/broker/archive".into()));
+            assert_eq!(config.retention.interval_secs, 86400);
+            assert_eq!(config.deployments.len(), 1);
+            assert_eq!(config.deployments[0].rpc_url, "http://localhost:8546");
+            assert_eq!(
+                config.deployments[0].boundless_market_address,
+                Address::from_hex("0x0000000000000000000000000000000000000001").unwrap()
+            );
+            assert_eq!(config.deployments[0].order_stream_url, None);
         }
         tracing::debug!("closing...");
     }
 
+    #[tokio::test]
+    async fn invalid_config() {
+        let mut config_temp = NamedTempFile::new().unwrap();
+
+        // Values are checked against their bounds, and every bad value is reported
+        let config = CONFIG_TEMPL
+            .replace("mcycle_price = \"0.1\"", "mcycle_price = \"0\"")
+            .replace("batch_size = 2", "batch_size = 0");
+        write_config(&config, config_temp.as_file_mut());
+        let err = Config::load(config_temp.path()).await.unwrap_err();
+        let err = format!("{err:?}");
+        assert!(err.contains("market.mcycle_price must be greater than 0"));
+        assert!(err.contains("batcher.batch_size must be greater than 0"));
+
+        // Ether amounts must parse
+        for amount in ["abc", "-1"] {
+            let config =
+                CONFIG_TEMPL.replace("max_stake = \"0.1\"", &format!("max_stake = \"{amount}\""));
+            write_config(&config, config_temp.as_file_mut());
+            let err = Config::load(config_temp.path()).await.unwrap_err();
+            assert!(format!("{err:?}").contains("Invalid ether amount"), "{amount}: {err:?}");
+        }
+
+        // Typos are not silently ignored
+        let config = CONFIG_TEMPL.replace("lookback_blocks", "lookback_block");
+        write_config(&config, config_temp.as_file_mut());
+        let err = Config::load(config_temp.path()).await.unwrap_err();
+        assert!(format!("{err:?}").contains("unknown field `lookback_block`"));
+    }
+
+    #[tokio::test]
+    async fn check_config_defaults() {
+        let mut config_temp = NamedTempFile::new().unwrap();
+        write_config(CONFIG_TEMPL, config_temp.as_file_mut());
+        let effective = check_config(config_temp.path()).await.unwrap();
+
+        // Defaults are filled in, and the effective config loads back the same
+        assert!(effective.contains("max_concurrent_locks = 4"));
+        assert!(effective.contains("wallet_reserve = \"0.05\""));
+        let config: Config = toml::from_str(&effective).unwrap();
+        assert_eq!(config.market.mcycle_price, ether("0.1"));
+        assert_eq!(config.batcher.batch_max_fees, Some(ether("0.1")));
+        assert_eq!(config.retention.interval_secs, 86400);
+    }
+
+    #[tokio::test]
+    async fn check_config_redacts_secrets() {
+        let mut config_temp = NamedTempFile::new().unwrap();
+        write_config(CONFIG_TEMPL_2, config_temp.as_file_mut());
+        let effective = check_config(config_temp.path()).await.unwrap();
+
+        assert!(!effective.contains("hunter2"));
+        let config: Config = toml::from_str(&effective).unwrap();
+        assert_eq!(config.webhook.secret.as_deref(), Some(REDACTED));
+        assert_eq!(config.webhook.url.as_deref(), Some("http://localhost:9000/hook"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn watcher_keeps_config_on_invalid_reload() {
+        let mut config_temp = NamedTempFile::new().unwrap();
+        write_config(CONFIG_TEMPL, config_temp.as_file_mut());
+        let config_mgnr = ConfigWatcher::new(config_temp.path()).await.unwrap();
+
+        let config = CONFIG_TEMPL
+            .replace("peak_prove_khz = 500", "peak_prove_khz = 1000")
+            .replace("max_stake = \"0.1\"", "max_stake = \"-1\"");
+        write_config(&config, config_temp.as_file_mut());
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+        {
+            let config = config_mgnr.config.lock_all().unwrap();
+            assert_eq!(config.market.peak_prove_khz, Some(500));
+            assert_eq!(config.market.max_stake, ether("0.1"));
+        }
+        assert!(logs_contain("keeping the current config"));
+    }
+
     #[tokio::test]
     #[traced_test]
     #[should_panic(expected = "Failed to parse toml file")]
@@ -37,7 +37,6 @@ enum DbOperation {
     AddOrder(u32),
     OperateOnExistingOrder(ExistingOrderOperation),
     BatchOperation(BatchOperation),
-    GetOrderForPricing,
     GetActivePricingOrders,
     GetPendingLockOrders(u32),
     GetProvingOrder,
@@ -61,6 +60,7 @@ enum ExistingOrderOperation {
     SetAggregationStatus,
     GetSubmissionOrder,
     OrderExists,
+    GetOrderForPricing,
 }
 
 #[derive(Debug, Arbitrary, Clone)]
@@ -112,6 +112,10 @@ fn generate_test_order(id: u32) -> Order {
         client_sig: vec![].into(),
         lock_price: Some(U256::from(10)),
         error_msg: None,
+        proof_failures: vec![],
+        dry_run: false,
+        input_digest: None,
+        assumption_ids: vec![],
     }
 }
 
@@ -224,6 +228,9 @@ proptest! {
                                     ExistingOrderOperation::OrderExists => {
                                         db.order_exists(U256::from(id)).await.unwrap();
                                     },
+                                    ExistingOrderOperation::GetOrderForPricing => {
+                                        db.get_order_for_pricing(U256::from(id)).await.unwrap();
+                                    },
                                 }
                             },
                             DbOperation::BatchOperation(operation) => {
@@ -291,9 +298,6 @@ proptest! {
                                     },
                                 }
                             },
-                            DbOperation::GetOrderForPricing => {
-                                db.get_order_for_pricing().await.unwrap();
-                            },
                             DbOperation::GetActivePricingOrders => {
                                 db.get_active_pricing_orders().await.unwrap();
                             },
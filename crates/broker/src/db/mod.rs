@@ -4,17 +4,19 @@
 
 use std::{default::Default, str::FromStr, sync::Arc};
 
-use alloy::primitives::{ruint::ParseError as RuintParseErr, B256, U256};
+use alloy::primitives::{ruint::ParseError as RuintParseErr, Address, B256, U256};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use risc0_zkvm::sha::Digest;
 use sqlx::{
-    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+    sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions},
     Row,
 };
 use thiserror::Error;
 
-use crate::{AggregationState, Batch, BatchStatus, Order, OrderStatus, ProofRequest};
+use crate::{
+    AggregationState, ArchiveStats, Batch, BatchStatus, LostLock, Order, OrderStatus, ProofRequest,
+};
 
 #[cfg(test)]
 mod fuzz_db;
@@ -52,6 +54,9 @@ pub enum DbError {
     #[error("Invalid block number: {0}")]
     BadBlockNumb(String),
 
+    #[error("Invalid chain ID: {0}")]
+    BadChainId(String),
+
     #[error("Failed to set last block")]
     SetBlockFail,
 
@@ -80,7 +85,7 @@ pub trait BrokerDb {
         &self,
         id: U256,
     ) -> Result<(ProofRequest, String, B256, U256), DbError>;
-    async fn get_order_for_pricing(&self) -> Result<Option<(U256, Order)>, DbError>;
+    async fn get_order_for_pricing(&self, id: U256) -> Result<Option<Order>, DbError>;
     async fn get_active_pricing_orders(&self) -> Result<Vec<(U256, Order)>, DbError>;
     async fn set_order_lock(
         &self,
@@ -92,22 +97,60 @@ pub trait BrokerDb {
     async fn set_order_failure(&self, id: U256, failure_str: String) -> Result<(), DbError>;
     async fn set_order_complete(&self, id: U256) -> Result<(), DbError>;
     async fn skip_order(&self, id: U256) -> Result<(), DbError>;
+    async fn set_order_dry_run(&self, id: U256, lock_price: U256) -> Result<(), DbError>;
+    async fn skip_dry_run_orders(&self) -> Result<u64, DbError>;
     async fn get_last_block(&self) -> Result<Option<u64>, DbError>;
     async fn set_last_block(&self, block_numb: u64) -> Result<(), DbError>;
+    async fn add_lost_lock(&self, id: U256, lost_lock: LostLock) -> Result<(), DbError>;
+    async fn get_lost_locks(&self) -> Result<Vec<(U256, LostLock)>, DbError>;
     async fn get_pending_lock_orders(
         &self,
         end_timestamp: u64,
     ) -> Result<Vec<(U256, Order)>, DbError>;
     async fn get_orders_committed_to_fulfill_count(&self) -> Result<u64, DbError>;
+    /// Orders either pending lock or locked, that the broker will have to fulfill
+    async fn get_orders_committed_to_fulfill(&self) -> Result<Vec<(U256, Order)>, DbError>;
+    async fn get_orders_by_status(
+        &self,
+        status: OrderStatus,
+    ) -> Result<Vec<(U256, Order)>, DbError>;
+    /// New orders inserted after the given sequence number, with their sequence numbers, in
+    /// insertion order
+    ///
+    /// Sequence numbers are never reused, even after orders are archived.
+    async fn get_new_orders_after(&self, seq: i64) -> Result<Vec<(i64, U256, Order)>, DbError>;
     async fn get_proving_order(&self) -> Result<Option<(U256, Order)>, DbError>;
     async fn get_active_proofs(&self) -> Result<Vec<(U256, Order)>, DbError>;
     async fn set_order_proof_id(&self, order_id: U256, proof_id: &str) -> Result<(), DbError>;
+    async fn add_proof_failure(&self, id: U256, failure: String) -> Result<(), DbError>;
     async fn set_image_input_ids(
         &self,
         id: U256,
         image_id: &str,
         input_id: &str,
     ) -> Result<(), DbError>;
+    async fn set_order_input_digest(&self, id: U256, input_digest: B256) -> Result<(), DbError>;
+    async fn set_order_assumption_ids(
+        &self,
+        id: U256,
+        assumption_ids: &[String],
+    ) -> Result<(), DbError>;
+    /// Indexes a finished proof by the image ID and input digest it proves
+    ///
+    /// The cache is shared by all markets, as they share the same prover backend.
+    async fn add_cached_proof(
+        &self,
+        image_id: B256,
+        input_digest: B256,
+        proof_id: &str,
+    ) -> Result<(), DbError>;
+    async fn get_cached_proof(
+        &self,
+        image_id: B256,
+        input_digest: B256,
+    ) -> Result<Option<String>, DbError>;
+    /// Removes the proofs cached before the given time, returning the number of removed proofs
+    async fn prune_cached_proofs(&self, added_before: DateTime<Utc>) -> Result<usize, DbError>;
     async fn set_aggregation_status(&self, id: U256) -> Result<(), DbError>;
     async fn get_aggregation_proofs(&self) -> Result<Vec<AggregationOrder>, DbError>;
     async fn complete_batch(&self, batch_id: usize, g16_proof_id: String) -> Result<(), DbError>;
@@ -128,19 +171,57 @@ pub trait BrokerDb {
         assessor_claim_digest: Option<Digest>,
     ) -> Result<(), DbError>;
     async fn get_batch(&self, batch_id: usize) -> Result<Batch, DbError>;
+    async fn get_batches_by_status(
+        &self,
+        status: BatchStatus,
+    ) -> Result<Vec<(usize, Batch)>, DbError>;
+    async fn set_batch_status(&self, batch_id: usize, status: BatchStatus) -> Result<(), DbError>;
+    /// Orders in a terminal status, last updated before the given time
+    async fn get_archivable_orders(
+        &self,
+        updated_before: DateTime<Utc>,
+    ) -> Result<Vec<(U256, Order)>, DbError>;
+    /// Removes terminal orders from the orders table, adding them to the archive stats
+    ///
+    /// Orders are moved to the archived orders table if `keep_archive` is set, and dropped
+    /// otherwise. Returns the number of archived orders.
+    async fn archive_orders(&self, ids: &[U256], keep_archive: bool) -> Result<usize, DbError>;
+    /// Submitted or failed batches, started before the given time
+    async fn get_archivable_batches(
+        &self,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<(usize, Batch)>, DbError>;
+    /// Removes submitted or failed batches from the batches table, see [BrokerDb::archive_orders]
+    async fn archive_batches(&self, ids: &[usize], keep_archive: bool) -> Result<usize, DbError>;
+    async fn get_archive_stats(&self) -> Result<ArchiveStats, DbError>;
+    /// Reclaims the space freed by archiving
+    async fn vacuum(&self) -> Result<(), DbError>;
 
     #[cfg(test)]
     async fn add_batch(&self, batch_id: usize, batch: Batch) -> Result<(), DbError>;
-    #[cfg(test)]
-    async fn set_batch_status(&self, batch_id: usize, status: BatchStatus) -> Result<(), DbError>;
 }
 
 pub type DbObj = Arc<dyn BrokerDb + Send + Sync>;
 
-const SQL_BLOCK_KEY: i64 = 0;
+/// Market ID of rows written before orders were namespaced by market
+const LEGACY_MARKET_ID: i64 = 0;
+
+/// Order statuses that are never changed again, and can be archived
+const ARCHIVABLE_ORDER_STATUSES: [OrderStatus; 4] =
+    [OrderStatus::Done, OrderStatus::Skipped, OrderStatus::Failed, OrderStatus::DryRun];
 
+/// Sqlite backed [BrokerDb]
+///
+/// Each instance only sees the orders and batches of a single market deployment, use
+/// [SqliteDb::for_market] to get a view of another market that shares the same connection pool.
+#[derive(Clone)]
 pub struct SqliteDb {
     pool: SqlitePool,
+    /// Mark every added order as a dry-run order
+    dry_run: bool,
+    /// ID of the (chain ID, market address) pair that namespaces all orders, batches and the last
+    /// processed block
+    market_id: i64,
 }
 
 impl SqliteDb {
@@ -165,24 +246,128 @@ impl SqliteDb {
 
         sqlx::migrate!("./migrations").run(&pool).await?;
 
-        Ok(Self { pool })
+        Ok(Self { pool, dry_run: false, market_id: LEGACY_MARKET_ID })
     }
 
     #[cfg(test)]
     pub async fn from(pool: SqlitePool) -> Result<Self, DbError> {
-        Ok(Self { pool })
+        Ok(Self { pool, dry_run: false, market_id: LEGACY_MARKET_ID })
+    }
+
+    /// View of the orders and batches of the market at `address` on the given chain, sharing
+    /// this DB connection
+    pub async fn for_market(&self, chain_id: u64, address: Address) -> Result<Self, DbError> {
+        let chain_id =
+            i64::try_from(chain_id).map_err(|_| DbError::BadChainId(chain_id.to_string()))?;
+        let address = address.to_string();
+
+        let mut txn = self.pool.begin().await?;
+        let existing: Option<i64> =
+            sqlx::query_scalar("SELECT id FROM markets WHERE chain_id = $1 AND address = $2")
+                .bind(chain_id)
+                .bind(&address)
+                .fetch_optional(&mut *txn)
+                .await?;
+        let market_id = match existing {
+            Some(market_id) => market_id,
+            None => {
+                sqlx::query_scalar(
+                    "INSERT INTO markets (chain_id, address) VALUES ($1, $2) RETURNING id",
+                )
+                .bind(chain_id)
+                .bind(&address)
+                .fetch_one(&mut *txn)
+                .await?
+            }
+        };
+        txn.commit().await?;
+
+        Ok(Self { market_id, ..self.clone() })
+    }
+
+    /// Move the rows written before orders were namespaced by market into this market
+    ///
+    /// Only one market can adopt them, the broker uses its primary market.
+    pub async fn adopt_legacy_rows(&self) -> Result<u64, DbError> {
+        if self.market_id == LEGACY_MARKET_ID {
+            return Ok(0);
+        }
+
+        let mut txn = self.pool.begin().await?;
+        let mut adopted = 0;
+        for table in ["orders", "batches", "lost_locks", "archived_orders", "archived_batches"] {
+            let res =
+                sqlx::query(&format!("UPDATE {table} SET market_id = $1 WHERE market_id = $2"))
+                    .bind(self.market_id)
+                    .bind(LEGACY_MARKET_ID)
+                    .execute(&mut *txn)
+                    .await?;
+            if table == "orders" {
+                adopted = res.rows_affected();
+            }
+        }
+        // These tables are keyed by market ID, existing rows of this market take precedence
+        for table in ["last_block", "archive_stats"] {
+            sqlx::query(&format!("UPDATE OR IGNORE {table} SET id = $1 WHERE id = $2"))
+                .bind(self.market_id)
+                .bind(LEGACY_MARKET_ID)
+                .execute(&mut *txn)
+                .await?;
+            sqlx::query(&format!("DELETE FROM {table} WHERE id = $1"))
+                .bind(LEGACY_MARKET_ID)
+                .execute(&mut *txn)
+                .await?;
+        }
+        txn.commit().await?;
+
+        Ok(adopted)
+    }
+
+    /// Flag all orders added from now on as found in dry-run mode
+    pub fn with_dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
     }
 
     async fn new_batch(&self) -> Result<usize, DbError> {
         let batch = Batch { start_time: Utc::now(), ..Default::default() };
 
-        let res: i64 = sqlx::query_scalar("INSERT INTO batches (data) VALUES ($1) RETURNING id")
-            .bind(sqlx::types::Json(&batch))
-            .fetch_one(&self.pool)
-            .await?;
+        let res: i64 = sqlx::query_scalar(
+            "INSERT INTO batches (market_id, data) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(self.market_id)
+        .bind(sqlx::types::Json(&batch))
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(res as usize)
     }
+
+    async fn read_archive_stats(
+        conn: &mut SqliteConnection,
+        market_id: i64,
+    ) -> Result<ArchiveStats, DbError> {
+        let stats: Option<sqlx::types::Json<ArchiveStats>> =
+            sqlx::query_scalar("SELECT data FROM archive_stats WHERE id = $1")
+                .bind(market_id)
+                .fetch_optional(conn)
+                .await?;
+
+        Ok(stats.map(|stats| stats.0).unwrap_or_default())
+    }
+
+    async fn write_archive_stats(
+        conn: &mut SqliteConnection,
+        market_id: i64,
+        stats: &ArchiveStats,
+    ) -> Result<(), DbError> {
+        sqlx::query("REPLACE INTO archive_stats (id, data) VALUES ($1, $2)")
+            .bind(market_id)
+            .bind(sqlx::types::Json(stats))
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
 }
 
 #[derive(sqlx::FromRow)]
@@ -192,6 +377,21 @@ struct DbOrder {
     data: Order,
 }
 
+#[derive(sqlx::FromRow)]
+struct DbSeqOrder {
+    seq: i64,
+    id: String,
+    #[sqlx(json)]
+    data: Order,
+}
+
+#[derive(sqlx::FromRow)]
+struct DbLostLock {
+    id: String,
+    #[sqlx(json)]
+    data: LostLock,
+}
+
 #[derive(sqlx::FromRow)]
 struct DbBatch {
     id: i64,
@@ -202,8 +402,10 @@ struct DbBatch {
 #[async_trait]
 impl BrokerDb for SqliteDb {
     async fn add_order(&self, id: U256, order: Order) -> Result<Option<Order>, DbError> {
+        let order = Order { dry_run: order.dry_run || self.dry_run, ..order };
         // TODO(austin): https://github.com/boundless-xyz/boundless/issues/162
-        sqlx::query("INSERT INTO orders (id, data) VALUES ($1, $2)")
+        sqlx::query("INSERT INTO orders (market_id, id, data) VALUES ($1, $2, $3)")
+            .bind(self.market_id)
             .bind(format!("{id:x}"))
             .bind(sqlx::types::Json(&order))
             .execute(&self.pool)
@@ -212,19 +414,23 @@ impl BrokerDb for SqliteDb {
     }
 
     async fn order_exists(&self, id: U256) -> Result<bool, DbError> {
-        let res: i64 = sqlx::query_scalar("SELECT COUNT(1) FROM orders WHERE id = $1")
-            .bind(format!("{id:x}"))
-            .fetch_one(&self.pool)
-            .await?;
+        let res: i64 =
+            sqlx::query_scalar("SELECT COUNT(1) FROM orders WHERE market_id = $1 AND id = $2")
+                .bind(self.market_id)
+                .bind(format!("{id:x}"))
+                .fetch_one(&self.pool)
+                .await?;
 
         Ok(res == 1)
     }
 
     async fn get_order(&self, id: U256) -> Result<Option<Order>, DbError> {
-        let order: Option<DbOrder> = sqlx::query_as("SELECT * FROM orders WHERE id = $1 LIMIT 1")
-            .bind(format!("{id:x}"))
-            .fetch_optional(&self.pool)
-            .await?;
+        let order: Option<DbOrder> =
+            sqlx::query_as("SELECT * FROM orders WHERE market_id = $1 AND id = $2 LIMIT 1")
+                .bind(self.market_id)
+                .bind(format!("{id:x}"))
+                .fetch_optional(&self.pool)
+                .await?;
 
         Ok(order.map(|x| x.data))
     }
@@ -246,35 +452,30 @@ impl BrokerDb for SqliteDb {
         }
     }
 
-    async fn get_order_for_pricing(&self) -> Result<Option<(U256, Order)>, DbError> {
+    async fn get_order_for_pricing(&self, id: U256) -> Result<Option<Order>, DbError> {
         let elm: Option<DbOrder> = sqlx::query_as(
             r#"
             UPDATE orders
             SET data = json_set(json_set(data, '$.status', $1), '$.update_at', $2)
-            WHERE id =
-                (SELECT id
-                FROM orders
-                WHERE data->>'status' = $3
-                LIMIT 1)
+            WHERE market_id = $3 AND id = $4 AND data->>'status' = $5
             RETURNING *
             "#,
         )
         .bind(OrderStatus::Pricing)
         .bind(Utc::now().timestamp())
+        .bind(self.market_id)
+        .bind(format!("{id:x}"))
         .bind(OrderStatus::New)
         .fetch_optional(&self.pool)
         .await?;
 
-        let Some(order) = elm else {
-            return Ok(None);
-        };
-
-        Ok(Some((U256::from_str_radix(&order.id, 16)?, order.data)))
+        Ok(elm.map(|order| order.data))
     }
 
     async fn get_active_pricing_orders(&self) -> Result<Vec<(U256, Order)>, DbError> {
         let orders: Vec<DbOrder> =
-            sqlx::query_as("SELECT * FROM orders WHERE data->>'status' = $1")
+            sqlx::query_as("SELECT * FROM orders WHERE market_id = $1 AND data->>'status' = $2")
+                .bind(self.market_id)
                 .bind(OrderStatus::Pricing)
                 .fetch_all(&self.pool)
                 .await?;
@@ -305,7 +506,7 @@ impl BrokerDb for SqliteDb {
                        '$.expire_timestamp', $3),
                        '$.updated_at', $4)
             WHERE
-                id = $5"#,
+                id = $5 AND market_id = $6"#,
         )
         .bind(OrderStatus::Locking)
         // TODO: can we work out how to correctly
@@ -321,6 +522,7 @@ impl BrokerDb for SqliteDb {
         )
         .bind(Utc::now().timestamp())
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -342,12 +544,13 @@ impl BrokerDb for SqliteDb {
                        '$.updated_at', $2),
                        '$.lock_price', $3)
             WHERE
-                id = $4"#,
+                id = $4 AND market_id = $5"#,
         )
         .bind(OrderStatus::Locked)
         .bind(Utc::now().timestamp())
         .bind(lock_price.to_string())
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -369,12 +572,13 @@ impl BrokerDb for SqliteDb {
                        '$.updated_at', $2),
                        '$.error_msg', $3)
             WHERE
-                id = $4"#,
+                id = $4 AND market_id = $5"#,
         )
         .bind(OrderStatus::Failed)
         .bind(Utc::now().timestamp())
         .bind(failure_str)
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -394,11 +598,12 @@ impl BrokerDb for SqliteDb {
                        '$.status', $1),
                        '$.updated_at', $2)
             WHERE
-                id = $3"#,
+                id = $3 AND market_id = $4"#,
         )
         .bind(OrderStatus::Done)
         .bind(Utc::now().timestamp())
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -418,11 +623,40 @@ impl BrokerDb for SqliteDb {
                        '$.status', $1),
                        '$.updated_at', $2)
             WHERE
-                id = $3"#,
+                id = $3 AND market_id = $4"#,
         )
         .bind(OrderStatus::Skipped)
         .bind(Utc::now().timestamp())
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id));
+        }
+
+        Ok(())
+    }
+
+    async fn set_order_dry_run(&self, id: U256, lock_price: U256) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = json_set(
+                       json_set(
+                       json_set(data,
+                       '$.status', $1),
+                       '$.updated_at', $2),
+                       '$.lock_price', $3)
+            WHERE
+                id = $4 AND market_id = $5"#,
+        )
+        .bind(OrderStatus::DryRun)
+        .bind(Utc::now().timestamp())
+        .bind(lock_price.to_string())
+        .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -433,10 +667,35 @@ impl BrokerDb for SqliteDb {
         Ok(())
     }
 
+    async fn skip_dry_run_orders(&self) -> Result<u64, DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = json_set(
+                       json_set(data,
+                       '$.status', $1),
+                       '$.updated_at', $2)
+            WHERE
+                data->>'dry_run' = 1
+                AND data->>'status' IN ($3, $4, $5)
+                AND market_id = $6"#,
+        )
+        .bind(OrderStatus::Skipped)
+        .bind(Utc::now().timestamp())
+        .bind(OrderStatus::New)
+        .bind(OrderStatus::Pricing)
+        .bind(OrderStatus::Locking)
+        .bind(self.market_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
     async fn get_last_block(&self) -> Result<Option<u64>, DbError> {
         // TODO: query_as, seems to not work correctly here
         let res = sqlx::query("SELECT block FROM last_block WHERE id = $1")
-            .bind(SQL_BLOCK_KEY)
+            .bind(self.market_id)
             .fetch_optional(&self.pool)
             .await?;
 
@@ -451,7 +710,7 @@ impl BrokerDb for SqliteDb {
 
     async fn set_last_block(&self, block_numb: u64) -> Result<(), DbError> {
         let res = sqlx::query("REPLACE INTO last_block (id, block) VALUES ($1, $2)")
-            .bind(SQL_BLOCK_KEY)
+            .bind(self.market_id)
             .bind(block_numb.to_string())
             .execute(&self.pool)
             .await?;
@@ -463,13 +722,38 @@ impl BrokerDb for SqliteDb {
         Ok(())
     }
 
+    async fn add_lost_lock(&self, id: U256, lost_lock: LostLock) -> Result<(), DbError> {
+        sqlx::query("REPLACE INTO lost_locks (market_id, id, data) VALUES ($1, $2, $3)")
+            .bind(self.market_id)
+            .bind(format!("{id:x}"))
+            .bind(sqlx::types::Json(&lost_lock))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_lost_locks(&self) -> Result<Vec<(U256, LostLock)>, DbError> {
+        let lost_locks: Vec<DbLostLock> =
+            sqlx::query_as("SELECT * FROM lost_locks WHERE market_id = $1")
+                .bind(self.market_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        lost_locks
+            .into_iter()
+            .map(|elm| Ok((U256::from_str_radix(&elm.id, 16)?, elm.data)))
+            .collect()
+    }
+
     async fn get_pending_lock_orders(
         &self,
         end_timestamp: u64,
     ) -> Result<Vec<(U256, Order)>, DbError> {
         let orders: Vec<DbOrder> = sqlx::query_as(
-            "SELECT * FROM orders WHERE data->>'status' = $1 AND data->>'target_timestamp' <= $2",
+            "SELECT * FROM orders WHERE market_id = $1 AND data->>'status' = $2 AND data->>'target_timestamp' <= $3",
         )
+        .bind(self.market_id)
         .bind(OrderStatus::Locking)
         .bind(end_timestamp as i64)
         .fetch_all(&self.pool)
@@ -486,7 +770,7 @@ impl BrokerDb for SqliteDb {
 
     async fn get_orders_committed_to_fulfill_count(&self) -> Result<u64, DbError> {
         let count: i64 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM orders WHERE data->>'status' IN ($1, $2, $3, $4, $5, $6)",
+            "SELECT COUNT(*) FROM orders WHERE data->>'status' IN ($1, $2, $3, $4, $5, $6) AND market_id = $7",
         )
         .bind(OrderStatus::Locking)
         .bind(OrderStatus::Locked)
@@ -494,27 +778,81 @@ impl BrokerDb for SqliteDb {
         .bind(OrderStatus::PendingAgg)
         .bind(OrderStatus::Aggregating)
         .bind(OrderStatus::PendingSubmission)
+        .bind(self.market_id)
         .fetch_one(&self.pool)
         .await?;
 
         Ok(count as u64)
     }
 
+    async fn get_orders_committed_to_fulfill(&self) -> Result<Vec<(U256, Order)>, DbError> {
+        let orders: Vec<DbOrder> = sqlx::query_as(
+            "SELECT * FROM orders WHERE data->>'status' IN ($1, $2, $3, $4, $5, $6) AND market_id = $7",
+        )
+        .bind(OrderStatus::Locking)
+        .bind(OrderStatus::Locked)
+        .bind(OrderStatus::Proving)
+        .bind(OrderStatus::PendingAgg)
+        .bind(OrderStatus::Aggregating)
+        .bind(OrderStatus::PendingSubmission)
+        .bind(self.market_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        orders.into_iter().map(|elm| Ok((U256::from_str_radix(&elm.id, 16)?, elm.data))).collect()
+    }
+
+    async fn get_orders_by_status(
+        &self,
+        status: OrderStatus,
+    ) -> Result<Vec<(U256, Order)>, DbError> {
+        let orders: Vec<DbOrder> =
+            sqlx::query_as("SELECT * FROM orders WHERE market_id = $1 AND data->>'status' = $2")
+                .bind(self.market_id)
+                .bind(status)
+                .fetch_all(&self.pool)
+                .await?;
+
+        let orders: Result<Vec<_>, _> = orders
+            .into_iter()
+            .map(|elm| Ok((U256::from_str_radix(&elm.id, 16)?, elm.data)))
+            .collect();
+
+        orders
+    }
+
+    async fn get_new_orders_after(&self, seq: i64) -> Result<Vec<(i64, U256, Order)>, DbError> {
+        let orders: Vec<DbSeqOrder> = sqlx::query_as(
+            "SELECT seq, id, data FROM orders WHERE seq > $1 AND market_id = $2 AND data->>'status' = $3 ORDER BY seq",
+        )
+        .bind(seq)
+        .bind(self.market_id)
+        .bind(OrderStatus::New)
+        .fetch_all(&self.pool)
+        .await?;
+
+        orders
+            .into_iter()
+            .map(|elm| Ok((elm.seq, U256::from_str_radix(&elm.id, 16)?, elm.data)))
+            .collect()
+    }
+
     async fn get_proving_order(&self) -> Result<Option<(U256, Order)>, DbError> {
         let elm: Option<DbOrder> = sqlx::query_as(
             r#"
             UPDATE orders
             SET data = json_set(json_set(data, '$.status', $1), '$.update_at', $2)
-            WHERE id =
+            WHERE market_id = $3 AND id =
                 (SELECT id
                 FROM orders
-                WHERE data->>'status' = $3
+                WHERE market_id = $3 AND data->>'status' = $4
                 LIMIT 1)
             RETURNING *
             "#,
         )
         .bind(OrderStatus::Proving)
         .bind(Utc::now().timestamp())
+        .bind(self.market_id)
         .bind(OrderStatus::Locked)
         .fetch_optional(&self.pool)
         .await?;
@@ -528,7 +866,8 @@ impl BrokerDb for SqliteDb {
 
     async fn get_active_proofs(&self) -> Result<Vec<(U256, Order)>, DbError> {
         let orders: Vec<DbOrder> =
-            sqlx::query_as("SELECT * FROM orders WHERE data->>'status' = $1")
+            sqlx::query_as("SELECT * FROM orders WHERE market_id = $1 AND data->>'status' = $2")
+                .bind(self.market_id)
                 .bind(OrderStatus::Proving)
                 .fetch_all(&self.pool)
                 .await?;
@@ -550,11 +889,38 @@ impl BrokerDb for SqliteDb {
                        '$.proof_id', $1),
                        '$.updated_at', $2)
             WHERE
-                id = $3"#,
+                id = $3 AND market_id = $4"#,
         )
         .bind(proof_id)
         .bind(Utc::now().timestamp())
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id));
+        }
+
+        Ok(())
+    }
+
+    async fn add_proof_failure(&self, id: U256, failure: String) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = json_set(
+                       json_set(data,
+                       '$.proof_failures',
+                       json_insert(COALESCE(json_extract(data, '$.proof_failures'), '[]'), '$[#]', $1)),
+                       '$.updated_at', $2)
+            WHERE
+                id = $3 AND market_id = $4"#,
+        )
+        .bind(failure)
+        .bind(Utc::now().timestamp())
+        .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -581,12 +947,67 @@ impl BrokerDb for SqliteDb {
                        '$.input_id', $2),
                        '$.updated_at', $3)
             WHERE
-                id = $4"#,
+                id = $4 AND market_id = $5"#,
         )
         .bind(image_id)
         .bind(input_id)
         .bind(Utc::now().timestamp())
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id));
+        }
+
+        Ok(())
+    }
+
+    async fn set_order_input_digest(&self, id: U256, input_digest: B256) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = json_set(
+                       json_set(data,
+                       '$.input_digest', $1),
+                       '$.updated_at', $2)
+            WHERE
+                id = $3 AND market_id = $4"#,
+        )
+        .bind(input_digest.to_string())
+        .bind(Utc::now().timestamp())
+        .bind(format!("{id:x}"))
+        .bind(self.market_id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id));
+        }
+
+        Ok(())
+    }
+
+    async fn set_order_assumption_ids(
+        &self,
+        id: U256,
+        assumption_ids: &[String],
+    ) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = json_set(
+                       json_set(data,
+                       '$.assumption_ids', json($1)),
+                       '$.updated_at', $2)
+            WHERE
+                id = $3 AND market_id = $4"#,
+        )
+        .bind(sqlx::types::Json(assumption_ids))
+        .bind(Utc::now().timestamp())
+        .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -597,6 +1018,50 @@ impl BrokerDb for SqliteDb {
         Ok(())
     }
 
+    async fn add_cached_proof(
+        &self,
+        image_id: B256,
+        input_digest: B256,
+        proof_id: &str,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO proof_cache (image_id, input_digest, proof_id, created_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(image_id.to_string())
+        .bind(input_digest.to_string())
+        .bind(proof_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_cached_proof(
+        &self,
+        image_id: B256,
+        input_digest: B256,
+    ) -> Result<Option<String>, DbError> {
+        let proof_id: Option<(String,)> = sqlx::query_as(
+            "SELECT proof_id FROM proof_cache WHERE image_id = $1 AND input_digest = $2",
+        )
+        .bind(image_id.to_string())
+        .bind(input_digest.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(proof_id.map(|(proof_id,)| proof_id))
+    }
+
+    async fn prune_cached_proofs(&self, added_before: DateTime<Utc>) -> Result<usize, DbError> {
+        let res = sqlx::query("DELETE FROM proof_cache WHERE created_at < $1")
+            .bind(added_before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(res.rows_affected() as usize)
+    }
+
     async fn set_aggregation_status(&self, id: U256) -> Result<(), DbError> {
         let res = sqlx::query(
             r#"
@@ -606,11 +1071,12 @@ impl BrokerDb for SqliteDb {
                        '$.status', $1),
                        '$.updated_at', $2)
             WHERE
-                id = $3"#,
+                id = $3 AND market_id = $4"#,
         )
         .bind(OrderStatus::PendingAgg)
         .bind(Utc::now().timestamp())
         .bind(format!("{id:x}"))
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -631,6 +1097,7 @@ impl BrokerDb for SqliteDb {
                        '$.update_at', $2)
             WHERE
                 data->>'status' IN ($3, $4)
+                AND market_id = $5
             RETURNING *
             "#,
         )
@@ -638,6 +1105,7 @@ impl BrokerDb for SqliteDb {
         .bind(Utc::now().timestamp())
         .bind(OrderStatus::PendingAgg)
         .bind(OrderStatus::Aggregating)
+        .bind(self.market_id)
         .fetch_all(&self.pool)
         .await?;
 
@@ -675,11 +1143,12 @@ impl BrokerDb for SqliteDb {
                        '$.status', $1),
                        '$.aggregation_state.groth16_proof_id', $2)
             WHERE
-                id = $3"#,
+                id = $3 AND market_id = $4"#,
         )
         .bind(BatchStatus::Complete)
         .bind(g16_proof_id)
         .bind(batch_id as i64)
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -699,12 +1168,13 @@ impl BrokerDb for SqliteDb {
             WHERE id =
                 (SELECT id
                 FROM batches
-                WHERE data->>'status' = $2
+                WHERE market_id = $2 AND data->>'status' = $3
                 LIMIT 1)
             RETURNING *
             "#,
         )
         .bind(BatchStatus::PendingSubmission)
+        .bind(self.market_id)
         .bind(BatchStatus::Complete)
         .fetch_optional(&self.pool)
         .await?;
@@ -723,10 +1193,11 @@ impl BrokerDb for SqliteDb {
             SET
                 data = json_set(data, '$.status', $1)
             WHERE
-                id = $2"#,
+                id = $2 AND market_id = $3"#,
         )
         .bind(BatchStatus::Submitted)
         .bind(batch_id as i64)
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -747,11 +1218,12 @@ impl BrokerDb for SqliteDb {
                        '$.status', $1),
                        '$.error_msg', $2)
             WHERE
-                id = $3"#,
+                id = $3 AND market_id = $4"#,
         )
         .bind(BatchStatus::Failed)
         .bind(err)
         .bind(batch_id as i64)
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -764,17 +1236,22 @@ impl BrokerDb for SqliteDb {
 
     async fn get_current_batch(&self) -> Result<usize, DbError> {
         let batch_count: i64 =
-            sqlx::query_scalar("SELECT COUNT(*) FROM batches").fetch_one(&self.pool).await?;
+            sqlx::query_scalar("SELECT COUNT(*) FROM batches WHERE market_id = $1")
+                .bind(self.market_id)
+                .fetch_one(&self.pool)
+                .await?;
 
         if batch_count == 0 {
             self.new_batch().await
         } else {
-            let cur_batch: Option<DbBatch> =
-                sqlx::query_as("SELECT * FROM batches WHERE data->>'status' IN ($1, $2) LIMIT 1")
-                    .bind(BatchStatus::Aggregating)
-                    .bind(BatchStatus::PendingCompression)
-                    .fetch_optional(&self.pool)
-                    .await?;
+            let cur_batch: Option<DbBatch> = sqlx::query_as(
+                "SELECT * FROM batches WHERE market_id = $1 AND data->>'status' IN ($2, $3) LIMIT 1",
+            )
+            .bind(self.market_id)
+            .bind(BatchStatus::Aggregating)
+            .bind(BatchStatus::PendingCompression)
+            .fetch_optional(&self.pool)
+            .await?;
 
             if let Some(batch) = cur_batch {
                 Ok(batch.id as usize)
@@ -793,8 +1270,9 @@ impl BrokerDb for SqliteDb {
     ) -> Result<(), DbError> {
         let mut txn = self.pool.begin().await?;
 
-        let rows = sqlx::query(r#"SELECT data->>'fees' as fees, data->>'deadline' as deadline FROM batches WHERE id = $1"#)
+        let rows = sqlx::query(r#"SELECT data->>'fees' as fees, data->>'deadline' as deadline FROM batches WHERE id = $1 AND market_id = $2"#)
             .bind(batch_id as i64)
+            .bind(self.market_id)
             .fetch_optional(&mut *txn)
             .await?;
 
@@ -827,12 +1305,13 @@ impl BrokerDb for SqliteDb {
                        '$.fees', $2),
                        '$.aggregation_state', json($3))
             WHERE
-                id = $4"#,
+                id = $4 AND market_id = $5"#,
         )
         .bind(new_deadline)
         .bind(format!("0x{new_fees:x}"))
         .bind(sqlx::types::Json(aggreagtion_state))
         .bind(batch_id as i64)
+        .bind(self.market_id)
         .execute(&mut *txn)
         .await?;
 
@@ -848,10 +1327,11 @@ impl BrokerDb for SqliteDb {
                 SET
                     data = json_set(data, '$.orders', json_insert(data->>'orders', '$[#]', $1))
                 WHERE
-                    id = $2"#,
+                    id = $2 AND market_id = $3"#,
             )
             .bind(format!("0x{:x}", order.order_id))
             .bind(batch_id as i64)
+            .bind(self.market_id)
             .execute(&mut *txn)
             .await?;
 
@@ -867,11 +1347,12 @@ impl BrokerDb for SqliteDb {
                            '$.status', $1),
                            '$.updated_at', $2)
                 WHERE
-                    id = $3"#,
+                    id = $3 AND market_id = $4"#,
             )
             .bind(OrderStatus::PendingSubmission)
             .bind(Utc::now().timestamp())
             .bind(format!("{:x}", order.order_id))
+            .bind(self.market_id)
             .execute(&mut *txn)
             .await?;
 
@@ -890,11 +1371,12 @@ impl BrokerDb for SqliteDb {
                            '$.status', $1),
                            '$.assessor_claim_digest', json($2))
                 WHERE
-                    id = $3"#,
+                    id = $3 AND market_id = $4"#,
             )
             .bind(BatchStatus::PendingCompression)
             .bind(sqlx::types::Json(assessor_claim_digest))
             .bind(batch_id as i64)
+            .bind(self.market_id)
             .execute(&mut *txn)
             .await?;
 
@@ -909,10 +1391,12 @@ impl BrokerDb for SqliteDb {
     }
 
     async fn get_batch(&self, batch_id: usize) -> Result<Batch, DbError> {
-        let batch: Option<DbBatch> = sqlx::query_as("SELECT * FROM batches WHERE id = $1")
-            .bind(batch_id as i64)
-            .fetch_optional(&self.pool)
-            .await?;
+        let batch: Option<DbBatch> =
+            sqlx::query_as("SELECT * FROM batches WHERE id = $1 AND market_id = $2")
+                .bind(batch_id as i64)
+                .bind(self.market_id)
+                .fetch_optional(&self.pool)
+                .await?;
 
         if let Some(batch) = batch {
             Ok(batch.data)
@@ -921,10 +1405,156 @@ impl BrokerDb for SqliteDb {
         }
     }
 
+    async fn get_batches_by_status(
+        &self,
+        status: BatchStatus,
+    ) -> Result<Vec<(usize, Batch)>, DbError> {
+        let batches: Vec<DbBatch> = sqlx::query_as(
+            "SELECT * FROM batches WHERE market_id = $1 AND data->>'status' = $2 ORDER BY id",
+        )
+        .bind(self.market_id)
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(batches.into_iter().map(|elm| (elm.id as usize, elm.data)).collect())
+    }
+
+    async fn get_archivable_orders(
+        &self,
+        updated_before: DateTime<Utc>,
+    ) -> Result<Vec<(U256, Order)>, DbError> {
+        let orders: Vec<DbOrder> = sqlx::query_as(
+            "SELECT * FROM orders WHERE data->>'status' IN ($1, $2, $3, $4) AND data->>'updated_at' < $5 AND market_id = $6",
+        )
+        .bind(ARCHIVABLE_ORDER_STATUSES[0])
+        .bind(ARCHIVABLE_ORDER_STATUSES[1])
+        .bind(ARCHIVABLE_ORDER_STATUSES[2])
+        .bind(ARCHIVABLE_ORDER_STATUSES[3])
+        .bind(updated_before.timestamp())
+        .bind(self.market_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        orders.into_iter().map(|elm| Ok((U256::from_str_radix(&elm.id, 16)?, elm.data))).collect()
+    }
+
+    async fn archive_orders(&self, ids: &[U256], keep_archive: bool) -> Result<usize, DbError> {
+        let mut txn = self.pool.begin().await?;
+        let mut stats = Self::read_archive_stats(&mut txn, self.market_id).await?;
+
+        let mut archived = 0;
+        for id in ids {
+            let order: Option<DbOrder> = sqlx::query_as(
+                "DELETE FROM orders WHERE id = $1 AND data->>'status' IN ($2, $3, $4, $5) AND market_id = $6 RETURNING *",
+            )
+            .bind(format!("{id:x}"))
+            .bind(ARCHIVABLE_ORDER_STATUSES[0])
+            .bind(ARCHIVABLE_ORDER_STATUSES[1])
+            .bind(ARCHIVABLE_ORDER_STATUSES[2])
+            .bind(ARCHIVABLE_ORDER_STATUSES[3])
+            .bind(self.market_id)
+            .fetch_optional(&mut *txn)
+            .await?;
+            let Some(order) = order else {
+                continue;
+            };
+
+            if keep_archive {
+                sqlx::query(
+                    "REPLACE INTO archived_orders (market_id, id, data) VALUES ($1, $2, $3)",
+                )
+                .bind(self.market_id)
+                .bind(&order.id)
+                .bind(sqlx::types::Json(&order.data))
+                .execute(&mut *txn)
+                .await?;
+            }
+            stats.add_order(&order.data);
+            archived += 1;
+        }
+
+        Self::write_archive_stats(&mut txn, self.market_id, &stats).await?;
+        txn.commit().await?;
+
+        Ok(archived)
+    }
+
+    async fn get_archivable_batches(
+        &self,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<(usize, Batch)>, DbError> {
+        let batches: Vec<DbBatch> = sqlx::query_as(
+            "SELECT * FROM batches WHERE market_id = $1 AND data->>'status' IN ($2, $3) ORDER BY id",
+        )
+        .bind(self.market_id)
+        .bind(BatchStatus::Submitted)
+        .bind(BatchStatus::Failed)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // start_time is stored as an RFC 3339 string, so it is compared here instead of in SQL
+        Ok(batches
+            .into_iter()
+            .filter(|elm| elm.data.start_time < started_before)
+            .map(|elm| (elm.id as usize, elm.data))
+            .collect())
+    }
+
+    async fn archive_batches(&self, ids: &[usize], keep_archive: bool) -> Result<usize, DbError> {
+        let mut txn = self.pool.begin().await?;
+        let mut stats = Self::read_archive_stats(&mut txn, self.market_id).await?;
+
+        let mut archived = 0;
+        for id in ids {
+            let batch: Option<DbBatch> = sqlx::query_as(
+                "DELETE FROM batches WHERE id = $1 AND data->>'status' IN ($2, $3) AND market_id = $4 RETURNING *",
+            )
+            .bind(*id as i64)
+            .bind(BatchStatus::Submitted)
+            .bind(BatchStatus::Failed)
+            .bind(self.market_id)
+            .fetch_optional(&mut *txn)
+            .await?;
+            let Some(batch) = batch else {
+                continue;
+            };
+
+            if keep_archive {
+                sqlx::query(
+                    "REPLACE INTO archived_batches (id, market_id, data) VALUES ($1, $2, $3)",
+                )
+                .bind(batch.id)
+                .bind(self.market_id)
+                .bind(sqlx::types::Json(&batch.data))
+                .execute(&mut *txn)
+                .await?;
+            }
+            stats.add_batch(&batch.data);
+            archived += 1;
+        }
+
+        Self::write_archive_stats(&mut txn, self.market_id, &stats).await?;
+        txn.commit().await?;
+
+        Ok(archived)
+    }
+
+    async fn get_archive_stats(&self) -> Result<ArchiveStats, DbError> {
+        let mut conn = self.pool.acquire().await?;
+        Self::read_archive_stats(&mut conn, self.market_id).await
+    }
+
+    async fn vacuum(&self) -> Result<(), DbError> {
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        Ok(())
+    }
+
     #[cfg(test)]
     async fn add_batch(&self, batch_id: usize, batch: Batch) -> Result<(), DbError> {
-        let res = sqlx::query("INSERT INTO batches (id, data) VALUES ($1, $2)")
+        let res = sqlx::query("INSERT INTO batches (id, market_id, data) VALUES ($1, $2, $3)")
             .bind(batch_id as i64)
+            .bind(self.market_id)
             .bind(sqlx::types::Json(batch))
             .execute(&self.pool)
             .await?;
@@ -936,7 +1566,6 @@ impl BrokerDb for SqliteDb {
         Ok(())
     }
 
-    #[cfg(test)]
     async fn set_batch_status(&self, batch_id: usize, status: BatchStatus) -> Result<(), DbError> {
         let res = sqlx::query(
             r#"
@@ -945,10 +1574,11 @@ impl BrokerDb for SqliteDb {
                     data = json_set(data,
                            '$.status', $1)
                 WHERE
-                    id = $2"#,
+                    id = $2 AND market_id = $3"#,
         )
         .bind(status)
         .bind(batch_id as i64)
+        .bind(self.market_id)
         .execute(&self.pool)
         .await?;
 
@@ -1004,6 +1634,10 @@ mod tests {
             client_sig: Bytes::new(),
             lock_price: None,
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         }
     }
 
@@ -1066,11 +1700,14 @@ mod tests {
         let order = create_order();
         db.add_order(id, order.clone()).await.unwrap();
 
-        let price_order = db.get_order_for_pricing().await.unwrap();
+        let price_order = db.get_order_for_pricing(id).await.unwrap();
         let price_order = price_order.unwrap();
-        assert_eq!(price_order.0, id);
-        assert_eq!(price_order.1.status, OrderStatus::Pricing);
-        assert_ne!(price_order.1.updated_at, order.updated_at);
+        assert_eq!(price_order.status, OrderStatus::Pricing);
+        assert_ne!(price_order.updated_at, order.updated_at);
+
+        // Already claimed for pricing
+        assert!(db.get_order_for_pricing(id).await.unwrap().is_none());
+        assert!(db.get_order_for_pricing(U256::from(1)).await.unwrap().is_none());
     }
 
     #[sqlx::test]
@@ -1088,6 +1725,64 @@ mod tests {
         assert_eq!(orders[0].1.status, OrderStatus::Pricing);
     }
 
+    #[sqlx::test]
+    async fn get_orders_by_status(pool: SqlitePool) {
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+
+        let mut order = create_order();
+        order.status = OrderStatus::Locking;
+        db.add_order(U256::from(1), order.clone()).await.unwrap();
+        order.status = OrderStatus::Proving;
+        db.add_order(U256::from(2), order).await.unwrap();
+
+        let orders = db.get_orders_by_status(OrderStatus::Locking).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].0, U256::from(1));
+        assert_eq!(orders[0].1.status, OrderStatus::Locking);
+    }
+
+    #[sqlx::test]
+    async fn get_new_orders_after(pool: SqlitePool) {
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+
+        let order = create_order();
+        db.add_order(U256::from(1), order.clone()).await.unwrap();
+        db.add_order(U256::from(2), order.clone()).await.unwrap();
+        db.skip_order(U256::from(1)).await.unwrap();
+
+        let orders = db.get_new_orders_after(0).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].1, U256::from(2));
+
+        db.add_order(U256::from(3), order).await.unwrap();
+        let orders = db.get_new_orders_after(orders[0].0).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].1, U256::from(3));
+    }
+
+    #[sqlx::test]
+    async fn get_new_orders_after_archive(pool: SqlitePool) {
+        let db = SqliteDb::from(pool).await.unwrap();
+
+        let order = create_order();
+        db.add_order(U256::from(1), order.clone()).await.unwrap();
+        db.add_order(U256::from(2), order.clone()).await.unwrap();
+        let orders = db.get_new_orders_after(0).await.unwrap();
+        assert_eq!(orders.len(), 2);
+        let last_seq = orders[1].0;
+
+        // Archiving the latest order and vacuuming does not hand out its sequence number again
+        db.set_order_failure(U256::from(2), "failed".into()).await.unwrap();
+        assert_eq!(db.archive_orders(&[U256::from(2)], false).await.unwrap(), 1);
+        db.vacuum().await.unwrap();
+
+        db.add_order(U256::from(3), order).await.unwrap();
+        let orders = db.get_new_orders_after(last_seq).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].1, U256::from(3));
+        assert!(orders[0].0 > last_seq);
+    }
+
     #[sqlx::test]
     async fn set_order_lock(pool: SqlitePool) {
         let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
@@ -1172,6 +1867,41 @@ mod tests {
         assert_eq!(db_order.status, OrderStatus::Skipped);
     }
 
+    #[sqlx::test]
+    async fn set_order_dry_run(pool: SqlitePool) {
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap().with_dry_run(true));
+        let id = U256::ZERO;
+        let order = create_order();
+        db.add_order(id, order.clone()).await.unwrap();
+
+        let lock_price = U256::from(10);
+        db.set_order_dry_run(id, lock_price).await.unwrap();
+        let db_order = db.get_order(id).await.unwrap().unwrap();
+        assert!(db_order.dry_run);
+        assert_eq!(db_order.status, OrderStatus::DryRun);
+        assert_eq!(db_order.lock_price, Some(lock_price));
+    }
+
+    #[sqlx::test]
+    async fn skip_dry_run_orders(pool: SqlitePool) {
+        let dry_run_db = SqliteDb::from(pool.clone()).await.unwrap().with_dry_run(true);
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+
+        let mut order = create_order();
+        order.status = OrderStatus::Locking;
+        dry_run_db.add_order(U256::from(1), order.clone()).await.unwrap();
+        dry_run_db.add_order(U256::from(2), order.clone()).await.unwrap();
+        dry_run_db.set_order_dry_run(U256::from(2), U256::from(10)).await.unwrap();
+        db.add_order(U256::from(3), order.clone()).await.unwrap();
+
+        assert_eq!(db.skip_dry_run_orders().await.unwrap(), 1);
+        let statuses = [OrderStatus::Skipped, OrderStatus::DryRun, OrderStatus::Locking];
+        for (id, status) in statuses.into_iter().enumerate() {
+            let db_order = db.get_order(U256::from(id + 1)).await.unwrap().unwrap();
+            assert_eq!(db_order.status, status);
+        }
+    }
+
     #[sqlx::test]
     async fn set_get_block(pool: SqlitePool) {
         let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
@@ -1189,6 +1919,28 @@ mod tests {
         assert_eq!(block_numb, db_block);
     }
 
+    #[sqlx::test]
+    async fn add_lost_lock(pool: SqlitePool) {
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+
+        let id = U256::from(7);
+        let lost_lock = LostLock {
+            prover: Address::ZERO,
+            lock_price: U256::from(10),
+            lock_block: 20,
+            recorded_at: Utc::now(),
+        };
+        db.add_lost_lock(id, lost_lock.clone()).await.unwrap();
+        // Recording the same order again replaces the entry
+        db.add_lost_lock(id, LostLock { lock_block: 21, ..lost_lock }).await.unwrap();
+
+        let lost_locks = db.get_lost_locks().await.unwrap();
+        assert_eq!(lost_locks.len(), 1);
+        assert_eq!(lost_locks[0].0, id);
+        assert_eq!(lost_locks[0].1.lock_block, 21);
+        assert_eq!(lost_locks[0].1.lock_price, U256::from(10));
+    }
+
     #[sqlx::test]
     async fn get_pending_lock_orders(pool: SqlitePool) {
         let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
@@ -1244,6 +1996,26 @@ mod tests {
         assert_eq!(db_order.proof_id, Some(proof_id.into()));
     }
 
+    #[sqlx::test]
+    async fn add_proof_failure(pool: SqlitePool) {
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+
+        let id = U256::ZERO;
+        let order = create_order();
+        db.add_order(id, order.clone()).await.unwrap();
+
+        db.add_proof_failure(id, "first".into()).await.unwrap();
+        db.add_proof_failure(id, "second".into()).await.unwrap();
+
+        let db_order = db.get_order(id).await.unwrap().unwrap();
+        assert_eq!(db_order.proof_failures, vec!["first".to_string(), "second".to_string()]);
+
+        assert!(matches!(
+            db.add_proof_failure(U256::from(1), "missing".into()).await,
+            Err(DbError::OrderNotFound(_))
+        ));
+    }
+
     #[sqlx::test]
     async fn get_active_proofs(pool: SqlitePool) {
         let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
@@ -1282,6 +2054,61 @@ mod tests {
         assert_eq!(db_order.input_id, Some(input_id.into()));
     }
 
+    #[sqlx::test]
+    async fn set_order_assumption_ids(pool: SqlitePool) {
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+
+        let id = U256::ZERO;
+        db.add_order(id, create_order()).await.unwrap();
+
+        let assumption_ids = vec!["receipt_1".to_string(), "receipt_2".to_string()];
+        db.set_order_assumption_ids(id, &assumption_ids).await.unwrap();
+
+        let db_order = db.get_order(id).await.unwrap().unwrap();
+        assert_eq!(db_order.assumption_ids, assumption_ids);
+    }
+
+    #[sqlx::test]
+    async fn proof_cache(pool: SqlitePool) {
+        let sqlite = SqliteDb::from(pool).await.unwrap();
+        let db: DbObj = Arc::new(sqlite.clone());
+
+        let id = U256::ZERO;
+        db.add_order(id, create_order()).await.unwrap();
+
+        let image_id = B256::repeat_byte(1);
+        let input_digest = B256::repeat_byte(2);
+        db.set_order_input_digest(id, input_digest).await.unwrap();
+        let db_order = db.get_order(id).await.unwrap().unwrap();
+        assert_eq!(db_order.input_digest, Some(input_digest));
+
+        assert_eq!(db.get_cached_proof(image_id, input_digest).await.unwrap(), None);
+        db.add_cached_proof(image_id, input_digest, "proof_1").await.unwrap();
+        assert_eq!(
+            db.get_cached_proof(image_id, input_digest).await.unwrap(),
+            Some("proof_1".into())
+        );
+        assert_eq!(db.get_cached_proof(image_id, B256::repeat_byte(3)).await.unwrap(), None);
+
+        // Proofs are shared across markets, and the latest proof wins
+        let other_market = sqlite.for_market(2, Address::ZERO).await.unwrap();
+        other_market.add_cached_proof(image_id, input_digest, "proof_2").await.unwrap();
+        assert_eq!(
+            db.get_cached_proof(image_id, input_digest).await.unwrap(),
+            Some("proof_2".into())
+        );
+
+        assert_eq!(
+            db.prune_cached_proofs(Utc::now() - chrono::Duration::days(1)).await.unwrap(),
+            0
+        );
+        assert_eq!(
+            db.prune_cached_proofs(Utc::now() + chrono::Duration::days(1)).await.unwrap(),
+            1
+        );
+        assert_eq!(db.get_cached_proof(image_id, input_digest).await.unwrap(), None);
+    }
+
     #[sqlx::test]
     async fn set_aggregation_status(pool: SqlitePool) {
         let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
@@ -1430,6 +2257,23 @@ mod tests {
         assert_eq!(db_batch.status, BatchStatus::PendingSubmission);
     }
 
+    #[sqlx::test]
+    async fn get_batches_by_status(pool: SqlitePool) {
+        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+
+        let batch = Batch {
+            start_time: Utc::now(),
+            status: BatchStatus::PendingSubmission,
+            ..Default::default()
+        };
+        db.add_batch(1, batch.clone()).await.unwrap();
+        db.add_batch(2, Batch { status: BatchStatus::Submitted, ..batch.clone() }).await.unwrap();
+        db.add_batch(3, batch).await.unwrap();
+
+        let batches = db.get_batches_by_status(BatchStatus::PendingSubmission).await.unwrap();
+        assert_eq!(batches.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![1, 3]);
+    }
+
     #[sqlx::test]
     async fn set_batch_submitted(pool: SqlitePool) {
         let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
@@ -1517,4 +2361,159 @@ mod tests {
         assert_eq!(&agg_state.proof_id, "c");
         assert_eq!(&agg_state.claim_digests, &claim_digests);
     }
+
+    #[sqlx::test]
+    async fn archive_orders(pool: SqlitePool) {
+        let db = SqliteDb::from(pool.clone()).await.unwrap();
+        let old = Utc::now() - chrono::Duration::days(10);
+
+        let mut done = create_order();
+        done.status = OrderStatus::Done;
+        done.updated_at = old;
+        done.lock_price = Some(U256::from(10));
+        let mut failed = done.clone();
+        failed.status = OrderStatus::Failed;
+        let mut recent = done.clone();
+        recent.updated_at = Utc::now();
+        let mut active = done.clone();
+        active.status = OrderStatus::Proving;
+        for (id, order) in [done, failed, recent, active].into_iter().enumerate() {
+            db.add_order(U256::from(id + 1), order).await.unwrap();
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        let mut ids: Vec<U256> =
+            db.get_archivable_orders(cutoff).await.unwrap().into_iter().map(|(id, _)| id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![U256::from(1), U256::from(2)]);
+
+        assert_eq!(db.archive_orders(&ids, true).await.unwrap(), 2);
+        assert!(db.get_order(U256::from(1)).await.unwrap().is_none());
+        assert!(db.get_order(U256::from(3)).await.unwrap().is_some());
+        let archived: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM archived_orders")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(archived, 2);
+
+        let stats = db.get_archive_stats().await.unwrap();
+        assert_eq!(stats.orders_done, 1);
+        assert_eq!(stats.orders_failed, 1);
+        assert_eq!(stats.done_lock_price, U256::from(10));
+
+        // Already archived and still active orders are left alone
+        assert_eq!(db.archive_orders(&[U256::from(1), U256::from(4)], false).await.unwrap(), 0);
+        assert_eq!(db.get_archive_stats().await.unwrap(), stats);
+        db.vacuum().await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn archive_batches(pool: SqlitePool) {
+        let db = SqliteDb::from(pool.clone()).await.unwrap();
+        let old = Utc::now() - chrono::Duration::days(10);
+
+        let batches = [
+            Batch {
+                status: BatchStatus::Submitted,
+                start_time: old,
+                fees: U256::from(5),
+                ..Default::default()
+            },
+            Batch { status: BatchStatus::Failed, start_time: old, ..Default::default() },
+            Batch { status: BatchStatus::Submitted, start_time: Utc::now(), ..Default::default() },
+            Batch { status: BatchStatus::Aggregating, start_time: old, ..Default::default() },
+        ];
+        for (id, batch) in batches.into_iter().enumerate() {
+            db.add_batch(id + 1, batch).await.unwrap();
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        let ids: Vec<usize> = db
+            .get_archivable_batches(cutoff)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        assert_eq!(db.archive_batches(&ids, false).await.unwrap(), 2);
+        assert!(matches!(db.get_batch(1).await, Err(DbError::BatchNotFound(1))));
+        assert_eq!(db.get_batch(3).await.unwrap().status, BatchStatus::Submitted);
+        let archived: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM archived_batches")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(archived, 0);
+
+        let stats = db.get_archive_stats().await.unwrap();
+        assert_eq!(stats.batches_submitted, 1);
+        assert_eq!(stats.batches_failed, 1);
+        assert_eq!(stats.submitted_batch_fees, U256::from(5));
+    }
+
+    #[sqlx::test]
+    async fn market_namespaces(pool: SqlitePool) {
+        let sqlite = SqliteDb::from(pool).await.unwrap();
+        // Two deployments on the same chain
+        let db_a: DbObj = Arc::new(sqlite.for_market(1, Address::ZERO).await.unwrap());
+        let db_b: DbObj = Arc::new(sqlite.for_market(1, Address::repeat_byte(1)).await.unwrap());
+        let id = U256::from(3);
+
+        // The same request ID can exist in both markets
+        db_a.add_order(id, create_order()).await.unwrap();
+        assert!(!db_b.order_exists(id).await.unwrap());
+        db_b.add_order(id, create_order()).await.unwrap();
+
+        db_a.skip_order(id).await.unwrap();
+        assert_eq!(db_a.get_order(id).await.unwrap().unwrap().status, OrderStatus::Skipped);
+        assert_eq!(db_b.get_order(id).await.unwrap().unwrap().status, OrderStatus::New);
+        assert_eq!(db_b.get_orders_by_status(OrderStatus::Skipped).await.unwrap().len(), 0);
+
+        db_a.set_last_block(10).await.unwrap();
+        db_b.set_last_block(20).await.unwrap();
+        assert_eq!(db_a.get_last_block().await.unwrap(), Some(10));
+        assert_eq!(db_b.get_last_block().await.unwrap(), Some(20));
+
+        let batch_a = db_a.get_current_batch().await.unwrap();
+        let batch_b = db_b.get_current_batch().await.unwrap();
+        assert_ne!(batch_a, batch_b);
+        assert!(matches!(db_b.get_batch(batch_a).await, Err(DbError::BatchNotFound(_))));
+    }
+
+    #[sqlx::test]
+    async fn adopt_legacy_rows(pool: SqlitePool) {
+        let legacy = SqliteDb::from(pool).await.unwrap();
+        legacy.add_order(U256::from(1), create_order()).await.unwrap();
+        legacy.set_last_block(10).await.unwrap();
+        let batch_id = legacy.get_current_batch().await.unwrap();
+
+        let db = legacy.for_market(5, Address::ZERO).await.unwrap();
+        assert!(db.get_order(U256::from(1)).await.unwrap().is_none());
+        assert_eq!(db.adopt_legacy_rows().await.unwrap(), 1);
+
+        assert!(db.get_order(U256::from(1)).await.unwrap().is_some());
+        assert_eq!(db.get_last_block().await.unwrap(), Some(10));
+        assert_eq!(db.get_current_batch().await.unwrap(), batch_id);
+        assert!(legacy.get_order(U256::from(1)).await.unwrap().is_none());
+        assert_eq!(legacy.get_last_block().await.unwrap(), None);
+        // Nothing left to adopt
+        assert_eq!(db.adopt_legacy_rows().await.unwrap(), 0);
+    }
+
+    #[sqlx::test]
+    async fn for_market(pool: SqlitePool) {
+        let sqlite = SqliteDb::from(pool).await.unwrap();
+        let market_a = sqlite.for_market(7, Address::ZERO).await.unwrap();
+        assert_eq!(
+            sqlite.for_market(7, Address::ZERO).await.unwrap().market_id,
+            market_a.market_id
+        );
+        let market_b = sqlite.for_market(7, Address::repeat_byte(1)).await.unwrap();
+        assert_ne!(market_b.market_id, market_a.market_id);
+        assert_ne!(
+            sqlite.for_market(8, Address::ZERO).await.unwrap().market_id,
+            market_a.market_id
+        );
+    }
 }
@@ -2,34 +2,40 @@
 //
 // All rights reserved.
 
-use std::{path::PathBuf, sync::Arc, time::SystemTime};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::SystemTime};
 
 use alloy::{
     network::Ethereum,
-    primitives::{Address, Bytes, U256},
+    primitives::{keccak256, Address, Bytes, B256, U256},
     providers::{Provider, WalletProvider},
     signers::local::PrivateKeySigner,
 };
 use anyhow::{ensure, Context, Result};
+pub use backtest::{run_backtest, BacktestArgs};
 use boundless_market::{
     contracts::{boundless_market::BoundlessMarketService, InputType, ProofRequest},
-    input::GuestEnv,
+    input::{GuestEnv, InputManifest},
     order_stream_client::Client as OrderStreamClient,
+    signer::{BoundlessSigner, SignerConfig, SignerError},
 };
 use chrono::{serde::ts_seconds, DateTime, Utc};
 use clap::Parser;
 use config::ConfigWatcher;
+pub use config::{check_config, EtherAmount};
 use db::{DbObj, SqliteDb};
 use provers::ProverObj;
 use risc0_ethereum_contracts::set_verifier::SetVerifierService;
-use risc0_zkvm::sha::Digest;
+use risc0_zkvm::{sha::Digest, Receipt};
 pub use rpc_retry_policy::CustomRetryPolicy;
 use serde::{Deserialize, Serialize};
 use storage::UriHandlerBuilder;
 use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
 pub(crate) mod aggregator;
+pub(crate) mod backtest;
+pub(crate) mod balance_manager;
 pub(crate) mod chain_monitor;
 pub(crate) mod config;
 pub(crate) mod db;
@@ -39,10 +45,13 @@ pub(crate) mod order_monitor;
 pub(crate) mod order_picker;
 pub(crate) mod provers;
 pub(crate) mod proving;
+pub(crate) mod reconcile;
+pub(crate) mod retention;
 pub(crate) mod rpc_retry_policy;
 pub(crate) mod storage;
 pub(crate) mod submitter;
 pub(crate) mod task;
+pub(crate) mod webhook;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -59,9 +68,39 @@ pub struct Args {
     #[clap(long, env)]
     pub order_stream_url: Option<Url>,
 
-    /// wallet key
-    #[clap(long, env)]
-    pub private_key: PrivateKeySigner,
+    /// Wallet signer
+    ///
+    /// Used for all transactions, unless a separate lock signer is set
+    #[clap(flatten)]
+    pub signer: SignerConfig,
+
+    /// Optional separate wallet key for lockRequest transactions
+    ///
+    /// When set, this address locks orders, holds the stake and receives payment, while the
+    /// main wallet only pays gas for fulfillment transactions
+    #[clap(long, env, conflicts_with_all = ["lock_keystore_path", "lock_remote_signer_url"])]
+    pub lock_private_key: Option<PrivateKeySigner>,
+
+    /// Path to an encrypted JSON keystore holding the lock wallet key
+    #[clap(
+        long,
+        env,
+        requires = "lock_keystore_password",
+        conflicts_with = "lock_remote_signer_url"
+    )]
+    pub lock_keystore_path: Option<PathBuf>,
+
+    /// Password to decrypt the lock wallet keystore
+    #[clap(long, env, requires = "lock_keystore_path")]
+    pub lock_keystore_password: Option<String>,
+
+    /// Remote signer URL for the lock wallet
+    #[clap(long, env, requires = "lock_remote_signer_address")]
+    pub lock_remote_signer_url: Option<Url>,
+
+    /// Address of the key held by the lock wallet remote signer
+    #[clap(long, env, requires = "lock_remote_signer_url")]
+    pub lock_remote_signer_address: Option<Address>,
 
     /// Boundless market address
     #[clap(long, env)]
@@ -90,6 +129,18 @@ pub struct Args {
     #[clap(long, env, conflicts_with = "bento_api_url")]
     bonsai_api_key: Option<String>,
 
+    /// Fallback prover API URL (Bonsai or Bento)
+    ///
+    /// Proofs that keep failing on the primary backend are re-routed here before their deadline
+    #[clap(long, env)]
+    fallback_prover_url: Option<Url>,
+
+    /// Fallback prover API key
+    ///
+    /// Required if the fallback backend is Bonsai
+    #[clap(long, env, requires = "fallback_prover_url")]
+    fallback_prover_api_key: Option<String>,
+
     /// Config file path
     #[clap(short, long, default_value = "broker.toml")]
     config_file: PathBuf,
@@ -117,6 +168,44 @@ pub struct Args {
     /// From the `RetryBackoffLayer` of Alloy
     #[clap(long, default_value_t = 100)]
     pub rpc_retry_cu: u64,
+
+    /// Graceful shutdown timeout (in seconds)
+    ///
+    /// Time given to in-flight lock and submission transactions to finish after a SIGTERM or
+    /// ctrl-c before the remaining tasks are aborted
+    #[clap(long, env, default_value_t = 30)]
+    pub shutdown_timeout: u64,
+
+    /// Dry-run (observe-only) mode
+    ///
+    /// Orders are found, priced and preflighted as usual, but the broker never sends a
+    /// transaction. Locks are simulated and recorded in the DB instead of being sent, and the
+    /// submitter and balance manager are disabled. The broker refuses to start in this mode if
+    /// the DB holds orders committed to by a live broker, as they would never be submitted.
+    #[clap(long, env, conflicts_with = "deposit_amount")]
+    pub dry_run: bool,
+}
+
+impl Args {
+    /// Signer configuration of the separate lock wallet, from the `lock_` prefixed args
+    pub fn lock_signer_config(&self) -> SignerConfig {
+        SignerConfig {
+            private_key: self.lock_private_key.clone(),
+            keystore_path: self.lock_keystore_path.clone(),
+            keystore_password: self.lock_keystore_password.clone(),
+            remote_signer_url: self.lock_remote_signer_url.clone(),
+            remote_signer_address: self.lock_remote_signer_address,
+        }
+    }
+
+    /// Construct the separate lock transaction signer, if one is configured
+    pub fn lock_signer(&self) -> Result<Option<BoundlessSigner>, SignerError> {
+        let config = self.lock_signer_config();
+        if !config.is_set() {
+            return Ok(None);
+        }
+        config.signer().map(Some)
+    }
 }
 
 /// Status of a order as it moves through the lifecycle
@@ -144,6 +233,8 @@ enum OrderStatus {
     Failed,
     /// Order was analyzed and marked as skipable
     Skipped,
+    /// Order would have been locked, but the broker was running in dry-run mode
+    DryRun,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -165,6 +256,16 @@ struct Order {
     ///
     ///  Populated after preflight
     input_id: Option<String>,
+    /// Digest of the input data
+    ///
+    /// Populated after the input is fetched, used to reuse proofs of the same image and input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input_digest: Option<B256>,
+    /// Prover receipt IDs of the assumptions of the input
+    ///
+    /// Populated with the input ID, passed to the prover when executing and proving
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    assumption_ids: Vec<String>,
     /// Proof Id
     ///
     /// Populated after proof completion
@@ -179,6 +280,14 @@ struct Order {
     lock_price: Option<U256>,
     /// Failure message
     error_msg: Option<String>,
+    /// Errors from proving attempts that failed and were retried
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    proof_failures: Vec<String>,
+    /// Order was found by a broker running in dry-run mode
+    ///
+    /// These orders are never locked or proven, including by a later live run on the same DB
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    dry_run: bool,
 }
 
 impl Order {
@@ -195,6 +304,69 @@ impl Order {
             client_sig,
             lock_price: None,
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
+        }
+    }
+}
+
+/// An order that was locked by another prover before we could lock it
+///
+/// Kept for competitive analysis of which provers win orders and at what price
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct LostLock {
+    /// Prover that holds the lock
+    prover: Address,
+    /// Price the winning prover locked the request at
+    lock_price: U256,
+    /// Block the winning lock landed in
+    lock_block: u64,
+    /// When the lost lock was recorded
+    #[serde(with = "ts_seconds")]
+    recorded_at: DateTime<Utc>,
+}
+
+/// Running totals of the orders and batches removed from the live tables by the retention policy
+///
+/// Kept so accounting and metrics still cover archived history.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+struct ArchiveStats {
+    orders_done: u64,
+    orders_skipped: u64,
+    orders_failed: u64,
+    orders_dry_run: u64,
+    /// Sum of the lock prices of the archived completed orders
+    done_lock_price: U256,
+    batches_submitted: u64,
+    batches_failed: u64,
+    /// Sum of the fees of the archived submitted batches
+    submitted_batch_fees: U256,
+}
+
+impl ArchiveStats {
+    fn add_order(&mut self, order: &Order) {
+        match order.status {
+            OrderStatus::Done => {
+                self.orders_done += 1;
+                self.done_lock_price += order.lock_price.unwrap_or_default();
+            }
+            OrderStatus::Skipped => self.orders_skipped += 1,
+            OrderStatus::Failed => self.orders_failed += 1,
+            OrderStatus::DryRun => self.orders_dry_run += 1,
+            _ => {}
+        }
+    }
+
+    fn add_batch(&mut self, batch: &Batch) {
+        match batch.status {
+            BatchStatus::Submitted => {
+                self.batches_submitted += 1;
+                self.submitted_batch_fees += batch.fees;
+            }
+            BatchStatus::Failed => self.batches_failed += 1,
+            _ => {}
         }
     }
 }
@@ -245,11 +417,58 @@ struct Batch {
     pub error_msg: Option<String>,
 }
 
-pub struct Broker<P> {
-    args: Args,
+/// Market deployment served by the broker
+#[derive(Clone, Debug)]
+pub struct MarketDeployment {
+    /// RPC URL of the deployment's chain
+    pub rpc_url: Url,
+    /// Boundless market address
+    pub boundless_market_address: Address,
+    /// Risc zero Set verifier address
+    pub set_verifier_address: Address,
+    /// Order stream server URL
+    pub order_stream_url: Option<Url>,
+}
+
+impl TryFrom<&config::DeploymentConf> for MarketDeployment {
+    type Error = anyhow::Error;
+
+    fn try_from(conf: &config::DeploymentConf) -> Result<Self> {
+        Ok(Self {
+            rpc_url: Url::parse(&conf.rpc_url).context("Invalid deployment rpc_url")?,
+            boundless_market_address: conf.boundless_market_address,
+            set_verifier_address: conf.set_verifier_address,
+            order_stream_url: conf
+                .order_stream_url
+                .as_deref()
+                .map(Url::parse)
+                .transpose()
+                .context("Invalid deployment order_stream_url")?,
+        })
+    }
+}
+
+/// Per market state, every market runs its own set of services
+struct Market<P> {
+    deployment: MarketDeployment,
+    chain_id: u64,
     provider: Arc<P>,
+    lock_provider: Option<Arc<P>>,
+    /// View of the broker DB namespaced to this market's chain and address
     db: DbObj,
+    /// Webhook events of this market, tagged with its chain ID and address
+    webhook_sink: webhook::WebhookSink,
+}
+
+pub struct Broker<P> {
+    args: Args,
+    /// Primary market from the command line first, followed by the additional deployments
+    markets: Vec<Market<P>>,
+    sqlite: SqliteDb,
     config_watcher: ConfigWatcher,
+    cancel_token: CancellationToken,
+    /// Wallet signer, constructed from [Args::signer] if not set with [Broker::with_signer]
+    signer: Option<BoundlessSigner>,
 }
 
 impl<P> Broker<P>
@@ -260,13 +479,121 @@ where
         let config_watcher =
             ConfigWatcher::new(&args.config_file).await.context("Failed to load broker config")?;
 
-        let db: DbObj =
-            Arc::new(SqliteDb::new(&args.db_url).await.context("Failed to connect to sqlite DB")?);
+        let sqlite = SqliteDb::new(&args.db_url)
+            .await
+            .context("Failed to connect to sqlite DB")?
+            .with_dry_run(args.dry_run);
+        let deployment = MarketDeployment {
+            rpc_url: args.rpc_url.clone(),
+            boundless_market_address: args.boundless_market_address,
+            set_verifier_address: args.set_verifier_address,
+            order_stream_url: args.order_stream_url.clone(),
+        };
+        let mut broker = Self {
+            args,
+            markets: vec![],
+            sqlite,
+            config_watcher,
+            cancel_token: CancellationToken::new(),
+            signer: None,
+        };
+        broker.add_market(deployment, provider, None).await?;
+
+        // Orders from before the DB was namespaced by chain belong to the primary market
+        let adopted = broker
+            .sqlite
+            .for_market(
+                broker.markets[0].chain_id,
+                broker.markets[0].deployment.boundless_market_address,
+            )
+            .await?
+            .adopt_legacy_rows()
+            .await
+            .context("Failed to adopt existing DB rows")?;
+        if adopted > 0 {
+            tracing::info!(
+                "Moved {adopted} existing orders to market {}",
+                broker.markets[0].deployment.boundless_market_address
+            );
+        }
+
+        Ok(broker)
+    }
+
+    /// Token that triggers a graceful shutdown of [Broker::start_service] when cancelled
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
+    }
 
-        Ok(Self { args, db, provider: Arc::new(provider), config_watcher })
+    /// Use an already constructed wallet signer, e.g. to avoid decrypting a keystore again
+    pub fn with_signer(mut self, signer: BoundlessSigner) -> Self {
+        self.signer = Some(signer);
+        self
     }
 
-    async fn get_assessor_image(&self) -> Result<(Digest, Vec<u8>)> {
+    /// Use a separate provider, with its own wallet, for lockRequest transactions
+    ///
+    /// Applies to the primary market, see [Broker::with_market] for additional deployments
+    pub fn with_lock_provider(mut self, lock_provider: P) -> Self {
+        self.markets[0].lock_provider = Some(Arc::new(lock_provider));
+        self
+    }
+
+    /// Additional market deployments from the `[[deployments]]` section of the config
+    pub fn deployments(&self) -> Result<Vec<MarketDeployment>> {
+        let config = self.config_watcher.config.lock_all().context("Failed to lock config")?;
+        config.deployments.iter().map(MarketDeployment::try_from).collect()
+    }
+
+    /// Serve an additional market deployment
+    ///
+    /// The deployment gets its own monitors, order picker, order monitor and submitter, and
+    /// shares the prover backend and pricing limits with the other markets. Orders are
+    /// namespaced by chain ID and market address, so several deployments can share a chain.
+    pub async fn with_market(
+        mut self,
+        deployment: MarketDeployment,
+        provider: P,
+        lock_provider: Option<P>,
+    ) -> Result<Self> {
+        self.add_market(deployment, provider, lock_provider).await?;
+        Ok(self)
+    }
+
+    async fn add_market(
+        &mut self,
+        deployment: MarketDeployment,
+        provider: P,
+        lock_provider: Option<P>,
+    ) -> Result<()> {
+        let chain_id = provider.get_chain_id().await.context("Failed to get chain ID")?;
+        let market_addr = deployment.boundless_market_address;
+        ensure!(
+            self.markets.iter().all(|market| {
+                market.chain_id != chain_id
+                    || market.deployment.boundless_market_address != market_addr
+            }),
+            "Market {market_addr} on chain {chain_id} is already served"
+        );
+
+        // Report order and batch status transitions to the configured webhook, if any
+        let webhook_sink =
+            webhook::WebhookSink::spawn(self.config_watcher.config.clone(), chain_id, market_addr);
+        let db: DbObj = Arc::new(self.sqlite.for_market(chain_id, market_addr).await?);
+        let db: DbObj = Arc::new(webhook::WebhookDb::new(db, webhook_sink.clone()));
+        self.markets.push(Market {
+            deployment,
+            chain_id,
+            provider: Arc::new(provider),
+            lock_provider: lock_provider.map(Arc::new),
+            db,
+            webhook_sink,
+        });
+
+        Ok(())
+    }
+
+    async fn get_assessor_image(&self, market: &Market<P>) -> Result<(Digest, Vec<u8>)> {
         let (assessor_path, max_file_size) = {
             let config = self.config_watcher.config.lock_all().context("Failed to lock config")?;
             (config.prover.assessor_set_guest_path.clone(), config.market.max_file_size)
@@ -280,8 +607,8 @@ where
             Ok((img_id, elf_buf))
         } else {
             let boundless_market = BoundlessMarketService::new(
-                self.args.boundless_market_address,
-                self.provider.clone(),
+                market.deployment.boundless_market_address,
+                market.provider.clone(),
                 Address::ZERO,
             );
 
@@ -298,7 +625,7 @@ where
         }
     }
 
-    async fn get_set_builder_image(&self) -> Result<(Digest, Vec<u8>)> {
+    async fn get_set_builder_image(&self, market: &Market<P>) -> Result<(Digest, Vec<u8>)> {
         let (set_builder_path, max_file_size) = {
             let config = self.config_watcher.config.lock_all().context("Failed to lock config")?;
             (config.prover.set_builder_guest_path.clone(), config.market.max_file_size)
@@ -312,8 +639,8 @@ where
             Ok((img_id, elf_buf))
         } else {
             let set_verifier_contract = SetVerifierService::new(
-                self.args.set_verifier_address,
-                self.provider.clone(),
+                market.deployment.set_verifier_address,
+                market.provider.clone(),
                 Address::ZERO,
             );
 
@@ -334,6 +661,222 @@ where
 
     pub async fn start_service(&self) -> Result<()> {
         let mut supervisor_tasks: JoinSet<Result<()>> = JoinSet::new();
+        // Tasks with in-flight transactions, given time to finish on shutdown
+        let mut drain_tasks: JoinSet<Result<()>> = JoinSet::new();
+
+        let signal_token = self.cancel_token.clone();
+        tokio::spawn(async move {
+            match shutdown_signal().await {
+                Ok(()) => tracing::info!("Shutdown requested, draining in-flight transactions"),
+                Err(err) => tracing::error!("Failed to wait for shutdown signal: {err:?}"),
+            }
+            signal_token.cancel();
+        });
+
+        if self.args.dry_run {
+            tracing::warn!("Running in dry-run mode, no transactions will be sent");
+        }
+
+        // Construct the prover object interface
+        let prover: provers::ProverObj = if risc0_zkvm::is_dev_mode() {
+            tracing::warn!("WARNING: Running the Broker in dev mode does not generate valid receipts. \
+            Receipts generated from this process are invalid and should never be used in production.");
+            Arc::new(provers::MockProver::default())
+        } else if let (Some(bonsai_api_key), Some(bonsai_api_url)) =
+            (self.args.bonsai_api_key.as_ref(), self.args.bonsai_api_url.as_ref())
+        {
+            tracing::info!("Configured to run with Bonsai backend");
+            Arc::new(
+                provers::Bonsai::new(
+                    self.config_watcher.config.clone(),
+                    bonsai_api_url.as_ref(),
+                    bonsai_api_key,
+                )
+                .context("Failed to construct Bonsai client")?,
+            )
+        } else if let Some(bento_api_url) = self.args.bento_api_url.as_ref() {
+            tracing::info!("Configured to run with Bento backend");
+
+            Arc::new(
+                provers::Bonsai::new(
+                    self.config_watcher.config.clone(),
+                    bento_api_url.as_ref(),
+                    "",
+                )
+                .context("Failed to initialize Bento client")?,
+            )
+        } else if cfg!(test) {
+            Arc::new(provers::MockProver::default())
+        } else {
+            anyhow::bail!("Failed to select a proving backend");
+        };
+
+        let fallback_prover: Option<provers::ProverObj> =
+            match self.args.fallback_prover_url.as_ref() {
+                Some(fallback_url) => {
+                    tracing::info!("Configured fallback prover backend: {fallback_url}");
+                    Some(Arc::new(
+                        provers::Bonsai::new(
+                            self.config_watcher.config.clone(),
+                            fallback_url.as_ref(),
+                            self.args.fallback_prover_api_key.as_deref().unwrap_or(""),
+                        )
+                        .context("Failed to initialize fallback prover client")?,
+                    ))
+                }
+                None => None,
+            };
+
+        // Preflight / download limits of the prover, shared by all markets
+        let capacity = order_picker::PricingCapacity::new(&self.config_watcher.config);
+
+        let signer = match &self.signer {
+            Some(signer) => signer.clone(),
+            None => self.args.signer.signer().context("Failed to construct wallet signer")?,
+        };
+
+        // Markets on the same chain can send transactions from the same wallet
+        let mut tx_locks = HashMap::new();
+        for market in self.markets.iter() {
+            tracing::info!(
+                "Starting services for market {} on chain {}",
+                market.deployment.boundless_market_address,
+                market.chain_id
+            );
+            self.start_market(
+                market,
+                &signer,
+                &prover,
+                &fallback_prover,
+                &capacity,
+                &mut tx_locks,
+                &mut supervisor_tasks,
+                &mut drain_tasks,
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to start market {} on chain {}",
+                    market.deployment.boundless_market_address, market.chain_id
+                )
+            })?;
+        }
+
+        // One retention service covers all markets, as they share the DB
+        let retention = self.markets.iter().fold(
+            retention::RetentionService::new(
+                Arc::new(self.sqlite.clone()),
+                self.config_watcher.config.clone(),
+            ),
+            |retention, market| {
+                retention.with_market(
+                    market.chain_id,
+                    market.deployment.boundless_market_address,
+                    market.db.clone(),
+                )
+            },
+        );
+        let retention = Arc::new(retention);
+        supervisor_tasks.spawn(async move {
+            task::supervisor(1, retention).await.context("Failed to start retention service")?;
+            Ok(())
+        });
+
+        // Monitor the different supervisor tasks until one fails or a shutdown is requested
+        let res = loop {
+            if supervisor_tasks.is_empty() && drain_tasks.is_empty() {
+                break Ok(());
+            }
+            let task_res = tokio::select! {
+                Some(res) = supervisor_tasks.join_next() => res,
+                Some(res) = drain_tasks.join_next() => res,
+                _ = self.cancel_token.cancelled() => break Ok(()),
+            };
+            if let Err(err) = task_exit_status(task_res) {
+                break Err(err);
+            }
+        };
+
+        // Stop locking and submitting, giving in-flight transactions time to land
+        self.cancel_token.cancel();
+        let shutdown_timeout = std::time::Duration::from_secs(self.args.shutdown_timeout);
+        let drained = tokio::time::timeout(shutdown_timeout, async {
+            while let Some(task_res) = drain_tasks.join_next().await {
+                if let Err(err) = task_exit_status(task_res) {
+                    tracing::error!("Task failed during shutdown: {err:?}");
+                }
+            }
+        })
+        .await;
+        if drained.is_err() {
+            tracing::warn!(
+                "In-flight transactions did not finish within {}s, aborting",
+                self.args.shutdown_timeout
+            );
+            drain_tasks.abort_all();
+        }
+        // Remaining services keep their progress in the DB and resume on restart
+        supervisor_tasks.abort_all();
+        tracing::info!("Broker shutdown complete");
+
+        res
+    }
+
+    /// Spawn the services of a single market
+    async fn start_market(
+        &self,
+        market: &Market<P>,
+        signer: &BoundlessSigner,
+        prover: &ProverObj,
+        fallback_prover: &Option<ProverObj>,
+        capacity: &order_picker::PricingCapacity,
+        tx_locks: &mut HashMap<(u64, Address), balance_manager::WalletTxLock>,
+        supervisor_tasks: &mut JoinSet<Result<()>>,
+        drain_tasks: &mut JoinSet<Result<()>>,
+    ) -> Result<()> {
+        if !self.args.dry_run {
+            // Orders from an earlier dry run must never be locked by a live broker
+            let skipped =
+                market.db.skip_dry_run_orders().await.context("Failed to skip dry-run orders")?;
+            if skipped > 0 {
+                tracing::info!("Skipped {skipped} open orders left over from a dry run");
+            }
+        }
+
+        let market_addr = market.deployment.boundless_market_address;
+        // Provider whose wallet locks orders and holds the stake
+        let lock_provider = market.lock_provider.clone().unwrap_or_else(|| market.provider.clone());
+        let prover_addr = lock_provider.default_signer_address();
+
+        // Bring the DB in line with the chain before resuming, e.g. after a crash or DB restore
+        reconcile::Reconciler::new(
+            market.db.clone(),
+            market.provider.clone(),
+            market_addr,
+            prover_addr,
+        )
+        .with_webhook_sink(market.webhook_sink.clone())
+        .reconcile()
+        .await
+        .context("Failed to reconcile DB with chain state")?;
+
+        if self.args.dry_run {
+            // A dry run never proves or submits, so live orders would be left to be slashed
+            let live_orders = market
+                .db
+                .get_orders_committed_to_fulfill()
+                .await
+                .context("Failed to fetch committed orders")?
+                .into_iter()
+                .filter(|(_, order)| !order.dry_run)
+                .count();
+            ensure!(
+                live_orders == 0,
+                "Refusing to start in dry-run mode: the DB holds {live_orders} orders locked or \
+                 being locked by a live broker on chain {}, use a separate DB for dry runs",
+                market.chain_id
+            );
+        }
 
         let loopback_blocks = {
             let config = match self.config_watcher.config.lock_all() {
@@ -344,7 +887,7 @@ where
         };
 
         let chain_monitor = Arc::new(
-            chain_monitor::ChainMonitorService::new(self.provider.clone())
+            chain_monitor::ChainMonitorService::new(market.provider.clone())
                 .await
                 .context("Failed to initialize chain monitor")?,
         );
@@ -358,13 +901,16 @@ where
         });
 
         // spin up a supervisor for the market monitor
-        let market_monitor = Arc::new(market_monitor::MarketMonitor::new(
-            loopback_blocks,
-            self.args.boundless_market_address,
-            self.provider.clone(),
-            self.db.clone(),
-            chain_monitor.clone(),
-        ));
+        let market_monitor = Arc::new(
+            market_monitor::MarketMonitor::new(
+                loopback_blocks,
+                market_addr,
+                market.provider.clone(),
+                market.db.clone(),
+                chain_monitor.clone(),
+            )
+            .with_webhook_sink(market.webhook_sink.clone()),
+        );
 
         let block_times =
             market_monitor.get_block_time().await.context("Failed to sample block times")?;
@@ -376,18 +922,18 @@ where
             Ok(())
         });
 
-        let chain_id = self.provider.get_chain_id().await.context("Failed to get chain ID")?;
-        let client =
-            self.args.order_stream_url.clone().map(|url| {
-                OrderStreamClient::new(url, self.args.boundless_market_address, chain_id)
-            });
+        let client = market
+            .deployment
+            .order_stream_url
+            .clone()
+            .map(|url| OrderStreamClient::new(url, market_addr, market.chain_id));
         // spin up a supervisor for the offchain market monitor
         if let Some(client) = client {
             let offchain_market_monitor =
                 Arc::new(offchain_market_monitor::OffchainMarketMonitor::new(
-                    self.db.clone(),
+                    market.db.clone(),
                     client.clone(),
-                    self.args.private_key.clone(),
+                    signer.clone(),
                 ));
             supervisor_tasks.spawn(async move {
                 task::supervisor(1, offchain_market_monitor)
@@ -397,74 +943,76 @@ where
             });
         }
 
-        // Construct the prover object interface
-        let prover: provers::ProverObj = if risc0_zkvm::is_dev_mode() {
-            tracing::warn!("WARNING: Running the Broker in dev mode does not generate valid receipts. \
-            Receipts generated from this process are invalid and should never be used in production.");
-            Arc::new(provers::MockProver::default())
-        } else if let (Some(bonsai_api_key), Some(bonsai_api_url)) =
-            (self.args.bonsai_api_key.as_ref(), self.args.bonsai_api_url.as_ref())
-        {
-            tracing::info!("Configured to run with Bonsai backend");
-            Arc::new(
-                provers::Bonsai::new(
-                    self.config_watcher.config.clone(),
-                    bonsai_api_url.as_ref(),
-                    bonsai_api_key,
-                )
-                .context("Failed to construct Bonsai client")?,
-            )
-        } else if let Some(bento_api_url) = self.args.bento_api_url.as_ref() {
-            tracing::info!("Configured to run with Bento backend");
-
-            Arc::new(
-                provers::Bonsai::new(
-                    self.config_watcher.config.clone(),
-                    bento_api_url.as_ref(),
-                    "",
-                )
-                .context("Failed to initialize Bento client")?,
-            )
-        } else if cfg!(test) {
-            Arc::new(provers::MockProver::default())
-        } else {
-            anyhow::bail!("Failed to select a proving backend");
-        };
-
         // Spin up the order picker to pre-flight and find orders to lock
-        let order_picker = Arc::new(order_picker::OrderPicker::new(
-            self.db.clone(),
-            self.config_watcher.config.clone(),
-            prover.clone(),
-            self.args.boundless_market_address,
-            self.provider.clone(),
-        ));
+        let order_picker = Arc::new(
+            order_picker::OrderPicker::new(
+                market.db.clone(),
+                self.config_watcher.config.clone(),
+                prover.clone(),
+                market_addr,
+                lock_provider.clone(),
+            )
+            .with_capacity(capacity.clone()),
+        );
         supervisor_tasks.spawn(async move {
             task::supervisor(1, order_picker).await.context("Failed to start order picker")?;
             Ok(())
         });
 
-        let order_monitor = Arc::new(order_monitor::OrderMonitor::new(
-            self.db.clone(),
-            self.provider.clone(),
-            chain_monitor.clone(),
-            self.config_watcher.config.clone(),
-            block_times,
-            self.args.boundless_market_address,
-        )?);
-        supervisor_tasks.spawn(async move {
+        let lock_pause = balance_manager::LockPause::default();
+        // Transactions from the lock wallet are serialized with the lock transactions, whose
+        // nonces the order monitor assigns itself, across all markets on this chain
+        let tx_lock = tx_locks
+            .entry((market.chain_id, lock_provider.default_signer_address()))
+            .or_default()
+            .clone();
+        // Balance top ups are transactions, so they are disabled in dry-run mode
+        if !self.args.dry_run {
+            let balance_manager = Arc::new(
+                balance_manager::BalanceManager::new(
+                    lock_provider.clone(),
+                    self.config_watcher.config.clone(),
+                    market_addr,
+                    lock_pause.clone(),
+                )?
+                .with_tx_lock(tx_lock.clone()),
+            );
+            supervisor_tasks.spawn(async move {
+                task::supervisor(1, balance_manager)
+                    .await
+                    .context("Failed to start balance manager")?;
+                Ok(())
+            });
+        }
+
+        let order_monitor = Arc::new(
+            order_monitor::OrderMonitor::new(
+                market.db.clone(),
+                lock_provider.clone(),
+                chain_monitor.clone(),
+                self.config_watcher.config.clone(),
+                block_times,
+                market_addr,
+            )?
+            .with_lock_pause(lock_pause)
+            .with_tx_lock(tx_lock.clone())
+            .with_cancel_token(self.cancel_token.clone())
+            .with_dry_run(self.args.dry_run),
+        );
+        drain_tasks.spawn(async move {
             task::supervisor(1, order_monitor).await.context("Failed to start order monitor")?;
             Ok(())
         });
 
         let proving_service = Arc::new(
             proving::ProvingService::new(
-                self.db.clone(),
+                market.db.clone(),
                 prover.clone(),
                 self.config_watcher.config.clone(),
             )
             .await
-            .context("Failed to initialize proving service")?,
+            .context("Failed to initialize proving service")?
+            .with_fallback_prover(fallback_prover.clone()),
         );
 
         supervisor_tasks.spawn(async move {
@@ -474,19 +1022,18 @@ where
             Ok(())
         });
 
-        let set_builder_img_data = self.get_set_builder_image().await?;
-        let assessor_img_data = self.get_assessor_image().await?;
+        let set_builder_img_data = self.get_set_builder_image(market).await?;
+        let assessor_img_data = self.get_assessor_image(market).await?;
 
-        let prover_addr = self.args.private_key.address();
         let aggregator = Arc::new(
             aggregator::AggregatorService::new(
-                self.db.clone(),
-                chain_id,
+                market.db.clone(),
+                market.chain_id,
                 set_builder_img_data.0,
                 set_builder_img_data.1,
                 assessor_img_data.0,
                 assessor_img_data.1,
-                self.args.boundless_market_address,
+                market_addr,
                 prover_addr,
                 self.config_watcher.config.clone(),
                 prover.clone(),
@@ -500,54 +1047,76 @@ where
             Ok(())
         });
 
-        let submitter = Arc::new(submitter::Submitter::new(
-            self.db.clone(),
-            self.config_watcher.config.clone(),
-            prover.clone(),
-            self.provider.clone(),
-            self.args.set_verifier_address,
-            self.args.boundless_market_address,
-            set_builder_img_data.0,
-        )?);
-        supervisor_tasks.spawn(async move {
-            task::supervisor(1, submitter).await.context("Failed to start submitter service")?;
-            Ok(())
-        });
-
-        // Monitor the different supervisor tasks
-        while let Some(res) = supervisor_tasks.join_next().await {
-            let status = match res {
-                Err(join_err) if join_err.is_cancelled() => {
-                    tracing::info!("Tokio task exited with cancellation status: {join_err:?}");
-                    continue;
-                }
-                Err(join_err) => {
-                    tracing::error!("Tokio task exited with error status: {join_err:?}");
-                    // TODO(#BM-470): Here, we should be using a cancellation token to signal to all
-                    // the tasks under this supervisor that they should exit, then set a timer (e.g.
-                    // for 30) to give them time to gracefully shut down.
-                    anyhow::bail!("Task exited with error status: {join_err:?}")
-                }
-                Ok(status) => status,
-            };
-            match status {
-                Err(err) => {
-                    tracing::error!("Task exited with error status: {err:?}");
-                    // TODO(#BM-470): Here, we should be using a cancellation token to signal to all
-                    // the tasks under this supervisor that they should exit, then set a timer (e.g.
-                    // for 30) to give them time to gracefully shut down.
-                    anyhow::bail!("Task exited with error status: {err:?}")
-                }
-                Ok(()) => {
-                    tracing::info!("Task exited with ok status");
-                }
+        if !self.args.dry_run {
+            let mut submitter = submitter::Submitter::new(
+                market.db.clone(),
+                self.config_watcher.config.clone(),
+                prover.clone(),
+                market.provider.clone(),
+                market.deployment.set_verifier_address,
+                market_addr,
+                set_builder_img_data.0,
+            )?
+            .with_prover_address(prover_addr)
+            .with_cancel_token(self.cancel_token.clone());
+            // Without a separate lock wallet, fulfillments are sent from the lock wallet too
+            if market.lock_provider.is_none() {
+                submitter = submitter.with_tx_lock(tx_lock.clone());
             }
+            let submitter = Arc::new(submitter);
+            drain_tasks.spawn(async move {
+                task::supervisor(1, submitter)
+                    .await
+                    .context("Failed to start submitter service")?;
+                Ok(())
+            });
         }
 
         Ok(())
     }
 }
 
+/// Classify the exit of a supervisor task, only errors stop the broker
+fn task_exit_status(res: std::result::Result<Result<()>, tokio::task::JoinError>) -> Result<()> {
+    let status = match res {
+        Err(join_err) if join_err.is_cancelled() => {
+            tracing::info!("Tokio task exited with cancellation status: {join_err:?}");
+            return Ok(());
+        }
+        Err(join_err) => {
+            tracing::error!("Tokio task exited with error status: {join_err:?}");
+            anyhow::bail!("Task exited with error status: {join_err:?}")
+        }
+        Ok(status) => status,
+    };
+    match status {
+        Err(err) => {
+            tracing::error!("Task exited with error status: {err:?}");
+            anyhow::bail!("Task exited with error status: {err:?}")
+        }
+        Ok(()) => {
+            tracing::info!("Task exited with ok status");
+            Ok(())
+        }
+    }
+}
+
+/// Wait for a SIGTERM or ctrl-c
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate()).context("Failed to install handler")?;
+        tokio::select! {
+            _ = sigterm.recv() => {}
+            res = tokio::signal::ctrl_c() => res.context("Failed to listen for ctrl-c")?,
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.context("Failed to listen for ctrl-c")?;
+    Ok(())
+}
+
 async fn upload_image_uri(
     prover: &ProverObj,
     order: &Order,
@@ -588,21 +1157,35 @@ async fn upload_image_uri(
         Ok(uri.id().context("Invalid image URI type")?)
     }
 }
+/// Order input uploaded to the prover
+pub(crate) struct UploadedInput {
+    /// Prover input ID
+    pub(crate) input_id: String,
+    /// keccak256 digest of the encoded guest env, when the broker had to fetch the input itself
+    ///
+    /// Used as the proof cache key, it covers the env vars and args as well as stdin.
+    pub(crate) input_digest: Option<B256>,
+    /// Prover receipt IDs of the assumptions of the guest env
+    pub(crate) assumption_ids: Vec<String>,
+}
+
+/// Maximum number of chunks of a manifest input, as each chunk is a separate download
+const MAX_INPUT_CHUNKS: usize = 1024;
+
+/// Uploads the order input, and the receipts of its assumptions, to the prover
+///
+/// Every fetched file, including each chunk of a manifest input, is limited to `max_size` bytes,
+/// while all chunks together, and the assembled and decompressed input, are limited to
+/// `max_input_size` bytes. Manifests can reference at most [MAX_INPUT_CHUNKS] chunks.
 async fn upload_input_uri(
     prover: &ProverObj,
     order: &Order,
     max_size: usize,
+    max_input_size: usize,
     retries: Option<u8>,
-) -> Result<String> {
-    Ok(match order.request.input.inputType {
-        InputType::Inline => prover
-            .upload_input(
-                GuestEnv::decode(&order.request.input.data)
-                    .with_context(|| "Failed to decode input")?
-                    .stdin,
-            )
-            .await
-            .context("Failed to upload input data")?,
+) -> Result<UploadedInput> {
+    let encoded = match order.request.input.inputType {
+        InputType::Inline => order.request.input.data.to_vec(),
 
         InputType::Url => {
             let input_uri_str =
@@ -615,24 +1198,86 @@ async fn upload_input_uri(
             }
             let input_uri = input_uri.build().context("Failed to parse input uri")?;
 
-            if !input_uri.exists() {
-                let input_data = GuestEnv::decode(
-                    &input_uri
-                        .fetch()
-                        .await
-                        .with_context(|| format!("Failed to fetch input URI: {input_uri_str}"))?,
-                )
-                .with_context(|| format!("Failed to decode input from URI: {input_uri_str}"))?
-                .stdin;
-
-                prover.upload_input(input_data).await.context("Failed to upload input")?
-            } else {
-                input_uri.id().context("invalid input URI type")?
+            if input_uri.exists() {
+                return Ok(UploadedInput {
+                    input_id: input_uri.id().context("invalid input URI type")?,
+                    input_digest: None,
+                    assumption_ids: vec![],
+                });
             }
+            input_uri
+                .fetch()
+                .await
+                .with_context(|| format!("Failed to fetch input URI: {input_uri_str}"))?
         }
         //???
         _ => anyhow::bail!("Invalid input type: {:?}", order.request.input.inputType),
-    })
+    };
+
+    let mut env = match InputManifest::decode(&encoded).context("Failed to decode input")? {
+        Some(manifest) => {
+            ensure!(
+                manifest.chunks.len() <= MAX_INPUT_CHUNKS,
+                "Input manifest has {} chunks, more than the limit of {MAX_INPUT_CHUNKS}",
+                manifest.chunks.len()
+            );
+            let mut chunks = Vec::with_capacity(manifest.chunks.len());
+            let mut total_size = 0;
+            for chunk in manifest.chunks.iter() {
+                let mut chunk_uri = UriHandlerBuilder::new(&chunk.url).set_max_size(max_size);
+                if let Some(retry) = retries {
+                    chunk_uri = chunk_uri.set_retries(retry);
+                }
+                let chunk_uri = chunk_uri.build().context("Failed to parse input chunk uri")?;
+                let data = chunk_uri
+                    .fetch()
+                    .await
+                    .with_context(|| format!("Failed to fetch input chunk: {}", chunk.url))?;
+                // Stop downloading as soon as the chunks cannot fit the input size limit
+                total_size += data.len();
+                ensure!(
+                    total_size <= max_input_size,
+                    "Input chunks exceed the max input size of {max_input_size} bytes"
+                );
+                chunks.push(data);
+            }
+            manifest.assemble(&chunks, max_input_size).context("Failed to assemble input chunks")?
+        }
+        None => GuestEnv::decode_with_limit(&encoded, max_input_size)
+            .context("Failed to decode input")?,
+    };
+
+    let input_digest = keccak256(env.encode().context("Failed to encode input")?);
+
+    // Backends that cannot provide the env vars or args to the guest fail here, before any
+    // assumption is fetched
+    let assumption_urls = std::mem::take(&mut env.assumption_urls);
+    let input_id = prover.upload_env(env).await.context("Failed to upload input")?;
+
+    let mut assumption_ids = Vec::with_capacity(assumption_urls.len());
+    for assumption_url in assumption_urls.iter() {
+        let mut uri = UriHandlerBuilder::new(assumption_url).set_max_size(max_size);
+        if let Some(retry) = retries {
+            uri = uri.set_retries(retry);
+        }
+        let uri = uri.build().context("Failed to parse assumption uri")?;
+
+        let receipt_id = if uri.exists() {
+            uri.id().context("invalid assumption URI type")?
+        } else {
+            let receipt_data = uri
+                .fetch()
+                .await
+                .with_context(|| format!("Failed to fetch assumption URI: {assumption_url}"))?;
+            let receipt: Receipt = bincode::deserialize(&receipt_data).with_context(|| {
+                format!("Failed to decode assumption receipt: {assumption_url}")
+            })?;
+            prover.upload_receipt(receipt).await.context("Failed to upload assumption receipt")?
+        };
+        assumption_ids.push(receipt_id);
+    }
+
+    Ok(UploadedInput { input_id, input_digest: Some(input_digest), assumption_ids })
 }
 
 /// A very small utility function to get the current unix timestamp.
@@ -652,6 +1297,8 @@ pub mod test_utils {
     use tempfile::NamedTempFile;
     use url::Url;
 
+    use boundless_market::signer::SignerConfig;
+
     use crate::{config::Config, Args, Broker};
 
     pub struct BrokerBuilder<P> {
@@ -669,7 +1316,7 @@ pub mod test_utils {
             let mut config = Config::default();
             config.prover.set_builder_guest_path = Some(SET_BUILDER_PATH.into());
             config.prover.assessor_set_guest_path = Some(ASSESSOR_GUEST_PATH.into());
-            config.market.mcycle_price = "0.00001".into();
+            config.market.mcycle_price = "0.00001".parse().unwrap();
             config.batcher.batch_size = Some(1);
             config.write(config_file.path()).await.unwrap();
 
@@ -680,14 +1327,23 @@ pub mod test_utils {
                 set_verifier_address: ctx.set_verifier_address,
                 rpc_url,
                 order_stream_url: None,
-                private_key: ctx.prover_signer.clone(),
+                signer: SignerConfig::private_key(ctx.prover_signer.clone()),
+                lock_private_key: None,
+                lock_keystore_path: None,
+                lock_keystore_password: None,
+                lock_remote_signer_url: None,
+                lock_remote_signer_address: None,
                 bento_api_url: None,
                 bonsai_api_key: None,
                 bonsai_api_url: None,
+                fallback_prover_url: None,
+                fallback_prover_api_key: None,
                 deposit_amount: None,
                 rpc_retry_max: 0,
                 rpc_retry_backoff: 200,
                 rpc_retry_cu: 1000,
+                shutdown_timeout: 30,
+                dry_run: false,
             };
             Self { args, provider: ctx.prover_provider.clone(), config_file }
         }
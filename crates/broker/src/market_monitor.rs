@@ -22,6 +22,7 @@ use crate::{
     chain_monitor::ChainMonitorService,
     db::DbError,
     task::{RetryRes, RetryTask, SupervisorErr},
+    webhook::{WebhookEvent, WebhookSink},
     DbObj, Order,
 };
 
@@ -33,6 +34,7 @@ pub struct MarketMonitor<P> {
     provider: Arc<P>,
     db: DbObj,
     chain_monitor: Arc<ChainMonitorService<P>>,
+    webhook_sink: Option<WebhookSink>,
 }
 
 impl<P> MarketMonitor<P>
@@ -46,7 +48,12 @@ where
         db: DbObj,
         chain_monitor: Arc<ChainMonitorService<P>>,
     ) -> Self {
-        Self { lookback_blocks, market_addr, provider, db, chain_monitor }
+        Self { lookback_blocks, market_addr, provider, db, chain_monitor, webhook_sink: None }
+    }
+
+    /// Report slashes of orders locked by this broker to the webhook
+    pub fn with_webhook_sink(self, webhook_sink: WebhookSink) -> Self {
+        Self { webhook_sink: Some(webhook_sink), ..self }
     }
 
     /// Queries chain history to sample for the median block time
@@ -73,20 +80,14 @@ where
         Ok(block_times[block_times.len() / 2])
     }
 
-    async fn find_open_orders(
-        lookback_blocks: u64,
+    /// Fetches the requests submitted on-chain between `start_block` and `end_block` (or the
+    /// latest block), along with the block each was submitted in
+    pub(crate) async fn query_submitted_requests(
+        provider: &P,
         market_addr: Address,
-        provider: Arc<P>,
-        db: DbObj,
-        chain_monitor: Arc<ChainMonitorService<P>>,
-    ) -> Result<u64> {
-        let current_block = chain_monitor.current_block_number().await?;
-
-        let start_block = current_block.saturating_sub(lookback_blocks);
-
-        tracing::info!("Searching for existing open orders: {start_block} - {current_block}");
-
-        let market = BoundlessMarketService::new(market_addr, provider.clone(), Address::ZERO);
+        start_block: u64,
+        end_block: Option<u64>,
+    ) -> Result<Vec<(IBoundlessMarket::submitRequestCall, u64)>> {
         // let event: Event<_, _, IBoundlessMarket::RequestSubmitted, _> = Event::new(
         //     provider.clone(),
         //     Filter::new().from_block(start_block).address(market_addr),
@@ -94,10 +95,13 @@ where
 
         // let logs = event.query().await.context("Failed to query RequestSubmitted events")?;
 
-        let filter = Filter::new()
+        let mut filter = Filter::new()
             .event_signature(IBoundlessMarket::RequestSubmitted::SIGNATURE_HASH)
             .from_block(start_block)
             .address(market_addr);
+        if let Some(end_block) = end_block {
+            filter = filter.to_block(end_block);
+        }
 
         // TODO: This could probably be cleaned up but the alloy examples
         // don't have a lot of clean log decoding samples, and the Event::query()
@@ -113,18 +117,48 @@ where
             }
         });
 
-        tracing::debug!("Found {} possible in the past {} blocks", logs.len(), lookback_blocks);
-        let mut order_count = 0;
+        let mut submitted = vec![];
         for log in decoded_logs {
-            let event = &log.inner.data;
-            let request_id = U256::from(event.requestId);
             let tx_hash = log.transaction_hash.context("Missing transaction hash")?;
+            let block_number = log.block_number.context("Missing block number")?;
             let tx_data = provider
                 .get_transaction_by_hash(tx_hash)
                 .await?
                 .context("Missing transaction data")?;
             let calldata = IBoundlessMarket::submitRequestCall::abi_decode(tx_data.input(), true)
                 .context("Failed to decode calldata")?;
+            submitted.push((calldata, block_number));
+        }
+
+        Ok(submitted)
+    }
+
+    async fn find_open_orders(
+        lookback_blocks: u64,
+        market_addr: Address,
+        provider: Arc<P>,
+        db: DbObj,
+        chain_monitor: Arc<ChainMonitorService<P>>,
+    ) -> Result<u64> {
+        let current_block = chain_monitor.current_block_number().await?;
+
+        let start_block = current_block.saturating_sub(lookback_blocks);
+
+        tracing::info!("Searching for existing open orders: {start_block} - {current_block}");
+
+        let market = BoundlessMarketService::new(market_addr, provider.clone(), Address::ZERO);
+
+        let submitted =
+            Self::query_submitted_requests(&provider, market_addr, start_block, None).await?;
+
+        tracing::debug!(
+            "Found {} possible in the past {} blocks",
+            submitted.len(),
+            lookback_blocks
+        );
+        let mut order_count = 0;
+        for (calldata, _block_number) in submitted {
+            let request_id = U256::from(calldata.request.id);
             let order_exists = match db.order_exists(request_id).await {
                 Ok(val) => val,
                 Err(err) => {
@@ -212,6 +246,62 @@ where
         anyhow::bail!("Event polling exited, polling failed (possible RPC error)");
     }
 
+    async fn monitor_slashes(
+        market_addr: Address,
+        provider: Arc<P>,
+        db: DbObj,
+        webhook_sink: WebhookSink,
+    ) -> Result<()> {
+        let market = BoundlessMarketService::new(market_addr, provider.clone(), Address::ZERO);
+        let event = market.instance().ProverSlashed_filter().watch().await?;
+        tracing::info!("Subscribed to ProverSlashed event");
+        event
+            .into_stream()
+            .for_each(|log_res| async {
+                match log_res {
+                    Ok((event, _)) => {
+                        if let Err(err) = Self::process_slash(event, &db, &webhook_sink).await {
+                            tracing::error!("Failed to process slash event: {err:?}");
+                        }
+                    }
+                    Err(err) => {
+                        tracing::warn!("Failed to fetch slash event log: {:?}", err);
+                    }
+                }
+            })
+            .await;
+
+        anyhow::bail!("Slash event polling exited, polling failed (possible RPC error)");
+    }
+
+    async fn process_slash(
+        event: IBoundlessMarket::ProverSlashed,
+        db: &DbObj,
+        webhook_sink: &WebhookSink,
+    ) -> Result<()> {
+        let order_id = U256::from(event.requestId);
+        // Only orders locked by this broker have a lock price
+        let Some(order) = db.get_order(order_id).await? else {
+            return Ok(());
+        };
+        if order.lock_price.is_none() {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            "Order {order_id:x} was slashed: {} stake burned, {} transferred to {}",
+            event.stakeBurned,
+            event.stakeTransferred,
+            event.stakeRecipient
+        );
+        webhook_sink.notify(WebhookEvent::Slashed {
+            order_id,
+            stake_burned: event.stakeBurned,
+            stake_transferred: event.stakeTransferred,
+        });
+        Ok(())
+    }
+
     async fn process_log(
         event: IBoundlessMarket::RequestSubmitted,
         log: Log,
@@ -229,8 +319,15 @@ where
         let calldata = IBoundlessMarket::submitRequestCall::abi_decode(tx_data.input(), true)
             .context("Failed to decode calldata")?;
 
-        if let Err(err) =
-            calldata.request.verify_signature(&calldata.clientSignature, market_addr, chain_id)
+        if let Err(err) = calldata
+            .request
+            .verify_signature_with_provider(
+                &calldata.clientSignature,
+                market_addr,
+                chain_id,
+                &provider,
+            )
+            .await
         {
             tracing::warn!(
                 "Failed to validate order signature: 0x{:x} - {err:?}",
@@ -273,6 +370,7 @@ where
         let provider = self.provider.clone();
         let db = self.db.clone();
         let chain_monitor = self.chain_monitor.clone();
+        let webhook_sink = self.webhook_sink.clone();
 
         Box::pin(async move {
             tracing::info!("Starting up market monitor");
@@ -290,7 +388,14 @@ where
                 SupervisorErr::Recover(err)
             })?;
 
-            Self::monitor_orders(market_addr, provider, db).await.map_err(|err| {
+            let monitor_res = match webhook_sink {
+                Some(webhook_sink) => tokio::select! {
+                    res = Self::monitor_orders(market_addr, provider.clone(), db.clone()) => res,
+                    res = Self::monitor_slashes(market_addr, provider, db, webhook_sink) => res,
+                },
+                None => Self::monitor_orders(market_addr, provider, db).await,
+            };
+            monitor_res.map_err(|err| {
                 tracing::error!("Monitor for new blocks failed, restarting: {err:?}");
 
                 SupervisorErr::Recover(err)
@@ -2,12 +2,12 @@
 //
 // All rights reserved.
 
-use alloy::{
-    primitives::U256,
-    signers::{local::PrivateKeySigner, Signer},
-};
+use alloy::{primitives::U256, signers::Signer};
 use anyhow::Result;
-use boundless_market::order_stream_client::{order_stream, Client as OrderStreamClient};
+use boundless_market::{
+    order_stream_client::{order_stream, Client as OrderStreamClient},
+    signer::BoundlessSigner,
+};
 use futures_util::StreamExt;
 
 use crate::{
@@ -18,11 +18,11 @@ use crate::{
 pub struct OffchainMarketMonitor {
     db: DbObj,
     client: OrderStreamClient,
-    signer: PrivateKeySigner,
+    signer: BoundlessSigner,
 }
 
 impl OffchainMarketMonitor {
-    pub fn new(db: DbObj, client: OrderStreamClient, signer: PrivateKeySigner) -> Self {
+    pub fn new(db: DbObj, client: OrderStreamClient, signer: BoundlessSigner) -> Self {
         Self { db, client, signer }
     }
 
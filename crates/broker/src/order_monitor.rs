@@ -3,16 +3,17 @@
 // All rights reserved.
 
 use crate::{
+    balance_manager::{LockPause, WalletTxLock},
     chain_monitor::ChainMonitorService,
     config::ConfigLock,
     db::DbObj,
     task::{RetryRes, RetryTask, SupervisorErr},
-    Order, OrderStatus,
+    LostLock, Order, OrderStatus,
 };
 use alloy::{
     network::Ethereum,
-    primitives::{utils::parse_ether, Address, U256},
-    providers::{Provider, WalletProvider},
+    primitives::{Address, U256},
+    providers::{PendingTransactionBuilder, Provider, WalletProvider},
     rpc::types::BlockTransactionsKind,
 };
 use anyhow::{Context, Result};
@@ -20,8 +21,14 @@ use boundless_market::contracts::{
     boundless_market::{BoundlessMarketService, MarketError},
     ProofStatus,
 };
+use chrono::Utc;
+use futures::future::join_all;
 use std::{sync::Arc, time::Duration};
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+/// Extra gas added to the estimated gas of a lock transaction, in percent
+const LOCK_GAS_BUFFER_PERCENT: u64 = 20;
 
 #[derive(Error, Debug)]
 pub enum LockOrderErr {
@@ -34,6 +41,9 @@ pub enum LockOrderErr {
     #[error("Order already locked")]
     AlreadyLocked,
 
+    #[error("Lock simulation failed: {0}")]
+    SimulationFailed(MarketError),
+
     #[error("Other: {0}")]
     OtherErr(#[from] anyhow::Error),
 }
@@ -46,6 +56,10 @@ pub struct OrderMonitor<P> {
     config: ConfigLock,
     market: BoundlessMarketService<Arc<P>>,
     provider: Arc<P>,
+    lock_pause: LockPause,
+    cancel_token: CancellationToken,
+    dry_run: bool,
+    tx_lock: WalletTxLock,
 }
 
 impl<P> OrderMonitor<P>
@@ -76,23 +90,62 @@ where
         {
             let config = config.lock_all().context("Failed to lock config")?;
             market = market.with_stake_balance_alert(
-                &config
-                    .market
-                    .stake_balance_warn_threshold
-                    .as_ref()
-                    .and_then(|s| parse_ether(s).ok()),
-                &config
-                    .market
-                    .stake_balance_error_threshold
-                    .as_ref()
-                    .and_then(|s| parse_ether(s).ok()),
+                &config.market.stake_balance_warn_threshold.map(|amount| amount.wei()),
+                &config.market.stake_balance_error_threshold.map(|amount| amount.wei()),
             );
         }
 
-        Ok(Self { db, chain_monitor, block_time, config, market, provider })
+        Ok(Self {
+            db,
+            chain_monitor,
+            block_time,
+            config,
+            market,
+            provider,
+            lock_pause: LockPause::default(),
+            cancel_token: CancellationToken::new(),
+            dry_run: false,
+            tx_lock: WalletTxLock::default(),
+        })
+    }
+
+    /// Pause locking of orders whenever the [LockPause] flag is set
+    pub fn with_lock_pause(self, lock_pause: LockPause) -> Self {
+        Self { lock_pause, ..self }
+    }
+
+    /// Hold the given lock while assigning nonces to lock transactions, see [WalletTxLock]
+    pub fn with_tx_lock(self, tx_lock: WalletTxLock) -> Self {
+        Self { tx_lock, ..self }
+    }
+
+    /// Stop locking new orders once the token is cancelled
+    ///
+    /// A lock transaction that is already in flight is always finished and recorded, remaining
+    /// orders stay in [OrderStatus::Locking] and are reconciled on the next start.
+    pub fn with_cancel_token(self, cancel_token: CancellationToken) -> Self {
+        Self { cancel_token, ..self }
+    }
+
+    /// Record the locks that would have been sent instead of sending them
+    pub fn with_dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    async fn block_timestamp(&self, block: u64) -> Result<u64> {
+        Ok(self
+            .provider
+            .get_block_by_number(block.into(), BlockTransactionsKind::Hashes)
+            .await
+            .with_context(|| format!("failed to get block {block}"))?
+            .with_context(|| format!("failed to get block {block}: block not found"))?
+            .header
+            .timestamp)
     }
 
-    async fn lock_order(&self, order_id: U256, order: &Order) -> Result<(), LockOrderErr> {
+    /// Checks the order is still open and simulates the lock against the pending block,
+    /// returning the gas limit to send the lock with
+    async fn prepare_lock(&self, order_id: U256, order: &Order) -> Result<u64, LockOrderErr> {
         if order.status != OrderStatus::Locking {
             return Err(LockOrderErr::InvalidStatus(order.status));
         }
@@ -104,32 +157,91 @@ where
             .context("Failed to get order status")?;
         if order_status != ProofStatus::Unknown {
             tracing::warn!("Order {order_id:x} not open: {order_status:?}, skipping");
-            // TODO: fetch some chain data to find out who / and for how much the order
-            // was locked in at
+            if matches!(order_status, ProofStatus::Locked | ProofStatus::Fulfilled) {
+                self.record_lost_lock(order_id, order).await;
+            }
             return Err(LockOrderErr::AlreadyLocked);
         }
 
-        let conf_priority_gas = {
-            let conf = self.config.lock_all().context("Failed to lock config")?;
-            conf.market.lockin_priority_gas
-        };
-
-        tracing::info!("Locking order: {order_id:x} for stake: {}", order.request.offer.lockStake);
-        let lock_block = self
+        let gas = self
             .market
-            .lock_request(&order.request, &order.client_sig, conf_priority_gas)
+            .simulate_lock_request(&order.request, &order.client_sig)
             .await
-            .map_err(LockOrderErr::OrderLockedInBlock)?;
+            .map_err(LockOrderErr::SimulationFailed)?;
 
-        let lock_timestamp = self
-            .provider
-            .get_block_by_number(lock_block.into(), BlockTransactionsKind::Hashes)
+        Ok(gas + gas * LOCK_GAS_BUFFER_PERCENT / 100)
+    }
+
+    /// Broadcasts the lock transactions of the given orders, one after the other
+    ///
+    /// Nonces are assigned from the signer's pending transaction count while holding the
+    /// [WalletTxLock], so no other transaction from the wallet can take them. If a transaction
+    /// fails to broadcast, the pending nonce is read again before sending the next one, so a
+    /// failure never leaves a gap that would stall the later locks.
+    async fn broadcast_locks<'a>(
+        &self,
+        orders: &[(U256, &'a Order, u64)],
+        priority_gas: Option<u64>,
+    ) -> Result<Vec<(U256, &'a Order, PendingTransactionBuilder<Ethereum>)>> {
+        let _tx_guard = self.tx_lock.lock().await;
+        let mut nonce = self.pending_nonce().await?;
+        let mut pending = Vec::with_capacity(orders.len());
+        for (order_id, order, gas_limit) in orders {
+            tracing::info!(
+                "Locking order: {order_id:x} for stake: {} nonce: {nonce}",
+                order.request.offer.lockStake
+            );
+            match self
+                .market
+                .broadcast_lock_request(
+                    &order.request,
+                    &order.client_sig,
+                    priority_gas,
+                    nonce,
+                    *gas_limit,
+                )
+                .await
+            {
+                Ok(pending_tx) => {
+                    pending.push((*order_id, *order, pending_tx));
+                    nonce += 1;
+                }
+                Err(err) => {
+                    self.handle_lock_failure(*order_id, LockOrderErr::OrderLockedInBlock(err))
+                        .await;
+                    nonce = self.pending_nonce().await?;
+                }
+            }
+        }
+        Ok(pending)
+    }
+
+    async fn pending_nonce(&self) -> Result<u64> {
+        self.provider
+            .get_transaction_count(self.provider.default_signer_address())
+            .pending()
             .await
-            .with_context(|| format!("failed to get block {lock_block}"))?
-            .with_context(|| format!("failed to get block {lock_block}: block not found"))?
-            .header
-            .timestamp;
+            .context("Failed to get lock signer nonce")
+    }
+
+    /// Waits for a broadcast lock transaction and moves the order to proving
+    async fn confirm_lock(
+        &self,
+        order_id: U256,
+        order: &Order,
+        pending_tx: PendingTransactionBuilder<Ethereum>,
+    ) -> Result<(), LockOrderErr> {
+        let lock_block = match self.market.confirm_lock_request(order_id, pending_tx).await {
+            Ok(lock_block) => lock_block,
+            Err(err) => {
+                if matches!(err, MarketError::LockRevert(_)) {
+                    self.record_lost_lock(order_id, order).await;
+                }
+                return Err(LockOrderErr::OrderLockedInBlock(err));
+            }
+        };
 
+        let lock_timestamp = self.block_timestamp(lock_block).await?;
         let lock_price = order
             .request
             .offer
@@ -145,30 +257,135 @@ where
         Ok(())
     }
 
+    /// Records the lock that would have been made, at the price of the current block
+    async fn dry_run_lock(&self, order_id: U256, order: &Order) -> Result<(), LockOrderErr> {
+        let lock_timestamp = self.chain_monitor.current_block_timestamp().await?;
+        let lock_price = order
+            .request
+            .offer
+            .price_at(lock_timestamp)
+            .context("Failed to calculate lock price")?;
+
+        tracing::info!(
+            "Dry run: would have locked order {order_id:x} for stake: {} at price: {lock_price}",
+            order.request.offer.lockStake
+        );
+        self.db
+            .set_order_dry_run(order_id, lock_price)
+            .await
+            .context("Failed to record dry-run lock")?;
+
+        Ok(())
+    }
+
+    /// Records which prover won the lock on an order, and at what price
+    ///
+    /// Only used for competitive analysis, so failures are logged and otherwise ignored.
+    async fn record_lost_lock(&self, order_id: U256, order: &Order) {
+        let res: Result<()> = async {
+            let (prover, lock_block) = self
+                .market
+                .query_request_locked_event(order_id, None, None)
+                .await
+                .context("Failed to find lock event")?;
+            let lock_timestamp = self.block_timestamp(lock_block).await?;
+            let lock_price = order
+                .request
+                .offer
+                .price_at(lock_timestamp)
+                .context("Failed to calculate lock price")?;
+
+            tracing::info!(
+                "Order {order_id:x} locked by {prover} in block {lock_block} at price {lock_price}"
+            );
+            self.db
+                .add_lost_lock(
+                    order_id,
+                    LostLock { prover, lock_price, lock_block, recorded_at: Utc::now() },
+                )
+                .await
+                .context("Failed to store lost lock")?;
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = res {
+            tracing::warn!("Failed to record lost lock for order {order_id:x}: {err:?}");
+        }
+    }
+
+    async fn handle_lock_failure(&self, order_id: U256, err: LockOrderErr) {
+        match err {
+            LockOrderErr::OtherErr(ref err) => {
+                tracing::error!("Failed to lock order: {order_id:x} {err:?}");
+            }
+            // Only warn on known / classified errors
+            _ => {
+                tracing::warn!("Soft failed to lock order: {order_id:x} {err:?}");
+            }
+        }
+        if let Err(err) = self.db.set_order_failure(order_id, format!("{err:?}")).await {
+            tracing::error!("Failed to set DB failure state for order: {order_id:x}, {err:?}");
+        }
+    }
+
+    /// Locks orders in groups of up to `max_concurrent_locks`
+    ///
+    /// Every lock in a group is simulated first, then the ones that pass are broadcast in order
+    /// with consecutive nonces, and confirmed together.
     async fn lock_orders(&self, current_block: u64, orders: Vec<(U256, Order)>) -> Result<u64> {
+        if self.lock_pause.is_paused() && !orders.is_empty() {
+            tracing::warn!(
+                "Order locking paused by balance manager, deferring {} orders",
+                orders.len()
+            );
+            return Ok(0);
+        }
+
+        let (max_concurrent_locks, priority_gas) = {
+            let conf = self.config.lock_all().context("Failed to lock config")?;
+            (conf.market.max_concurrent_locks.max(1), conf.market.lockin_priority_gas)
+        };
+
         let mut order_count = 0;
-        for (order_id, order) in orders.iter() {
-            match self.lock_order(*order_id, order).await {
-                Ok(_) => tracing::info!("Locked order: {order_id:x}"),
-                Err(ref err) => {
-                    match err {
-                        LockOrderErr::OtherErr(err) => {
-                            tracing::error!("Failed to lock order: {order_id:x} {err:?}");
-                        }
-                        // Only warn on known / classified errors
-                        _ => {
-                            tracing::warn!("Soft failed to lock order: {order_id:x} {err:?}");
-                        }
+        for chunk in orders.chunks(max_concurrent_locks) {
+            if self.cancel_token.is_cancelled() {
+                tracing::info!("Shutdown requested, deferring remaining locks");
+                break;
+            }
+
+            let simulations =
+                join_all(chunk.iter().map(|(order_id, order)| self.prepare_lock(*order_id, order)))
+                    .await;
+            let mut ready = vec![];
+            for ((order_id, order), res) in chunk.iter().zip(simulations) {
+                match res {
+                    Ok(gas_limit) => ready.push((*order_id, order, gas_limit)),
+                    Err(err) => self.handle_lock_failure(*order_id, err).await,
+                }
+            }
+
+            if self.dry_run {
+                for (order_id, order, _) in ready {
+                    if let Err(err) = self.dry_run_lock(order_id, order).await {
+                        self.handle_lock_failure(order_id, err).await;
                     }
-                    if let Err(err) = self.db.set_order_failure(*order_id, format!("{err:?}")).await
-                    {
-                        tracing::error!(
-                            "Failed to set DB failure state for order: {order_id:x}, {err:?}"
-                        );
+                }
+            } else if !ready.is_empty() {
+                let pending = self.broadcast_locks(&ready, priority_gas).await?;
+                let locks =
+                    join_all(pending.into_iter().map(|(order_id, order, pending_tx)| async move {
+                        (order_id, self.confirm_lock(order_id, order, pending_tx).await)
+                    }))
+                    .await;
+                for (order_id, res) in locks {
+                    match res {
+                        Ok(_) => tracing::info!("Locked order: {order_id:x}"),
+                        Err(err) => self.handle_lock_failure(order_id, err).await,
                     }
                 }
             }
-            order_count += 1;
+            order_count += chunk.len() as u64;
         }
 
         if !orders.is_empty() {
@@ -219,6 +436,10 @@ where
         let mut last_block = 0;
         let mut first_block = 0;
         loop {
+            if self.cancel_token.is_cancelled() {
+                tracing::info!("Order monitor shutting down");
+                return Ok(());
+            }
             let current_block = self.chain_monitor.current_block_number().await?;
             let current_block_timestamp = self.chain_monitor.current_block_timestamp().await?;
 
@@ -245,7 +466,10 @@ where
             }
 
             // Attempt to wait 1/2 a block time to catch each new block
-            tokio::time::sleep(tokio::time::Duration::from_secs(self.block_time / 2)).await
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(self.block_time / 2)) => {}
+                _ = self.cancel_token.cancelled() => {}
+            }
         }
     }
 }
@@ -361,6 +585,10 @@ mod tests {
             client_sig: client_sig.into(),
             lock_price: None,
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
         let request_id = boundless_market.submit_request(&order.request, &signer).await.unwrap();
         assert_eq!(request_id, order_id);
@@ -472,6 +700,10 @@ mod tests {
             client_sig,
             lock_price: None,
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
 
         let _request_id = boundless_market.submit_request(&order.request, &signer).await.unwrap();
@@ -497,4 +729,188 @@ mod tests {
         let order = db.get_order(order_id).await.unwrap().unwrap();
         assert_eq!(order.status, OrderStatus::Locked);
     }
+
+    async fn locking_order(
+        signer: &PrivateKeySigner,
+        market_address: Address,
+        chain_id: u64,
+        idx: u32,
+    ) -> (U256, Order) {
+        let request = ProofRequest::new(
+            idx,
+            &signer.address(),
+            Requirements::new(
+                Digest::ZERO,
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            "http://risczero.com/image",
+            Input { inputType: InputType::Inline, data: Default::default() },
+            Offer {
+                minPrice: U256::from(1),
+                maxPrice: U256::from(2),
+                biddingStart: now_timestamp(),
+                rampUpPeriod: 1,
+                timeout: 100,
+                lockTimeout: 100,
+                lockStake: U256::from(0),
+            },
+        );
+        let client_sig =
+            request.sign_request(signer, market_address, chain_id).await.unwrap().as_bytes();
+        let order_id = U256::from(request.id);
+        let mut order = Order::new(request, client_sig.into());
+        order.status = OrderStatus::Locking;
+        order.target_timestamp = Some(0);
+        (order_id, order)
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn concurrent_locks_and_lost_lock() {
+        let anvil = Anvil::new().spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let other_signer: PrivateKeySigner = anvil.keys()[1].clone().into();
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer.clone()))
+                .on_builtin(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+        let other_provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(other_signer.clone()))
+                .on_builtin(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+
+        let hit_points = deploy_hit_points(&signer, provider.clone()).await.unwrap();
+        let market_address = deploy_boundless_market(
+            &signer,
+            provider.clone(),
+            Address::ZERO,
+            hit_points,
+            Digest::from(ASSESSOR_GUEST_ID),
+            Some(signer.address()),
+        )
+        .await
+        .unwrap();
+        let boundless_market = BoundlessMarketService::new(
+            market_address,
+            provider.clone(),
+            provider.default_signer_address(),
+        );
+        let other_market =
+            BoundlessMarketService::new(market_address, other_provider, other_signer.address());
+        boundless_market.deposit(U256::from(10)).await.unwrap();
+
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let config = ConfigLock::default();
+        config.load_write().unwrap().market.max_concurrent_locks = 3;
+
+        let chain_id = provider.get_chain_id().await.unwrap();
+        let mut orders = vec![];
+        for idx in 0..3 {
+            orders.push(locking_order(&signer, market_address, chain_id, idx).await);
+        }
+        for (order_id, order) in orders.iter() {
+            db.add_order(*order_id, order.clone()).await.unwrap();
+        }
+
+        // Another prover wins the race for the last order
+        let (lost_id, lost_order) = orders.last().unwrap();
+        other_market.lock_request(&lost_order.request, &lost_order.client_sig, None).await.unwrap();
+
+        let chain_monitor = Arc::new(ChainMonitorService::new(provider.clone()).await.unwrap());
+        let monitor = OrderMonitor::new(
+            db.clone(),
+            provider.clone(),
+            chain_monitor.clone(),
+            config.clone(),
+            2,
+            market_address,
+        )
+        .unwrap();
+
+        let current_block = provider.get_block_number().await.unwrap();
+        let order_count = monitor.lock_orders(current_block, orders.clone()).await.unwrap();
+        assert_eq!(order_count, 3);
+
+        for (order_id, _) in orders.iter().take(2) {
+            let order = db.get_order(*order_id).await.unwrap().unwrap();
+            assert_eq!(order.status, OrderStatus::Locked);
+            assert!(boundless_market.is_locked(*order_id).await.unwrap());
+        }
+
+        let order = db.get_order(*lost_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Failed);
+
+        let lost_locks = db.get_lost_locks().await.unwrap();
+        assert_eq!(lost_locks.len(), 1);
+        assert_eq!(lost_locks[0].0, *lost_id);
+        assert_eq!(lost_locks[0].1.prover, other_signer.address());
+        assert!(lost_locks[0].1.lock_price >= U256::from(1));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn dry_run_records_lock() {
+        let anvil = Anvil::new().spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer.clone()))
+                .on_builtin(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+
+        let hit_points = deploy_hit_points(&signer, provider.clone()).await.unwrap();
+        let market_address = deploy_boundless_market(
+            &signer,
+            provider.clone(),
+            Address::ZERO,
+            hit_points,
+            Digest::from(ASSESSOR_GUEST_ID),
+            Some(signer.address()),
+        )
+        .await
+        .unwrap();
+        let boundless_market = BoundlessMarketService::new(
+            market_address,
+            provider.clone(),
+            provider.default_signer_address(),
+        );
+
+        let db: DbObj =
+            Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap().with_dry_run(true));
+        let chain_id = provider.get_chain_id().await.unwrap();
+        let (order_id, order) = locking_order(&signer, market_address, chain_id, 0).await;
+        db.add_order(order_id, order.clone()).await.unwrap();
+
+        let chain_monitor = Arc::new(ChainMonitorService::new(provider.clone()).await.unwrap());
+        tokio::spawn(chain_monitor.spawn());
+        let monitor = OrderMonitor::new(
+            db.clone(),
+            provider.clone(),
+            chain_monitor.clone(),
+            ConfigLock::default(),
+            2,
+            market_address,
+        )
+        .unwrap()
+        .with_dry_run(true);
+
+        let nonce = provider.get_transaction_count(signer.address()).await.unwrap();
+        let current_block = provider.get_block_number().await.unwrap();
+        monitor.lock_orders(current_block, vec![(order_id, order)]).await.unwrap();
+
+        let order = db.get_order(order_id).await.unwrap().unwrap();
+        assert!(order.dry_run);
+        assert_eq!(order.status, OrderStatus::DryRun);
+        assert!(order.lock_price.unwrap() >= U256::from(1));
+        assert!(!boundless_market.is_locked(order_id).await.unwrap());
+        assert_eq!(provider.get_transaction_count(signer.address()).await.unwrap(), nonce);
+    }
 }
@@ -2,20 +2,44 @@
 //
 // All rights reserved.
 
-use std::sync::Arc;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    sync::{Arc, Mutex},
+};
 
 use crate::now_timestamp;
 use alloy::{
     network::Ethereum,
-    primitives::{
-        utils::{format_ether, parse_ether},
-        Address, FixedBytes, U256,
-    },
+    primitives::{utils::format_ether, Address, FixedBytes, B256, U256},
     providers::{Provider, WalletProvider},
 };
 use anyhow::{Context, Result};
-use boundless_market::contracts::{boundless_market::BoundlessMarketService, RequestError};
+use boundless_market::contracts::{
+    boundless_market::BoundlessMarketService, ProofRequest, RequestError,
+};
 use thiserror::Error;
+use tokio::sync::Semaphore;
+
+alloy::sol! {
+    #[sol(rpc)]
+    interface IBoundlessMarketCallback {
+        function handleProof(bytes32 imageId, bytes calldata journal, bytes calldata seal) external;
+    }
+}
+
+/// Intrinsic and ABI decoding gas of a simulated callback transaction, on top of the callback's
+/// gas limit which is all the callback itself gets on-chain
+const CALLBACK_SIM_BASE_GAS: u64 = 25_000;
+
+/// Gas limit of the request's callback, if it has one
+pub(crate) fn callback_gas_limit(request: &ProofRequest) -> Option<u64> {
+    let callback = &request.requirements.callback;
+    if callback.addr == Address::ZERO {
+        return None;
+    }
+    Some(callback.gasLimit.saturating_to())
+}
 
 #[derive(Error, Debug)]
 #[non_exhaustive]
@@ -41,9 +65,86 @@ use crate::{
     db::DbObj,
     provers::{ProverError, ProverObj},
     task::{RetryRes, RetryTask, SupervisorErr},
-    Order,
+    Order, OrderStatus,
 };
 
+/// Priority of a new order in the pricing queue, greater priorities are priced first
+///
+/// Compared field by field, in order of declaration.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct PricingPriority {
+    /// Client is in the `priority_client_addresses` list
+    priority_client: bool,
+    /// Max price of the order per expected mcycle (in wei)
+    max_price_per_mcycle: U256,
+    /// Max price of the order (in wei), ranks orders without a cycle estimate
+    max_price: U256,
+    /// Lock expiration of the order, closer deadlines are more urgent
+    expiration: Reverse<u64>,
+}
+
+/// New orders waiting to be priced
+///
+/// Orders are read from the DB incrementally, by sequence number, and their priority is computed
+/// once when they are queued.
+#[derive(Default)]
+struct PricingQueue {
+    /// Queued orders by priority, ties going to the order that was added first
+    orders: BinaryHeap<(PricingPriority, Reverse<i64>, U256)>,
+    /// Sequence number of the last order read from the DB
+    last_seq: i64,
+}
+
+/// Chain state an order is priced against
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PricingAt {
+    /// Current time, in seconds since the UNIX epoch
+    pub now: u64,
+    /// Gas price (in wei) used for the callback cost, the current gas price if unset
+    pub gas_price: Option<u128>,
+    /// Block the callback is simulated at, the latest block if unset
+    pub block: Option<u64>,
+}
+
+impl PricingAt {
+    /// The current time, gas price and block
+    fn latest() -> Self {
+        Self { now: now_timestamp(), gas_price: None, block: None }
+    }
+}
+
+/// Pricing capacity of the prover, shared by the order pickers of every market
+#[derive(Clone)]
+pub struct PricingCapacity {
+    /// Cycle count of the last preflight of each image ID
+    cycle_estimates: Arc<Mutex<HashMap<B256, u64>>>,
+    /// Limits the number of orders priced at once
+    preflight_permits: Arc<Semaphore>,
+    /// Limits the number of image / input downloads at once
+    download_permits: Arc<Semaphore>,
+}
+
+impl PricingCapacity {
+    /// Size the limits from the config, they are only read at startup
+    pub fn new(config: &ConfigLock) -> Self {
+        let (max_preflights, max_downloads) = match config.lock_all() {
+            Ok(config) => {
+                (config.market.max_concurrent_preflights, config.market.max_concurrent_downloads)
+            }
+            Err(err) => {
+                tracing::error!("Failed to read config, using default pricing limits: {err:?}");
+                let market_conf = crate::config::MarketConf::default();
+                (market_conf.max_concurrent_preflights, market_conf.max_concurrent_downloads)
+            }
+        };
+        Self {
+            cycle_estimates: Default::default(),
+            preflight_permits: Arc::new(Semaphore::new(max_preflights.max(1))),
+            download_permits: Arc::new(Semaphore::new(max_downloads.max(1))),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct OrderPicker<P> {
     db: DbObj,
@@ -51,6 +152,9 @@ pub struct OrderPicker<P> {
     prover: ProverObj,
     provider: Arc<P>,
     market: BoundlessMarketService<Arc<P>>,
+    check_balances: bool,
+    capacity: PricingCapacity,
+    queue: Arc<tokio::sync::Mutex<PricingQueue>>,
 }
 
 impl<P> OrderPicker<P>
@@ -69,15 +173,52 @@ where
             provider.clone(),
             provider.default_signer_address(),
         );
-        Self { db, config, prover, provider, market }
+        let capacity = PricingCapacity::new(&config);
+        Self {
+            db,
+            config,
+            prover,
+            provider,
+            market,
+            check_balances: true,
+            capacity,
+            queue: Default::default(),
+        }
+    }
+
+    /// Share the pricing capacity with the order pickers of other markets
+    pub fn with_capacity(self, capacity: PricingCapacity) -> Self {
+        Self { capacity, ..self }
+    }
+
+    /// Toggle the gas and stake balance checks of the signer account
+    ///
+    /// Disabled when replaying orders for backtesting, where no transactions are sent
+    pub fn with_balance_checks(self, check_balances: bool) -> Self {
+        Self { check_balances, ..self }
     }
 
     async fn price_order(&self, order_id: U256, order: &Order) -> Result<(), PriceOrderErr> {
+        self.price_order_at(order_id, order, PricingAt::latest()).await
+    }
+
+    /// Price the order against the chain state described by `at`
+    pub(crate) async fn price_order_at(
+        &self,
+        order_id: U256,
+        order: &Order,
+        at: PricingAt,
+    ) -> Result<(), PriceOrderErr> {
+        let now = at.now;
         tracing::debug!("Processing order {order_id:x}: {order:?}");
 
-        let (min_deadline, allowed_addresses_opt) = {
+        let (min_deadline, allowed_addresses_opt, max_callback_gas) = {
             let config = self.config.lock_all().context("Failed to read config")?;
-            (config.market.min_deadline, config.market.allow_client_addresses.clone())
+            (
+                config.market.min_deadline,
+                config.market.allow_client_addresses.clone(),
+                config.market.max_callback_gas,
+            )
         };
 
         // Initial sanity checks:
@@ -98,12 +239,21 @@ where
             return Ok(());
         }
 
+        // The callback gas is paid by the prover at fulfillment
+        let callback_gas = callback_gas_limit(&order.request);
+        if let (Some(callback_gas), Some(max_callback_gas)) = (callback_gas, max_callback_gas) {
+            if callback_gas > max_callback_gas {
+                tracing::warn!("Removing order {order_id:x} because its callback gas limit {callback_gas} exceeds max_callback_gas {max_callback_gas}");
+                self.db.skip_order(order_id).await.context("Failed to delete order")?;
+                return Ok(());
+            }
+        }
+
         // is the order expired already?
         // TODO: Handle lockTimeout separately from timeout.
 
         let expiration = order.request.offer.biddingStart + order.request.offer.lockTimeout as u64;
 
-        let now = now_timestamp();
         if expiration <= now {
             tracing::warn!("Removing order {order_id:x} because it has expired");
             self.db.skip_order(order_id).await.context("Failed to delete expired order")?;
@@ -121,7 +271,7 @@ where
         // Check if the stake is sane and if we can afford it
         let max_stake = {
             let config = self.config.lock_all().context("Failed to read config")?;
-            parse_ether(&config.market.max_stake).context("Failed to parse max_stake")?
+            config.market.max_stake.wei()
         };
 
         let lockin_stake = U256::from(order.request.offer.lockStake);
@@ -132,26 +282,39 @@ where
         }
 
         // Check that we have both enough staking tokens to stake, and enough gas tokens to lock and fulfil
-        let gas_price = self.provider.get_gas_price().await.context("Failed to get gas price")?;
-        let gas_to_lock_order =
-            U256::from(gas_price) * U256::from(self.estimate_gas_to_lock(order).await?);
-        let available_gas = self.available_gas_balance().await?;
-        let available_stake = self.available_stake_balance().await?;
+        if self.check_balances {
+            let gas_price =
+                self.provider.get_gas_price().await.context("Failed to get gas price")?;
+            let gas_to_lock_order = U256::from(gas_price)
+                * U256::from(
+                    self.estimate_gas_to_lock(order).await?
+                        + self.estimate_gas_to_fulfill(order).await?,
+                );
+            let available_gas = self.available_gas_balance().await?;
+            let available_stake = self.available_stake_balance().await?;
 
-        if gas_to_lock_order > available_gas {
-            tracing::warn!("Estimated there will be insufficient gas to lock this order after locking and fulfilling pending orders");
-            self.db.skip_order(order_id).await.context("Failed to delete order")?;
-            return Ok(());
-        }
-        if lockin_stake > available_stake {
-            tracing::warn!(
-                "Insufficient available stake to lock order {order_id:x}. Requires {lockin_stake}, has {available_stake}"
-            );
-            self.db.skip_order(order_id).await.context("Failed to delete order")?;
-            return Ok(());
+            if gas_to_lock_order > available_gas {
+                tracing::warn!("Estimated there will be insufficient gas to lock and fulfill this order after locking and fulfilling pending orders");
+                self.db.skip_order(order_id).await.context("Failed to delete order")?;
+                return Ok(());
+            }
+            if lockin_stake > available_stake {
+                tracing::warn!(
+                    "Insufficient available stake to lock order {order_id:x}. Requires {lockin_stake}, has {available_stake}"
+                );
+                self.db.skip_order(order_id).await.context("Failed to delete order")?;
+                return Ok(());
+            }
         }
 
-        let (skip_preflight, max_size, peak_prove_khz, fetch_retries, max_mcycle_limit) = {
+        let (
+            skip_preflight,
+            max_size,
+            max_input_size,
+            peak_prove_khz,
+            fetch_retries,
+            max_mcycle_limit,
+        ) = {
             let config = self.config.lock_all().context("Failed to read config")?;
             let skip_preflight =
                 if let Some(skip_preflights) = config.market.skip_preflight_ids.as_ref() {
@@ -163,6 +326,7 @@ where
             (
                 skip_preflight,
                 config.market.max_file_size,
+                config.market.max_input_size,
                 config.market.peak_prove_khz,
                 config.market.max_fetch_retries,
                 config.market.max_mcycle_limit,
@@ -178,25 +342,80 @@ where
             return Ok(());
         }
 
-        // TODO: Move URI handling like this into the prover impls
-        let image_id = crate::upload_image_uri(&self.prover, order, max_size, fetch_retries)
-            .await
-            .map_err(PriceOrderErr::FetchImageErr)?;
+        let (image_id, input) = {
+            let _download_permit =
+                self.capacity.download_permits.acquire().await.context("Download limit closed")?;
 
-        let input_id = crate::upload_input_uri(&self.prover, order, max_size, fetch_retries)
+            // TODO: Move URI handling like this into the prover impls
+            let image_id = crate::upload_image_uri(&self.prover, order, max_size, fetch_retries)
+                .await
+                .map_err(PriceOrderErr::FetchImageErr)?;
+
+            let input = match crate::upload_input_uri(
+                &self.prover,
+                order,
+                max_size,
+                max_input_size,
+                fetch_retries,
+            )
             .await
-            .map_err(PriceOrderErr::FetchInputErr)?;
+            {
+                Ok(input) => input,
+                Err(err)
+                    if matches!(
+                        err.downcast_ref::<ProverError>(),
+                        Some(ProverError::UnsupportedGuestEnv)
+                    ) =>
+                {
+                    tracing::warn!("Removing order {order_id:x} because it sets guest env vars or args, which the prover backend cannot provide");
+                    self.db.skip_order(order_id).await.context("Failed to delete order")?;
+                    return Ok(());
+                }
+                Err(err) => return Err(PriceOrderErr::FetchInputErr(err)),
+            };
+
+            (image_id, input)
+        };
 
         // Record the image/input IDs for proving stage
         self.db
-            .set_image_input_ids(order_id, &image_id, &input_id)
+            .set_image_input_ids(order_id, &image_id, &input.input_id)
             .await
             .context("Failed to record Input/Image IDs to DB")?;
+        if !input.assumption_ids.is_empty() {
+            self.db
+                .set_order_assumption_ids(order_id, &input.assumption_ids)
+                .await
+                .context("Failed to record assumption IDs to DB")?;
+        }
+
+        // Requests are often resubmitted with the same image and input, in which case the earlier
+        // proof is reused instead of proving again
+        if let Some(input_digest) = input.input_digest {
+            self.db
+                .set_order_input_digest(order_id, input_digest)
+                .await
+                .context("Failed to record input digest to DB")?;
+            let cached_proof = self
+                .db
+                .get_cached_proof(order.request.requirements.imageId, input_digest)
+                .await
+                .context("Failed to query proof cache")?;
+            if let Some(proof_id) = cached_proof {
+                if self
+                    .price_cached_order(order_id, order, &proof_id, callback_gas, expiration, at)
+                    .await?
+                {
+                    return Ok(());
+                }
+                tracing::debug!("Cached proof {proof_id} of order {order_id:x} is gone, pricing");
+            }
+        }
 
         // Create a executor limit based on the max price of the order
         let config_min_mcycle_price = {
             let config = self.config.lock_all().context("Failed to read config")?;
-            parse_ether(&config.market.mcycle_price).context("Failed to parse mcycle_price")?
+            config.market.mcycle_price.wei()
         };
 
         let exec_limit: u64 = (U256::from(order.request.offer.maxPrice) / config_min_mcycle_price)
@@ -222,9 +441,9 @@ where
             .prover
             .preflight(
                 &image_id,
-                &input_id,
-                vec![],
-                /* TODO assumptions */ Some(exec_limit * 1024 * 1024),
+                &input.input_id,
+                input.assumption_ids.clone(),
+                Some(exec_limit * 1024 * 1024),
             )
             .await
             .map_err(|err| match err {
@@ -239,6 +458,11 @@ where
                 }
                 _ => PriceOrderErr::OtherErr(err.into()),
             })?;
+        self.capacity
+            .cycle_estimates
+            .lock()
+            .unwrap()
+            .insert(order.request.requirements.imageId, proof_res.stats.total_cycles);
 
         // If a max_mcycle_limit is configured check if the order is over that limit
         if let Some(mcycle_limit) = max_mcycle_limit {
@@ -270,32 +494,22 @@ where
             .context("Failed to fetch preflight journal")?
             .context("Failed to find preflight journal")?;
 
-        // ensure the journal is a size we are willing to submit on-chain
-        let max_journal_bytes =
-            self.config.lock_all().context("Failed to read config")?.market.max_journal_bytes;
-        if journal.len() > max_journal_bytes {
-            tracing::warn!(
-                "Order {order_id:x} journal larger than set limit ({} > {}), skipping",
-                journal.len(),
-                max_journal_bytes
-            );
-            self.db.skip_order(order_id).await.context("Failed to delete order")?;
-            return Ok(());
-        }
-
-        // Validate the predicates:
-        if !order.request.requirements.predicate.eval(journal.clone()) {
-            tracing::warn!("Order {order_id:x} predicate check failed, skipping");
-            self.db.skip_order(order_id).await.context("Failed to delete order")?;
+        let Some(callback_gas_cost) =
+            self.check_journal(order_id, order, &journal, callback_gas, at).await?
+        else {
             return Ok(());
-        }
+        };
 
         let one_mill = U256::from(1_000_000);
 
+        // The callback gas is a cost of fulfilling this order, so it is deducted from the price
+        // paid for proving
         let mcycle_price_min = (U256::from(order.request.offer.minPrice)
+            .saturating_sub(callback_gas_cost)
             / U256::from(proof_res.stats.total_cycles))
             * one_mill;
         let mcycle_price_max = (U256::from(order.request.offer.maxPrice)
+            .saturating_sub(callback_gas_cost)
             / U256::from(proof_res.stats.total_cycles))
             * one_mill;
 
@@ -331,7 +545,8 @@ where
         // TODO: Clean up and do more testing on this since its just a rough shot first draft
         else {
             let target_min_price =
-                config_min_mcycle_price * (U256::from(proof_res.stats.total_cycles)) / one_mill;
+                config_min_mcycle_price * (U256::from(proof_res.stats.total_cycles)) / one_mill
+                    + callback_gas_cost;
             tracing::debug!("Target price: {target_min_price}");
 
             let target_timestamp: u64 = order
@@ -369,6 +584,7 @@ where
         for (order_id, order) in pricing_orders {
             let self_copy = self.clone();
             tokio::spawn(async move {
+                let _permit = self_copy.capacity.preflight_permits.acquire().await;
                 if let Err(err) = self_copy.price_order(order_id, &order).await {
                     self_copy
                         .db
@@ -382,6 +598,85 @@ where
         Ok(())
     }
 
+    /// Priority of a new order in the pricing queue
+    fn pricing_priority(
+        &self,
+        order: &Order,
+        mcycle_price: U256,
+        priority_clients: &[Address],
+    ) -> PricingPriority {
+        let offer = &order.request.offer;
+        let priority_client = order
+            .request
+            .client_address()
+            .map(|client_addr| priority_clients.contains(&client_addr))
+            .unwrap_or(false);
+
+        // Without an earlier preflight of the image, assume the order needs all the cycles its
+        // max price pays for at the configured mcycle price, and rank it by its max price
+        let cycles = self
+            .capacity
+            .cycle_estimates
+            .lock()
+            .unwrap()
+            .get(&order.request.requirements.imageId)
+            .copied();
+        let max_price_per_mcycle = match cycles {
+            Some(cycles) => offer.maxPrice * U256::from(1_000_000) / U256::from(cycles.max(1)),
+            None => mcycle_price,
+        };
+
+        PricingPriority {
+            priority_client,
+            max_price_per_mcycle,
+            max_price: offer.maxPrice,
+            expiration: Reverse(offer.biddingStart + offer.lockTimeout as u64),
+        }
+    }
+
+    /// Moves the highest priority new order to pricing and returns it
+    ///
+    /// Only the orders added to the DB since the last call are read, and queued by priority.
+    async fn next_order_for_pricing(&self) -> Result<Option<(U256, Order)>> {
+        let mut queue = self.queue.lock().await;
+
+        let new_orders = self
+            .db
+            .get_new_orders_after(queue.last_seq)
+            .await
+            .context("Failed to get new orders from db")?;
+        if let Some((seq, _, _)) = new_orders.last() {
+            queue.last_seq = *seq;
+        }
+        if !new_orders.is_empty() {
+            let (mcycle_price, priority_clients) = {
+                let config = self.config.lock_all().context("Failed to read config")?;
+                (
+                    config.market.mcycle_price.wei(),
+                    config.market.priority_client_addresses.clone().unwrap_or_default(),
+                )
+            };
+            for (seq, order_id, order) in new_orders {
+                let priority = self.pricing_priority(&order, mcycle_price, &priority_clients);
+                queue.orders.push((priority, Reverse(seq), order_id));
+            }
+        }
+
+        // Orders may have left the New status since they were queued, e.g. when expired
+        while let Some(order_id) = queue.orders.peek().map(|(_, _, order_id)| *order_id) {
+            let order = self
+                .db
+                .get_order_for_pricing(order_id)
+                .await
+                .context("Failed to move order to pricing")?;
+            queue.orders.pop();
+            if let Some(order) = order {
+                return Ok(Some((order_id, order)));
+            }
+        }
+        Ok(None)
+    }
+
     /// Return the total amount of stake that is marked locally in the DB to be locked
     /// but has not yet been locked in the market contract thus has not been deducted from the account balance
     async fn pending_locked_stake(&self) -> Result<U256> {
@@ -410,11 +705,147 @@ where
         Ok(gas)
     }
 
+    /// Estimate of gas for fulfilling the order, including its callback
+    async fn estimate_gas_to_fulfill(&self, order: &Order) -> Result<u64> {
+        let fulfill_gas =
+            self.config.lock_all().context("Failed to read config")?.market.fulfill_gas_estimate;
+        Ok(fulfill_gas + callback_gas_limit(&order.request).unwrap_or(0))
+    }
+
     /// Estimate of gas for fulfilling any orders either pending lock or locked
     async fn estimate_gas_to_fulfill_pending(&self) -> Result<u64> {
-        let pending_fulfill_orders = self.db.get_orders_committed_to_fulfill_count().await?;
-        Ok((pending_fulfill_orders)
-            * self.config.lock_all().context("Failed to read config")?.market.fulfill_gas_estimate)
+        let mut gas = 0;
+        for (_, order) in self.db.get_orders_committed_to_fulfill().await?.iter() {
+            gas += self.estimate_gas_to_fulfill(order).await?;
+        }
+        Ok(gas)
+    }
+
+    /// Checks that the journal can be delivered to the requestor, skipping the order if not
+    ///
+    /// Returns the gas cost of the order callback, or None if the order was skipped.
+    async fn check_journal(
+        &self,
+        order_id: U256,
+        order: &Order,
+        journal: &[u8],
+        callback_gas: Option<u64>,
+        at: PricingAt,
+    ) -> Result<Option<U256>> {
+        // ensure the journal is a size we are willing to submit on-chain
+        let max_journal_bytes =
+            self.config.lock_all().context("Failed to read config")?.market.max_journal_bytes;
+        if journal.len() > max_journal_bytes {
+            tracing::warn!(
+                "Order {order_id:x} journal larger than set limit ({} > {}), skipping",
+                journal.len(),
+                max_journal_bytes
+            );
+            self.db.skip_order(order_id).await.context("Failed to delete order")?;
+            return Ok(None);
+        }
+
+        // Validate the predicates:
+        if !order.request.requirements.predicate.eval(journal) {
+            tracing::warn!("Order {order_id:x} predicate check failed, skipping");
+            self.db.skip_order(order_id).await.context("Failed to delete order")?;
+            return Ok(None);
+        }
+
+        // Make sure the callback will not revert with the journal we are going to deliver
+        let Some(callback_gas) = callback_gas else {
+            return Ok(Some(U256::ZERO));
+        };
+        if let Err(err) = self.simulate_callback(order, journal, callback_gas, at.block).await {
+            tracing::warn!("Order {order_id:x} callback simulation failed, skipping: {err:?}");
+            self.db.skip_order(order_id).await.context("Failed to delete order")?;
+            return Ok(None);
+        }
+        let gas_price = match at.gas_price {
+            Some(gas_price) => gas_price,
+            None => self.provider.get_gas_price().await.context("Failed to get gas price")?,
+        };
+        Ok(Some(U256::from(gas_price) * U256::from(callback_gas)))
+    }
+
+    /// Prices an order that can reuse an earlier proof of the same image and input
+    ///
+    /// No proving is needed, so the only cost of the order is fulfilling it and it is locked ASAP.
+    /// Returns false if the cached proof is no longer available on the prover.
+    async fn price_cached_order(
+        &self,
+        order_id: U256,
+        order: &Order,
+        proof_id: &str,
+        callback_gas: Option<u64>,
+        expiration: u64,
+        at: PricingAt,
+    ) -> Result<bool> {
+        let journal = match self.prover.get_journal(proof_id).await {
+            Ok(Some(journal)) => journal,
+            Ok(None) => return Ok(false),
+            Err(err) => {
+                tracing::warn!("Failed to fetch journal of cached proof {proof_id}: {err:?}");
+                return Ok(false);
+            }
+        };
+
+        let Some(callback_gas_cost) =
+            self.check_journal(order_id, order, &journal, callback_gas, at).await?
+        else {
+            return Ok(true);
+        };
+        if U256::from(order.request.offer.maxPrice) <= callback_gas_cost {
+            tracing::warn!("Removing under priced order {order_id:x}");
+            self.db.skip_order(order_id).await.context("Failed to delete order")?;
+            return Ok(true);
+        }
+
+        tracing::info!(
+            "Selecting order {order_id:x} at price {} - ASAP, reusing proof {proof_id}",
+            format_ether(U256::from(order.request.offer.minPrice))
+        );
+        self.db
+            .set_order_proof_id(order_id, proof_id)
+            .await
+            .with_context(|| format!("Failed to set order {order_id:x} proof id: {proof_id}"))?;
+        self.db
+            .set_order_lock(order_id, 0, expiration)
+            .await
+            .with_context(|| format!("Failed to set_order_lock for order {order_id:x}"))?;
+
+        Ok(true)
+    }
+
+    /// Simulate the order's callback, as called by the market once the order is fulfilled
+    ///
+    /// The seal is left empty, callbacks called by the market do not need to verify it. Simulated
+    /// at `block` if set, otherwise at the latest block.
+    async fn simulate_callback(
+        &self,
+        order: &Order,
+        journal: &[u8],
+        gas_limit: u64,
+        block: Option<u64>,
+    ) -> Result<()> {
+        let callback = IBoundlessMarketCallback::new(
+            order.request.requirements.callback.addr,
+            self.provider.clone(),
+        );
+        let mut call = callback
+            .handleProof(
+                order.request.requirements.imageId,
+                journal.to_vec().into(),
+                Default::default(),
+            )
+            .from(*self.market.instance().address())
+            .gas(gas_limit + CALLBACK_SIM_BASE_GAS + 16 * journal.len() as u64);
+        if let Some(block) = block {
+            call = call.block(block.into());
+        }
+        call.call().await.context("Callback reverted")?;
+
+        Ok(())
     }
 
     /// Estimate the total gas tokens reserved to lock and fulfill all pending orders
@@ -470,39 +901,48 @@ where
             picker_copy.find_existing_orders().await.map_err(SupervisorErr::Fault)?;
 
             loop {
-                let order_res = picker_copy
-                    .db
-                    .get_order_for_pricing()
+                // Only take an order off the queue once there is capacity to price it, so
+                // orders are always priced in priority order
+                let permit = picker_copy
+                    .capacity
+                    .preflight_permits
+                    .clone()
+                    .acquire_owned()
                     .await
-                    .map_err(|err| SupervisorErr::Recover(err.into()))?;
-
-                if let Some((order_id, order)) = order_res {
-                    let picker_clone = picker_copy.clone();
-                    // TODO: We should consider having handles for these inner tasks
-                    // but they are one-shots that self-clean up on the DB so maybe its fine?
-                    tokio::spawn(async move {
-                        if let Err(err) = picker_clone.price_order(order_id, &order).await {
-                            picker_clone
-                                .db
-                                .set_order_failure(order_id, err.to_string())
-                                .await
-                                .expect("Failed to set order failure");
-                            match err {
-                                PriceOrderErr::OtherErr(err) => {
-                                    tracing::error!("Pricing order failed: {order_id:x} {err:?}");
-                                }
-                                // Only warn on known / classified errors
-                                _ => {
-                                    tracing::warn!(
-                                        "Pricing order soft failed: {order_id:x} {err:?}"
-                                    );
-                                }
+                    .map_err(|err| SupervisorErr::Fault(err.into()))?;
+
+                let order_res =
+                    picker_copy.next_order_for_pricing().await.map_err(SupervisorErr::Recover)?;
+
+                let Some((order_id, order)) = order_res else {
+                    drop(permit);
+                    // TODO: Configuration
+                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                    continue;
+                };
+
+                let picker_clone = picker_copy.clone();
+                // TODO: We should consider having handles for these inner tasks
+                // but they are one-shots that self-clean up on the DB so maybe its fine?
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    if let Err(err) = picker_clone.price_order(order_id, &order).await {
+                        picker_clone
+                            .db
+                            .set_order_failure(order_id, err.to_string())
+                            .await
+                            .expect("Failed to set order failure");
+                        match err {
+                            PriceOrderErr::OtherErr(err) => {
+                                tracing::error!("Pricing order failed: {order_id:x} {err:?}");
+                            }
+                            // Only warn on known / classified errors
+                            _ => {
+                                tracing::warn!("Pricing order soft failed: {order_id:x} {err:?}");
                             }
                         }
-                    });
-                }
-                // TODO: Configuration
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                    }
+                });
             }
         })
     }
@@ -511,9 +951,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        chain_monitor::ChainMonitorService, db::SqliteDb, provers::MockProver, OrderStatus,
-    };
+    use crate::{chain_monitor::ChainMonitorService, db::SqliteDb, provers::MockProver};
     use alloy::{
         network::EthereumWallet,
         node_bindings::{Anvil, AnvilInstance},
@@ -521,15 +959,19 @@ mod tests {
         providers::{ext::AnvilApi, ProviderBuilder},
         signers::local::PrivateKeySigner,
     };
-    use boundless_market::contracts::{
-        test_utils::{deploy_boundless_market, deploy_hit_points},
-        Input, Offer, Predicate, PredicateType, ProofRequest, Requirements,
+    use boundless_market::{
+        contracts::{
+            test_utils::{deploy_boundless_market, deploy_hit_points},
+            Callback, Input, Offer, Predicate, PredicateType, ProofRequest, RequestId,
+            Requirements,
+        },
+        input::{InputBuilder, InputChunk, InputManifest},
     };
     use chrono::Utc;
     use guest_assessor::ASSESSOR_GUEST_ID;
     use guest_util::{ECHO_ELF, ECHO_ID};
     use httpmock::prelude::*;
-    use risc0_zkvm::sha::Digest;
+    use risc0_zkvm::{sha::Digest, FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
     use tracing_test::traced_test;
 
     /// Reusable context for testing the order picker
@@ -600,6 +1042,10 @@ mod tests {
                     client_sig: Bytes::new(),
                     lock_price: None,
                     error_msg: None,
+                    proof_failures: vec![],
+                    dry_run: false,
+                    input_digest: None,
+                    assumption_ids: vec![],
                 },
             )
         }
@@ -610,6 +1056,7 @@ mod tests {
         initial_signer_eth: Option<i32>,
         initial_hp: Option<U256>,
         config: Option<ConfigLock>,
+        prover: Option<ProverObj>,
     }
 
     impl TestCtxBuilder {
@@ -623,6 +1070,9 @@ mod tests {
         pub fn with_config(self, config: ConfigLock) -> Self {
             Self { config: Some(config), ..self }
         }
+        pub fn with_prover(self, prover: ProverObj) -> Self {
+            Self { prover: Some(prover), ..self }
+        }
         pub async fn build(self) -> TestCtx<impl Provider + WalletProvider + Clone + 'static> {
             let anvil = Anvil::new()
                 .args(["--balance", &format!("{}", self.initial_signer_eth.unwrap_or(10000))])
@@ -676,7 +1126,7 @@ mod tests {
 
             let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
             let config = self.config.unwrap_or_default();
-            let prover: ProverObj = Arc::new(MockProver::default());
+            let prover = self.prover.unwrap_or_else(|| Arc::new(MockProver::default()));
             let chain_monitor = Arc::new(ChainMonitorService::new(provider.clone()).await.unwrap());
             tokio::spawn(chain_monitor.spawn());
 
@@ -692,7 +1142,7 @@ mod tests {
     async fn price_order() {
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
         }
         let ctx = TestCtxBuilder::default().with_config(config).build().await;
 
@@ -718,7 +1168,7 @@ mod tests {
     async fn skip_bad_predicate() {
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
         }
         let ctx = TestCtxBuilder::default().with_config(config).build().await;
 
@@ -749,7 +1199,7 @@ mod tests {
     async fn skip_unallowed_addr() {
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
             config.load_write().unwrap().market.allow_client_addresses = Some(vec![Address::ZERO]);
         }
         let ctx = TestCtxBuilder::default().with_config(config).build().await;
@@ -777,7 +1227,7 @@ mod tests {
     async fn resume_order_pricing() {
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
         }
         let ctx = TestCtxBuilder::default().with_config(config).build().await;
 
@@ -821,8 +1271,8 @@ mod tests {
 
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
-            config.load_write().unwrap().market.max_stake = "10".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+            config.load_write().unwrap().market.max_stake = "10".parse().unwrap();
         }
 
         let ctx = TestCtxBuilder::default()
@@ -852,7 +1302,7 @@ mod tests {
         let lockin_gas = 123_456;
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
             config.load_write().unwrap().market.lockin_gas_estimate = lockin_gas;
         }
 
@@ -876,7 +1326,7 @@ mod tests {
         let fulfill_gas = 123_456;
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
             config.load_write().unwrap().market.fulfill_gas_estimate = fulfill_gas;
         }
 
@@ -909,7 +1359,7 @@ mod tests {
         let fulfill_gas = 50000;
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
             config.load_write().unwrap().market.fulfill_gas_estimate = fulfill_gas;
             config.load_write().unwrap().market.lockin_gas_estimate = lockin_gas;
         }
@@ -945,8 +1395,8 @@ mod tests {
 
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
-            config.load_write().unwrap().market.max_stake = "10".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+            config.load_write().unwrap().market.max_stake = "10".parse().unwrap();
         }
 
         let ctx = TestCtxBuilder::default()
@@ -984,7 +1434,7 @@ mod tests {
         // set this by testing a very small limit (1 byte)
         let config = ConfigLock::default();
         {
-            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
             config.load_write().unwrap().market.max_journal_bytes = 1;
         }
         let lockin_stake = U256::from(10);
@@ -1005,4 +1455,370 @@ mod tests {
         assert_eq!(ctx.db.get_order(order_id).await.unwrap().unwrap().status, OrderStatus::Skipped);
         assert!(logs_contain("journal larger than set limit"));
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn pricing_queue_priority() {
+        let priority_client = Address::with_last_byte(1);
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+            config.load_write().unwrap().market.priority_client_addresses =
+                Some(vec![priority_client]);
+        }
+        let ctx = TestCtxBuilder::default().with_config(config).build().await;
+        let image_id = B256::from_slice(Digest::from(ECHO_ID).as_bytes());
+        ctx.picker.capacity.cycle_estimates.lock().unwrap().insert(image_id, 1_000_000);
+
+        let (_, mut low_value) = ctx.next_order(U256::from(1), U256::from(1000), U256::ZERO).await;
+        low_value.status = OrderStatus::New;
+        let mut high_value = low_value.clone();
+        high_value.request.offer.maxPrice = U256::from(400000000000u64);
+        // Same value as the low value order, but with a closer deadline
+        let mut urgent = low_value.clone();
+        urgent.request.offer.lockTimeout -= 100;
+        let mut from_priority_client = low_value.clone();
+        from_priority_client.request.id = RequestId::u256(priority_client, 0);
+
+        let orders = [
+            (U256::from(1), low_value),
+            (U256::from(2), high_value),
+            (U256::from(3), urgent),
+            (U256::from(4), from_priority_client),
+        ];
+        for (order_id, order) in orders.iter() {
+            ctx.db.add_order(*order_id, order.clone()).await.unwrap();
+        }
+
+        for expected_id in [4, 2, 3, 1] {
+            let (order_id, order) = ctx.picker.next_order_for_pricing().await.unwrap().unwrap();
+            assert_eq!(order_id, U256::from(expected_id));
+            assert_eq!(order.status, OrderStatus::Pricing);
+        }
+        assert!(ctx.picker.next_order_for_pricing().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn pricing_queue_without_estimate() {
+        let ctx = TestCtxBuilder::default().build().await;
+
+        // No cycle estimate of the image, so orders are ranked by max price
+        let (_, mut low_value) = ctx.next_order(U256::from(1), U256::from(1000), U256::ZERO).await;
+        low_value.status = OrderStatus::New;
+        let mut high_value = low_value.clone();
+        high_value.request.offer.maxPrice = U256::from(2000);
+
+        ctx.db.add_order(U256::from(1), low_value).await.unwrap();
+        let (order_id, _) = ctx.picker.next_order_for_pricing().await.unwrap().unwrap();
+        assert_eq!(order_id, U256::from(1));
+
+        // Orders added after the queue was read are still picked up
+        ctx.db.add_order(U256::from(2), high_value.clone()).await.unwrap();
+        let mut lowest_value = high_value.clone();
+        lowest_value.request.offer.maxPrice = U256::from(10);
+        ctx.db.add_order(U256::from(3), lowest_value).await.unwrap();
+        ctx.db.add_order(U256::from(4), high_value).await.unwrap();
+        // Skipped orders are dropped from the queue
+        ctx.db.skip_order(U256::from(2)).await.unwrap();
+
+        for expected_id in [4, 3] {
+            let (order_id, _) = ctx.picker.next_order_for_pricing().await.unwrap().unwrap();
+            assert_eq!(order_id, U256::from(expected_id));
+        }
+        assert!(ctx.picker.next_order_for_pricing().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn callback_gas_and_simulation() {
+        let fulfill_gas = 100_000;
+        let callback_gas = 50_000;
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+            config.load_write().unwrap().market.fulfill_gas_estimate = fulfill_gas;
+        }
+        let ctx = TestCtxBuilder::default().with_config(config.clone()).build().await;
+
+        // A callback that always succeeds, and one that always reverts
+        let ok_callback = Address::repeat_byte(0xaa);
+        let revert_callback = Address::repeat_byte(0xbb);
+        ctx.provider.anvil_set_code(ok_callback, Bytes::from_static(&[0x00])).await.unwrap();
+        // PUSH1 0 PUSH1 0 REVERT
+        ctx.provider
+            .anvil_set_code(revert_callback, Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xfd]))
+            .await
+            .unwrap();
+
+        // Prices need to cover the callback gas on top of proving
+        let min_price = U256::from(1_000_000_000_000_000u64);
+        let max_price = U256::from(2_000_000_000_000_000u64);
+        let orders = [
+            (ok_callback, callback_gas),
+            (revert_callback, callback_gas),
+            (ok_callback, 20_000_000),
+        ];
+        for (index, (addr, gas_limit)) in orders.into_iter().enumerate() {
+            let (_, mut order) = ctx.next_order(min_price, max_price, U256::ZERO).await;
+            order.request.requirements.callback =
+                Callback::default().with_addr(addr).with_gas_limit(gas_limit);
+            ctx.db.add_order(U256::from(index), order.clone()).await.unwrap();
+            if index == 2 {
+                config.load_write().unwrap().market.max_callback_gas = Some(1_000_000);
+            }
+            ctx.picker.price_order(U256::from(index), &order).await.unwrap();
+        }
+
+        let status = |order: Option<Order>| order.unwrap().status;
+        assert_eq!(status(ctx.db.get_order(U256::from(0)).await.unwrap()), OrderStatus::Locking);
+        assert_eq!(status(ctx.db.get_order(U256::from(1)).await.unwrap()), OrderStatus::Skipped);
+        assert!(logs_contain("callback simulation failed"));
+        assert_eq!(status(ctx.db.get_order(U256::from(2)).await.unwrap()), OrderStatus::Skipped);
+        assert!(logs_contain("exceeds max_callback_gas"));
+
+        // The callback gas is reserved on top of the fulfillment gas
+        assert_eq!(
+            ctx.picker.estimate_gas_to_fulfill_pending().await.unwrap(),
+            fulfill_gas + callback_gas
+        );
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn price_order_at_block_and_gas_price() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+        }
+        let ctx = TestCtxBuilder::default().with_config(config).build().await;
+
+        // The callback reverted up to `block`, and succeeds after it
+        let callback = Address::repeat_byte(0xaa);
+        ctx.provider
+            .anvil_set_code(callback, Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xfd]))
+            .await
+            .unwrap();
+        ctx.provider.anvil_mine(Some(1), None).await.unwrap();
+        let block = ctx.provider.get_block_number().await.unwrap();
+        ctx.provider.anvil_set_code(callback, Bytes::from_static(&[0x00])).await.unwrap();
+        ctx.provider.anvil_mine(Some(1), None).await.unwrap();
+
+        let min_price = U256::from(1_000_000_000_000_000u64);
+        let max_price = U256::from(2_000_000_000_000_000u64);
+        let ats = [
+            PricingAt { now: now_timestamp(), gas_price: None, block: Some(block) },
+            PricingAt { now: now_timestamp(), gas_price: None, block: None },
+            // The callback alone costs more than the max price at this gas price
+            PricingAt { now: now_timestamp(), gas_price: Some(100_000_000_000), block: None },
+        ];
+        for (index, at) in ats.into_iter().enumerate() {
+            let (_, mut order) = ctx.next_order(min_price, max_price, U256::ZERO).await;
+            order.request.requirements.callback =
+                Callback::default().with_addr(callback).with_gas_limit(50_000);
+            ctx.db.add_order(U256::from(index), order.clone()).await.unwrap();
+            ctx.picker.price_order_at(U256::from(index), &order, at).await.unwrap();
+        }
+
+        let status = |order: Option<Order>| order.unwrap().status;
+        assert_eq!(status(ctx.db.get_order(U256::from(0)).await.unwrap()), OrderStatus::Skipped);
+        assert!(logs_contain("callback simulation failed"));
+        assert_eq!(status(ctx.db.get_order(U256::from(1)).await.unwrap()), OrderStatus::Locking);
+        assert_eq!(status(ctx.db.get_order(U256::from(2)).await.unwrap()), OrderStatus::Skipped);
+        assert!(logs_contain("Removing under priced order"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn reuse_cached_proof() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+        }
+        let ctx = TestCtxBuilder::default().with_config(config).build().await;
+
+        let min_price = U256::from(200000000000u64);
+        let max_price = U256::from(400000000000u64);
+
+        let (order_id, order) = ctx.next_order(min_price, max_price, U256::ZERO).await;
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        ctx.picker.price_order(order_id, &order).await.unwrap();
+
+        // Prove the first order and index its proof like the proving service does
+        let db_order = ctx.db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.proof_id, None);
+        let input_digest = db_order.input_digest.unwrap();
+        let proof_id = ctx
+            .picker
+            .prover
+            .prove_stark(&db_order.image_id.unwrap(), &db_order.input_id.unwrap(), vec![])
+            .await
+            .unwrap();
+        let image_id = order.request.requirements.imageId;
+        ctx.db.add_cached_proof(image_id, input_digest, &proof_id).await.unwrap();
+
+        // A resubmission of the same image and input is locked with the cached proof
+        let (order_id, order) = ctx.next_order(min_price, max_price, U256::ZERO).await;
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        ctx.picker.price_order(order_id, &order).await.unwrap();
+
+        let db_order = ctx.db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Locking);
+        assert_eq!(db_order.target_timestamp, Some(0));
+        assert_eq!(db_order.proof_id, Some(proof_id));
+        assert!(logs_contain("reusing proof"));
+
+        // Cached proofs that are gone from the prover are priced from scratch
+        ctx.db.add_cached_proof(image_id, input_digest, "missing").await.unwrap();
+        let (order_id, order) = ctx.next_order(min_price, max_price, U256::ZERO).await;
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        ctx.picker.price_order(order_id, &order).await.unwrap();
+
+        let db_order = ctx.db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Locking);
+        assert_eq!(db_order.proof_id, None);
+    }
+    #[tokio::test]
+    #[traced_test]
+    async fn price_order_guest_env() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+        }
+        let ctx = TestCtxBuilder::default().with_config(config).build().await;
+
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt::new(ReceiptClaim::ok(Digest::ZERO, vec![]))),
+            vec![],
+        );
+        let _receipt_mock = ctx.image_server.mock(|when, then| {
+            when.method(GET).path("/receipt");
+            then.status(200).body(bincode::serialize(&receipt).unwrap());
+        });
+        let receipt_url = format!("http://{}/receipt", ctx.image_server.address());
+
+        // Assumption receipts are uploaded to the prover and used in preflight
+        let (order_id, mut order) = ctx
+            .next_order(U256::from(200000000000u64), U256::from(400000000000u64), U256::ZERO)
+            .await;
+        order.request.input = Input::builder()
+            .write_slice(&[0x41, 0x41, 0x41, 0x41])
+            .with_assumption_url(&receipt_url)
+            .build_inline()
+            .unwrap();
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        ctx.picker.price_order(order_id, &order).await.unwrap();
+
+        let db_order = ctx.db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Locking);
+        assert_eq!(db_order.assumption_ids.len(), 1);
+
+        // Env vars and args are provided to the guest in preflight
+        let (order_id, mut order) = ctx
+            .next_order(U256::from(200000000000u64), U256::from(400000000000u64), U256::ZERO)
+            .await;
+        order.request.input = Input::builder()
+            .write_slice(&[0x41, 0x41, 0x41, 0x41])
+            .with_env_var("KEY", "value")
+            .with_args(["arg"])
+            .build_inline()
+            .unwrap();
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        ctx.picker.price_order(order_id, &order).await.unwrap();
+        let db_order = ctx.db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Locking);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn price_order_unsupported_guest_env() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+        }
+        let ctx = TestCtxBuilder::default()
+            .with_config(config)
+            .with_prover(Arc::new(MockProver::stdin_only()))
+            .build()
+            .await;
+
+        // Orders the prover backend cannot prove as requested are skipped
+        let (order_id, mut order) = ctx
+            .next_order(U256::from(200000000000u64), U256::from(400000000000u64), U256::ZERO)
+            .await;
+        order.request.input = Input::builder()
+            .write_slice(&[0x41, 0x41, 0x41, 0x41])
+            .with_env_var("KEY", "value")
+            .build_inline()
+            .unwrap();
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        ctx.picker.price_order(order_id, &order).await.unwrap();
+
+        let db_order = ctx.db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Skipped);
+        assert!(logs_contain("which the prover backend cannot provide"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn price_order_chunked_input() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".parse().unwrap();
+        }
+        let ctx = TestCtxBuilder::default().with_config(config.clone()).build().await;
+
+        let encoded = InputBuilder::new()
+            .write_slice(&[0x41, 0x41, 0x41, 0x41])
+            .build_env()
+            .unwrap()
+            .encode_compressed()
+            .unwrap();
+        let mut chunks = vec![];
+        for (i, chunk) in encoded.chunks(8).enumerate() {
+            let path = format!("/chunk/{i}");
+            ctx.image_server.mock(|when, then| {
+                when.method(GET).path(path.clone());
+                then.status(200).body(chunk);
+            });
+            chunks.push(InputChunk::new(
+                format!("http://{}{path}", ctx.image_server.address()),
+                chunk,
+            ));
+        }
+        let manifest = InputManifest::new(chunks);
+
+        // Chunks are fetched, checked and decompressed before upload to the prover
+        let (order_id, mut order) = ctx
+            .next_order(U256::from(200000000000u64), U256::from(400000000000u64), U256::ZERO)
+            .await;
+        order.request.input = Input::inline(manifest.encode().unwrap());
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        ctx.picker.price_order(order_id, &order).await.unwrap();
+
+        let db_order = ctx.db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Locking);
+
+        // A chunk that does not match its digest fails the input fetch
+        let mut bad_manifest = manifest.clone();
+        bad_manifest.chunks.swap(0, 1);
+        let (order_id, mut order) = ctx
+            .next_order(U256::from(200000000000u64), U256::from(400000000000u64), U256::ZERO)
+            .await;
+        order.request.input = Input::inline(bad_manifest.encode().unwrap());
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        let res = ctx.picker.price_order(order_id, &order).await;
+        assert!(matches!(res, Err(PriceOrderErr::FetchInputErr(_))));
+
+        // Chunks are not downloaded past the max input size
+        config.load_write().unwrap().market.max_input_size = encoded.len() - 1;
+        let (order_id, mut order) = ctx
+            .next_order(U256::from(200000000000u64), U256::from(400000000000u64), U256::ZERO)
+            .await;
+        order.request.input = Input::inline(manifest.encode().unwrap());
+        ctx.db.add_order(order_id, order.clone()).await.unwrap();
+        let res = ctx.picker.price_order(order_id, &order).await;
+        assert!(matches!(res, Err(PriceOrderErr::FetchInputErr(err))
+            if err.to_string().contains("exceed the max input size")));
+    }
 }
@@ -3,7 +3,7 @@
 // All rights reserved.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     sync::{Arc, Mutex},
     time::Instant,
 };
@@ -13,7 +13,7 @@ use bonsai_sdk::{
     non_blocking::{Client as BonsaiClient, SessionId, SnarkId},
     SdkErr,
 };
-use boundless_market::input::InputBuilder;
+use boundless_market::input::{GuestEnv, InputBuilder};
 use risc0_zkvm::{
     compute_image_id, sha::Digestible, FakeReceipt, InnerReceipt, MaybePruned, Receipt,
     ReceiptClaim,
@@ -59,6 +59,16 @@ pub enum ProverError {
 
     #[error("proof status expired retry count")]
     StatusFailure,
+
+    #[error("Prover backend cannot provide env vars or args to the guest")]
+    UnsupportedGuestEnv,
+}
+
+impl ProverError {
+    /// Whether the failure came from the backend itself, so the same request may succeed if retried
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::BonsaiErr(_) | Self::MissingStatus | Self::StatusFailure)
+    }
 }
 
 #[derive(Clone)]
@@ -76,6 +86,16 @@ pub fn encode_input(input: &impl serde::Serialize) -> Result<Vec<u8>, anyhow::Er
 #[async_trait]
 pub trait Prover {
     async fn upload_input(&self, input: Vec<u8>) -> Result<String, ProverError>;
+    /// Upload a guest env, returning an input ID that provides its stdin, env vars and args
+    ///
+    /// Backends that only provide stdin to the guest reject envs with env vars or args. The
+    /// assumptions of the env are uploaded separately, see [Prover::upload_receipt].
+    async fn upload_env(&self, env: GuestEnv) -> Result<String, ProverError> {
+        if !env.env_vars.is_empty() || !env.args.is_empty() {
+            return Err(ProverError::UnsupportedGuestEnv);
+        }
+        self.upload_input(env.stdin).await
+    }
     async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError>;
     async fn preflight(
         &self,
@@ -102,6 +122,8 @@ pub trait Prover {
     async fn get_journal(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError>;
     async fn compress(&self, proof_id: &str) -> Result<String, ProverError>;
     async fn get_compressed_receipt(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError>;
+    /// Import a receipt proven elsewhere, returning an ID usable like a proof ID from this backend
+    async fn upload_receipt(&self, receipt: Receipt) -> Result<String, ProverError>;
 }
 
 pub type ProverObj = Arc<dyn Prover + Send + Sync>;
@@ -318,17 +340,36 @@ impl Prover for Bonsai {
 
         Ok(Some(receipt_buf))
     }
+
+    async fn upload_receipt(&self, receipt: Receipt) -> Result<String, ProverError> {
+        Ok(self.client.upload_receipt(bincode::serialize(&receipt)?).await?)
+    }
+}
+
+/// Input uploaded to the [MockProver]
+#[derive(Clone, Default)]
+struct MockInput {
+    stdin: Vec<u8>,
+    env_vars: BTreeMap<String, String>,
+    args: Vec<String>,
 }
 
 #[derive(Default)]
 pub struct MockProver {
     images: Mutex<HashMap<String, Vec<u8>>>,
-    inputs: Mutex<HashMap<String, Vec<u8>>>,
+    inputs: Mutex<HashMap<String, MockInput>>,
     starks: Mutex<HashMap<String, (ProofResult, Receipt)>>,
     snarks: Mutex<HashMap<String, Receipt>>,
+    /// Reject env vars and args, like the Bonsai and Bento backends
+    stdin_only: bool,
 }
 
 impl MockProver {
+    /// Mock of a backend that only provides stdin and assumptions to the guest
+    pub fn stdin_only() -> Self {
+        Self { stdin_only: true, ..Default::default() }
+    }
+
     fn mock_prove_stark(
         &self,
         image_id: &str,
@@ -352,7 +393,9 @@ impl MockProver {
             .clone();
 
         let mut env = ExecutorEnv::builder();
-        env.write_slice(&input);
+        env.write_slice(&input.stdin);
+        env.env_vars(input.env_vars.into_iter().collect());
+        env.args(&input.args);
         env.session_limit(executor_limit);
 
         for assumption_id in assumptions.iter() {
@@ -374,7 +417,9 @@ impl MockProver {
         let elapsed = Instant::now() - start;
 
         let image_id = compute_image_id(&image).unwrap();
-        let session = default_executor().execute(env, &image).unwrap();
+        let session = default_executor()
+            .execute(env, &image)
+            .map_err(|err| ProverError::ProvingFailed(format!("{err:?}")))?;
         let id = Uuid::new_v4().to_string();
 
         let receipt = Receipt::new(
@@ -408,6 +453,19 @@ impl MockProver {
 impl Prover for MockProver {
     async fn upload_input(&self, input: Vec<u8>) -> Result<String, ProverError> {
         let id = Uuid::new_v4().to_string();
+        self.inputs
+            .lock()
+            .unwrap()
+            .insert(id.clone(), MockInput { stdin: input, ..Default::default() });
+        Ok(id)
+    }
+
+    async fn upload_env(&self, env: GuestEnv) -> Result<String, ProverError> {
+        if self.stdin_only && (!env.env_vars.is_empty() || !env.args.is_empty()) {
+            return Err(ProverError::UnsupportedGuestEnv);
+        }
+        let id = Uuid::new_v4().to_string();
+        let input = MockInput { stdin: env.stdin, env_vars: env.env_vars, args: env.args };
         self.inputs.lock().unwrap().insert(id.clone(), input);
         Ok(id)
     }
@@ -489,4 +547,20 @@ impl Prover for MockProver {
 
         Ok(Some(bincode::serialize(&res).unwrap()))
     }
+
+    async fn upload_receipt(&self, receipt: Receipt) -> Result<String, ProverError> {
+        let id = Uuid::new_v4().to_string();
+        let proof_res = ProofResult {
+            id: id.clone(),
+            stats: ExecutorResp {
+                assumption_count: 0,
+                segments: 0,
+                user_cycles: 0,
+                total_cycles: 0,
+            },
+            elapsed_time: 0.0,
+        };
+        self.starks.lock().unwrap().insert(id.clone(), (proof_res, receipt));
+        Ok(id)
+    }
 }
@@ -5,7 +5,8 @@
 use crate::{
     config::ConfigLock,
     db::DbObj,
-    provers::ProverObj,
+    now_timestamp,
+    provers::{ProverError, ProverObj},
     task::{RetryRes, RetryTask, SupervisorErr},
     Order,
 };
@@ -16,12 +17,18 @@ use anyhow::{Context, Result};
 pub struct ProvingService {
     db: DbObj,
     prover: ProverObj,
+    fallback_prover: Option<ProverObj>,
     config: ConfigLock,
 }
 
 impl ProvingService {
     pub async fn new(db: DbObj, prover: ProverObj, config: ConfigLock) -> Result<Self> {
-        Ok(Self { db, prover, config })
+        Ok(Self { db, prover, fallback_prover: None, config })
+    }
+
+    /// Backend that proofs are re-routed to after repeated failures on the primary prover
+    pub fn with_fallback_prover(self, fallback_prover: Option<ProverObj>) -> Self {
+        Self { fallback_prover, ..self }
     }
 
     pub async fn monitor_proof(&self, order_id: U256, proof_id: String) -> Result<()> {
@@ -43,9 +50,13 @@ impl ProvingService {
     }
 
     pub async fn prove_order(&self, order_id: U256, order: Order) -> Result<()> {
-        let (max_file_size, fetch_retries) = {
+        let (max_file_size, max_input_size, fetch_retries) = {
             let config = self.config.lock_all().context("Failed to read config")?;
-            (config.market.max_file_size, config.market.max_fetch_retries)
+            (
+                config.market.max_file_size,
+                config.market.max_input_size,
+                config.market.max_fetch_retries,
+            )
         };
 
         // If the ID's are not present then upload them now
@@ -56,18 +67,27 @@ impl ProvingService {
                 .await
                 .context("Failed to upload image")?,
         };
-        let input_id = match order.input_id.as_ref() {
-            Some(val) => val.clone(),
-            None => crate::upload_input_uri(&self.prover, &order, max_file_size, fetch_retries)
+        let (input_id, assumption_ids) = match order.input_id.as_ref() {
+            Some(val) => (val.clone(), order.assumption_ids.clone()),
+            None => {
+                let input = crate::upload_input_uri(
+                    &self.prover,
+                    &order,
+                    max_file_size,
+                    max_input_size,
+                    fetch_retries,
+                )
                 .await
-                .context("Failed to upload input")?,
+                .context("Failed to upload input")?;
+                (input.input_id, input.assumption_ids)
+            }
         };
 
         tracing::info!("Proving order {order_id:x}");
 
         let proof_id = self
             .prover
-            .prove_stark(&image_id, &input_id, /* TODO assumptions */ vec![])
+            .prove_stark(&image_id, &input_id, assumption_ids)
             .await
             .context("Failed to prove customer proof STARK order")?;
 
@@ -81,6 +101,173 @@ impl ProvingService {
         Ok(())
     }
 
+    /// Proves the order on the fallback backend and imports the receipt into the primary prover
+    ///
+    /// The aggregator and submitter only reference proofs by ID on the primary prover, so the
+    /// fallback receipt is uploaded there before the order moves on to aggregation.
+    async fn prove_on_fallback(
+        &self,
+        fallback: &ProverObj,
+        order_id: U256,
+        order: &Order,
+    ) -> Result<()> {
+        let (max_file_size, max_input_size, fetch_retries) = {
+            let config = self.config.lock_all().context("Failed to read config")?;
+            (
+                config.market.max_file_size,
+                config.market.max_input_size,
+                config.market.max_fetch_retries,
+            )
+        };
+
+        // Image and input IDs on the order belong to the primary prover
+        let image_id = crate::upload_image_uri(fallback, order, max_file_size, fetch_retries)
+            .await
+            .context("Failed to upload image to fallback prover")?;
+        let input =
+            crate::upload_input_uri(fallback, order, max_file_size, max_input_size, fetch_retries)
+                .await
+                .context("Failed to upload input to fallback prover")?;
+
+        tracing::info!("Proving order {order_id:x} on fallback prover");
+
+        let proof_res = fallback
+            .prove_and_monitor_stark(&image_id, &input.input_id, input.assumption_ids)
+            .await
+            .context("Failed to prove customer proof STARK order on fallback prover")?;
+        let receipt = fallback
+            .get_receipt(&proof_res.id)
+            .await
+            .context("Failed to get fallback proof receipt")?
+            .context("Fallback proof receipt missing")?;
+
+        let proof_id = self
+            .prover
+            .upload_receipt(receipt)
+            .await
+            .context("Failed to import fallback receipt into primary prover")?;
+
+        self.db
+            .set_order_proof_id(order_id, &proof_id)
+            .await
+            .with_context(|| format!("Failed to set order {order_id:x} proof id: {}", proof_id))?;
+        self.db
+            .set_aggregation_status(order_id)
+            .await
+            .with_context(|| format!("Failed to set the DB record to aggregation {order_id:x}"))?;
+
+        tracing::info!(
+            "Customer Proof complete on fallback prover, order_id: {order_id:x} cycles: {} time: {}",
+            proof_res.stats.total_cycles,
+            proof_res.elapsed_time,
+        );
+
+        Ok(())
+    }
+
+    /// Indexes the finished proof of an order so later orders with the same input can reuse it
+    async fn cache_proof(&self, order_id: U256, order: &Order) {
+        let Some(input_digest) = order.input_digest else {
+            return;
+        };
+        let proof_id = match self.db.get_order(order_id).await {
+            Ok(Some(Order { proof_id: Some(proof_id), .. })) => proof_id,
+            Ok(_) => {
+                tracing::warn!("Order {order_id:x} missing proof_id, not caching proof");
+                return;
+            }
+            Err(err) => {
+                tracing::warn!("Failed to get order {order_id:x} to cache its proof: {err:?}");
+                return;
+            }
+        };
+        if let Err(err) = self
+            .db
+            .add_cached_proof(order.request.requirements.imageId, input_digest, &proof_id)
+            .await
+        {
+            tracing::warn!("Failed to cache proof {proof_id} of order {order_id:x}: {err:?}");
+        }
+    }
+
+    /// Whether a failed proof attempt is worth another try
+    ///
+    /// Backend errors are retried in place; a failed proof is only retried if it can still be
+    /// re-routed to the fallback backend.
+    fn is_retryable(err: &anyhow::Error, can_escalate: bool) -> bool {
+        match err.chain().find_map(|inner| inner.downcast_ref::<ProverError>()) {
+            Some(ProverError::ProvingFailed(_)) => can_escalate,
+            Some(prover_err) => prover_err.is_transient(),
+            None => false,
+        }
+    }
+
+    /// Proves an order, retrying failed attempts while there is time left before the lock deadline
+    ///
+    /// If `proof_id` is set the first attempt resumes monitoring that proof instead of starting a
+    /// new one. Once `proof_escalate_after` attempts have failed, the remaining attempts run on
+    /// the fallback prover if one is configured. Every failed attempt is recorded on the order.
+    pub async fn prove_with_retries(
+        &self,
+        order_id: U256,
+        order: Order,
+        mut proof_id: Option<String>,
+    ) -> Result<()> {
+        let (retry_count, retry_sleep_ms, deadline_buffer_secs, escalate_after) = {
+            let config = self.config.lock_all().context("Failed to read config")?;
+            (
+                config.prover.proof_retry_count,
+                config.prover.proof_retry_sleep_ms,
+                config.prover.proof_retry_deadline_buffer_secs,
+                config.prover.proof_escalate_after,
+            )
+        };
+
+        let mut attempt: u32 = 0;
+        loop {
+            let fallback = self.fallback_prover.as_ref().filter(|_| attempt >= escalate_after);
+            let res = match (fallback, proof_id.take()) {
+                (Some(fallback), _) => self.prove_on_fallback(fallback, order_id, &order).await,
+                (None, Some(proof_id)) => self.monitor_proof(order_id, proof_id).await,
+                (None, None) => self.prove_order(order_id, order.clone()).await,
+            };
+            let Err(err) = res else {
+                self.cache_proof(order_id, &order).await;
+                return Ok(());
+            };
+            attempt += 1;
+
+            let backend = if fallback.is_some() { "fallback" } else { "primary" };
+            tracing::warn!(
+                "Proof attempt {attempt} for order {order_id:x} failed on {backend} prover: {err:?}"
+            );
+            if let Err(db_err) = self
+                .db
+                .add_proof_failure(order_id, format!("attempt {attempt} ({backend}): {err:#}"))
+                .await
+            {
+                tracing::error!("Failed to record order {order_id:x} proof failure: {db_err:?}");
+            }
+
+            let can_escalate = self.fallback_prover.is_some() && fallback.is_none();
+            if !Self::is_retryable(&err, can_escalate) {
+                return Err(err);
+            }
+            if attempt > retry_count {
+                return Err(err.context(format!("Proof failed after {attempt} attempts")));
+            }
+            if let Some(expire_timestamp) = order.expire_timestamp {
+                if now_timestamp() + deadline_buffer_secs >= expire_timestamp {
+                    return Err(
+                        err.context("Not enough time left before the lock deadline to retry")
+                    );
+                }
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_millis(retry_sleep_ms)).await;
+        }
+    }
+
     pub async fn find_and_monitor_proofs(&self) -> Result<()> {
         let current_proofs = self
             .db
@@ -92,7 +279,7 @@ impl ProvingService {
         tracing::info!("Found {} proofs currently proving", current_proofs.len());
         for (order_id, order) in current_proofs {
             let prove_serv = self.clone();
-            let Some(proof_id) = order.proof_id else {
+            let Some(proof_id) = order.proof_id.clone() else {
                 tracing::error!("Order in status Proving missing proof_id: {order_id:x}");
                 if let Err(inner_err) = prove_serv
                     .db
@@ -107,7 +294,7 @@ impl ProvingService {
             // They should all be fail-able without triggering a larger failure so it should be
             // fine.
             tokio::spawn(async move {
-                match prove_serv.monitor_proof(order_id, proof_id).await {
+                match prove_serv.prove_with_retries(order_id, order, Some(proof_id)).await {
                     Ok(_) => tracing::info!("Successfully complete order proof {order_id:x}"),
                     Err(err) => {
                         tracing::error!("FATAL: Order failed to prove: {err:?}");
@@ -153,8 +340,13 @@ impl RetryTask for ProvingService {
 
                 if let Some((order_id, order)) = order_res {
                     let prov_serv = proving_service_copy.clone();
+                    // Orders priced against a cached proof reuse it instead of proving again
+                    let proof_id = order.proof_id.clone();
+                    if let Some(proof_id) = proof_id.as_ref() {
+                        tracing::info!("Reusing proof {proof_id} for order {order_id:x}");
+                    }
                     tokio::spawn(async move {
-                        match prov_serv.prove_order(order_id, order).await {
+                        match prov_serv.prove_with_retries(order_id, order, proof_id).await {
                             Ok(_) => {
                                 tracing::info!("Successfully complete order proof {order_id:x}")
                             }
@@ -186,20 +378,166 @@ mod tests {
     use super::*;
     use crate::{
         db::SqliteDb,
-        now_timestamp,
-        provers::{encode_input, MockProver},
+        provers::{encode_input, MockProver, ProofResult, Prover},
         OrderStatus,
     };
-    use alloy::primitives::{Bytes, U256};
-    use boundless_market::contracts::{
-        Input, InputType, Offer, Predicate, PredicateType, ProofRequest, Requirements,
+    use alloy::primitives::{Address, Bytes, B256, U256};
+    use async_trait::async_trait;
+    use boundless_market::{
+        contracts::{
+            Input, InputType, Offer, Predicate, PredicateType, ProofRequest, Requirements,
+        },
+        input::GuestEnv,
     };
     use chrono::Utc;
     use guest_util::{ECHO_ELF, ECHO_ID};
-    use risc0_zkvm::sha::Digest;
-    use std::sync::Arc;
+    use httpmock::prelude::*;
+    use risc0_zkvm::{sha::Digest, Receipt};
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
     use tracing_test::traced_test;
 
+    /// Mock prover whose first `failures` STARK proofs fail with a backend error
+    struct FlakyProver {
+        inner: MockProver,
+        failures: AtomicU32,
+    }
+
+    impl FlakyProver {
+        fn new(failures: u32) -> Self {
+            Self { inner: MockProver::default(), failures: AtomicU32::new(failures) }
+        }
+    }
+
+    #[async_trait]
+    impl Prover for FlakyProver {
+        async fn upload_input(&self, input: Vec<u8>) -> Result<String, ProverError> {
+            self.inner.upload_input(input).await
+        }
+        async fn upload_env(&self, env: GuestEnv) -> Result<String, ProverError> {
+            self.inner.upload_env(env).await
+        }
+        async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError> {
+            self.inner.upload_image(image_id, image).await
+        }
+        async fn preflight(
+            &self,
+            image_id: &str,
+            input_id: &str,
+            assumptions: Vec<String>,
+            executor_limit: Option<u64>,
+        ) -> Result<ProofResult, ProverError> {
+            self.inner.preflight(image_id, input_id, assumptions, executor_limit).await
+        }
+        async fn prove_stark(
+            &self,
+            image_id: &str,
+            input_id: &str,
+            assumptions: Vec<String>,
+        ) -> Result<String, ProverError> {
+            let remaining = self.failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.failures.store(remaining - 1, Ordering::SeqCst);
+                return Err(ProverError::MissingStatus);
+            }
+            self.inner.prove_stark(image_id, input_id, assumptions).await
+        }
+        async fn prove_and_monitor_stark(
+            &self,
+            image_id: &str,
+            input_id: &str,
+            assumptions: Vec<String>,
+        ) -> Result<ProofResult, ProverError> {
+            let proof_id = self.prove_stark(image_id, input_id, assumptions).await?;
+            self.wait_for_stark(&proof_id).await
+        }
+        async fn wait_for_stark(&self, proof_id: &str) -> Result<ProofResult, ProverError> {
+            self.inner.wait_for_stark(proof_id).await
+        }
+        async fn get_receipt(&self, proof_id: &str) -> Result<Option<Receipt>, ProverError> {
+            self.inner.get_receipt(proof_id).await
+        }
+        async fn get_preflight_journal(
+            &self,
+            proof_id: &str,
+        ) -> Result<Option<Vec<u8>>, ProverError> {
+            self.inner.get_preflight_journal(proof_id).await
+        }
+        async fn get_journal(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+            self.inner.get_journal(proof_id).await
+        }
+        async fn compress(&self, proof_id: &str) -> Result<String, ProverError> {
+            self.inner.compress(proof_id).await
+        }
+        async fn get_compressed_receipt(
+            &self,
+            proof_id: &str,
+        ) -> Result<Option<Vec<u8>>, ProverError> {
+            self.inner.get_compressed_receipt(proof_id).await
+        }
+        async fn upload_receipt(&self, receipt: Receipt) -> Result<String, ProverError> {
+            self.inner.upload_receipt(receipt).await
+        }
+    }
+
+    fn retry_config() -> ConfigLock {
+        let config = ConfigLock::default();
+        {
+            let mut config = config.load_write().unwrap();
+            config.prover.proof_retry_count = 2;
+            config.prover.proof_retry_sleep_ms = 0;
+            config.prover.proof_escalate_after = 1;
+        }
+        config
+    }
+
+    fn create_order(
+        image_url: String,
+        image_id: Option<String>,
+        input_id: Option<String>,
+    ) -> Order {
+        Order {
+            status: OrderStatus::Proving,
+            updated_at: Utc::now(),
+            target_timestamp: Some(0),
+            request: ProofRequest::new(
+                0,
+                &Address::ZERO,
+                Requirements::new(
+                    Digest::from(ECHO_ID),
+                    Predicate {
+                        predicateType: PredicateType::PrefixMatch,
+                        data: Default::default(),
+                    },
+                ),
+                image_url,
+                Input::builder().write_slice(&[0x41, 0x41, 0x41, 0x41]).build_inline().unwrap(),
+                Offer {
+                    minPrice: U256::from(2),
+                    maxPrice: U256::from(4),
+                    biddingStart: now_timestamp(),
+                    rampUpPeriod: 1,
+                    lockTimeout: 1000,
+                    timeout: 1000,
+                    lockStake: U256::from(10),
+                },
+            ),
+            image_id,
+            input_id,
+            proof_id: None,
+            expire_timestamp: Some(now_timestamp() + 1000),
+            client_sig: Bytes::new(),
+            lock_price: None,
+            error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
+        }
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn prove_order() {
@@ -253,6 +591,10 @@ mod tests {
             client_sig: Bytes::new(),
             lock_price: None,
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
 
         db.add_order(order_id, order.clone()).await.unwrap();
@@ -263,6 +605,94 @@ mod tests {
         assert_eq!(order.status, OrderStatus::PendingAgg);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn reuse_cached_proof() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let config = ConfigLock::default();
+        let prover: ProverObj = Arc::new(MockProver::default());
+
+        let image_id = Digest::from(ECHO_ID).to_string();
+        prover.upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        let input_id = prover
+            .upload_input(encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap())
+            .await
+            .unwrap();
+
+        let proving_service =
+            ProvingService::new(db.clone(), prover, config.clone()).await.unwrap();
+
+        let input_digest = B256::repeat_byte(1);
+        let order = Order {
+            status: OrderStatus::Proving,
+            updated_at: Utc::now(),
+            target_timestamp: Some(0),
+            request: ProofRequest {
+                id: U256::ZERO,
+                requirements: Requirements::new(
+                    Digest::from(ECHO_ID),
+                    Predicate {
+                        predicateType: PredicateType::PrefixMatch,
+                        data: Default::default(),
+                    },
+                ),
+                imageUrl: "http://risczero.com/image".into(),
+                input: Input { inputType: InputType::Inline, data: Default::default() },
+                offer: Offer {
+                    minPrice: U256::from(2),
+                    maxPrice: U256::from(4),
+                    biddingStart: now_timestamp(),
+                    rampUpPeriod: 1,
+                    lockTimeout: 100,
+                    timeout: 100,
+                    lockStake: U256::from(10),
+                },
+            },
+            image_id: Some(image_id),
+            input_id: Some(input_id),
+            proof_id: None,
+            expire_timestamp: None,
+            client_sig: Bytes::new(),
+            lock_price: None,
+            error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: Some(input_digest),
+            assumption_ids: vec![],
+        };
+
+        // Finished proofs are indexed by image ID and input digest
+        let first_id = U256::ZERO;
+        db.add_order(first_id, order.clone()).await.unwrap();
+        proving_service.prove_with_retries(first_id, order.clone(), None).await.unwrap();
+        let proof_id = db.get_order(first_id).await.unwrap().unwrap().proof_id.unwrap();
+        let image_id = order.request.requirements.imageId;
+        assert_eq!(
+            db.get_cached_proof(image_id, input_digest).await.unwrap(),
+            Some(proof_id.clone())
+        );
+
+        // An order priced against the cached proof skips straight to aggregation
+        let reuse_id = U256::from(1);
+        let reuse_order =
+            Order { status: OrderStatus::Locked, proof_id: Some(proof_id.clone()), ..order };
+        db.add_order(reuse_id, reuse_order).await.unwrap();
+        tokio::spawn(crate::task::supervisor(1, Arc::new(proving_service)));
+
+        for _ in 0..10 {
+            let db_order = db.get_order(reuse_id).await.unwrap().unwrap();
+            if db_order.status == OrderStatus::PendingAgg {
+                break;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+
+        let db_order = db.get_order(reuse_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::PendingAgg);
+        assert_eq!(db_order.proof_id, Some(proof_id));
+        assert!(logs_contain("Reusing proof"));
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn resume_proving() {
@@ -320,6 +750,10 @@ mod tests {
             client_sig: Bytes::new(),
             lock_price: None,
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
         let order_id = U256::from(order_id);
         db.add_order(order_id, order.clone()).await.unwrap();
@@ -341,4 +775,104 @@ mod tests {
 
         assert!(logs_contain("Found 1 proofs currently proving"));
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn retry_transient_failure() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let prover: ProverObj = Arc::new(FlakyProver::new(2));
+
+        let image_id = Digest::from(ECHO_ID).to_string();
+        prover.upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        let input_id = prover
+            .upload_input(encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap())
+            .await
+            .unwrap();
+
+        let proving_service =
+            ProvingService::new(db.clone(), prover, retry_config()).await.unwrap();
+
+        let order_id = U256::ZERO;
+        let order =
+            create_order("http://risczero.com/image".into(), Some(image_id), Some(input_id));
+        db.add_order(order_id, order.clone()).await.unwrap();
+
+        proving_service.prove_with_retries(order_id, order, None).await.unwrap();
+
+        let order = db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::PendingAgg);
+        assert_eq!(order.proof_failures.len(), 2);
+        assert!(order.proof_failures[0].starts_with("attempt 1 (primary)"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn retry_stops_at_deadline() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let prover: ProverObj = Arc::new(FlakyProver::new(u32::MAX));
+
+        let image_id = Digest::from(ECHO_ID).to_string();
+        prover.upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        let input_id = prover
+            .upload_input(encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap())
+            .await
+            .unwrap();
+
+        let proving_service =
+            ProvingService::new(db.clone(), prover, retry_config()).await.unwrap();
+
+        let order_id = U256::ZERO;
+        let mut order =
+            create_order("http://risczero.com/image".into(), Some(image_id), Some(input_id));
+        // Inside the default deadline buffer, so no retry should be attempted
+        order.expire_timestamp = Some(now_timestamp() + 10);
+        db.add_order(order_id, order.clone()).await.unwrap();
+
+        let err = proving_service.prove_with_retries(order_id, order, None).await.unwrap_err();
+        assert!(format!("{err:?}").contains("Not enough time left before the lock deadline"));
+
+        let order = db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.proof_failures.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn escalate_to_fallback() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let prover: ProverObj = Arc::new(FlakyProver::new(u32::MAX));
+        let fallback: ProverObj = Arc::new(MockProver::default());
+
+        let image_server = MockServer::start();
+        let _get_mock = image_server.mock(|when, then| {
+            when.method(GET).path("/image");
+            then.status(200).body(ECHO_ELF);
+        });
+
+        let image_id = Digest::from(ECHO_ID).to_string();
+        prover.upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        let input_id = prover
+            .upload_input(encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap())
+            .await
+            .unwrap();
+
+        let proving_service = ProvingService::new(db.clone(), prover.clone(), retry_config())
+            .await
+            .unwrap()
+            .with_fallback_prover(Some(fallback));
+
+        let order_id = U256::ZERO;
+        let order = create_order(image_server.url("/image"), Some(image_id), Some(input_id));
+        db.add_order(order_id, order.clone()).await.unwrap();
+
+        proving_service.prove_with_retries(order_id, order, None).await.unwrap();
+
+        let order = db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::PendingAgg);
+        assert_eq!(order.proof_failures.len(), 1);
+
+        // The fallback receipt must be usable through the primary prover
+        let proof_id = order.proof_id.unwrap();
+        assert!(prover.get_receipt(&proof_id).await.unwrap().is_some());
+        assert!(prover.get_journal(&proof_id).await.unwrap().is_some());
+    }
 }
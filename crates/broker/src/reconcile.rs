@@ -0,0 +1,468 @@
+// Copyright (c) 2025 RISC Zero, Inc.
+//
+// All rights reserved.
+
+use std::sync::Arc;
+
+use alloy::{
+    network::Ethereum,
+    primitives::{Address, U256},
+    providers::Provider,
+    rpc::types::BlockTransactionsKind,
+};
+use anyhow::{Context, Result};
+use boundless_market::contracts::{boundless_market::BoundlessMarketService, ProofStatus};
+
+use crate::{
+    db::DbObj,
+    webhook::{WebhookEvent, WebhookSink},
+    BatchStatus, Order, OrderStatus,
+};
+
+/// Order statuses that have not reached a terminal state
+const ACTIVE_STATUSES: [OrderStatus; 8] = [
+    OrderStatus::New,
+    OrderStatus::Pricing,
+    OrderStatus::Locking,
+    OrderStatus::Locked,
+    OrderStatus::Proving,
+    OrderStatus::PendingAgg,
+    OrderStatus::Aggregating,
+    OrderStatus::PendingSubmission,
+];
+
+/// Orders in these statuses are locked by this broker according to the DB
+fn is_committed(status: OrderStatus) -> bool {
+    matches!(
+        status,
+        OrderStatus::Locked
+            | OrderStatus::Proving
+            | OrderStatus::PendingAgg
+            | OrderStatus::Aggregating
+            | OrderStatus::PendingSubmission
+    )
+}
+
+/// An order whose DB status disagreed with the chain
+#[derive(Debug)]
+pub struct Discrepancy {
+    pub order_id: U256,
+    /// Status recorded in the DB before reconciliation
+    pub db_status: OrderStatus,
+    /// Status of the request on chain
+    pub chain_status: ProofStatus,
+    /// Status the order was moved to
+    pub resolved_status: OrderStatus,
+    pub reason: String,
+}
+
+/// Summary of a reconciliation pass
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    /// Number of active orders checked against the chain
+    pub orders_checked: usize,
+    /// Number of orders that could not be checked, e.g. due to an RPC error
+    pub orders_failed: usize,
+    /// Number of batches pending submission that were settled
+    pub batches_settled: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+/// Reconciles the broker DB against on-chain request state
+///
+/// Run once at startup, before any of the services are spawned, so that orders left behind by an
+/// interrupted broker or a DB restore are resumed from the state the chain actually reached.
+pub struct Reconciler<P> {
+    db: DbObj,
+    provider: Arc<P>,
+    market: BoundlessMarketService<Arc<P>>,
+    prover_addr: Address,
+    webhook_sink: Option<WebhookSink>,
+}
+
+impl<P> Reconciler<P>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+{
+    pub fn new(db: DbObj, provider: Arc<P>, market_addr: Address, prover_addr: Address) -> Self {
+        let market = BoundlessMarketService::new(market_addr, provider.clone(), prover_addr);
+        Self { db, provider, market, prover_addr, webhook_sink: None }
+    }
+
+    /// Report slashes found while reconciling to the webhook
+    pub fn with_webhook_sink(self, webhook_sink: WebhookSink) -> Self {
+        Self { webhook_sink: Some(webhook_sink), ..self }
+    }
+
+    /// Check every non-terminal order and pending batch against the chain
+    pub async fn reconcile(&self) -> Result<ReconcileReport> {
+        let mut report = ReconcileReport::default();
+
+        // Snapshot all active orders before changing any of them, so an order moved to another
+        // active status, e.g. from Locking to Locked, is not checked twice
+        let mut orders = vec![];
+        for status in ACTIVE_STATUSES {
+            orders.extend(
+                self.db
+                    .get_orders_by_status(status)
+                    .await
+                    .with_context(|| format!("Failed to fetch {status:?} orders"))?,
+            );
+        }
+
+        for (order_id, order) in orders {
+            report.orders_checked += 1;
+            match self.reconcile_order(order_id, &order).await {
+                Ok(Some(discrepancy)) => {
+                    tracing::warn!(
+                        "Reconciled order {order_id:x}: {:?} -> {:?}, chain status {:?}: {}",
+                        discrepancy.db_status,
+                        discrepancy.resolved_status,
+                        discrepancy.chain_status,
+                        discrepancy.reason
+                    );
+                    report.discrepancies.push(discrepancy);
+                }
+                Ok(None) => {}
+                // Leave the order as is, the services re-check the chain before acting on it
+                Err(err) => {
+                    tracing::error!("Failed to reconcile order {order_id:x}, skipping: {err:?}");
+                    report.orders_failed += 1;
+                }
+            }
+        }
+
+        report.batches_settled =
+            self.reconcile_submissions().await.context("Failed to reconcile pending batches")?;
+
+        tracing::info!(
+            "Reconciled {} orders and {} pending batches, {} discrepancies, {} failed",
+            report.orders_checked,
+            report.batches_settled,
+            report.discrepancies.len(),
+            report.orders_failed
+        );
+
+        Ok(report)
+    }
+
+    async fn reconcile_order(&self, order_id: U256, order: &Order) -> Result<Option<Discrepancy>> {
+        let db_status = order.status;
+        let chain_status = self
+            .market
+            .get_status(order_id, Some(order.request.expires_at()))
+            .await
+            .context("Failed to get request status")?;
+
+        let (resolved_status, reason) = match (&chain_status, is_committed(db_status)) {
+            // Consistent states, nothing to do
+            (ProofStatus::Unknown, false) | (ProofStatus::Locked, true) => return Ok(None),
+
+            (ProofStatus::Fulfilled, true) => {
+                self.db.set_order_complete(order_id).await?;
+                (OrderStatus::Done, "fulfilled on chain".to_string())
+            }
+            (ProofStatus::Fulfilled, false) => {
+                self.db.skip_order(order_id).await?;
+                (OrderStatus::Skipped, "fulfilled by another prover".to_string())
+            }
+
+            (ProofStatus::Expired, true) => {
+                let reason = if self.market.is_slashed(order_id).await? {
+                    self.notify_slashed(order_id).await;
+                    "slashed".to_string()
+                } else {
+                    "expired before fulfillment".to_string()
+                };
+                self.db.set_order_failure(order_id, reason.clone()).await?;
+                (OrderStatus::Failed, reason)
+            }
+            (ProofStatus::Expired, false) => {
+                self.db.skip_order(order_id).await?;
+                (OrderStatus::Skipped, "expired".to_string())
+            }
+
+            (ProofStatus::Unknown, true) => {
+                let reason = "lock not found on chain".to_string();
+                self.db.set_order_failure(order_id, reason.clone()).await?;
+                (OrderStatus::Failed, reason)
+            }
+
+            (ProofStatus::Locked, false) => {
+                let (locker, lock_block) =
+                    match self.market.query_request_locked_event(order_id, None, None).await {
+                        Ok(res) => res,
+                        Err(err) => {
+                            // Leave the order for the order monitor, which re-checks the status
+                            // before sending any lock
+                            tracing::error!(
+                                "Lock event not found for locked order {order_id:x}: {err:?}"
+                            );
+                            return Ok(None);
+                        }
+                    };
+
+                if locker == self.prover_addr {
+                    let lock_price = self.lock_price(order, lock_block).await?;
+                    self.db.set_proving_status(order_id, lock_price).await.with_context(|| {
+                        format!(
+                            "FATAL STAKE AT RISK: {order_id:x} failed to move from {db_status:?} -> proving status"
+                        )
+                    })?;
+                    (OrderStatus::Locked, format!("locked by this broker in block {lock_block}"))
+                } else {
+                    let reason = format!("locked by another prover: {locker}");
+                    self.db.set_order_failure(order_id, reason.clone()).await?;
+                    (OrderStatus::Failed, reason)
+                }
+            }
+        };
+
+        Ok(Some(Discrepancy { order_id, db_status, chain_status, resolved_status, reason }))
+    }
+
+    /// Report the slash of an order to the webhook, if one is configured
+    async fn notify_slashed(&self, order_id: U256) {
+        let Some(webhook_sink) = self.webhook_sink.as_ref() else {
+            return;
+        };
+        match self.market.query_prover_slashed_event(order_id).await {
+            Ok(event) => webhook_sink.notify(WebhookEvent::Slashed {
+                order_id,
+                stake_burned: event.stakeBurned,
+                stake_transferred: event.stakeTransferred,
+            }),
+            Err(err) => tracing::warn!("Slash event not found for order {order_id:x}: {err:?}"),
+        }
+    }
+
+    /// Settle batches whose submission was interrupted
+    ///
+    /// If every order in the batch is already fulfilled on chain the batch is marked submitted.
+    /// If only some of them are, the fulfilled orders are marked done and the others failed, as
+    /// the batch can no longer be submitted as a whole. Otherwise it is handed back to the
+    /// submitter.
+    async fn reconcile_submissions(&self) -> Result<usize> {
+        let batches = self.db.get_batches_by_status(BatchStatus::PendingSubmission).await?;
+
+        let mut count = 0;
+        for (batch_id, batch) in batches {
+            let fulfilled = match self.batch_fulfillments(&batch.orders).await {
+                Ok(fulfilled) => fulfilled,
+                // Leave the batch as is, the submitter re-checks each order before submitting
+                Err(err) => {
+                    tracing::error!("Failed to reconcile batch {batch_id}, skipping: {err:?}");
+                    continue;
+                }
+            };
+
+            if fulfilled.iter().all(|f| *f) {
+                tracing::info!("Batch {batch_id} was submitted before shutdown, marking complete");
+                for order_id in batch.orders.iter() {
+                    self.db.set_order_complete(*order_id).await?;
+                }
+                self.db.set_batch_submitted(batch_id).await?;
+            } else if fulfilled.iter().any(|f| *f) {
+                tracing::warn!(
+                    "Batch {batch_id} is partially fulfilled on chain, failing the other orders"
+                );
+                for (order_id, is_fulfilled) in batch.orders.iter().zip(fulfilled) {
+                    if is_fulfilled {
+                        self.db.set_order_complete(*order_id).await?;
+                    } else {
+                        self.db
+                            .set_order_failure(
+                                *order_id,
+                                "Batch partially fulfilled before shutdown".into(),
+                            )
+                            .await?;
+                    }
+                }
+                self.db
+                    .set_batch_failure(batch_id, "Batch partially fulfilled before shutdown".into())
+                    .await?;
+            } else {
+                tracing::info!("Returning batch {batch_id} to the submitter");
+                self.db.set_batch_status(batch_id, BatchStatus::Complete).await?;
+            }
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Whether each of the given orders is fulfilled on chain
+    async fn batch_fulfillments(&self, order_ids: &[U256]) -> Result<Vec<bool>> {
+        let mut fulfilled = Vec::with_capacity(order_ids.len());
+        for order_id in order_ids {
+            let is_fulfilled = self
+                .market
+                .is_fulfilled(*order_id)
+                .await
+                .with_context(|| format!("Failed to get fulfillment of {order_id:x}"))?;
+            fulfilled.push(is_fulfilled);
+        }
+        Ok(fulfilled)
+    }
+
+    async fn lock_price(&self, order: &Order, lock_block: u64) -> Result<U256> {
+        let lock_timestamp = self
+            .provider
+            .get_block_by_number(lock_block.into(), BlockTransactionsKind::Hashes)
+            .await
+            .with_context(|| format!("failed to get block {lock_block}"))?
+            .with_context(|| format!("failed to get block {lock_block}: block not found"))?
+            .header
+            .timestamp;
+
+        order.request.offer.price_at(lock_timestamp).context("Failed to calculate lock price")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SqliteDb, now_timestamp, Batch};
+    use alloy::{
+        network::EthereumWallet,
+        node_bindings::{Anvil, AnvilInstance},
+        providers::ProviderBuilder,
+        signers::local::PrivateKeySigner,
+    };
+    use boundless_market::contracts::{
+        test_utils::{deploy_boundless_market, deploy_hit_points},
+        Input, InputType, Offer, Predicate, PredicateType, ProofRequest, Requirements,
+    };
+    use chrono::Utc;
+    use guest_assessor::ASSESSOR_GUEST_ID;
+    use risc0_zkvm::sha::Digest;
+
+    async fn setup(
+        anvil: &AnvilInstance,
+    ) -> (Arc<impl Provider + Clone + 'static>, PrivateKeySigner, Address, Order) {
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer.clone()))
+                .on_builtin(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+
+        let hit_points = deploy_hit_points(&signer, provider.clone()).await.unwrap();
+        let market_address = deploy_boundless_market(
+            &signer,
+            provider.clone(),
+            Address::ZERO,
+            hit_points,
+            Digest::from(ASSESSOR_GUEST_ID),
+            Some(signer.address()),
+        )
+        .await
+        .unwrap();
+
+        let request = ProofRequest::new(
+            1,
+            &signer.address(),
+            Requirements::new(
+                Digest::ZERO,
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            "http://risczero.com/image",
+            Input { inputType: InputType::Inline, data: Default::default() },
+            Offer {
+                minPrice: U256::from(1),
+                maxPrice: U256::from(2),
+                biddingStart: now_timestamp(),
+                rampUpPeriod: 1,
+                timeout: 100,
+                lockTimeout: 100,
+                lockStake: U256::from(0),
+            },
+        );
+        let chain_id = provider.get_chain_id().await.unwrap();
+        let client_sig =
+            request.sign_request(&signer, market_address, chain_id).await.unwrap().as_bytes();
+
+        let order = Order {
+            status: OrderStatus::Locking,
+            updated_at: Utc::now(),
+            target_timestamp: Some(0),
+            request,
+            image_id: None,
+            input_id: None,
+            proof_id: None,
+            expire_timestamp: None,
+            client_sig: client_sig.into(),
+            lock_price: None,
+            error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
+        };
+
+        (provider, signer, market_address, order)
+    }
+
+    #[tokio::test]
+    async fn reconcile_interrupted_lock_and_batch() {
+        let anvil = Anvil::new().spawn();
+        let (provider, signer, market_address, order) = setup(&anvil).await;
+        let order_id = U256::from(order.request.id);
+
+        let market =
+            BoundlessMarketService::new(market_address, provider.clone(), signer.address());
+        market.submit_request(&order.request, &signer).await.unwrap();
+
+        // Lock on chain without recording it, as if the broker stopped right after sending
+        market.lock_request(&order.request, &order.client_sig, None).await.unwrap();
+
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        db.add_order(order_id, order).await.unwrap();
+        let batch = Batch {
+            status: BatchStatus::PendingSubmission,
+            orders: vec![order_id],
+            start_time: Utc::now(),
+            ..Default::default()
+        };
+        db.add_batch(1, batch).await.unwrap();
+
+        let reconciler = Reconciler::new(db.clone(), provider, market_address, signer.address());
+        let report = reconciler.reconcile().await.unwrap();
+        assert_eq!(report.orders_checked, 1);
+        assert_eq!(report.orders_failed, 0);
+        assert_eq!(report.batches_settled, 1);
+        assert_eq!(report.discrepancies.len(), 1);
+        assert_eq!(report.discrepancies[0].chain_status, ProofStatus::Locked);
+
+        let order = db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Locked);
+        assert!(order.lock_price.is_some());
+
+        // Order is not fulfilled, so the batch goes back to the submitter
+        let batch = db.get_batch(1).await.unwrap();
+        assert_eq!(batch.status, BatchStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn reconcile_missing_lock() {
+        let anvil = Anvil::new().spawn();
+        let (provider, signer, market_address, mut order) = setup(&anvil).await;
+        let order_id = U256::from(order.request.id);
+
+        // The DB claims the order is proving, but it was never locked on chain
+        order.status = OrderStatus::Proving;
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        db.add_order(order_id, order).await.unwrap();
+
+        let reconciler = Reconciler::new(db.clone(), provider, market_address, signer.address());
+        let report = reconciler.reconcile().await.unwrap();
+        assert_eq!(report.discrepancies.len(), 1);
+        assert_eq!(report.discrepancies[0].db_status, OrderStatus::Proving);
+        assert_eq!(report.discrepancies[0].resolved_status, OrderStatus::Failed);
+
+        let order = db.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Failed);
+    }
+}
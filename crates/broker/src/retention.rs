@@ -0,0 +1,327 @@
+// Copyright (c) 2025 RISC Zero, Inc.
+//
+// All rights reserved.
+
+use std::{fs::OpenOptions, io::Write, path::Path, time::Duration};
+
+use alloy::primitives::Address;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    config::ConfigLock,
+    db::DbObj,
+    task::{RetryRes, RetryTask, SupervisorErr},
+};
+
+/// Line of an archive export file
+///
+/// IDs are only unique within a market, so each entry names the market it was archived from.
+#[derive(Serialize)]
+struct ArchiveEntry<'a, I, T> {
+    chain_id: u64,
+    market_address: Address,
+    id: I,
+    data: &'a T,
+}
+
+/// Appends the entries to a JSON lines file, creating it if needed
+async fn export_jsonl<'a, I, T>(
+    path: &Path,
+    entries: impl Iterator<Item = ArchiveEntry<'a, I, T>>,
+) -> Result<()>
+where
+    I: Serialize,
+    T: Serialize + 'a,
+{
+    let mut data = vec![];
+    for entry in entries {
+        serde_json::to_writer(&mut data, &entry).context("Failed to serialize archive entry")?;
+        data.push(b'\n');
+    }
+
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open archive file {}", path.display()))?;
+        file.write_all(&data).context("Failed to write archive file")?;
+        file.sync_all().context("Failed to sync archive file")
+    })
+    .await
+    .context("Archive export task failed")?
+}
+
+/// Market whose orders and batches are archived
+#[derive(Clone)]
+struct RetentionMarket {
+    chain_id: u64,
+    market_address: Address,
+    /// View of the DB namespaced to the market
+    db: DbObj,
+}
+
+/// Periodically archives old terminal orders and batches, then compacts the DB
+///
+/// A single service covers every market sharing the DB, so the DB wide proof cache pruning and
+/// compaction run once per pass.
+#[derive(Clone)]
+pub struct RetentionService {
+    db: DbObj,
+    markets: Vec<RetentionMarket>,
+    config: ConfigLock,
+}
+
+impl RetentionService {
+    pub fn new(db: DbObj, config: ConfigLock) -> Self {
+        Self { db, markets: vec![], config }
+    }
+
+    /// Archive the orders and batches of a market, read through its namespaced view of the DB
+    pub fn with_market(mut self, chain_id: u64, market_address: Address, db: DbObj) -> Self {
+        self.markets.push(RetentionMarket { chain_id, market_address, db });
+        self
+    }
+
+    /// Runs a single retention pass, returning the number of archived orders and batches
+    ///
+    /// Cached proofs older than the retention period are pruned as well. Exports are written
+    /// before anything is removed from the DB, so a crash in between can only duplicate entries
+    /// in the export files.
+    pub async fn run_retention(&self) -> Result<(usize, usize)> {
+        let (retention_days, archive_dir) = {
+            let config = self.config.lock_all().context("Failed to read config")?;
+            (config.retention.retention_days, config.retention.archive_dir.clone())
+        };
+        let Some(retention_days) = retention_days else {
+            return Ok((0, 0));
+        };
+
+        let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
+        let pruned =
+            self.db.prune_cached_proofs(cutoff).await.context("Failed to prune proof cache")?;
+        if pruned > 0 {
+            tracing::info!("Pruned {pruned} cached proofs older than {retention_days} days");
+        }
+
+        let (mut archived_orders, mut archived_batches) = (0, 0);
+        for market in self.markets.iter() {
+            let (orders, batches) = Self::archive_market(market, cutoff, archive_dir.as_deref())
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to archive market {} on chain {}",
+                        market.market_address, market.chain_id
+                    )
+                })?;
+            archived_orders += orders;
+            archived_batches += batches;
+        }
+        if archived_orders == 0 && archived_batches == 0 {
+            return Ok((0, 0));
+        }
+
+        self.db.vacuum().await.context("Failed to vacuum DB")?;
+        tracing::info!(
+            "Archived {archived_orders} orders and {archived_batches} batches older than {retention_days} days"
+        );
+
+        Ok((archived_orders, archived_batches))
+    }
+
+    /// Archives the old orders and batches of a single market
+    async fn archive_market(
+        market: &RetentionMarket,
+        cutoff: DateTime<Utc>,
+        archive_dir: Option<&Path>,
+    ) -> Result<(usize, usize)> {
+        let orders =
+            market.db.get_archivable_orders(cutoff).await.context("Failed to get old orders")?;
+        let batches =
+            market.db.get_archivable_batches(cutoff).await.context("Failed to get old batches")?;
+        if orders.is_empty() && batches.is_empty() {
+            return Ok((0, 0));
+        }
+
+        if let Some(archive_dir) = archive_dir {
+            tokio::fs::create_dir_all(archive_dir)
+                .await
+                .context("Failed to create archive directory")?;
+            export_jsonl(
+                &archive_dir.join("orders.jsonl"),
+                orders.iter().map(|(id, order)| ArchiveEntry {
+                    chain_id: market.chain_id,
+                    market_address: market.market_address,
+                    id: format!("{id:x}"),
+                    data: order,
+                }),
+            )
+            .await?;
+            export_jsonl(
+                &archive_dir.join("batches.jsonl"),
+                batches.iter().map(|(id, batch)| ArchiveEntry {
+                    chain_id: market.chain_id,
+                    market_address: market.market_address,
+                    id: *id,
+                    data: batch,
+                }),
+            )
+            .await?;
+        }
+
+        // Exported entries do not need to be kept in the DB archive tables
+        let keep_archive = archive_dir.is_none();
+        let order_ids: Vec<_> = orders.iter().map(|(id, _)| *id).collect();
+        let archived_orders = market
+            .db
+            .archive_orders(&order_ids, keep_archive)
+            .await
+            .context("Failed to archive orders")?;
+        let batch_ids: Vec<_> = batches.iter().map(|(id, _)| *id).collect();
+        let archived_batches = market
+            .db
+            .archive_batches(&batch_ids, keep_archive)
+            .await
+            .context("Failed to archive batches")?;
+
+        Ok((archived_orders, archived_batches))
+    }
+
+    async fn start_retention(&self) -> Result<()> {
+        loop {
+            self.run_retention().await?;
+
+            let interval_secs =
+                self.config.lock_all().context("Failed to read config")?.retention.interval_secs;
+            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+        }
+    }
+}
+
+impl RetryTask for RetentionService {
+    fn spawn(&self) -> RetryRes {
+        let service = self.clone();
+        Box::pin(async move {
+            tracing::info!("Starting retention service");
+            service.start_retention().await.map_err(SupervisorErr::Recover)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SqliteDb, Batch, BatchStatus, Order, OrderStatus};
+    use alloy::primitives::{Address, Bytes, U256};
+    use boundless_market::contracts::{
+        Input, InputType, Offer, Predicate, PredicateType, ProofRequest, Requirements,
+    };
+    use risc0_zkvm::sha::Digest;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn old_order(status: OrderStatus) -> Order {
+        let mut order = Order::new(
+            ProofRequest::new(
+                1,
+                &Address::ZERO,
+                Requirements::new(
+                    Digest::ZERO,
+                    Predicate {
+                        predicateType: PredicateType::PrefixMatch,
+                        data: Default::default(),
+                    },
+                ),
+                "http://risczero.com",
+                Input { inputType: InputType::Inline, data: Default::default() },
+                Offer {
+                    minPrice: U256::from(1),
+                    maxPrice: U256::from(2),
+                    biddingStart: 0,
+                    timeout: 100,
+                    lockTimeout: 100,
+                    rampUpPeriod: 1,
+                    lockStake: U256::ZERO,
+                },
+            ),
+            Bytes::new(),
+        );
+        order.status = status;
+        order.updated_at = Utc::now() - chrono::Duration::days(10);
+        order
+    }
+
+    #[tokio::test]
+    async fn export_old_orders() {
+        let sqlite = SqliteDb::new("sqlite::memory:").await.unwrap();
+        let market_a = Address::repeat_byte(0xa);
+        let market_b = Address::repeat_byte(0xb);
+        let db: DbObj = Arc::new(sqlite.for_market(1, market_a).await.unwrap());
+        let other_db: DbObj = Arc::new(sqlite.for_market(1, market_b).await.unwrap());
+        let archive_dir = TempDir::new().unwrap();
+        let config = ConfigLock::default();
+        {
+            let mut config = config.load_write().unwrap();
+            config.retention.retention_days = Some(7);
+            config.retention.archive_dir = Some(archive_dir.path().join("archive"));
+        }
+
+        db.add_order(U256::from(1), old_order(OrderStatus::Done)).await.unwrap();
+        db.add_order(U256::from(2), old_order(OrderStatus::Skipped)).await.unwrap();
+        db.add_order(U256::from(3), old_order(OrderStatus::Proving)).await.unwrap();
+        let old_batch = Batch {
+            status: BatchStatus::Submitted,
+            start_time: Utc::now() - chrono::Duration::days(10),
+            ..Default::default()
+        };
+        db.add_batch(1, old_batch).await.unwrap();
+        // Same order ID in another market on the same chain
+        other_db.add_order(U256::from(1), old_order(OrderStatus::Failed)).await.unwrap();
+
+        let retention = RetentionService::new(Arc::new(sqlite), config)
+            .with_market(1, market_a, db.clone())
+            .with_market(1, market_b, other_db.clone());
+        assert_eq!(retention.run_retention().await.unwrap(), (3, 1));
+        // Nothing left to archive
+        assert_eq!(retention.run_retention().await.unwrap(), (0, 0));
+
+        assert!(db.get_order(U256::from(1)).await.unwrap().is_none());
+        assert!(db.get_order(U256::from(3)).await.unwrap().is_some());
+        let stats = db.get_archive_stats().await.unwrap();
+        assert_eq!(stats.orders_done, 1);
+        assert_eq!(stats.orders_skipped, 1);
+        assert_eq!(stats.batches_submitted, 1);
+        assert_eq!(other_db.get_archive_stats().await.unwrap().orders_failed, 1);
+
+        // Exported entries name their market
+        let orders =
+            std::fs::read_to_string(archive_dir.path().join("archive/orders.jsonl")).unwrap();
+        let mut ids: Vec<(u64, Address, String)> = orders
+            .lines()
+            .map(|line| {
+                let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+                (
+                    entry["chain_id"].as_u64().unwrap(),
+                    entry["market_address"].as_str().unwrap().parse().unwrap(),
+                    entry["id"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                (1, market_a, "1".to_string()),
+                (1, market_a, "2".to_string()),
+                (1, market_b, "1".to_string())
+            ]
+        );
+        let batches =
+            std::fs::read_to_string(archive_dir.path().join("archive/batches.jsonl")).unwrap();
+        assert_eq!(batches.lines().count(), 1);
+    }
+}
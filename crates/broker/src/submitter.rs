@@ -21,8 +21,10 @@ use risc0_zkvm::{
     sha::{Digest, Digestible},
     MaybePruned, Receipt, ReceiptClaim,
 };
+use tokio_util::sync::CancellationToken;
 
 use crate::{
+    balance_manager::WalletTxLock,
     config::ConfigLock,
     db::DbObj,
     provers::ProverObj,
@@ -40,6 +42,8 @@ pub struct Submitter<P> {
     set_builder_img_id: Digest,
     prover_address: Address,
     config: ConfigLock,
+    cancel_token: CancellationToken,
+    tx_lock: Option<WalletTxLock>,
 }
 
 impl<P> Submitter<P>
@@ -90,9 +94,32 @@ where
             set_builder_img_id,
             prover_address,
             config,
+            cancel_token: CancellationToken::new(),
+            tx_lock: None,
         })
     }
 
+    /// Set the prover address used in the assessor receipt
+    ///
+    /// Must match the address that locked the orders, defaults to the provider's wallet
+    pub fn with_prover_address(self, prover_address: Address) -> Self {
+        Self { prover_address, ..self }
+    }
+
+    /// Stop picking up new batches once the token is cancelled
+    ///
+    /// A batch submission that is already in flight is always finished before exiting.
+    pub fn with_cancel_token(self, cancel_token: CancellationToken) -> Self {
+        Self { cancel_token, ..self }
+    }
+
+    /// Hold the given lock while submitting, when the wallet also sends lock transactions
+    ///
+    /// See [WalletTxLock].
+    pub fn with_tx_lock(self, tx_lock: WalletTxLock) -> Self {
+        Self { tx_lock: Some(tx_lock), ..self }
+    }
+
     async fn fetch_encode_g16(&self, g16_proof_id: &str) -> Result<Vec<u8>> {
         let groth16_receipt = self
             .prover
@@ -247,6 +274,10 @@ where
             prover: self.prover_address,
             callbacks: vec![],
         };
+        let _tx_guard = match self.tx_lock.as_ref() {
+            Some(tx_lock) => Some(tx_lock.lock().await),
+            None => None,
+        };
         if single_txn_fulfill {
             if let Err(err) = self
                 .market
@@ -391,12 +422,17 @@ where
 
         Box::pin(async move {
             tracing::info!("Starting Submitter service");
-            loop {
+            while !obj_clone.cancel_token.is_cancelled() {
                 obj_clone.process_next_batch().await?;
 
                 // TODO: configuration
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(tokio::time::Duration::from_secs(1)) => {}
+                    _ = obj_clone.cancel_token.cancelled() => {}
+                }
             }
+            tracing::info!("Submitter shutting down");
+            Ok(())
         })
     }
 }
@@ -609,6 +645,10 @@ mod tests {
             client_sig: client_sig.into(),
             lock_price: Some(U256::ZERO),
             error_msg: None,
+            proof_failures: vec![],
+            dry_run: false,
+            input_digest: None,
+            assumption_ids: vec![],
         };
         let order_id = U256::from(order.request.id);
         db.add_order(order_id, order.clone()).await.unwrap();
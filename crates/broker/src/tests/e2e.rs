@@ -4,22 +4,91 @@
 
 use alloy::{
     node_bindings::Anvil,
-    primitives::{utils, U256},
+    primitives::{utils, Bytes, U256},
+    providers::{Provider, WalletProvider},
 };
 use httpmock::prelude::*;
 use risc0_zkvm::sha::Digest;
 use tempfile::NamedTempFile;
 // use broker::Broker;
-use crate::{config::Config, now_timestamp, Args, Broker};
-use boundless_market::contracts::{
-    hit_points::default_allowance, test_utils::create_test_ctx, Input, Offer, Predicate,
-    PredicateType, ProofRequest, Requirements,
+use crate::{config::Config, now_timestamp, Args, Broker, MarketDeployment};
+use boundless_market::{
+    client::{ClientBuilder, ClientError, VerificationError},
+    contracts::{
+        hit_points::default_allowance,
+        test_utils::{create_test_ctx, deploy_mock_wallet, set_mock_wallet_signature, TestCtx},
+        Input, Offer, Predicate, PredicateType, ProofRequest, Requirements,
+    },
+    signer::SignerConfig,
 };
 use guest_assessor::{ASSESSOR_GUEST_ID, ASSESSOR_GUEST_PATH};
 use guest_set_builder::{SET_BUILDER_ID, SET_BUILDER_PATH};
 use guest_util::{ECHO_ELF, ECHO_ID};
 use tokio::time::Duration;
 use tracing_test::traced_test;
+use url::Url;
+
+fn broker_args<P>(ctx: &TestCtx<P>, rpc_url: Url, config_file: &NamedTempFile) -> Args {
+    Args {
+        db_url: "sqlite::memory:".into(),
+        config_file: config_file.path().to_path_buf(),
+        boundless_market_address: ctx.boundless_market_address,
+        set_verifier_address: ctx.set_verifier_address,
+        rpc_url,
+        order_stream_url: None,
+        signer: SignerConfig::private_key(ctx.prover_signer.clone()),
+        lock_private_key: None,
+        lock_keystore_path: None,
+        lock_keystore_password: None,
+        lock_remote_signer_url: None,
+        lock_remote_signer_address: None,
+        bento_api_url: None,
+        bonsai_api_key: None,
+        bonsai_api_url: None,
+        fallback_prover_url: None,
+        fallback_prover_api_key: None,
+        deposit_amount: None,
+        rpc_retry_max: 0,
+        rpc_retry_backoff: 200,
+        rpc_retry_cu: 1000,
+        shutdown_timeout: 30,
+        dry_run: false,
+    }
+}
+
+async fn echo_request<P: Provider + WalletProvider + Clone + 'static>(
+    ctx: &TestCtx<P>,
+    image_uri: &str,
+) -> ProofRequest {
+    ProofRequest::new(
+        ctx.customer_market.index_from_nonce().await.unwrap(),
+        &ctx.customer_signer.address(),
+        Requirements::new(
+            Digest::from(ECHO_ID),
+            Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+        ),
+        image_uri,
+        Input::builder().write_slice(&[0x41, 0x41, 0x41, 0x41]).build_inline().unwrap(),
+        Offer {
+            minPrice: U256::from(20000000000000u64),
+            maxPrice: U256::from(40000000000000u64),
+            biddingStart: now_timestamp(),
+            timeout: 1200,
+            lockTimeout: 1200,
+            rampUpPeriod: 1,
+            lockStake: U256::from(10),
+        },
+    )
+}
+
+fn e2e_config() -> Config {
+    let mut config = Config::default();
+    config.prover.set_builder_guest_path = Some(SET_BUILDER_PATH.into());
+    config.prover.assessor_set_guest_path = Some(ASSESSOR_GUEST_PATH.into());
+    config.market.mcycle_price = "0.00001".parse().unwrap();
+    config.batcher.batch_size = Some(1);
+    config
+}
 
 #[tokio::test]
 #[traced_test]
@@ -48,60 +117,21 @@ async fn simple_e2e() {
 
     // Start broker
     let config_file = NamedTempFile::new().unwrap();
-    let mut config = Config::default();
-    // - modify config here
-    config.prover.set_builder_guest_path = Some(SET_BUILDER_PATH.into());
-    config.prover.assessor_set_guest_path = Some(ASSESSOR_GUEST_PATH.into());
-    config.market.mcycle_price = "0.00001".into();
-    config.batcher.batch_size = Some(1);
-    config.write(config_file.path()).await.unwrap();
+    e2e_config().write(config_file.path()).await.unwrap();
 
-    let args = Args {
-        db_url: "sqlite::memory:".into(),
-        config_file: config_file.path().to_path_buf(),
-        boundless_market_address: ctx.boundless_market_address,
-        set_verifier_address: ctx.set_verifier_address,
-        rpc_url: anvil.endpoint_url(),
-        order_stream_url: None,
-        private_key: ctx.prover_signer,
-        bento_api_url: None,
-        bonsai_api_key: None,
-        bonsai_api_url: None,
-        deposit_amount: None,
-        rpc_retry_max: 0,
-        rpc_retry_backoff: 200,
-        rpc_retry_cu: 1000,
-    };
+    let args = broker_args(&ctx, anvil.endpoint_url(), &config_file);
     let broker = Broker::new(args, ctx.prover_provider).await.unwrap();
     let broker_task = tokio::spawn(async move {
         broker.start_service().await.unwrap();
     });
 
     // Submit a order
-
-    let request = ProofRequest::new(
-        ctx.customer_market.index_from_nonce().await.unwrap(),
-        &ctx.customer_signer.address(),
-        Requirements::new(
-            Digest::from(ECHO_ID),
-            Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
-        ),
-        &image_uri,
-        Input::builder().write_slice(&[0x41, 0x41, 0x41, 0x41]).build_inline().unwrap(),
-        Offer {
-            minPrice: U256::from(20000000000000u64),
-            maxPrice: U256::from(40000000000000u64),
-            biddingStart: now_timestamp(),
-            timeout: 1200,
-            lockTimeout: 1200,
-            rampUpPeriod: 1,
-            lockStake: U256::from(10),
-        },
-    );
+    let request = echo_request(&ctx, &image_uri).await;
 
     ctx.customer_market.submit_request(&request, &ctx.customer_signer).await.unwrap();
 
-    ctx.customer_market
+    let (journal, seal) = ctx
+        .customer_market
         .wait_for_request_fulfillment(
             U256::from(request.id),
             Duration::from_secs(1),
@@ -110,6 +140,53 @@ async fn simple_e2e() {
         .await
         .unwrap();
 
+    // The fulfillment verifies with the set verifier contract, and locally
+    let client = ClientBuilder::new()
+        .with_rpc_url(anvil.endpoint_url())
+        .with_boundless_market_address(ctx.boundless_market_address)
+        .with_set_verifier_address(ctx.set_verifier_address)
+        .with_private_key(ctx.customer_signer.clone())
+        .build()
+        .await
+        .unwrap();
+    client.verify_fulfillment(&request, journal.clone(), seal.clone()).await.unwrap();
+    let fulfillment = client
+        .verify_fulfillment_offchain(&request, journal.clone(), seal.clone(), SET_BUILDER_ID)
+        .await
+        .unwrap();
+    assert_eq!(fulfillment.journal, journal);
+
+    // Tampered journals and seals are rejected
+    let mut tampered_journal = journal.to_vec();
+    tampered_journal.push(0);
+    let res = client
+        .verify_fulfillment_offchain(
+            &request,
+            tampered_journal.into(),
+            seal.clone(),
+            SET_BUILDER_ID,
+        )
+        .await;
+    assert!(res.is_err());
+    let mut tampered_seal = seal.to_vec();
+    *tampered_seal.last_mut().unwrap() ^= 1;
+    let res = client
+        .verify_fulfillment_offchain(
+            &request,
+            journal.clone(),
+            tampered_seal.into(),
+            SET_BUILDER_ID,
+        )
+        .await;
+    assert!(res.is_err());
+
+    // The root proof must come from the expected set builder
+    let err = client
+        .verify_fulfillment_offchain(&request, journal, seal, Digest::ZERO)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::VerificationError(VerificationError::InvalidSeal(_))));
+
     // Check for a broker panic
     if broker_task.is_finished() {
         broker_task.await.unwrap();
@@ -118,3 +195,150 @@ async fn simple_e2e() {
     }
     get_mock.assert();
 }
+
+#[tokio::test]
+#[traced_test]
+async fn smart_contract_wallet_e2e() {
+    let anvil = Anvil::new().spawn();
+    let ctx = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+    ctx.prover_market
+        .deposit_stake_with_permit(default_allowance(), &ctx.prover_signer)
+        .await
+        .unwrap();
+
+    // The customer owns an ERC-1271 wallet, which pays for the request
+    let deposit = utils::parse_ether("0.5").unwrap();
+    let wallet = deploy_mock_wallet(&ctx.customer_provider, ctx.boundless_market_address, deposit)
+        .await
+        .unwrap();
+
+    let server = MockServer::start();
+    let get_mock = server.mock(|when, then| {
+        when.method(GET).path("/image");
+        then.status(200).body(ECHO_ELF);
+    });
+    let image_uri = format!("http://{}/image", server.address());
+
+    let config_file = NamedTempFile::new().unwrap();
+    e2e_config().write(config_file.path()).await.unwrap();
+    let args = broker_args(&ctx, anvil.endpoint_url(), &config_file);
+    let broker = Broker::new(args, ctx.prover_provider.clone()).await.unwrap();
+    let broker_task = tokio::spawn(async move {
+        broker.start_service().await.unwrap();
+    });
+
+    // The wallet accepts the signature of the request by its owner
+    let mut request = echo_request(&ctx, &image_uri).await;
+    request.id = ctx.customer_market.smart_contract_request_id_from_rand(wallet).await.unwrap();
+    let chain_id = anvil.chain_id();
+    let client_sig: Bytes = request
+        .sign_request(&ctx.customer_signer, ctx.boundless_market_address, chain_id)
+        .await
+        .unwrap()
+        .as_bytes()
+        .into();
+    set_mock_wallet_signature(&ctx.customer_provider, wallet, client_sig.clone()).await.unwrap();
+    ctx.customer_market.verify_request_signature(&request, &client_sig).await.unwrap();
+
+    // Submitted onchain, the broker validates the signature with the wallet, locks and fulfills
+    ctx.customer_market.submit_request(&request, &ctx.customer_signer).await.unwrap();
+    ctx.customer_market
+        .wait_for_request_fulfillment(
+            U256::from(request.id),
+            Duration::from_secs(1),
+            request.expires_at(),
+        )
+        .await
+        .unwrap();
+    assert!(ctx.customer_market.balance_of(wallet).await.unwrap() < deposit);
+
+    if broker_task.is_finished() {
+        broker_task.await.unwrap();
+    } else {
+        broker_task.abort();
+    }
+    get_mock.assert();
+}
+
+#[tokio::test]
+#[traced_test]
+async fn two_markets_one_chain() {
+    let anvil = Anvil::new().spawn();
+    // Each context deploys its own set of contracts to the chain
+    let ctx_a = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+    let ctx_b = create_test_ctx(&anvil, SET_BUILDER_ID, ASSESSOR_GUEST_ID).await.unwrap();
+    assert_ne!(ctx_a.boundless_market_address, ctx_b.boundless_market_address);
+
+    for ctx in [&ctx_a, &ctx_b] {
+        ctx.prover_market
+            .deposit_stake_with_permit(default_allowance(), &ctx.prover_signer)
+            .await
+            .unwrap();
+        ctx.customer_market.deposit(utils::parse_ether("0.5").unwrap()).await.unwrap();
+    }
+
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/image");
+        then.status(200).body(ECHO_ELF);
+    });
+    let image_uri = format!("http://{}/image", server.address());
+
+    let config_file = NamedTempFile::new().unwrap();
+    e2e_config().write(config_file.path()).await.unwrap();
+    let args = broker_args(&ctx_a, anvil.endpoint_url(), &config_file);
+    let deployment = MarketDeployment {
+        rpc_url: anvil.endpoint_url(),
+        boundless_market_address: ctx_b.boundless_market_address,
+        set_verifier_address: ctx_b.set_verifier_address,
+        order_stream_url: None,
+    };
+    let broker = Broker::new(args, ctx_a.prover_provider.clone())
+        .await
+        .unwrap()
+        .with_market(deployment.clone(), ctx_b.prover_provider.clone(), None)
+        .await
+        .unwrap();
+
+    // Serving the same deployment twice is rejected
+    let args = broker_args(&ctx_a, anvil.endpoint_url(), &config_file);
+    let err = Broker::new(args, ctx_a.prover_provider.clone())
+        .await
+        .unwrap()
+        .with_market(deployment.clone(), ctx_b.prover_provider.clone(), None)
+        .await
+        .unwrap()
+        .with_market(deployment, ctx_b.prover_provider.clone(), None)
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("is already served"));
+
+    let broker_task = tokio::spawn(async move {
+        broker.start_service().await.unwrap();
+    });
+
+    // Both markets start at the same request index, so both requests have the same ID
+    let request_a = echo_request(&ctx_a, &image_uri).await;
+    let request_b = echo_request(&ctx_b, &image_uri).await;
+    assert_eq!(request_a.id, request_b.id);
+    ctx_a.customer_market.submit_request(&request_a, &ctx_a.customer_signer).await.unwrap();
+    ctx_b.customer_market.submit_request(&request_b, &ctx_b.customer_signer).await.unwrap();
+
+    for (ctx, request) in [(&ctx_a, &request_a), (&ctx_b, &request_b)] {
+        ctx.customer_market
+            .wait_for_request_fulfillment(
+                U256::from(request.id),
+                Duration::from_secs(1),
+                request.expires_at(),
+            )
+            .await
+            .unwrap();
+    }
+
+    if broker_task.is_finished() {
+        broker_task.await.unwrap();
+    } else {
+        broker_task.abort();
+    }
+}
@@ -0,0 +1,613 @@
+// Copyright (c) 2025 RISC Zero, Inc.
+//
+// All rights reserved.
+
+use std::{sync::Arc, time::Duration};
+
+use alloy::primitives::{hex, Address, B256, U256};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use risc0_zkvm::sha::Digest;
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::{
+    config::ConfigLock,
+    db::{AggregationOrder, BrokerDb, DbError, DbObj},
+    AggregationState, ArchiveStats, Batch, BatchStatus, LostLock, Order, OrderStatus, ProofRequest,
+};
+
+/// Header carrying the hex HMAC-SHA256 of the payload body
+pub const SIGNATURE_HEADER: &str = "X-Boundless-Signature";
+
+/// Order or batch status transition reported to the webhook
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Order {
+        order_id: U256,
+        status: OrderStatus,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Batch {
+        batch_id: usize,
+        status: BatchStatus,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// Stake of an order locked by this broker was slashed on chain
+    Slashed { order_id: U256, stake_burned: U256, stake_transferred: U256 },
+}
+
+impl WebhookEvent {
+    fn order(order_id: U256, status: OrderStatus) -> Self {
+        Self::Order { order_id, status, error: None }
+    }
+
+    fn batch(batch_id: usize, status: BatchStatus) -> Self {
+        Self::Batch { batch_id, status, error: None }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    /// Chain and market the event belongs to, order and batch IDs are only unique within a market
+    chain_id: u64,
+    market_address: Address,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    /// Position of the event in the order it was emitted, starting at 1 when the sink is spawned
+    sequence: u64,
+    timestamp: i64,
+}
+
+/// Hex encoded HMAC-SHA256 of the body with the shared secret
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Upper bound of the delay between two delivery attempts of an event
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Max number of events being delivered at once
+const MAX_IN_FLIGHT: usize = 32;
+
+/// Queue of lifecycle events of a single market, delivered by a background task
+///
+/// Delivery never blocks the caller, events are dropped when no webhook URL is configured. Each
+/// event is delivered by its own task, so a slow or failing endpoint does not hold back later
+/// events; as a result events may arrive out of order, receivers should order them by their
+/// `sequence`. The `timestamp` only has a resolution of one second, and the sequence starts over
+/// when the broker restarts.
+#[derive(Clone)]
+pub struct WebhookSink {
+    tx: mpsc::UnboundedSender<WebhookEvent>,
+}
+
+impl WebhookSink {
+    /// Spawn the delivery task of the market at `market_address` on the given chain
+    pub fn spawn(config: ConfigLock, chain_id: u64, market_address: Address) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<WebhookEvent>();
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+            let mut sequence = 0;
+            while let Some(event) = rx.recv().await {
+                // Sequence and timestamp the event when it is emitted, not when it is delivered
+                sequence += 1;
+                let timestamp = Utc::now().timestamp();
+                let Ok(permit) = in_flight.clone().acquire_owned().await else {
+                    break;
+                };
+                let client = client.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    let payload = WebhookPayload {
+                        chain_id,
+                        market_address,
+                        event: &event,
+                        sequence,
+                        timestamp,
+                    };
+                    if let Err(err) = deliver(&client, &config, &payload).await {
+                        tracing::warn!("Failed to deliver webhook event {event:?}: {err:?}");
+                    }
+                    drop(permit);
+                });
+            }
+        });
+        Self { tx }
+    }
+
+    pub fn notify(&self, event: WebhookEvent) {
+        if self.tx.send(event).is_err() {
+            tracing::error!("Webhook delivery task has exited");
+        }
+    }
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    config: &ConfigLock,
+    payload: &WebhookPayload<'_>,
+) -> Result<()> {
+    let (url, secret, max_retries, retry_delay, timeout) = {
+        let config = config.lock_all().context("Failed to read config")?;
+        let Some(url) = config.webhook.url.clone() else {
+            return Ok(());
+        };
+        (
+            url,
+            config.webhook.secret.clone(),
+            config.webhook.max_retries,
+            Duration::from_millis(config.webhook.retry_delay_ms),
+            Duration::from_secs(config.webhook.timeout_secs),
+        )
+    };
+
+    let body = serde_json::to_vec(payload).context("Failed to serialize webhook payload")?;
+    let signature = secret.map(|secret| sign_payload(&secret, &body));
+
+    let mut attempt = 0;
+    loop {
+        let mut req = client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .timeout(timeout)
+            .body(body.clone());
+        if let Some(signature) = signature.as_ref() {
+            req = req.header(SIGNATURE_HEADER, signature);
+        }
+
+        let err = match req.send().await {
+            Ok(res) if res.status().is_success() => return Ok(()),
+            Ok(res) => anyhow::anyhow!("HTTP status {}", res.status()),
+            Err(err) => err.into(),
+        };
+        if attempt >= max_retries {
+            return Err(err.context(format!("Webhook failed after {attempt} retries")));
+        }
+        tracing::debug!("Webhook attempt {}/{max_retries} failed: {err:?}", attempt + 1);
+        tokio::time::sleep(retry_backoff(retry_delay, attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Delay before the retry following the given attempt, doubled on each attempt and capped
+fn retry_backoff(retry_delay: Duration, attempt: u32) -> Duration {
+    let factor = 2u32.checked_pow(attempt).unwrap_or(u32::MAX);
+    retry_delay.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+/// [BrokerDb] wrapper that reports status transitions to a [WebhookSink]
+///
+/// Events are emitted only after the underlying update succeeds.
+pub struct WebhookDb {
+    inner: DbObj,
+    sink: WebhookSink,
+}
+
+impl WebhookDb {
+    pub fn new(inner: DbObj, sink: WebhookSink) -> Self {
+        Self { inner, sink }
+    }
+}
+
+#[async_trait]
+impl BrokerDb for WebhookDb {
+    async fn add_order(&self, id: U256, order: Order) -> Result<Option<Order>, DbError> {
+        let status = order.status;
+        let res = self.inner.add_order(id, order).await?;
+        self.sink.notify(WebhookEvent::order(id, status));
+        Ok(res)
+    }
+
+    async fn order_exists(&self, id: U256) -> Result<bool, DbError> {
+        self.inner.order_exists(id).await
+    }
+
+    async fn get_order(&self, id: U256) -> Result<Option<Order>, DbError> {
+        self.inner.get_order(id).await
+    }
+
+    async fn get_submission_order(
+        &self,
+        id: U256,
+    ) -> Result<(ProofRequest, String, B256, U256), DbError> {
+        self.inner.get_submission_order(id).await
+    }
+
+    async fn get_order_for_pricing(&self, id: U256) -> Result<Option<Order>, DbError> {
+        let res = self.inner.get_order_for_pricing(id).await?;
+        if res.is_some() {
+            self.sink.notify(WebhookEvent::order(id, OrderStatus::Pricing));
+        }
+        Ok(res)
+    }
+
+    async fn get_active_pricing_orders(&self) -> Result<Vec<(U256, Order)>, DbError> {
+        self.inner.get_active_pricing_orders().await
+    }
+
+    async fn set_order_lock(
+        &self,
+        id: U256,
+        lock_timestamp: u64,
+        expire_timestamp: u64,
+    ) -> Result<(), DbError> {
+        self.inner.set_order_lock(id, lock_timestamp, expire_timestamp).await?;
+        self.sink.notify(WebhookEvent::order(id, OrderStatus::Locking));
+        Ok(())
+    }
+
+    async fn set_proving_status(&self, id: U256, lock_price: U256) -> Result<(), DbError> {
+        self.inner.set_proving_status(id, lock_price).await?;
+        self.sink.notify(WebhookEvent::order(id, OrderStatus::Locked));
+        Ok(())
+    }
+
+    async fn set_order_failure(&self, id: U256, failure_str: String) -> Result<(), DbError> {
+        self.inner.set_order_failure(id, failure_str.clone()).await?;
+        self.sink.notify(WebhookEvent::Order {
+            order_id: id,
+            status: OrderStatus::Failed,
+            error: Some(failure_str),
+        });
+        Ok(())
+    }
+
+    async fn set_order_complete(&self, id: U256) -> Result<(), DbError> {
+        self.inner.set_order_complete(id).await?;
+        self.sink.notify(WebhookEvent::order(id, OrderStatus::Done));
+        Ok(())
+    }
+
+    async fn skip_order(&self, id: U256) -> Result<(), DbError> {
+        self.inner.skip_order(id).await?;
+        self.sink.notify(WebhookEvent::order(id, OrderStatus::Skipped));
+        Ok(())
+    }
+
+    async fn set_order_dry_run(&self, id: U256, lock_price: U256) -> Result<(), DbError> {
+        self.inner.set_order_dry_run(id, lock_price).await?;
+        self.sink.notify(WebhookEvent::order(id, OrderStatus::DryRun));
+        Ok(())
+    }
+
+    async fn skip_dry_run_orders(&self) -> Result<u64, DbError> {
+        self.inner.skip_dry_run_orders().await
+    }
+
+    async fn get_last_block(&self) -> Result<Option<u64>, DbError> {
+        self.inner.get_last_block().await
+    }
+
+    async fn set_last_block(&self, block_numb: u64) -> Result<(), DbError> {
+        self.inner.set_last_block(block_numb).await
+    }
+
+    async fn add_lost_lock(&self, id: U256, lost_lock: LostLock) -> Result<(), DbError> {
+        self.inner.add_lost_lock(id, lost_lock).await
+    }
+
+    async fn get_lost_locks(&self) -> Result<Vec<(U256, LostLock)>, DbError> {
+        self.inner.get_lost_locks().await
+    }
+
+    async fn get_pending_lock_orders(
+        &self,
+        end_timestamp: u64,
+    ) -> Result<Vec<(U256, Order)>, DbError> {
+        self.inner.get_pending_lock_orders(end_timestamp).await
+    }
+
+    async fn get_orders_committed_to_fulfill_count(&self) -> Result<u64, DbError> {
+        self.inner.get_orders_committed_to_fulfill_count().await
+    }
+
+    async fn get_orders_committed_to_fulfill(&self) -> Result<Vec<(U256, Order)>, DbError> {
+        self.inner.get_orders_committed_to_fulfill().await
+    }
+
+    async fn get_orders_by_status(
+        &self,
+        status: OrderStatus,
+    ) -> Result<Vec<(U256, Order)>, DbError> {
+        self.inner.get_orders_by_status(status).await
+    }
+
+    async fn get_new_orders_after(&self, seq: i64) -> Result<Vec<(i64, U256, Order)>, DbError> {
+        self.inner.get_new_orders_after(seq).await
+    }
+
+    async fn get_proving_order(&self) -> Result<Option<(U256, Order)>, DbError> {
+        let res = self.inner.get_proving_order().await?;
+        if let Some((id, _)) = res.as_ref() {
+            self.sink.notify(WebhookEvent::order(*id, OrderStatus::Proving));
+        }
+        Ok(res)
+    }
+
+    async fn get_active_proofs(&self) -> Result<Vec<(U256, Order)>, DbError> {
+        self.inner.get_active_proofs().await
+    }
+
+    async fn set_order_proof_id(&self, order_id: U256, proof_id: &str) -> Result<(), DbError> {
+        self.inner.set_order_proof_id(order_id, proof_id).await
+    }
+
+    async fn add_proof_failure(&self, id: U256, failure: String) -> Result<(), DbError> {
+        self.inner.add_proof_failure(id, failure).await
+    }
+
+    async fn set_image_input_ids(
+        &self,
+        id: U256,
+        image_id: &str,
+        input_id: &str,
+    ) -> Result<(), DbError> {
+        self.inner.set_image_input_ids(id, image_id, input_id).await
+    }
+
+    async fn set_order_input_digest(&self, id: U256, input_digest: B256) -> Result<(), DbError> {
+        self.inner.set_order_input_digest(id, input_digest).await
+    }
+
+    async fn set_order_assumption_ids(
+        &self,
+        id: U256,
+        assumption_ids: &[String],
+    ) -> Result<(), DbError> {
+        self.inner.set_order_assumption_ids(id, assumption_ids).await
+    }
+
+    async fn add_cached_proof(
+        &self,
+        image_id: B256,
+        input_digest: B256,
+        proof_id: &str,
+    ) -> Result<(), DbError> {
+        self.inner.add_cached_proof(image_id, input_digest, proof_id).await
+    }
+
+    async fn get_cached_proof(
+        &self,
+        image_id: B256,
+        input_digest: B256,
+    ) -> Result<Option<String>, DbError> {
+        self.inner.get_cached_proof(image_id, input_digest).await
+    }
+
+    async fn prune_cached_proofs(&self, added_before: DateTime<Utc>) -> Result<usize, DbError> {
+        self.inner.prune_cached_proofs(added_before).await
+    }
+
+    async fn set_aggregation_status(&self, id: U256) -> Result<(), DbError> {
+        self.inner.set_aggregation_status(id).await?;
+        self.sink.notify(WebhookEvent::order(id, OrderStatus::PendingAgg));
+        Ok(())
+    }
+
+    // Orders already in Aggregating are returned on every poll, so the transition is not reported
+    async fn get_aggregation_proofs(&self) -> Result<Vec<AggregationOrder>, DbError> {
+        self.inner.get_aggregation_proofs().await
+    }
+
+    async fn complete_batch(&self, batch_id: usize, g16_proof_id: String) -> Result<(), DbError> {
+        self.inner.complete_batch(batch_id, g16_proof_id).await?;
+        self.sink.notify(WebhookEvent::batch(batch_id, BatchStatus::Complete));
+        Ok(())
+    }
+
+    async fn get_complete_batch(&self) -> Result<Option<(usize, Batch)>, DbError> {
+        let res = self.inner.get_complete_batch().await?;
+        if let Some((batch_id, _)) = res.as_ref() {
+            self.sink.notify(WebhookEvent::batch(*batch_id, BatchStatus::PendingSubmission));
+        }
+        Ok(res)
+    }
+
+    async fn set_batch_submitted(&self, batch_id: usize) -> Result<(), DbError> {
+        self.inner.set_batch_submitted(batch_id).await?;
+        self.sink.notify(WebhookEvent::batch(batch_id, BatchStatus::Submitted));
+        Ok(())
+    }
+
+    async fn set_batch_failure(&self, batch_id: usize, err: String) -> Result<(), DbError> {
+        self.inner.set_batch_failure(batch_id, err.clone()).await?;
+        self.sink.notify(WebhookEvent::Batch {
+            batch_id,
+            status: BatchStatus::Failed,
+            error: Some(err),
+        });
+        Ok(())
+    }
+
+    async fn get_current_batch(&self) -> Result<usize, DbError> {
+        self.inner.get_current_batch().await
+    }
+
+    async fn update_batch(
+        &self,
+        batch_id: usize,
+        aggreagtion_state: &AggregationState,
+        orders: &[AggregationOrder],
+        assessor_claim_digest: Option<Digest>,
+    ) -> Result<(), DbError> {
+        self.inner.update_batch(batch_id, aggreagtion_state, orders, assessor_claim_digest).await?;
+        for order in orders {
+            self.sink.notify(WebhookEvent::order(order.order_id, OrderStatus::PendingSubmission));
+        }
+        if assessor_claim_digest.is_some() {
+            self.sink.notify(WebhookEvent::batch(batch_id, BatchStatus::PendingCompression));
+        }
+        Ok(())
+    }
+
+    async fn get_batch(&self, batch_id: usize) -> Result<Batch, DbError> {
+        self.inner.get_batch(batch_id).await
+    }
+
+    async fn get_batches_by_status(
+        &self,
+        status: BatchStatus,
+    ) -> Result<Vec<(usize, Batch)>, DbError> {
+        self.inner.get_batches_by_status(status).await
+    }
+
+    async fn set_batch_status(&self, batch_id: usize, status: BatchStatus) -> Result<(), DbError> {
+        self.inner.set_batch_status(batch_id, status.clone()).await?;
+        self.sink.notify(WebhookEvent::batch(batch_id, status));
+        Ok(())
+    }
+
+    async fn get_archivable_orders(
+        &self,
+        updated_before: DateTime<Utc>,
+    ) -> Result<Vec<(U256, Order)>, DbError> {
+        self.inner.get_archivable_orders(updated_before).await
+    }
+
+    async fn archive_orders(&self, ids: &[U256], keep_archive: bool) -> Result<usize, DbError> {
+        self.inner.archive_orders(ids, keep_archive).await
+    }
+
+    async fn get_archivable_batches(
+        &self,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<(usize, Batch)>, DbError> {
+        self.inner.get_archivable_batches(started_before).await
+    }
+
+    async fn archive_batches(&self, ids: &[usize], keep_archive: bool) -> Result<usize, DbError> {
+        self.inner.archive_batches(ids, keep_archive).await
+    }
+
+    async fn get_archive_stats(&self) -> Result<ArchiveStats, DbError> {
+        self.inner.get_archive_stats().await
+    }
+
+    async fn vacuum(&self) -> Result<(), DbError> {
+        self.inner.vacuum().await
+    }
+
+    #[cfg(test)]
+    async fn add_batch(&self, batch_id: usize, batch: Batch) -> Result<(), DbError> {
+        self.inner.add_batch(batch_id, batch).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDb;
+    use alloy::primitives::Address;
+    use boundless_market::contracts::{
+        Input, InputType, Offer, Predicate, PredicateType, Requirements,
+    };
+    use httpmock::prelude::*;
+
+    #[test]
+    fn signature() {
+        let sig = sign_payload("secret", b"{}");
+        assert!(sig.starts_with("sha256="));
+        assert_eq!(sig.len(), "sha256=".len() + 64);
+        assert_ne!(sig, sign_payload("other", b"{}"));
+    }
+
+    #[test]
+    fn backoff() {
+        let delay = Duration::from_millis(1000);
+        assert_eq!(retry_backoff(delay, 0), delay);
+        assert_eq!(retry_backoff(delay, 3), Duration::from_secs(8));
+        assert_eq!(retry_backoff(delay, 10), MAX_RETRY_DELAY);
+        // Does not overflow with large retry counts
+        assert_eq!(retry_backoff(delay, 40), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn slashed_event_payload() {
+        let event = WebhookEvent::Slashed {
+            order_id: U256::from(1),
+            stake_burned: U256::from(2),
+            stake_transferred: U256::from(3),
+        };
+        let payload = WebhookPayload {
+            chain_id: 1,
+            market_address: Address::ZERO,
+            event: &event,
+            sequence: 1,
+            timestamp: 0,
+        };
+        let body = serde_json::to_string(&payload).unwrap();
+        assert!(body.contains(r#""chain_id":1"#));
+        assert!(body.contains(r#""market_address":"0x0000000000000000000000000000000000000000""#));
+        assert!(body.contains(r#""type":"slashed""#));
+        assert!(body.contains(r#""sequence":1"#));
+        assert!(body.contains(r#""stake_burned":"0x2""#));
+    }
+
+    #[tokio::test]
+    async fn order_failure_event() {
+        let server = MockServer::start();
+        let hook_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/hook")
+                .header_exists(SIGNATURE_HEADER)
+                .body_contains(r#""type":"order""#)
+                .body_contains(r#""status":"Failed""#)
+                .body_contains(r#""error":"test""#)
+                .body_contains(r#""sequence":1"#)
+                .body_contains(r#""chain_id":7"#);
+            then.status(200);
+        });
+
+        let config = ConfigLock::default();
+        {
+            let mut config = config.load_write().unwrap();
+            config.webhook.url = Some(format!("http://{}/hook", server.address()));
+            config.webhook.secret = Some("secret".into());
+        }
+
+        let inner: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let db = WebhookDb::new(inner.clone(), WebhookSink::spawn(config, 7, Address::ZERO));
+
+        let order_id = U256::from(1);
+        let request = ProofRequest::new(
+            1,
+            &Address::ZERO,
+            Requirements::new(
+                Digest::ZERO,
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            "http://risczero.com",
+            Input { inputType: InputType::Inline, data: Default::default() },
+            Offer {
+                minPrice: U256::from(1),
+                maxPrice: U256::from(2),
+                biddingStart: 0,
+                timeout: 100,
+                lockTimeout: 100,
+                rampUpPeriod: 1,
+                lockStake: U256::from(0),
+            },
+        );
+        inner.add_order(order_id, Order::new(request, Default::default())).await.unwrap();
+        db.set_order_failure(order_id, "test".into()).await.unwrap();
+
+        for _ in 0..50 {
+            if hook_mock.hits_async().await > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        hook_mock.assert_async().await;
+    }
+}
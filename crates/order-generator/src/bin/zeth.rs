@@ -33,6 +33,8 @@ use zeth_preflight::BlockBuilder;
 use zeth_preflight_ethereum::RethBlockBuilder;
 
 const RETRY_DELAY_SECS: u64 = 5;
+/// Max size of each uploaded input chunk, inputs above it are referenced by a manifest.
+const INPUT_CHUNK_SIZE: usize = 10_000_000;
 
 /// Arguments of order-generator-zeth CLI.
 #[derive(Parser, Debug)]
@@ -93,6 +95,11 @@ struct Args {
     /// Submit the request offchain.
     #[clap(long)]
     offchain: bool,
+    /// Compress the input before uploading it.
+    ///
+    /// Requires provers that support compressed inputs.
+    #[clap(long)]
+    compress_input: bool,
     #[clap(long, default_value = "3")]
     max_retries: u32,
 }
@@ -186,6 +193,7 @@ async fn main() -> Result<()> {
             timeout: args.timeout,
             stake: args.stake,
             offchain: args.offchain,
+            compress_input: args.compress_input,
         };
         // Attempt to submit a request.
         match submit_request(build_args, chain_id, boundless_client.clone(), params).await {
@@ -245,6 +253,7 @@ struct RequestParams {
     timeout: u32,
     stake: U256,
     offchain: bool,
+    compress_input: bool,
 }
 
 async fn submit_request<T, P, S>(
@@ -273,8 +282,18 @@ where
         .write_frame(&build_result.encoded_rkyv_input)
         .write_frame(&build_result.encoded_chain_input)
         .build_env()?;
-    let input_url = boundless_client.upload_input(&guest_env.encode()?).await?;
-    tracing::info!("Uploaded input to {}", input_url);
+    let encoded_input =
+        if params.compress_input { guest_env.encode_compressed()? } else { guest_env.encode()? };
+    let input = if encoded_input.len() > INPUT_CHUNK_SIZE {
+        let manifest =
+            boundless_client.upload_chunked_input(&encoded_input, INPUT_CHUNK_SIZE).await?;
+        tracing::info!("Uploaded input in {} chunks", manifest.chunks.len());
+        Input::inline(manifest.encode()?)
+    } else {
+        let input_url = boundless_client.upload_input(&encoded_input).await?;
+        tracing::info!("Uploaded input to {}", input_url);
+        Input::url(input_url)
+    };
 
     tracing::info!("Executing for block {} ...", build_args.block_number);
     // run executor only
@@ -298,7 +317,7 @@ where
 
     let request = ProofRequest::builder()
         .with_image_url(params.image_url)
-        .with_input(input_url)
+        .with_input(input)
         .with_requirements(Requirements::new(
             ZETH_GUESTS_RETH_ETHEREUM_ID,
             Predicate::digest_match(journal.digest()),
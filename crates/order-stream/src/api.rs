@@ -6,7 +6,8 @@ use alloy::primitives::Address;
 use anyhow::Context;
 use axum::extract::{Json, Path, Query, State};
 use boundless_market::order_stream_client::{
-    ErrMsg, Nonce, OrderData, SubmitOrderRes, HEALTH_CHECK, ORDER_LIST_PATH, ORDER_SUBMISSION_PATH,
+    ErrMsg, Nonce, OrderData, SubmitOrderRes, SubmitOrdersRes, HEALTH_CHECK, MAX_BATCH_ORDERS,
+    ORDER_BATCH_SUBMISSION_PATH, ORDER_LIST_PATH, ORDER_SUBMISSION_PATH,
 };
 use serde::Deserialize;
 use std::sync::Arc;
@@ -32,7 +33,9 @@ pub(crate) async fn submit_order(
     Json(order): Json<Order>,
 ) -> Result<Json<SubmitOrderRes>, AppError> {
     // Validate the order
-    order.validate(state.config.market_address, state.chain_id)?;
+    order
+        .validate_with_provider(state.config.market_address, state.chain_id, &state.rpc_provider)
+        .await?;
     let order_req_id = order.request.id;
     let order_id = state.db.add_order(order).await.context("failed to add order to db")?;
 
@@ -40,6 +43,43 @@ pub(crate) async fn submit_order(
     Ok(Json(SubmitOrderRes { status: "success".into(), request_id: order_req_id }))
 }
 
+#[utoipa::path(
+    post,
+    path = ORDER_BATCH_SUBMISSION_PATH,
+    request_body = Vec<Order>,
+    responses(
+        (status = 200, description = "Orders submission response", body = SubmitOrdersRes),
+        (status = 400, description = "Invalid orders", body = ErrMsg),
+        (status = 500, description = "Internal error", body = ErrMsg)
+    )
+)]
+/// Submit a batch of new orders to the market order-stream
+///
+/// Either all of the orders are accepted, or none of them.
+pub(crate) async fn submit_orders(
+    State(state): State<Arc<AppState>>,
+    Json(orders): Json<Vec<Order>>,
+) -> Result<Json<SubmitOrdersRes>, AppError> {
+    if orders.len() > MAX_BATCH_ORDERS {
+        return Err(AppError::BatchTooLarge(orders.len()));
+    }
+    // Validate all the orders before adding any of them
+    for order in &orders {
+        order
+            .validate_with_provider(
+                state.config.market_address,
+                state.chain_id,
+                &state.rpc_provider,
+            )
+            .await?;
+    }
+    let request_ids: Vec<_> = orders.iter().map(|order| order.request.id).collect();
+    let order_ids = state.db.add_orders(orders).await.context("failed to add orders to db")?;
+
+    tracing::debug!("Batch of {} orders {order_ids:?} submitted", order_ids.len());
+    Ok(Json(SubmitOrdersRes { status: "success".into(), request_ids }))
+}
+
 const MAX_ORDERS: u64 = 1000;
 
 /// Paging query parameters
@@ -23,8 +23,8 @@ use axum::{
     Router,
 };
 use boundless_market::order_stream_client::{
-    AuthMsg, ErrMsg, Order, OrderError, AUTH_GET_NONCE, HEALTH_CHECK, ORDER_LIST_PATH,
-    ORDER_SUBMISSION_PATH, ORDER_WS_PATH,
+    AuthMsg, ErrMsg, Order, OrderError, AUTH_GET_NONCE, HEALTH_CHECK, MAX_BATCH_ORDERS,
+    ORDER_BATCH_SUBMISSION_PATH, ORDER_LIST_PATH, ORDER_SUBMISSION_PATH, ORDER_WS_PATH,
 };
 use clap::Parser;
 use reqwest::Url;
@@ -44,7 +44,8 @@ mod ws;
 
 use api::{
     __path_find_orders_by_request_id, __path_get_nonce, __path_health, __path_list_orders,
-    __path_submit_order, find_orders_by_request_id, get_nonce, health, list_orders, submit_order,
+    __path_submit_order, __path_submit_orders, find_orders_by_request_id, get_nonce, health,
+    list_orders, submit_order, submit_orders,
 };
 use order_db::OrderDb;
 use ws::{__path_websocket_handler, start_broadcast_task, websocket_handler, ConnectionsMap};
@@ -61,6 +62,9 @@ pub enum AppError {
     #[error("address not found")]
     AddrNotFound(Address),
 
+    #[error("batch of {0} orders exceeds the maximum of {MAX_BATCH_ORDERS}")]
+    BatchTooLarge(usize),
+
     #[error("internal error")]
     InternalErr(AnyhowErr),
 }
@@ -71,6 +75,7 @@ impl AppError {
             Self::InvalidOrder(_) => "InvalidOrder",
             Self::QueryParamErr(_) => "QueryParamErr",
             Self::AddrNotFound(_) => "AddrNotFound",
+            Self::BatchTooLarge(_) => "BatchTooLarge",
             Self::InternalErr(_) => "InternalErr",
         }
         .into()
@@ -92,7 +97,9 @@ impl From<OrderError> for AppError {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let code = match self {
-            Self::InvalidOrder(_) | Self::QueryParamErr(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidOrder(_) | Self::QueryParamErr(_) | Self::BatchTooLarge(_) => {
+                StatusCode::BAD_REQUEST
+            }
             Self::AddrNotFound(_) => StatusCode::NOT_FOUND,
             Self::InternalErr(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
@@ -268,11 +275,13 @@ impl AppState {
 }
 
 const MAX_ORDER_SIZE: usize = 25 * 1024 * 1024; // 25 mb
+const MAX_BATCH_SIZE: usize = 100 * 1024 * 1024; // 100 mb
 
 #[derive(OpenApi, Debug, Deserialize)]
 #[openapi(
     paths(
         submit_order,
+        submit_orders,
         list_orders,
         find_orders_by_request_id,
         get_nonce,
@@ -293,9 +302,11 @@ struct ApiDoc;
 /// Create the application router
 pub fn app(state: Arc<AppState>) -> Router {
     let body_size_limit = RequestBodyLimitLayer::new(MAX_ORDER_SIZE);
+    let batch_body_size_limit = RequestBodyLimitLayer::new(MAX_BATCH_SIZE);
 
     Router::new()
         .route(ORDER_SUBMISSION_PATH, post(submit_order).layer(body_size_limit))
+        .route(ORDER_BATCH_SUBMISSION_PATH, post(submit_orders).layer(batch_body_size_limit))
         .route(ORDER_LIST_PATH, get(list_orders))
         .route(&format!("{ORDER_LIST_PATH}/:request_id"), get(find_orders_by_request_id))
         .route(&format!("{AUTH_GET_NONCE}:addr"), get(get_nonce))
@@ -381,8 +392,8 @@ mod tests {
     use boundless_market::{
         contracts::{
             hit_points::default_allowance,
-            test_utils::{create_test_ctx, TestCtx},
-            Offer, Predicate, ProofRequest, Requirements,
+            test_utils::{create_test_ctx, deploy_mock_wallet, set_mock_wallet_signature, TestCtx},
+            Offer, Predicate, ProofRequest, RequestId, Requirements,
         },
         input::InputBuilder,
         order_stream_client::{order_stream, Client},
@@ -621,6 +632,109 @@ mod tests {
         server_handle.abort();
     }
 
+    #[sqlx::test]
+    async fn test_submit_requests(pool: PgPool) {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (app_state, ctx, _anvil) = setup_test_env(pool, 20, Some(&listener)).await;
+
+        let client = Client::new(
+            Url::parse(&format!("http://{addr}")).unwrap(),
+            app_state.config.market_address,
+            app_state.chain_id,
+        );
+        let app_state_clone = app_state.clone();
+        let server_handle = tokio::spawn(async move {
+            self::run_from_parts(app_state_clone, listener).await.unwrap();
+        });
+        wait_for_server_health(&client, &addr, 5).await;
+
+        let customer = ctx.customer_signer.address();
+        let requests = vec![new_request(1, &customer), new_request(2, &customer)];
+        let orders = client.submit_requests(&requests, &ctx.customer_signer).await.unwrap();
+        assert_eq!(orders.len(), 2);
+
+        let db_orders = app_state.db.list_orders(0, 10).await.unwrap();
+        assert_eq!(db_orders.len(), 2);
+        assert_eq!(db_orders[0].order, orders[0]);
+        assert_eq!(db_orders[1].order, orders[1]);
+
+        app_state.shutdown.cancel();
+        server_handle.abort();
+    }
+
+    #[sqlx::test]
+    async fn test_submit_smart_contract_signed_requests(pool: PgPool) {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (app_state, ctx, _anvil) = setup_test_env(pool, 20, Some(&listener)).await;
+        let market = app_state.config.market_address;
+        let chain_id = app_state.chain_id;
+
+        let client = Client::new(Url::parse(&format!("http://{addr}")).unwrap(), market, chain_id);
+        let app_state_clone = app_state.clone();
+        let server_handle = tokio::spawn(async move {
+            self::run_from_parts(app_state_clone, listener).await.unwrap();
+        });
+        wait_for_server_health(&client, &addr, 5).await;
+
+        // The customer signs requests as the owner of a smart contract wallet
+        let owner = &ctx.customer_signer;
+        let wallet = deploy_mock_wallet(&ctx.customer_provider, market, parse_ether("1").unwrap())
+            .await
+            .unwrap();
+        let now =
+            std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let wallet_request = |idx: u32| {
+            let mut request = new_request(idx, &wallet);
+            request.id = RequestId::new(wallet, idx).with_smart_contract_signed(true).into();
+            request.offer.biddingStart = now;
+            request
+        };
+
+        // Rejected until the wallet accepts the signature of the request
+        let request = wallet_request(1);
+        client.submit_request(&request, owner).await.unwrap_err();
+        let sig = request.sign_request(owner, market, chain_id).await.unwrap();
+        set_mock_wallet_signature(&ctx.customer_provider, wallet, sig.as_bytes().into())
+            .await
+            .unwrap();
+        let order = client.submit_request(&request, owner).await.unwrap();
+        assert_eq!(app_state.db.list_orders(0, 10).await.unwrap().len(), 1);
+
+        // The market verifies the signature with the wallet when the prover locks the order
+        ctx.prover_market
+            .lock_request(&order.request, &order.signature.as_bytes().into(), None)
+            .await
+            .unwrap();
+        assert!(ctx.prover_market.is_locked(order.request.id).await.unwrap());
+
+        // The wallet accepts one signature at a time, so a batch with two of its requests is
+        // rejected as a whole
+        let (request_2, request_3) = (wallet_request(2), wallet_request(3));
+        let sig = request_2.sign_request(owner, market, chain_id).await.unwrap();
+        set_mock_wallet_signature(&ctx.customer_provider, wallet, sig.as_bytes().into())
+            .await
+            .unwrap();
+        client.submit_requests(&[request_2.clone(), request_3], owner).await.unwrap_err();
+        assert_eq!(app_state.db.list_orders(0, 10).await.unwrap().len(), 1);
+
+        // Batches can mix smart contract and EOA signed requests
+        let requests = vec![request_2, new_request(4, &owner.address())];
+        let orders = client.submit_requests(&requests, owner).await.unwrap();
+        let db_orders = app_state.db.list_orders(0, 10).await.unwrap();
+        assert_eq!(db_orders.len(), 3);
+        assert_eq!(db_orders[1].order, orders[0]);
+        assert_eq!(db_orders[2].order, orders[1]);
+
+        app_state.shutdown.cancel();
+        server_handle.abort();
+    }
+
     #[sqlx::test]
     async fn test_pending_connection_timeout(pool: PgPool) {
         // No need for a listener in this test
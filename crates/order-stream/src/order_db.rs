@@ -8,7 +8,7 @@ use boundless_market::order_stream_client::Order;
 use futures_util::Stream;
 use serde::{Deserialize, Serialize};
 use sqlx::{
-    postgres::{PgListener, PgPool, PgPoolOptions},
+    postgres::{PgConnection, PgListener, PgPool, PgPoolOptions},
     types::chrono::{DateTime, Utc},
 };
 use std::pin::Pin;
@@ -163,13 +163,36 @@ impl OrderDb {
     /// all listeners of the new order.
     pub async fn add_order(&self, order: Order) -> Result<i64, OrderDbErr> {
         let mut txn = self.pool.begin().await?;
+        let id = Self::insert_order(&mut txn, order).await?;
+        txn.commit().await?;
+
+        Ok(id)
+    }
+
+    /// Add orders to DB and notify listeners
+    ///
+    /// Adds the orders to the database in a single transaction, returning their db identifiers in
+    /// the same order. Either all of the orders are added, or none of them. Listeners are notified
+    /// of each new order once the transaction is committed.
+    pub async fn add_orders(&self, orders: Vec<Order>) -> Result<Vec<i64>, OrderDbErr> {
+        let mut txn = self.pool.begin().await?;
+        let mut ids = Vec::with_capacity(orders.len());
+        for order in orders {
+            ids.push(Self::insert_order(&mut txn, order).await?);
+        }
+        txn.commit().await?;
+
+        Ok(ids)
+    }
+
+    async fn insert_order(conn: &mut PgConnection, order: Order) -> Result<i64, OrderDbErr> {
         let row_res: Option<(i64, DateTime<Utc>)> = sqlx::query_as(
             "INSERT INTO orders (request_id, request_digest, order_data, created_at) VALUES ($1, $2, $3, NOW()) RETURNING id, created_at",
         )
         .bind(order.request.id.to_string())
         .bind(order.request_digest.to_string())
         .bind(sqlx::types::Json(order.clone()))
-        .fetch_optional(&mut *txn)
+        .fetch_optional(&mut *conn)
         .await?;
 
         let Some(row) = row_res else {
@@ -182,11 +205,9 @@ impl OrderDb {
         sqlx::query("SELECT pg_notify($1, $2::text)")
             .bind(ORDER_CHANNEL)
             .bind(sqlx::types::Json(DbOrder { id, created_at: Some(created_at), order }))
-            .execute(&mut *txn)
+            .execute(&mut *conn)
             .await?;
 
-        txn.commit().await?;
-
         Ok(id)
     }
 
@@ -347,6 +368,20 @@ mod tests {
         assert_eq!(order_id, 1);
     }
 
+    #[sqlx::test]
+    async fn add_orders(pool: PgPool) {
+        let db = OrderDb::from_pool(pool).await.unwrap();
+
+        let orders = vec![create_order(U256::from(1)).await, create_order(U256::from(2)).await];
+        let order_ids = db.add_orders(orders).await.unwrap();
+        assert_eq!(order_ids, vec![1, 2]);
+
+        let orders = db.list_orders(0, 10).await.unwrap();
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].order.request.id, U256::from(1));
+        assert_eq!(orders[1].order.request.id, U256::from(2));
+    }
+
     #[sqlx::test]
     async fn del_order(pool: PgPool) {
         let db = OrderDb::from_pool(pool).await.unwrap();